@@ -0,0 +1,240 @@
+//! End-to-end throughput of the parser, alone and feeding an order tracker
+//! and books, on a synthetic session.
+//!
+//! The session is generated from a fixed seed, so results are comparable
+//! across versions: run `cargo bench --bench pipeline` on each and compare
+//! the reported throughput in messages per second. The mean latency per
+//! message is the reported time divided by `MESSAGES`.
+//!
+//! The `compact` group walks the session held in memory as `Message`s and
+//! as `CompactMessage`s, which shows the effect of message size on cache
+//! use.
+
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use itchy::{
+    AddOrder, ArrayString8, Body, BookManager, CompactMessage, EventCode, Message, MessageStream,
+    OrderBody, OrderTracker, ReplaceOrder, Side,
+};
+
+const MESSAGES: usize = 1_000_000;
+const SYMBOLS: u16 = 100;
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+// splitmix64
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// A session of `MESSAGES` messages with the mix of a busy day: mostly adds,
+/// deletes and cancels around a drifting price for each symbol, with some
+/// executions and replaces. Every message is consistent with the orders
+/// before it.
+fn synthetic_session() -> Vec<u8> {
+    let mut rng = Rng(SEED);
+    let mut messages = Vec::with_capacity(MESSAGES);
+    let event = |timestamp, event| Message {
+        tag: b'S',
+        stock_locate: 0,
+        tracking_number: 0,
+        timestamp,
+        body: Body::SystemEvent { event },
+    };
+    messages.push(event(0, EventCode::StartOfMessages));
+
+    let stocks: Vec<ArrayString8> = (0..SYMBOLS)
+        .map(|ix| ArrayString8::from(&format!("SYM{:<5}", ix)).unwrap())
+        .collect();
+    let mut mids: Vec<u32> = (0..SYMBOLS).map(|ix| 100_000 + ix as u32 * 500).collect();
+    // live orders: reference to (locate, shares)
+    let mut live: Vec<u64> = Vec::new();
+    let mut orders: HashMap<u64, (u16, u32)> = HashMap::new();
+    let (mut next_reference, mut match_number) = (1, 1);
+
+    while messages.len() < MESSAGES - 1 {
+        let timestamp = 34_200_000_000_000 + messages.len() as u64 * 20_000;
+        let roll = rng.below(100);
+        let (tag, locate, body) = if live.len() < 1_000 || roll < 45 {
+            let locate = rng.below(SYMBOLS as u64) as u16;
+            let mid = &mut mids[locate as usize];
+            *mid = (*mid as i64 + rng.below(21) as i64 - 10).max(10_000) as u32;
+            let side = if rng.below(2) == 0 {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let offset = rng.below(50) as u32 * 100;
+            let price = match side {
+                Side::Buy => *mid - offset,
+                Side::Sell => *mid + offset,
+            };
+            let shares = 100 * (1 + rng.below(10) as u32);
+            let reference = next_reference;
+            next_reference += 1;
+            live.push(reference);
+            orders.insert(reference, (locate + 1, shares));
+            let add = AddOrder {
+                reference,
+                side,
+                shares,
+                stock: stocks[locate as usize],
+                price: price.into(),
+                mpid: None,
+            };
+            (b'A', locate + 1, Body::AddOrder(add))
+        } else {
+            let ix = rng.below(live.len() as u64) as usize;
+            let reference = live[ix];
+            let (locate, shares) = orders[&reference];
+            if roll < 75 {
+                live.swap_remove(ix);
+                orders.remove(&reference);
+                (b'D', locate, Body::DeleteOrder { reference })
+            } else if roll < 85 && shares > 100 {
+                orders.insert(reference, (locate, shares - 100));
+                let cancel = Body::OrderCancelled {
+                    reference,
+                    cancelled: 100,
+                };
+                (b'X', locate, cancel)
+            } else if roll < 95 {
+                let executed = shares.min(100 * (1 + rng.below(3) as u32));
+                if executed == shares {
+                    live.swap_remove(ix);
+                    orders.remove(&reference);
+                } else {
+                    orders.insert(reference, (locate, shares - executed));
+                }
+                match_number += 1;
+                let exec = Body::OrderExecuted {
+                    reference,
+                    executed,
+                    match_number,
+                };
+                (b'E', locate, exec)
+            } else {
+                let new_reference = next_reference;
+                next_reference += 1;
+                live[ix] = new_reference;
+                orders.remove(&reference);
+                orders.insert(new_reference, (locate, shares));
+                let mid = mids[locate as usize - 1];
+                let replace = ReplaceOrder {
+                    old_reference: reference,
+                    new_reference,
+                    shares,
+                    price: (mid + rng.below(50) as u32 * 100).into(),
+                };
+                (b'U', locate, Body::ReplaceOrder(replace))
+            }
+        };
+        messages.push(Message {
+            tag,
+            stock_locate: locate,
+            tracking_number: 0,
+            timestamp,
+            body,
+        });
+    }
+    messages.push(event(57_600_000_000_000, EventCode::EndOfMessages));
+
+    let mut data = Vec::new();
+    for msg in &messages {
+        msg.encode(&mut data);
+    }
+    data
+}
+
+fn pipeline(c: &mut Criterion) {
+    let data = synthetic_session();
+    let mut group = c.benchmark_group("pipeline");
+    group.throughput(Throughput::Elements(MESSAGES as u64));
+    group.sample_size(20);
+
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for msg in MessageStream::from_reader(&data[..]) {
+                black_box(msg.unwrap());
+                count += 1;
+            }
+            assert_eq!(count, MESSAGES);
+        })
+    });
+
+    group.bench_function("parse_track", |b| {
+        b.iter(|| {
+            let mut tracker = OrderTracker::new();
+            for msg in MessageStream::from_reader(&data[..]) {
+                black_box(tracker.apply(&msg.unwrap()));
+            }
+            tracker
+        })
+    });
+
+    group.bench_function("parse_book", |b| {
+        b.iter(|| {
+            let mut books = BookManager::new();
+            for msg in MessageStream::from_reader(&data[..]) {
+                black_box(books.update(&msg.unwrap()));
+            }
+            books
+        })
+    });
+
+    group.finish();
+}
+
+fn compact(c: &mut Criterion) {
+    let messages: Vec<Message> = MessageStream::from_reader(&synthetic_session()[..])
+        .map(|msg| msg.unwrap())
+        .collect();
+    let compact: Vec<CompactMessage> = messages.iter().cloned().map(Into::into).collect();
+    let mut group = c.benchmark_group("compact");
+    group.throughput(Throughput::Elements(MESSAGES as u64));
+
+    // total shares added, touching every message
+    group.bench_function("message", |b| {
+        b.iter(|| {
+            let mut shares = 0u64;
+            for msg in &messages {
+                if let Body::AddOrder(ref add) = msg.body {
+                    shares += add.shares as u64;
+                }
+            }
+            black_box(shares)
+        })
+    });
+
+    group.bench_function("compact_message", |b| {
+        b.iter(|| {
+            let mut shares = 0u64;
+            for msg in &compact {
+                if let Some(OrderBody::AddOrder { shares: ct, .. }) =
+                    msg.as_order().map(|order| &order.body)
+                {
+                    shares += *ct as u64;
+                }
+            }
+            black_box(shares)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pipeline, compact);
+criterion_main!(benches);
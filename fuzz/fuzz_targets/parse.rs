@@ -0,0 +1,23 @@
+//! The message and body parsers must reject, not panic on, any input
+#![no_main]
+
+use itchy::raw_parsers::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_message(data);
+    let _ = parse_unframed(data);
+    let _ = parse_add_order(data);
+    let _ = parse_add_order_with_mpid(data);
+    let _ = parse_cross_trade(data);
+    let _ = parse_imbalance_indicator(data);
+    let _ = parse_ipo_quoting_period(data);
+    let _ = parse_noncross_trade(data);
+    let _ = parse_participant_position(data);
+    let _ = parse_reg_sho_restriction(data);
+    let _ = parse_replace_order(data);
+    let _ = parse_retail_price_improvement_indicator(data);
+    let _ = parse_stock_directory(data);
+    let _ = parse_system_event(data);
+    let _ = parse_trading_action(data);
+});
@@ -0,0 +1,23 @@
+//! A message stream must end, not panic, on any input, with any error
+//! policy and buffer size
+#![no_main]
+
+use itchy::{ErrorPolicy, MessageStream};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&options, input)) = data.split_first() else {
+        return;
+    };
+    let policy = match options % 3 {
+        0 => ErrorPolicy::Halt,
+        1 => ErrorPolicy::SkipMessage,
+        _ => ErrorPolicy::SkipToNextValid,
+    };
+    let buffer_size = 256 << (options >> 5);
+    let stream = MessageStream::builder()
+        .buffer_size(buffer_size)
+        .error_policy(policy)
+        .build(input);
+    for _ in stream {}
+});
@@ -0,0 +1,231 @@
+use std::fmt;
+
+use crate::{Error, Result};
+
+/// What [`ResultIterExt::on_error`] does with errors
+#[derive(Default)]
+pub enum OnError {
+    /// End iteration at the first error, keeping it to be inspected with
+    /// [`Handled::error`]
+    #[default]
+    FailFast,
+    /// Drop errors, counting them
+    Skip,
+    /// Pass each error to a callback, count it and carry on
+    Callback(Box<dyn FnMut(&Error) + Send>),
+}
+
+impl fmt::Debug for OnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnError::FailFast => f.write_str("FailFast"),
+            OnError::Skip => f.write_str("Skip"),
+            OnError::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Counts from [`ResultIterExt::collect_summary`]
+#[derive(Debug, Default)]
+pub struct ResultSummary {
+    pub ok: u64,
+    pub errors: u64,
+    /// The first error seen, if any
+    pub first_error: Option<Error>,
+}
+
+/// Combinators for iterators of `Result`s, such as a
+/// [`MessageStream`](crate::MessageStream), which act on the `Ok` items and
+/// handle errors in one place
+pub trait ResultIterExt<T>: Iterator<Item = Result<T>> + Sized {
+    /// Keep the `Ok` items matching a predicate. Errors are passed through.
+    fn ok_filter<P>(self, predicate: P) -> OkFilter<Self, P>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        OkFilter {
+            iter: self,
+            predicate,
+        }
+    }
+
+    /// Transform the `Ok` items. Errors are passed through.
+    fn ok_map<U, F>(self, f: F) -> OkMap<Self, F>
+    where
+        F: FnMut(T) -> U,
+    {
+        OkMap { iter: self, f }
+    }
+
+    /// Unwrap the items, dealing with errors according to `policy`
+    fn on_error(self, policy: OnError) -> Handled<Self> {
+        Handled {
+            iter: self,
+            policy,
+            errors: 0,
+            error: None,
+        }
+    }
+
+    /// Drain the iterator, counting items and errors
+    fn collect_summary(self) -> ResultSummary {
+        let mut summary = ResultSummary::default();
+        for item in self {
+            match item {
+                Ok(_) => summary.ok += 1,
+                Err(e) => {
+                    summary.errors += 1;
+                    summary.first_error.get_or_insert(e);
+                }
+            }
+        }
+        summary
+    }
+}
+
+impl<T, I: Iterator<Item = Result<T>>> ResultIterExt<T> for I {}
+
+/// Iterator returned by [`ResultIterExt::ok_filter`]
+#[derive(Debug)]
+pub struct OkFilter<I, P> {
+    iter: I,
+    predicate: P,
+}
+
+impl<T, I, P> Iterator for OkFilter<I, P>
+where
+    I: Iterator<Item = Result<T>>,
+    P: FnMut(&T) -> bool,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            match self.iter.next()? {
+                Ok(item) => {
+                    if (self.predicate)(&item) {
+                        return Some(Ok(item));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`ResultIterExt::ok_map`]
+#[derive(Debug)]
+pub struct OkMap<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<T, U, I, F> Iterator for OkMap<I, F>
+where
+    I: Iterator<Item = Result<T>>,
+    F: FnMut(T) -> U,
+{
+    type Item = Result<U>;
+
+    fn next(&mut self) -> Option<Result<U>> {
+        Some(self.iter.next()?.map(&mut self.f))
+    }
+}
+
+/// Iterator returned by [`ResultIterExt::on_error`]
+#[derive(Debug)]
+pub struct Handled<I> {
+    iter: I,
+    policy: OnError,
+    errors: u64,
+    error: Option<Error>,
+}
+
+impl<I> Handled<I> {
+    /// Number of errors seen so far
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// The error that ended iteration, with [`OnError::FailFast`]
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Take the error that ended iteration, if any
+    pub fn into_error(self) -> Option<Error> {
+        self.error
+    }
+}
+
+impl<T, I: Iterator<Item = Result<T>>> Iterator for Handled<I> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error.is_some() {
+            return None;
+        }
+        loop {
+            match self.iter.next()? {
+                Ok(item) => return Some(item),
+                Err(e) => {
+                    self.errors += 1;
+                    match self.policy {
+                        OnError::FailFast => {
+                            self.error = Some(e);
+                            return None;
+                        }
+                        OnError::Skip => {}
+                        OnError::Callback(ref mut callback) => callback(&e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    fn items() -> Vec<Result<crate::Message>> {
+        vec![
+            Ok(msg(1, add(1, Side::Buy, 100, 10_000))),
+            Err(Error::Parse("bad".into())),
+            Ok(msg(2, add(2, Side::Sell, 200, 10_100))),
+            Ok(msg(3, add(3, Side::Buy, 300, 9_900))),
+        ]
+    }
+
+    #[test]
+    fn applies_error_policy() {
+        let timestamps: Vec<_> = items()
+            .into_iter()
+            .ok_filter(|m| m.timestamp != 2)
+            .ok_map(|m| m.timestamp)
+            .collect();
+        assert!(timestamps[1].is_err());
+        assert_eq!(timestamps[2].as_ref().unwrap(), &3);
+
+        let mut handled = items().into_iter().on_error(OnError::FailFast);
+        assert_eq!(handled.by_ref().count(), 1);
+        assert!(handled.error().is_some());
+
+        let seen = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&seen);
+        let callback = OnError::Callback(Box::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+        let mut handled = items().into_iter().on_error(callback);
+        assert_eq!(handled.by_ref().count(), 3);
+        assert_eq!((handled.errors(), seen.load(Ordering::Relaxed)), (1, 1));
+
+        let summary = items().into_iter().collect_summary();
+        assert_eq!((summary.ok, summary.errors), (3, 1));
+    }
+}
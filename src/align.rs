@@ -0,0 +1,103 @@
+use std::io::{Chain, Cursor, Read};
+
+use crate::version::length_v50;
+use crate::{Error, Result};
+
+// frames whose length prefixes must match their message types in a row
+const CONSECUTIVE: usize = 4;
+
+/// A reader starting at a message boundary found by [`align`]: the bytes
+/// read ahead while searching, followed by the rest of the input
+pub type Aligned<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// Find the first message boundary in a stream that may start part-way
+/// through a message, e.g. after seeking to an arbitrary offset in a file
+/// or joining a stream mid-session.
+///
+/// A boundary is taken to be an offset from which several consecutive
+/// frames have the length prefix of their message type (fewer, if they run
+/// exactly to the end of the input). Returns the number of bytes skipped
+/// and a reader starting at the boundary, from which to build a
+/// [`MessageStream`](crate::MessageStream).
+pub fn align<R: Read>(mut reader: R) -> Result<(u64, Aligned<R>)> {
+    let mut buf = Vec::new();
+    let mut skipped = 0;
+    let mut eof = false;
+    loop {
+        let mut start = 0;
+        while start < buf.len() {
+            match boundary_at(&buf[start..], eof) {
+                Some(true) => {
+                    buf.drain(..start);
+                    return Ok((skipped + start as u64, Cursor::new(buf).chain(reader)));
+                }
+                Some(false) => start += 1,
+                None => break,
+            }
+        }
+        buf.drain(..start);
+        skipped += start as u64;
+        if eof {
+            if skipped == 0 {
+                // empty input
+                return Ok((0, Cursor::new(buf).chain(reader)));
+            }
+            return Err(Error::Parse(format!(
+                "no message boundary found in {} bytes",
+                skipped
+            )));
+        }
+        let mut chunk = [0; 4096];
+        let n = reader.read(&mut chunk)?;
+        eof = n == 0;
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+// whether a run of well-framed messages starts the input, `None` if more
+// input is needed to tell
+fn boundary_at(buf: &[u8], eof: bool) -> Option<bool> {
+    let mut at = 0;
+    for _ in 0..CONSECUTIVE {
+        let rest = &buf[at..];
+        if rest.is_empty() && eof {
+            return Some(at > 0);
+        }
+        if rest.len() < 3 {
+            return if eof { Some(false) } else { None };
+        }
+        let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        if length_v50(rest[2]) != Some(len) {
+            return Some(false);
+        }
+        if rest.len() < 2 + len {
+            return if eof { Some(false) } else { None };
+        }
+        at += 2 + len;
+    }
+    Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+
+    #[test]
+    fn finds_boundary_mid_stream() {
+        let mut data = Vec::new();
+        for ts in 0..10u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        // start part-way through the third message
+        let (skipped, reader) = align(&data[33..]).unwrap();
+        assert_eq!(skipped, 9);
+        let timestamps: Vec<_> = MessageStream::from_reader(reader)
+            .map(|m| m.unwrap().timestamp)
+            .collect();
+        assert_eq!(timestamps, (3..10).collect::<Vec<_>>());
+        assert!(align(&[0, 12, b'S', 1][..]).is_err());
+    }
+}
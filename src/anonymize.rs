@@ -0,0 +1,322 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use crate::{ArrayString4, ArrayString8, Body, Message, Price4, Result};
+
+/// Rewrites a capture so it can be shared without revealing the licensed
+/// data it came from, while keeping its structure realistic.
+///
+/// - Symbols and MPIDs are replaced by random codes of the same length,
+///   consistently across the capture.
+/// - Prices of each symbol are scaled by a random factor between 0.5 and
+///   2, and rounded to whole cents above $1, so the relative shape of the
+///   book is preserved.
+/// - Share counts of each symbol are multiplied by a random whole number
+///   from 1 to 3, so executions and cancels still add up to the orders
+///   they apply to.
+/// - Order references and match numbers are renumbered from 1 in order of
+///   first appearance.
+///
+/// Timestamps, message types and stock locates are kept, as are the
+/// bodies of [extension](crate::ParserRegistry) messages, which are
+/// opaque. The output is deterministic for a given seed, so keep the seed
+/// private.
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    seed: u64,
+    symbols: HashMap<ArrayString8, ArrayString8>,
+    mpids: HashMap<ArrayString4, ArrayString4>,
+    // codes handed out, to keep the mappings one-to-one
+    used: HashSet<String>,
+    references: HashMap<u64, u64>,
+    matches: HashMap<u64, u64>,
+}
+
+impl Anonymizer {
+    pub fn new(seed: u64) -> Anonymizer {
+        Anonymizer {
+            seed,
+            symbols: HashMap::new(),
+            mpids: HashMap::new(),
+            used: HashSet::new(),
+            references: HashMap::new(),
+            matches: HashMap::new(),
+        }
+    }
+
+    /// Anonymize one message
+    pub fn apply(&mut self, msg: &Message) -> Message {
+        let locate = msg.stock_locate;
+        let mut body = msg.body.clone();
+        match body {
+            Body::AddOrder(ref mut add) => {
+                add.reference = self.reference(add.reference);
+                add.shares = self.shares(locate, add.shares);
+                add.stock = self.symbol(add.stock);
+                add.price = self.price(locate, add.price);
+                add.mpid = add.mpid.map(|mpid| self.mpid(mpid));
+            }
+            Body::BrokenTrade {
+                ref mut match_number,
+            } => *match_number = self.match_number(*match_number),
+            Body::CrossTrade(ref mut cross) => {
+                cross.shares = self.shares64(locate, cross.shares);
+                cross.stock = self.symbol(cross.stock);
+                cross.cross_price = self.price(locate, cross.cross_price);
+                cross.match_number = self.match_number(cross.match_number);
+            }
+            Body::DeleteOrder { ref mut reference } => *reference = self.reference(*reference),
+            Body::Imbalance(ref mut imbalance) => {
+                imbalance.paired_shares = self.shares64(locate, imbalance.paired_shares);
+                imbalance.imbalance_shares = self.shares64(locate, imbalance.imbalance_shares);
+                imbalance.stock = self.symbol(imbalance.stock);
+                imbalance.far_price = self.price(locate, imbalance.far_price);
+                imbalance.near_price = self.price(locate, imbalance.near_price);
+                imbalance.current_ref_price = self.price(locate, imbalance.current_ref_price);
+            }
+            Body::IpoQuotingPeriod(ref mut ipo) => {
+                ipo.stock = self.symbol(ipo.stock);
+                ipo.price = self.price(locate, ipo.price);
+            }
+            Body::LULDAuctionCollar {
+                ref mut stock,
+                ref mut ref_price,
+                ref mut upper_price,
+                ref mut lower_price,
+                ..
+            } => {
+                *stock = self.symbol(*stock);
+                *ref_price = self.price(locate, *ref_price);
+                *upper_price = self.price(locate, *upper_price);
+                *lower_price = self.price(locate, *lower_price);
+            }
+            Body::NonCrossTrade(ref mut trade) => {
+                // non-displayed orders are not otherwise referred to
+                trade.reference = self.reference(trade.reference);
+                trade.shares = self.shares(locate, trade.shares);
+                trade.stock = self.symbol(trade.stock);
+                trade.price = self.price(locate, trade.price);
+                trade.match_number = self.match_number(trade.match_number);
+            }
+            Body::OrderCancelled {
+                ref mut reference,
+                ref mut cancelled,
+            } => {
+                *reference = self.reference(*reference);
+                *cancelled = self.shares(locate, *cancelled);
+            }
+            Body::OrderExecuted {
+                ref mut reference,
+                ref mut executed,
+                ref mut match_number,
+            } => {
+                *reference = self.reference(*reference);
+                *executed = self.shares(locate, *executed);
+                *match_number = self.match_number(*match_number);
+            }
+            Body::OrderExecutedWithPrice {
+                ref mut reference,
+                ref mut executed,
+                ref mut match_number,
+                ref mut price,
+                ..
+            } => {
+                *reference = self.reference(*reference);
+                *executed = self.shares(locate, *executed);
+                *match_number = self.match_number(*match_number);
+                *price = self.price(locate, *price);
+            }
+            Body::ParticipantPosition(ref mut position) => {
+                position.mpid = self.mpid(position.mpid);
+                position.stock = self.symbol(position.stock);
+            }
+            Body::RegShoRestriction { ref mut stock, .. }
+            | Body::TradingAction { ref mut stock, .. } => *stock = self.symbol(*stock),
+            Body::ReplaceOrder(ref mut replace) => {
+                replace.old_reference = self.reference(replace.old_reference);
+                replace.new_reference = self.reference(replace.new_reference);
+                replace.shares = self.shares(locate, replace.shares);
+                replace.price = self.price(locate, replace.price);
+            }
+            Body::RetailPriceImprovementIndicator(ref mut rpi) => {
+                rpi.stock = self.symbol(rpi.stock);
+            }
+            Body::StockDirectory(ref mut dir) => dir.stock = self.symbol(dir.stock),
+            Body::Breach(_)
+            | Body::MwcbDeclineLevel { .. }
+            | Body::SystemEvent { .. }
+            | Body::Extension(_) => {}
+        }
+        Message { body, ..*msg }
+    }
+
+    /// Anonymize a stream of messages, writing them out as length-prefixed
+    /// ITCH 5.0. Returns the number of messages written.
+    pub fn rewrite<I, W>(&mut self, messages: I, mut writer: W) -> Result<u64>
+    where
+        I: IntoIterator<Item = Result<Message>>,
+        W: Write,
+    {
+        let mut buf = Vec::new();
+        let mut count = 0;
+        for msg in messages {
+            buf.clear();
+            self.apply(&msg?).encode(&mut buf);
+            writer.write_all(&buf)?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    // a pseudo-random value for a key, fixed by the seed (splitmix64)
+    fn random(&self, key: u64) -> u64 {
+        let mut z = self.seed ^ key.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // an unused code of upper case letters, of the given length unless
+    // codes of that length are running out
+    fn code(&mut self, mut len: usize, mut key: u64) -> String {
+        let mut attempts = 0u32;
+        loop {
+            attempts += 1;
+            if attempts.is_multiple_of(64) && len < 8 {
+                len += 1;
+            }
+            let mut r = self.random(key);
+            let code: String = (0..len)
+                .map(|_| {
+                    let c = (b'A' + (r % 26) as u8) as char;
+                    r /= 26;
+                    c
+                })
+                .collect();
+            if self.used.insert(code.clone()) {
+                return code;
+            }
+            key = key.wrapping_add(1);
+        }
+    }
+
+    fn symbol(&mut self, stock: ArrayString8) -> ArrayString8 {
+        if let Some(&mapped) = self.symbols.get(&stock) {
+            return mapped;
+        }
+        let len = stock.trim_end().len().clamp(1, 8);
+        let key = u64::from_be_bytes(stock.as_bytes().try_into().unwrap_or_default());
+        let code = self.code(len, key);
+        // codes are at most 8 letters
+        let mapped = ArrayString8::from(&format!("{:<8}", code)).unwrap_or_default();
+        self.symbols.insert(stock, mapped);
+        mapped
+    }
+
+    fn mpid(&mut self, mpid: ArrayString4) -> ArrayString4 {
+        if let Some(&mapped) = self.mpids.get(&mpid) {
+            return mapped;
+        }
+        let key = u32::from_be_bytes(mpid.as_bytes().try_into().unwrap_or_default());
+        // MPIDs and symbols share the code space, so both stay one-to-one
+        let code = self.code(4, u64::from(key) << 32);
+        let mapped = ArrayString4::from(&code).unwrap_or_default();
+        self.mpids.insert(mpid, mapped);
+        mapped
+    }
+
+    fn reference(&mut self, reference: u64) -> u64 {
+        let next = self.references.len() as u64 + 1;
+        *self.references.entry(reference).or_insert(next)
+    }
+
+    fn match_number(&mut self, match_number: u64) -> u64 {
+        let next = self.matches.len() as u64 + 1;
+        *self.matches.entry(match_number).or_insert(next)
+    }
+
+    fn price(&self, locate: u16, price: Price4) -> Price4 {
+        let raw = price.raw();
+        // zero means no price, e.g. in imbalance messages
+        if raw == 0 {
+            return price;
+        }
+        // a factor in [0.5, 2), keyed apart from the share multiplier
+        let r = self.random(u64::from(locate) | 1 << 32) >> 11;
+        let factor = 2f64.powf(r as f64 / (1u64 << 53) as f64 * 2.0 - 1.0);
+        let mut scaled = (f64::from(raw) * factor).round() as u64;
+        if scaled >= u64::from(Price4::SCALE) {
+            scaled = (scaled + 50) / 100 * 100;
+        }
+        Price4::from(scaled.clamp(1, u64::from(u32::MAX)) as u32)
+    }
+
+    fn multiplier(&self, locate: u16) -> u64 {
+        1 + self.random(u64::from(locate) | 2 << 32) % 3
+    }
+
+    fn shares(&self, locate: u16, shares: u32) -> u32 {
+        (u64::from(shares) * self.multiplier(locate)).min(u64::from(u32::MAX)) as u32
+    }
+
+    fn shares64(&self, locate: u16, shares: u64) -> u64 {
+        shares.saturating_mul(self.multiplier(locate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{BookManager, MessageStream, Side};
+
+    #[test]
+    fn scrambles_consistently() {
+        let tagged = |tag, ts, body| {
+            Ok(Message {
+                tag,
+                ..msg(ts, body)
+            })
+        };
+        let messages = vec![
+            tagged(b'A', 1, add(1_000, Side::Buy, 100, 150_000)),
+            tagged(b'A', 2, add(2_000, Side::Buy, 200, 149_900)),
+            tagged(
+                b'E',
+                3,
+                Body::OrderExecuted {
+                    reference: 1_000,
+                    executed: 100,
+                    match_number: 77,
+                },
+            ),
+        ];
+        let mut out = Vec::new();
+        let count = Anonymizer::new(42).rewrite(messages, &mut out).unwrap();
+        assert_eq!(count, 3);
+
+        let rewritten: Vec<_> = MessageStream::from_reader(&out[..])
+            .map(|m| m.unwrap())
+            .collect();
+        let Body::AddOrder(ref first) = rewritten[0].body else {
+            panic!("expected an add order");
+        };
+        let Body::AddOrder(ref second) = rewritten[1].body else {
+            panic!("expected an add order");
+        };
+        assert_eq!((first.reference, second.reference), (1, 2));
+        assert_ne!(first.stock.as_str(), "ZXZZT   ");
+        assert_eq!(first.stock, second.stock);
+        assert_eq!(first.stock.trim_end().len(), 5);
+        assert!(first.price > second.price);
+        assert_eq!(first.price.raw() % 100, 0);
+
+        // the execution still fills the first order completely
+        let mut books = BookManager::new();
+        for msg in &rewritten {
+            books.update(msg);
+        }
+        assert_eq!(books.orders().len(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Envelope, MoldPacket, Packet, PacketSource, Result};
+
+// packets buffered from each line ahead of the arbiter
+const LINE_BUFFER: usize = 1024;
+const DEFAULT_GAP_TIMEOUT: Duration = Duration::from_millis(10);
+// first arrivals remembered to measure how late the other line's copies are
+const ARRIVALS: usize = 10_000;
+
+/// One of the two lines of an [`ArbitratedReceiver`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Line {
+    A,
+    B,
+}
+
+impl Line {
+    fn index(self) -> usize {
+        match self {
+            Line::A => 0,
+            Line::B => 1,
+        }
+    }
+}
+
+/// Statistics of one line of an [`ArbitratedReceiver`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LineStats {
+    pub packets: u64,
+    /// Messages received, whichever line delivered them first
+    pub messages: u64,
+    /// Messages this line delivered before the other
+    pub first: u64,
+    /// Sequence gaps on this line alone, whether or not the other line
+    /// filled them
+    pub gaps: u64,
+    /// Messages missing in this line's gaps
+    pub missed: u64,
+    /// Packets which arrived after the other line's copy
+    pub late: u64,
+    /// Total and largest time behind the other line of late packets with
+    /// receive timestamps, in nanoseconds
+    pub lag_total: u64,
+    pub lag_max: u64,
+    /// Errors from the source or malformed packets
+    pub errors: u64,
+}
+
+impl LineStats {
+    /// Mean time behind the other line of late packets, in nanoseconds
+    pub fn mean_lag(&self) -> Option<u64> {
+        self.lag_total.checked_div(self.late)
+    }
+}
+
+/// Decodes MoldUDP64 packets from two redundant lines of the same feed,
+/// such as the A and B multicast groups, yielding each message from
+/// whichever line delivers it first.
+///
+/// Each line is read on its own thread, so sources may block, like a
+/// `UdpSocket`. When one line skips ahead, its packets are held back until
+/// the other line fills the gap, and messages are only lost when both
+/// lines miss them, or when the gap is not filled within the
+/// [`gap_timeout`](ArbitratedReceiver::gap_timeout). Lost messages are
+/// counted like a [`MoldReceiver`](crate::MoldReceiver)'s gaps, and
+/// [`LineStats`] are kept for each line, with how far behind the other it
+/// runs.
+///
+/// Iteration ends at an end of session packet, or once both sources are
+/// exhausted. A line's thread exits when its source is exhausted, or after
+/// the receiver is dropped and the source delivers another packet.
+///
+/// ```ignore
+/// let line_a = UdpSocket::bind("0.0.0.0:26400")?;
+/// let line_b = UdpSocket::bind("0.0.0.0:26401")?;
+/// let mut receiver = itchy::ArbitratedReceiver::new(line_a, line_b);
+/// for envelope in receiver.by_ref() {
+///     println!("{:?}", envelope?.message);
+/// }
+/// println!("{:?}", receiver.stats(itchy::Line::B));
+/// ```
+#[derive(Debug)]
+pub struct ArbitratedReceiver {
+    packets: Receiver<(Line, Option<Result<Packet>>)>,
+    pending: VecDeque<Result<Envelope>>,
+    expected: Option<u64>,
+    gaps: u64,
+    missed: u64,
+    stats: [LineStats; 2],
+    // next sequence number of each line, and whether it is still open
+    line_expected: [Option<u64>; 2],
+    open: [bool; 2],
+    // packets past a gap, by sequence number
+    held: BTreeMap<u64, (Line, Packet)>,
+    gap_timeout: Duration,
+    waiting_since: Option<Instant>,
+    // receive time of the first copy of recent packets, by sequence number
+    arrivals: VecDeque<(u64, u64)>,
+    ended: bool,
+}
+
+impl ArbitratedReceiver {
+    pub fn new<A, B>(line_a: A, line_b: B) -> ArbitratedReceiver
+    where
+        A: PacketSource + Send + 'static,
+        B: PacketSource + Send + 'static,
+    {
+        let (tx, packets) = mpsc::sync_channel(LINE_BUFFER);
+        spawn_line(line_a, Line::A, tx.clone());
+        spawn_line(line_b, Line::B, tx);
+        ArbitratedReceiver {
+            packets,
+            pending: VecDeque::new(),
+            expected: None,
+            gaps: 0,
+            missed: 0,
+            stats: [LineStats::default(); 2],
+            line_expected: [None; 2],
+            open: [true; 2],
+            held: BTreeMap::new(),
+            gap_timeout: DEFAULT_GAP_TIMEOUT,
+            waiting_since: None,
+            arrivals: VecDeque::new(),
+            ended: false,
+        }
+    }
+
+    /// How long to wait for one line to fill a gap in the other before
+    /// giving up on the missing messages (10 ms by default)
+    pub fn gap_timeout(mut self, timeout: Duration) -> Self {
+        self.gap_timeout = timeout;
+        self
+    }
+
+    pub fn stats(&self, line: Line) -> &LineStats {
+        &self.stats[line.index()]
+    }
+
+    /// Number of sequence gaps missed by both lines
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Number of messages missed by both lines
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Sequence number of the next message expected, once a packet has been seen
+    pub fn expected(&self) -> Option<u64> {
+        self.expected
+    }
+
+    fn receive(&mut self, line: Line, packet: Packet) -> Result<()> {
+        let mold = MoldPacket::parse(&packet.data)?;
+        let (sequence, next) = (mold.sequence, mold.next_sequence());
+        let count = next - sequence;
+        self.ended |= mold.is_end_of_session();
+        let stats = &mut self.stats[line.index()];
+        stats.packets += 1;
+        stats.messages += count;
+        let line_expected = self.line_expected[line.index()].unwrap_or(sequence);
+        if sequence > line_expected {
+            stats.gaps += 1;
+            stats.missed += sequence - line_expected;
+        }
+        self.line_expected[line.index()] = Some(line_expected.max(next));
+
+        let expected = *self.expected.get_or_insert(sequence);
+        let held = self.held.get(&sequence).map(|(_, held)| held.receive_ts);
+        if count == 0 {
+            // heartbeats only move the line on
+        } else if next <= expected || held.is_some() {
+            // the other line delivered this packet already
+            stats.late += 1;
+            let first = held.flatten().or_else(|| {
+                let ix = self.arrivals.partition_point(|&(seq, _)| seq < sequence);
+                let (seq, ts) = *self.arrivals.get(ix)?;
+                (seq == sequence).then_some(ts)
+            });
+            if let (Some(first), Some(ts)) = (first, packet.receive_ts) {
+                let lag = ts.saturating_sub(first);
+                stats.lag_total += lag;
+                stats.lag_max = stats.lag_max.max(lag);
+            }
+        } else if sequence <= expected {
+            self.deliver(line, &packet);
+        } else {
+            self.held.insert(sequence, (line, packet));
+        }
+        self.resolve(false);
+        Ok(())
+    }
+
+    // yield the messages of a packet not yet seen
+    fn deliver(&mut self, line: Line, packet: &Packet) {
+        let Ok(mold) = MoldPacket::parse(&packet.data) else {
+            return;
+        };
+        let expected = self.expected.unwrap_or(mold.sequence);
+        if mold.next_sequence() <= expected {
+            return;
+        }
+        let skip = expected.saturating_sub(mold.sequence);
+        self.stats[line.index()].first += mold.next_sequence() - mold.sequence - skip;
+        if let Some(ts) = packet.receive_ts {
+            if self.arrivals.len() == ARRIVALS {
+                self.arrivals.pop_front();
+            }
+            self.arrivals.push_back((mold.sequence, ts));
+        }
+        self.expected = Some(mold.next_sequence());
+        self.pending
+            .extend(mold.envelopes(packet.receive_ts).skip(skip as usize));
+    }
+
+    // deliver held packets which follow on, and skip over gaps which both
+    // lines have passed, or every gap when forced
+    fn resolve(&mut self, force: bool) {
+        loop {
+            while let Some(entry) = self.held.first_entry() {
+                if Some(*entry.key()) > self.expected {
+                    break;
+                }
+                let (line, packet) = entry.remove();
+                self.deliver(line, &packet);
+            }
+            let Some(expected) = self.expected else {
+                return;
+            };
+            let lines = self.line_expected.iter().zip(self.open);
+            let passed = lines
+                .clone()
+                .all(|(&next, open)| !open || next > Some(expected));
+            let resume = match self.held.keys().next() {
+                Some(&sequence) => Some(sequence),
+                // messages up to the line furthest behind may still come
+                None if force => self.line_expected.iter().flatten().max().copied(),
+                None => lines
+                    .filter(|(_, open)| *open)
+                    .filter_map(|(&next, _)| next)
+                    .min(),
+            };
+            let waiting = resume.is_some_and(|resume| resume > expected);
+            match resume {
+                Some(resume) if resume > expected && (passed || force) => {
+                    self.gaps += 1;
+                    self.missed += resume - expected;
+                    self.expected = Some(resume);
+                    self.waiting_since = None;
+                }
+                _ => {
+                    if !waiting {
+                        self.waiting_since = None;
+                    } else if self.waiting_since.is_none() {
+                        self.waiting_since = Some(Instant::now());
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for ArbitratedReceiver {
+    type Item = Result<Envelope>;
+
+    fn next(&mut self) -> Option<Result<Envelope>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if self.ended || self.open == [false; 2] {
+                if self.held.is_empty() {
+                    return None;
+                }
+                self.resolve(true);
+                continue;
+            }
+            let received = match self.waiting_since {
+                Some(since) => {
+                    let timeout = self.gap_timeout.saturating_sub(since.elapsed());
+                    match self.packets.recv_timeout(timeout) {
+                        Ok(received) => received,
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.resolve(true);
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return None,
+                    }
+                }
+                // both senders are only dropped once their lines are closed
+                None => self.packets.recv().ok()?,
+            };
+            let (line, packet) = received;
+            let result = match packet {
+                Some(Ok(packet)) => self.receive(line, packet),
+                Some(Err(e)) => Err(e),
+                None => {
+                    self.open[line.index()] = false;
+                    self.resolve(false);
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                self.stats[line.index()].errors += 1;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn spawn_line<S>(mut source: S, line: Line, tx: SyncSender<(Line, Option<Result<Packet>>)>)
+where
+    S: PacketSource + Send + 'static,
+{
+    let name = match line {
+        Line::A => "itchy-line-a",
+        Line::B => "itchy-line-b",
+    };
+    // like `thread::spawn`, panics if the OS cannot create a thread
+    #[allow(clippy::expect_used)]
+    thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            while let Some(packet) = source.next_packet() {
+                if tx.send((line, Some(packet))).is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send((line, None));
+        })
+        .expect("failed to spawn line thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::mold::tests::mold_packet;
+
+    fn packet(sequence: u64, count: usize, receive_ts: u64) -> Packet {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        let messages = vec![&event[..]; count];
+        Packet {
+            data: mold_packet("SESSION", sequence, &messages),
+            receive_ts: Some(receive_ts),
+        }
+    }
+
+    #[test]
+    fn takes_first_copy_of_each_message() {
+        // A misses messages 3 and 4, and B misses 5 to 7 and lags by 10ns
+        let line_a = vec![packet(1, 2, 100), packet(5, 3, 300), packet(8, 1, 400)];
+        let line_b = vec![packet(1, 2, 110), packet(3, 2, 210), packet(8, 1, 410)];
+        // line A finishes first, so its copies are first in the channel
+        let (tx, rx) = mpsc::channel();
+        let line_b = DelayedSource(rx, line_b.into_iter());
+        let mut receiver = ArbitratedReceiver::new(Notify(tx, line_a.into_iter()), line_b)
+            .gap_timeout(Duration::from_secs(10));
+        let sequences: Vec<_> = receiver.by_ref().map(|e| e.unwrap().sequence).collect();
+        assert_eq!(sequences, (1..=8).collect::<Vec<_>>());
+        assert_eq!(receiver.gaps(), 0);
+
+        let (a, b) = (receiver.stats(Line::A), receiver.stats(Line::B));
+        assert_eq!((a.packets, a.messages, a.first), (3, 6, 6));
+        assert_eq!((a.gaps, a.missed, a.late), (1, 2, 0));
+        assert_eq!((b.packets, b.messages, b.first), (3, 5, 2));
+        assert_eq!((b.gaps, b.missed, b.late), (1, 3, 2));
+        assert_eq!((b.mean_lag(), b.lag_max), (Some(10), 10));
+    }
+
+    // a source which signals when it is exhausted
+    struct Notify(mpsc::Sender<()>, std::vec::IntoIter<Packet>);
+
+    impl PacketSource for Notify {
+        fn next_packet(&mut self) -> Option<Result<Packet>> {
+            let packet = self.1.next();
+            if packet.is_none() {
+                let _ = self.0.send(());
+            }
+            packet.map(Ok)
+        }
+    }
+
+    // a source which waits for a signal before delivering anything
+    struct DelayedSource(mpsc::Receiver<()>, std::vec::IntoIter<Packet>);
+
+    impl PacketSource for DelayedSource {
+        fn next_packet(&mut self) -> Option<Result<Packet>> {
+            let _ = self.0.recv();
+            self.1.next().map(Ok)
+        }
+    }
+}
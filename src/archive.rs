@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read, Write};
+use std::ops::ControlFlow;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::{decode_message, Error, Message, MessageSink, Result};
+
+const MAGIC: &[u8; 8] = b"ITCHARC1";
+
+// bytes before the body: tag, stock locate, tracking number, timestamp
+const HEADER_LEN: usize = 11;
+
+// body length and offset of the stock symbol within the body, by tag
+fn layout(tag: u8) -> Option<(usize, Option<usize>)> {
+    Some(match tag {
+        b'A' => (25, Some(13)),
+        b'B' => (8, None),
+        b'C' => (25, None),
+        b'D' => (8, None),
+        b'E' => (20, None),
+        b'F' => (29, Some(13)),
+        b'H' => (14, Some(0)),
+        b'I' => (39, Some(17)),
+        b'J' => (24, Some(0)),
+        b'K' => (17, Some(0)),
+        b'L' => (15, Some(4)),
+        b'N' => (9, Some(0)),
+        b'P' => (33, Some(13)),
+        b'Q' => (29, Some(8)),
+        b'R' => (28, Some(0)),
+        b'S' => (1, None),
+        b'U' => (24, None),
+        b'V' => (24, None),
+        b'W' => (1, None),
+        b'X' => (12, None),
+        b'Y' => (9, Some(0)),
+        _ => return None,
+    })
+}
+
+/// Writes messages in a compact archival format, read back with
+/// [`ArchiveReader`].
+///
+/// Each message is stored as its timestamp delta from the previous message
+/// and its wire format body, with the stock symbol replaced by an index
+/// into a dictionary built up as the archive is written. The result is
+/// gzip-compressed. As a [`MessageSink`], write errors stop the stream and
+/// are kept for [`finish`](ArchiveWriter::finish).
+pub struct ArchiveWriter<W: Write> {
+    out: GzEncoder<W>,
+    last_timestamp: u64,
+    symbols: HashMap<[u8; 8], u64>,
+    frame: Vec<u8>,
+    record: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(writer: W) -> io::Result<ArchiveWriter<W>> {
+        let mut out = GzEncoder::new(writer, Compression::best());
+        out.write_all(MAGIC)?;
+        Ok(ArchiveWriter {
+            out,
+            last_timestamp: 0,
+            symbols: HashMap::new(),
+            frame: Vec::with_capacity(64),
+            record: Vec::with_capacity(64),
+            error: None,
+        })
+    }
+
+    pub fn write(&mut self, msg: &Message) -> io::Result<()> {
+        self.frame.clear();
+        msg.encode_unframed(&mut self.frame);
+        let Some((body_len, stock_at)) = layout(msg.tag) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot archive message type '{}'", msg.tag as char),
+            ));
+        };
+        debug_assert_eq!(self.frame.len(), HEADER_LEN + body_len);
+        self.record.clear();
+        let delta = msg.timestamp.wrapping_sub(self.last_timestamp) as i64;
+        self.last_timestamp = msg.timestamp;
+        put_varint(&mut self.record, zigzag(delta));
+        self.record.push(msg.tag);
+        put_varint(&mut self.record, msg.stock_locate as u64);
+        put_varint(&mut self.record, msg.tracking_number as u64);
+        let body = &self.frame[HEADER_LEN..];
+        match stock_at {
+            Some(at) => {
+                self.record.extend_from_slice(&body[..at]);
+                let mut stock = [0; 8];
+                stock.copy_from_slice(&body[at..at + 8]);
+                let next = self.symbols.len() as u64;
+                let index = *self.symbols.entry(stock).or_insert(next);
+                put_varint(&mut self.record, index);
+                if index == next {
+                    self.record.extend_from_slice(&stock);
+                }
+                self.record.extend_from_slice(&body[at + 8..]);
+            }
+            None => self.record.extend_from_slice(body),
+        }
+        self.out.write_all(&self.record)
+    }
+
+    /// Finish compression and return the writer, or the first error met as
+    /// a sink
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.finish()
+    }
+}
+
+impl<W: Write> MessageSink for ArchiveWriter<W> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.write(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// Reads back the messages written by an [`ArchiveWriter`]
+pub struct ArchiveReader<R: Read> {
+    input: BufReader<GzDecoder<R>>,
+    last_timestamp: u64,
+    symbols: Vec<[u8; 8]>,
+    frame: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    pub fn new(reader: R) -> Result<ArchiveReader<R>> {
+        let mut input = BufReader::new(GzDecoder::new(reader));
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Parse("not an itchy archive".into()));
+        }
+        Ok(ArchiveReader {
+            input,
+            last_timestamp: 0,
+            symbols: Vec::new(),
+            frame: Vec::with_capacity(64),
+            done: false,
+        })
+    }
+
+    fn read_message(&mut self) -> Result<Option<Message>> {
+        let Some(delta) = self.read_varint(true)? else {
+            return Ok(None);
+        };
+        let timestamp = self.last_timestamp.wrapping_add(unzigzag(delta) as u64) & 0xffff_ffff_ffff;
+        self.last_timestamp = timestamp;
+        let tag = self.read_byte()?;
+        let (body_len, stock_at) = layout(tag).ok_or_else(|| {
+            Error::Parse(format!("unknown message type '{}' in archive", tag as char))
+        })?;
+        let locate = self.read_u16()?;
+        let tracking = self.read_u16()?;
+        self.frame.clear();
+        self.frame.push(tag);
+        self.frame.extend_from_slice(&locate.to_be_bytes());
+        self.frame.extend_from_slice(&tracking.to_be_bytes());
+        self.frame.extend_from_slice(&timestamp.to_be_bytes()[2..]);
+        let mut rest = body_len;
+        if let Some(at) = stock_at {
+            self.read_into_frame(at)?;
+            let index = self.read_value()? as usize;
+            if index == self.symbols.len() {
+                let mut stock = [0; 8];
+                self.input.read_exact(&mut stock)?;
+                self.symbols.push(stock);
+            }
+            let stock = self
+                .symbols
+                .get(index)
+                .ok_or_else(|| Error::Parse(format!("unknown symbol index {}", index)))?;
+            self.frame.extend_from_slice(stock);
+            rest -= at + 8;
+        }
+        self.read_into_frame(rest)?;
+        decode_message(&self.frame).map(Some)
+    }
+
+    fn read_into_frame(&mut self, len: usize) -> io::Result<()> {
+        let start = self.frame.len();
+        self.frame.resize(start + len, 0);
+        self.input.read_exact(&mut self.frame[start..])
+    }
+
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        self.input.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let value = self.read_value()?;
+        u16::try_from(value).map_err(|_| Error::Parse(format!("{} out of range", value)))
+    }
+
+    // returns None at a clean end of input if `at_boundary`
+    fn read_value(&mut self) -> io::Result<u64> {
+        self.read_varint(false)?
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn read_varint(&mut self, at_boundary: bool) -> io::Result<Option<u64>> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0];
+            if self.input.read(&mut byte)? == 0 {
+                if at_boundary && shift == 0 {
+                    return Ok(None);
+                }
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            value |= ((byte[0] & 0x7f) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "varint too long",
+        ))
+    }
+}
+
+impl<R: Read> Iterator for ArchiveReader<R> {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_message().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, Side};
+
+    #[test]
+    fn round_trips_messages() {
+        let tagged = |tag, ts, body| Message {
+            tag,
+            ..msg(ts, body)
+        };
+        let messages = vec![
+            tagged(b'A', 1_000, add(1, Side::Buy, 100, 10_000)),
+            tagged(b'A', 2_500, add(2, Side::Sell, 50, 10_100)),
+            tagged(b'D', 2_400, Body::DeleteOrder { reference: 1 }),
+            tagged(b'A', 9_000_000, add(3, Side::Sell, 50, 10_100)),
+        ];
+        let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+        for m in &messages {
+            writer.write(m).unwrap();
+        }
+        let archive = writer.finish().unwrap();
+
+        let reader = ArchiveReader::new(&archive[..]).unwrap();
+        let read: Vec<_> = reader.map(|m| m.unwrap()).collect();
+        assert_eq!(read, messages);
+
+        assert!(ArchiveReader::new(&b"not gzip"[..]).is_err());
+    }
+}
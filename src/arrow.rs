@@ -0,0 +1,378 @@
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    Decimal128Builder, Float64Builder, StringBuilder, TimestampNanosecondBuilder, UInt16Builder,
+    UInt32Builder, UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+
+use crate::clock::Session;
+use crate::export::{ExportOptions, Fields, PriceFormat, TimestampFormat};
+use crate::{Message, MessageSink, Side};
+
+// digits of the largest `Price4`, as a decimal
+const PRICE_PRECISION: u8 = 10;
+
+// zone of `TimestampFormat::Iso8601` columns, which hold UTC instants
+const TIME_ZONE: &str = "America/New_York";
+
+fn price_type(options: ExportOptions) -> DataType {
+    let places = options.scaled(0.into()).decimal_places();
+    match (options.prices, places) {
+        (PriceFormat::Fixed, Some(places)) => DataType::Decimal128(PRICE_PRECISION, places as i8),
+        (PriceFormat::Fixed | PriceFormat::Float, _) => DataType::Float64,
+        (PriceFormat::Raw, _) => DataType::UInt32,
+    }
+}
+
+/// Writes messages as an Arrow IPC stream, in record batches with the
+/// columns of [`CsvWriter`](crate::CsvWriter).
+///
+/// The stream can be read as it is written, e.g. by `pyarrow.ipc.open_stream`
+/// or `polars` on the other end of a socket, so the writer can be any
+/// [`Write`], such as a `TcpStream`. Rows are sent once a batch fills
+/// (65,536 rows by default), and the rest by
+/// [`finish`](ArrowStreamWriter::finish), which must be called to end the
+/// stream.
+///
+/// Columns which do not apply to a message type are null. With
+/// [`ExportOptions`], prices are decimals with the places of the price
+/// scale (or floats, if it is not a power of ten), floats or the raw
+/// integers, and timestamps are nanoseconds since midnight or, with
+/// [`TimestampFormat::Iso8601`], UTC timestamps in the `America/New_York`
+/// time zone. As a [`MessageSink`], write errors stop the stream and are
+/// kept for `finish`.
+pub struct ArrowStreamWriter<W: Write> {
+    writer: Option<W>,
+    stream: Option<StreamWriter<W>>,
+    options: ExportOptions,
+    batch_size: usize,
+    columns: Option<Columns>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> ArrowStreamWriter<W> {
+    pub fn new(writer: W) -> ArrowStreamWriter<W> {
+        ArrowStreamWriter {
+            writer: Some(writer),
+            stream: None,
+            options: ExportOptions::default(),
+            batch_size: 65_536,
+            columns: None,
+            error: None,
+        }
+    }
+
+    /// How to represent prices and timestamps, which sets the column types
+    /// of the schema
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Rows per record batch
+    pub fn batch_size(mut self, rows: NonZeroUsize) -> Self {
+        self.batch_size = rows.get();
+        self
+    }
+
+    /// Add one message, sending the batch if it is full
+    pub fn write(&mut self, msg: &Message) -> io::Result<()> {
+        let options = self.options;
+        let columns = self
+            .columns
+            .get_or_insert_with(|| Columns::new(options, self.batch_size));
+        columns.push(msg);
+        if columns.len >= self.batch_size {
+            self.send_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Send the remaining rows and end the stream, returning the writer, or
+    /// the first error met as a sink
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.send_batch()?;
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            // nothing written, but the stream still carries the schema
+            None => self.start()?,
+        };
+        stream.finish().map_err(io_error)?;
+        let mut writer = stream.into_inner().map_err(io_error)?;
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn send_batch(&mut self) -> io::Result<()> {
+        let batch = match self.columns {
+            Some(ref mut columns) if columns.len > 0 => columns.finish().map_err(io_error)?,
+            _ => return Ok(()),
+        };
+        let stream = match self.stream {
+            Some(ref mut stream) => stream,
+            None => {
+                let stream = self.start()?;
+                self.stream.insert(stream)
+            }
+        };
+        stream.write(&batch).map_err(io_error)?;
+        // send the batch now rather than when the writer's buffer fills
+        stream.flush().map_err(io_error)
+    }
+
+    // write the schema, which opens the stream
+    fn start(&mut self) -> io::Result<StreamWriter<W>> {
+        let writer = self
+            .writer
+            .take()
+            .ok_or_else(|| io::Error::other("arrow stream already started"))?;
+        StreamWriter::try_new(writer, &schema(self.options)).map_err(io_error)
+    }
+}
+
+impl<W: Write> MessageSink for ArrowStreamWriter<W> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.write(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+fn io_error(e: ArrowError) -> io::Error {
+    match e {
+        ArrowError::IoError(_, e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+fn schema(options: ExportOptions) -> SchemaRef {
+    let timestamp = match options.timestamps {
+        TimestampFormat::Nanos => DataType::UInt64,
+        TimestampFormat::Iso8601(_) => {
+            DataType::Timestamp(TimeUnit::Nanosecond, Some(TIME_ZONE.into()))
+        }
+    };
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp", timestamp, false),
+        Field::new("tag", DataType::Utf8, false),
+        Field::new("stock_locate", DataType::UInt16, false),
+        Field::new("tracking_number", DataType::UInt16, false),
+        Field::new("stock", DataType::Utf8, true),
+        Field::new("reference", DataType::UInt64, true),
+        Field::new("side", DataType::Utf8, true),
+        Field::new("shares", DataType::UInt64, true),
+        Field::new("price", price_type(options), true),
+        Field::new("match_number", DataType::UInt64, true),
+    ]))
+}
+
+enum Timestamps {
+    Nanos(UInt64Builder),
+    Utc(Session, TimestampNanosecondBuilder),
+}
+
+enum Prices {
+    Fixed(Decimal128Builder),
+    Float(Float64Builder),
+    Raw(UInt32Builder),
+}
+
+// the rows of the batch being built
+struct Columns {
+    schema: SchemaRef,
+    options: ExportOptions,
+    len: usize,
+    timestamp: Timestamps,
+    tag: StringBuilder,
+    stock_locate: UInt16Builder,
+    tracking_number: UInt16Builder,
+    stock: StringBuilder,
+    reference: UInt64Builder,
+    side: StringBuilder,
+    shares: UInt64Builder,
+    price: Prices,
+    match_number: UInt64Builder,
+}
+
+impl Columns {
+    fn new(options: ExportOptions, rows: usize) -> Columns {
+        let timestamp = match options.timestamps {
+            TimestampFormat::Nanos => Timestamps::Nanos(UInt64Builder::with_capacity(rows)),
+            TimestampFormat::Iso8601(session) => Timestamps::Utc(
+                session,
+                TimestampNanosecondBuilder::with_capacity(rows).with_timezone(TIME_ZONE),
+            ),
+        };
+        let price = match price_type(options) {
+            DataType::Float64 => Prices::Float(Float64Builder::with_capacity(rows)),
+            DataType::UInt32 => Prices::Raw(UInt32Builder::with_capacity(rows)),
+            decimal => {
+                Prices::Fixed(Decimal128Builder::with_capacity(rows).with_data_type(decimal))
+            }
+        };
+        Columns {
+            schema: schema(options),
+            options,
+            len: 0,
+            timestamp,
+            tag: StringBuilder::new(),
+            stock_locate: UInt16Builder::with_capacity(rows),
+            tracking_number: UInt16Builder::with_capacity(rows),
+            stock: StringBuilder::new(),
+            reference: UInt64Builder::with_capacity(rows),
+            side: StringBuilder::new(),
+            shares: UInt64Builder::with_capacity(rows),
+            price,
+            match_number: UInt64Builder::with_capacity(rows),
+        }
+    }
+
+    fn push(&mut self, msg: &Message) {
+        let fields = Fields::of(&msg.body);
+        match self.timestamp {
+            Timestamps::Nanos(ref mut b) => b.append_value(msg.timestamp),
+            Timestamps::Utc(session, ref mut b) => {
+                b.append_value(session.to_utc_nanos(msg.timestamp))
+            }
+        }
+        let mut tag = [0; 4];
+        self.tag
+            .append_value((msg.tag as char).encode_utf8(&mut tag));
+        self.stock_locate.append_value(msg.stock_locate);
+        self.tracking_number.append_value(msg.tracking_number);
+        self.stock.append_option(fields.stock.map(str::trim_end));
+        self.reference.append_option(fields.reference);
+        self.side.append_option(fields.side.map(|side| match side {
+            Side::Buy => "B",
+            Side::Sell => "S",
+        }));
+        self.shares.append_option(fields.shares);
+        match self.price {
+            Prices::Fixed(ref mut b) => b.append_option(fields.price.map(|p| p.raw() as i128)),
+            Prices::Float(ref mut b) => {
+                b.append_option(fields.price.map(|p| f64::from(self.options.scaled(p))))
+            }
+            Prices::Raw(ref mut b) => b.append_option(fields.price.map(|p| p.raw())),
+        }
+        self.match_number.append_option(fields.match_number);
+        self.len += 1;
+    }
+
+    // take the rows as a batch, leaving the builders empty
+    fn finish(&mut self) -> Result<RecordBatch, ArrowError> {
+        let timestamp: ArrayRef = match self.timestamp {
+            Timestamps::Nanos(ref mut b) => Arc::new(b.finish()),
+            Timestamps::Utc(_, ref mut b) => Arc::new(b.finish()),
+        };
+        let price: ArrayRef = match self.price {
+            Prices::Fixed(ref mut b) => Arc::new(b.finish()),
+            Prices::Float(ref mut b) => Arc::new(b.finish()),
+            Prices::Raw(ref mut b) => Arc::new(b.finish()),
+        };
+        self.len = 0;
+        RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                timestamp,
+                Arc::new(self.tag.finish()),
+                Arc::new(self.stock_locate.finish()),
+                Arc::new(self.tracking_number.finish()),
+                Arc::new(self.stock.finish()),
+                Arc::new(self.reference.finish()),
+                Arc::new(self.side.finish()),
+                Arc::new(self.shares.finish()),
+                price,
+                Arc::new(self.match_number.finish()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drive;
+    use crate::orders::tests::{add, msg};
+    use crate::Body;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Decimal128Type, UInt64Type};
+    use arrow_array::Array;
+    use arrow_ipc::reader::StreamReader;
+
+    #[test]
+    fn writes_record_batches() {
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 40,
+            match_number: 7,
+        };
+        let tagged = |tag, msg: Message| Ok(Message { tag, ..msg });
+        let messages = vec![
+            tagged(b'A', msg(5, add(1, Side::Buy, 100, 123_456))),
+            tagged(b'E', msg(6, exec)),
+            tagged(b'D', msg(7, Body::DeleteOrder { reference: 1 })),
+        ];
+        let mut writer =
+            ArrowStreamWriter::new(Vec::new()).batch_size(NonZeroUsize::new(2).unwrap());
+        drive(messages, &mut writer).unwrap();
+        let buf = writer.finish().unwrap();
+
+        let batches: Vec<_> = StreamReader::try_new(&buf[..], None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 2);
+        let first = &batches[0];
+        assert_eq!(first.num_rows(), 2);
+        assert_eq!(first.schema(), schema(ExportOptions::default()));
+        let timestamps = first.column(0).as_primitive::<UInt64Type>();
+        assert_eq!(timestamps.values(), &[5, 6]);
+        assert_eq!(first.column(4).as_string::<i32>().value(0), "ZXZZT");
+        assert!(first.column(4).is_null(1));
+        let prices = first.column(8).as_primitive::<Decimal128Type>();
+        assert_eq!(prices.value_as_string(0), "12.3456");
+        assert!(prices.is_null(1));
+        assert_eq!(batches[1].num_rows(), 1);
+    }
+
+    #[test]
+    fn uses_price_scale() {
+        let messages = vec![Ok(msg(5, add(1, Side::Buy, 100, 12_345)))];
+        let options = ExportOptions {
+            price_scale: std::num::NonZeroU32::new(100).unwrap(),
+            ..ExportOptions::default()
+        };
+        let mut writer = ArrowStreamWriter::new(Vec::new()).options(options);
+        drive(messages, &mut writer).unwrap();
+        let buf = writer.finish().unwrap();
+
+        let batch = StreamReader::try_new(&buf[..], None)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let prices = batch.column(8).as_primitive::<Decimal128Type>();
+        assert_eq!(prices.value_as_string(0), "123.45");
+    }
+
+    #[test]
+    fn writes_schema_without_rows() {
+        let buf = ArrowStreamWriter::new(Vec::new()).finish().unwrap();
+        let reader = StreamReader::try_new(&buf[..], None).unwrap();
+        assert_eq!(reader.schema().fields().len(), 10);
+        assert_eq!(reader.count(), 0);
+    }
+}
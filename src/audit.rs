@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::{ArrayString8, Body, Message, OrderError, OrderTracker, OrderUpdate};
+
+/// An integrity problem found by [`OrderAudit`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntegrityIssue {
+    Order(OrderError),
+    /// A new order used the reference of an order which is no longer live
+    ReusedReference {
+        reference: u64,
+    },
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrityIssue::Order(e) => e.fmt(f),
+            IntegrityIssue::ReusedReference { reference } => {
+                write!(f, "retired order reference {} was reused", reference)
+            }
+        }
+    }
+}
+
+/// Per-symbol message counts and integrity problems from an [`OrderAudit`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SymbolIntegrity {
+    pub stock: Option<ArrayString8>,
+    pub adds: u64,
+    pub executions: u64,
+    pub cancels: u64,
+    pub deletes: u64,
+    pub replaces: u64,
+    pub unknown_references: u64,
+    pub overfills: u64,
+    pub duplicate_references: u64,
+    pub reused_references: u64,
+}
+
+impl SymbolIntegrity {
+    /// True if no integrity problems were found for this symbol
+    pub fn is_clean(&self) -> bool {
+        self.unknown_references == 0
+            && self.overfills == 0
+            && self.duplicate_references == 0
+            && self.reused_references == 0
+    }
+}
+
+/// Audits order references for integrity.
+///
+/// Verifies that executions, cancels, deletes and replaces refer to live
+/// orders, that order volumes never go negative, and that references are
+/// never reused once an order is gone. Results are collected per stock locate.
+///
+/// Retired references are remembered for the whole session, so memory use
+/// grows with the number of orders seen.
+#[derive(Debug, Clone, Default)]
+pub struct OrderAudit {
+    tracker: OrderTracker,
+    retired: HashSet<u64>,
+    symbols: BTreeMap<u16, SymbolIntegrity>,
+}
+
+impl OrderAudit {
+    pub fn new() -> OrderAudit {
+        OrderAudit::default()
+    }
+
+    /// Check a message, returning the problem found, if any
+    pub fn check(&mut self, msg: &Message) -> Option<IntegrityIssue> {
+        if let Body::StockDirectory(ref dir) = msg.body {
+            self.symbols.entry(msg.stock_locate).or_default().stock = Some(dir.stock);
+            return None;
+        }
+        let new_reference = match msg.body {
+            Body::AddOrder(ref add) => Some(add.reference),
+            Body::ReplaceOrder(ref replace) => Some(replace.new_reference),
+            _ => None,
+        };
+        let reused = new_reference.filter(|r| self.retired.contains(r));
+        let result = self.tracker.apply(msg)?;
+
+        let stats = self.symbols.entry(msg.stock_locate).or_default();
+        let issue = match result {
+            Ok(update) => {
+                match update {
+                    OrderUpdate::Added { order, .. } => {
+                        stats.adds += 1;
+                        stats.stock.get_or_insert(order.stock);
+                    }
+                    OrderUpdate::Executed { .. } => stats.executions += 1,
+                    OrderUpdate::Cancelled { .. } => stats.cancels += 1,
+                    OrderUpdate::Deleted { .. } => stats.deletes += 1,
+                    OrderUpdate::Replaced { .. } => stats.replaces += 1,
+                }
+                if update.is_terminal() {
+                    self.retired.insert(retired_reference(&update));
+                }
+                None
+            }
+            Err(e) => {
+                match e {
+                    OrderError::UnknownReference { .. } => stats.unknown_references += 1,
+                    OrderError::Overfill { reference, .. } => {
+                        stats.overfills += 1;
+                        self.retired.insert(reference);
+                    }
+                    OrderError::DuplicateReference { .. } => stats.duplicate_references += 1,
+                }
+                Some(IntegrityIssue::Order(e))
+            }
+        };
+        match reused {
+            Some(reference) => {
+                stats.reused_references += 1;
+                self.retired.remove(&reference);
+                Some(IntegrityIssue::ReusedReference { reference })
+            }
+            None => issue,
+        }
+    }
+
+    /// Per-symbol results so far, keyed by stock locate
+    pub fn report(&self) -> &BTreeMap<u16, SymbolIntegrity> {
+        &self.symbols
+    }
+
+    /// True if no integrity problems have been found
+    pub fn is_clean(&self) -> bool {
+        self.symbols.values().all(SymbolIntegrity::is_clean)
+    }
+
+    /// Orders which are still live
+    pub fn live_orders(&self) -> &OrderTracker {
+        &self.tracker
+    }
+}
+
+fn retired_reference(update: &OrderUpdate) -> u64 {
+    match *update {
+        OrderUpdate::Added { reference, .. }
+        | OrderUpdate::Executed { reference, .. }
+        | OrderUpdate::Cancelled { reference, .. }
+        | OrderUpdate::Deleted { reference, .. } => reference,
+        OrderUpdate::Replaced { old_reference, .. } => old_reference,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn audit_report() {
+        let mut audit = OrderAudit::new();
+        assert_eq!(audit.check(&msg(1, add(1, Side::Buy, 100, 10))), None);
+        assert_eq!(
+            audit.check(&msg(2, Body::DeleteOrder { reference: 1 })),
+            None
+        );
+        assert!(audit.is_clean());
+
+        assert_eq!(
+            audit.check(&msg(3, add(1, Side::Buy, 100, 10))),
+            Some(IntegrityIssue::ReusedReference { reference: 1 })
+        );
+        let exec = Body::OrderExecuted {
+            reference: 7,
+            executed: 1,
+            match_number: 1,
+        };
+        assert_eq!(
+            audit.check(&msg(4, exec)),
+            Some(IntegrityIssue::Order(OrderError::UnknownReference {
+                reference: 7
+            }))
+        );
+
+        let stats = &audit.report()[&1];
+        assert_eq!(stats.stock.unwrap().as_str(), "ZXZZT   ");
+        assert_eq!((stats.adds, stats.deletes), (2, 1));
+        assert_eq!((stats.reused_references, stats.unknown_references), (1, 1));
+        assert!(!audit.is_clean());
+        assert_eq!(audit.live_orders().len(), 1);
+    }
+}
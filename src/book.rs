@@ -0,0 +1,715 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek};
+
+use crate::{
+    ArrayString4, Body, CrossType, DenseBook, DepthBook, IpoReleaseQualifier, Message,
+    MessageStream, OrderTracker, OrderUpdate, Price4, Result, Side, StreamPosition, TradingState,
+};
+
+/// Aggregate of the resting orders at one price
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PriceLevel {
+    pub price: Price4,
+    pub shares: u64,
+    pub orders: u32,
+}
+
+/// A price-level book for a single symbol, maintained from the updates of
+/// an [`OrderTracker`].
+///
+/// Implementations trade memory for speed in different ways, see
+/// [`SymbolBook`] to choose one per symbol in a [`BookManager`].
+pub trait Book {
+    /// Apply an order update
+    fn apply(&mut self, update: &OrderUpdate);
+
+    fn best_bid(&self) -> Option<PriceLevel>;
+
+    fn best_ask(&self) -> Option<PriceLevel>;
+
+    /// Bid levels, best (highest) first
+    fn bids(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_>;
+
+    /// Ask levels, best (lowest) first
+    fn asks(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_>;
+
+    /// The level at a price on one side, if any orders rest there
+    fn level(&self, side: Side, price: Price4) -> Option<PriceLevel>;
+
+    fn is_empty(&self) -> bool {
+        self.best_bid().is_none() && self.best_ask().is_none()
+    }
+
+    /// True if the best bid is at or above the best ask, which can only
+    /// persist while the symbol is not trading
+    fn is_crossed(&self) -> bool {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => bid.price >= ask.price,
+            _ => false,
+        }
+    }
+
+    /// Best ask less best bid, negative if the book is crossed
+    fn spread(&self) -> Option<f64> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        let raw = i64::from(ask.price.raw()) - i64::from(bid.price.raw());
+        Some(raw as f64 / f64::from(Price4::SCALE))
+    }
+
+    /// Midpoint of the best bid and ask
+    fn mid(&self) -> Option<f64> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        Some((bid.price.as_f64() + ask.price.as_f64()) / 2.0)
+    }
+
+    /// Size-weighted midpoint of the best bid and ask, which leans towards
+    /// the side with less size as it is likely to trade through first
+    fn microprice(&self) -> Option<f64> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        let (bid_size, ask_size) = (bid.shares as f64, ask.shares as f64);
+        if bid_size + ask_size == 0.0 {
+            return None;
+        }
+        Some(
+            (bid.price.as_f64() * ask_size + ask.price.as_f64() * bid_size) / (bid_size + ask_size),
+        )
+    }
+
+    /// Order book imbalance over the top `levels` levels of each side:
+    /// bid shares less ask shares over their total, from -1 (only asks)
+    /// to 1 (only bids). `None` if the book is empty.
+    fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid: u64 = self.bids().take(levels).map(|l| l.shares).sum();
+        let ask: u64 = self.asks().take(levels).map(|l| l.shares).sum();
+        if bid + ask == 0 {
+            return None;
+        }
+        Some((bid as f64 - ask as f64) / (bid + ask) as f64)
+    }
+}
+
+/// Sparse price-level order book for a single symbol, suitable for any
+/// price distribution
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrderBook {
+    bids: BTreeMap<Price4, PriceLevel>,
+    asks: BTreeMap<Price4, PriceLevel>,
+}
+
+impl OrderBook {
+    pub fn new() -> OrderBook {
+        OrderBook::default()
+    }
+
+    /// Apply an order update from an [`OrderTracker`]
+    pub fn apply(&mut self, update: &OrderUpdate) {
+        match *update {
+            OrderUpdate::Added { order, .. } => {
+                self.add(order.side, order.price, order.shares as u64);
+            }
+            OrderUpdate::Executed { order, shares, .. }
+            | OrderUpdate::Cancelled { order, shares, .. } => {
+                let terminal = update.is_terminal();
+                self.remove(order.side, order.price, shares as u64, terminal);
+            }
+            OrderUpdate::Deleted { order, .. } => {
+                self.remove(order.side, order.price, order.shares as u64, true);
+            }
+            OrderUpdate::Replaced { old, new, .. } => {
+                self.remove(old.side, old.price, old.shares as u64, true);
+                self.add(new.side, new.price, new.shares as u64);
+            }
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Price4, PriceLevel> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    pub(crate) fn add(&mut self, side: Side, price: Price4, shares: u64) {
+        let level = self.side_mut(side).entry(price).or_insert(PriceLevel {
+            price,
+            shares: 0,
+            orders: 0,
+        });
+        level.shares += shares;
+        level.orders += 1;
+    }
+
+    pub(crate) fn remove(&mut self, side: Side, price: Price4, shares: u64, terminal: bool) {
+        let levels = self.side_mut(side);
+        let Some(level) = levels.get_mut(&price) else {
+            return;
+        };
+        level.shares = level.shares.saturating_sub(shares);
+        if terminal {
+            level.orders = level.orders.saturating_sub(1);
+        }
+        if level.orders == 0 {
+            levels.remove(&price);
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<&PriceLevel> {
+        self.bids.values().next_back()
+    }
+
+    pub fn best_ask(&self) -> Option<&PriceLevel> {
+        self.asks.values().next()
+    }
+
+    /// Bid levels, best (highest) first
+    pub fn bids(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.bids.values().rev()
+    }
+
+    /// Ask levels, best (lowest) first
+    pub fn asks(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.asks.values()
+    }
+
+    /// The level at a price on one side, if any orders rest there
+    pub fn level(&self, side: Side, price: Price4) -> Option<&PriceLevel> {
+        match side {
+            Side::Buy => self.bids.get(&price),
+            Side::Sell => self.asks.get(&price),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+}
+
+impl Book for OrderBook {
+    fn apply(&mut self, update: &OrderUpdate) {
+        OrderBook::apply(self, update)
+    }
+
+    fn best_bid(&self) -> Option<PriceLevel> {
+        OrderBook::best_bid(self).copied()
+    }
+
+    fn best_ask(&self) -> Option<PriceLevel> {
+        OrderBook::best_ask(self).copied()
+    }
+
+    fn bids(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        Box::new(OrderBook::bids(self).copied())
+    }
+
+    fn asks(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        Box::new(OrderBook::asks(self).copied())
+    }
+
+    fn level(&self, side: Side, price: Price4) -> Option<PriceLevel> {
+        OrderBook::level(self, side, price).copied()
+    }
+
+    fn is_empty(&self) -> bool {
+        OrderBook::is_empty(self)
+    }
+}
+
+/// The book implementation used for a symbol in a [`BookManager`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolBook {
+    /// Full depth, for any prices
+    Sparse(OrderBook),
+    /// Full depth, fastest for prices on a known tick grid
+    Dense(DenseBook),
+    /// Only the top levels of each side
+    Depth(DepthBook),
+}
+
+impl Default for SymbolBook {
+    fn default() -> Self {
+        SymbolBook::Sparse(OrderBook::new())
+    }
+}
+
+impl SymbolBook {
+    fn inner(&self) -> &dyn Book {
+        match self {
+            SymbolBook::Sparse(book) => book,
+            SymbolBook::Dense(book) => book,
+            SymbolBook::Depth(book) => book,
+        }
+    }
+}
+
+impl Book for SymbolBook {
+    fn apply(&mut self, update: &OrderUpdate) {
+        match self {
+            SymbolBook::Sparse(book) => book.apply(update),
+            SymbolBook::Dense(book) => book.apply(update),
+            SymbolBook::Depth(book) => book.apply(update),
+        }
+    }
+
+    fn best_bid(&self) -> Option<PriceLevel> {
+        self.inner().best_bid()
+    }
+
+    fn best_ask(&self) -> Option<PriceLevel> {
+        self.inner().best_ask()
+    }
+
+    fn bids(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        self.inner().bids()
+    }
+
+    fn asks(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        self.inner().asks()
+    }
+
+    fn level(&self, side: Side, price: Price4) -> Option<PriceLevel> {
+        self.inner().level(side, price)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner().is_empty()
+    }
+}
+
+/// Trading status of a symbol, from Trading Action, IPO Quoting Period
+/// Update and Cross Trade messages
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BookStatus {
+    pub trading_state: TradingState,
+    /// Reason given for the trading state, e.g. `"LUDP"` or `"IPO1"`
+    pub reason: ArrayString4,
+    /// Timestamp of the last Trading Action message
+    pub since: u64,
+    /// Type of the last cross in the symbol, if any
+    pub last_cross: Option<CrossType>,
+    /// Anticipated release time of an IPO, in nanoseconds since midnight,
+    /// until the release is cancelled or the symbol starts trading
+    pub ipo_release: Option<u64>,
+}
+
+impl BookStatus {
+    /// True unless the symbol is trading.
+    ///
+    /// While a symbol is halted, paused or quoting only, orders are still
+    /// added and cancelled but nothing executes, so the book may lock or
+    /// cross and its prices are not executable. Orders are not purged: any
+    /// cancelled by Nasdaq are removed by their own Delete messages, so the
+    /// book is accurate again once trading resumes after the reopening cross.
+    pub fn is_stale(&self) -> bool {
+        self.trading_state != TradingState::Trading
+    }
+
+    /// True while the symbol is quoting ahead of an anticipated IPO release
+    pub fn awaits_ipo(&self) -> bool {
+        self.ipo_release.is_some()
+    }
+}
+
+/// Maintains a book for every symbol in a stream.
+///
+/// Symbols use a sparse [`OrderBook`] unless another implementation is
+/// chosen with [`set_book`](BookManager::set_book). Messages inconsistent
+/// with the tracked orders (see [`OrderTracker`]) are ignored.
+///
+/// The trading state of each symbol is tracked alongside its book, see
+/// [`BookStatus`]. Books are not cleared across halts, IPO releases or
+/// crosses: Nasdaq reports every order it removes, including those
+/// executed in a cross, with its own message. A book can still be
+/// cleared with [`clear`](BookManager::clear), or automatically when an
+/// IPO is cancelled, see
+/// [`set_clear_on_ipo_cancel`](BookManager::set_clear_on_ipo_cancel).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct BookManager {
+    tracker: OrderTracker,
+    books: HashMap<u16, SymbolBook>,
+    status: HashMap<u16, BookStatus>,
+    clear_on_ipo_cancel: bool,
+}
+
+impl BookManager {
+    pub fn new() -> BookManager {
+        BookManager::default()
+    }
+
+    /// Apply a message, returning true if a book changed
+    pub fn update(&mut self, msg: &Message) -> bool {
+        self.apply(msg).is_some()
+    }
+
+    /// Apply a message, returning the order update applied to its book
+    pub fn apply(&mut self, msg: &Message) -> Option<OrderUpdate> {
+        match msg.body {
+            Body::TradingAction {
+                trading_state,
+                reason,
+                ..
+            } => {
+                let status = self.status.entry(msg.stock_locate).or_insert(BookStatus {
+                    trading_state,
+                    reason,
+                    since: msg.timestamp,
+                    last_cross: None,
+                    ipo_release: None,
+                });
+                status.trading_state = trading_state;
+                status.reason = reason;
+                status.since = msg.timestamp;
+                if trading_state == TradingState::Trading {
+                    // released
+                    status.ipo_release = None;
+                }
+                return None;
+            }
+            Body::IpoQuotingPeriod(ref ipo) => {
+                // only sent while an IPO is quoting
+                let status = self.status.entry(msg.stock_locate).or_insert(BookStatus {
+                    trading_state: TradingState::QuotationOnly,
+                    reason: ArrayString4::new(),
+                    since: msg.timestamp,
+                    last_cross: None,
+                    ipo_release: None,
+                });
+                match ipo.release_qualifier {
+                    IpoReleaseQualifier::Anticipated => {
+                        status.ipo_release = Some(ipo.release_time as u64 * 1_000_000_000);
+                    }
+                    IpoReleaseQualifier::Cancelled => {
+                        status.ipo_release = None;
+                        if self.clear_on_ipo_cancel {
+                            self.clear(msg.stock_locate);
+                        }
+                    }
+                }
+                return None;
+            }
+            Body::CrossTrade(ref cross) => {
+                if let Some(status) = self.status.get_mut(&msg.stock_locate) {
+                    status.last_cross = Some(cross.cross_type);
+                }
+                return None;
+            }
+            _ => {}
+        }
+        let Some(Ok(update)) = self.tracker.apply(msg) else {
+            return None;
+        };
+        self.books
+            .entry(msg.stock_locate)
+            .or_default()
+            .apply(&update);
+        Some(update)
+    }
+
+    /// Use the given book for a stock locate, replacing any existing book.
+    ///
+    /// This is intended to be called before any orders for the symbol
+    /// arrive, e.g. when its Stock Directory message is seen.
+    pub fn set_book(&mut self, locate: u16, book: SymbolBook) {
+        self.books.insert(locate, book);
+    }
+
+    /// Whether to clear a symbol's book when its IPO release is cancelled
+    /// (off by default), for consumers which treat the quoting period as
+    /// void. Nasdaq reports the orders it cancels with Delete messages,
+    /// which are then ignored.
+    pub fn set_clear_on_ipo_cancel(&mut self, clear: bool) {
+        self.clear_on_ipo_cancel = clear;
+    }
+
+    /// Remove every order of a stock locate from its book and from the
+    /// tracked orders, so later messages for them are ignored
+    pub fn clear(&mut self, locate: u16) {
+        let orders = self.tracker.remove_symbol(locate);
+        if let Some(book) = self.books.get_mut(&locate) {
+            for (reference, order) in orders {
+                book.apply(&OrderUpdate::Deleted { reference, order });
+            }
+        }
+    }
+
+    /// The book for a stock locate
+    pub fn book(&self, locate: u16) -> Option<&SymbolBook> {
+        self.books.get(&locate)
+    }
+
+    /// Trading status of a stock locate, once a Trading Action message has
+    /// been seen for it
+    pub fn status(&self, locate: u16) -> Option<&BookStatus> {
+        self.status.get(&locate)
+    }
+
+    /// True if the book for a stock locate is not executable because the
+    /// symbol is not trading, see [`BookStatus::is_stale`]
+    pub fn is_stale(&self, locate: u16) -> bool {
+        self.status(locate).is_some_and(BookStatus::is_stale)
+    }
+
+    /// All books, in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &SymbolBook)> {
+        self.books.iter().map(|(l, b)| (*l, b))
+    }
+
+    /// Orders currently live across all books
+    pub fn orders(&self) -> &OrderTracker {
+        &self.tracker
+    }
+
+    /// Snapshot the books, which reflect every message before `position`
+    pub fn snapshot(&self, position: StreamPosition) -> BookSnapshot {
+        BookSnapshot {
+            position,
+            books: self.clone(),
+        }
+    }
+
+    /// Restore books from a snapshot and seek the stream to the first
+    /// message not yet applied, so that processing can continue without
+    /// replaying the session from the start.
+    ///
+    /// The stream must read the same data as the one the snapshot was
+    /// taken from.
+    pub fn resume_from<R: Read + Seek>(
+        snapshot: BookSnapshot,
+        stream: &mut MessageStream<R>,
+    ) -> Result<BookManager> {
+        stream.restore(snapshot.position.into())?;
+        Ok(snapshot.books)
+    }
+}
+
+/// The state of a [`BookManager`] at a position in a stream, for warm
+/// restarts.
+///
+/// With the `serde` feature this can be persisted in any serde format.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct BookSnapshot {
+    /// Position of the next message to apply
+    pub position: StreamPosition,
+    pub books: BookManager,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{ArrayString8, CrossTrade, IpoQuotingPeriod, PriceGrid, ReplaceOrder};
+
+    #[test]
+    fn builds_price_levels() {
+        let mut books = BookManager::new();
+        books.update(&msg(1, add(1, Side::Buy, 100, 10_000)));
+        books.update(&msg(2, add(2, Side::Buy, 200, 10_000)));
+        books.update(&msg(3, add(3, Side::Buy, 50, 9_900)));
+        books.update(&msg(4, add(4, Side::Sell, 300, 10_100)));
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 40,
+            match_number: 1,
+        };
+        books.update(&msg(5, exec));
+
+        let book = books.book(1).unwrap();
+        let best = book.best_bid().unwrap();
+        assert_eq!(
+            (best.price.raw(), best.shares, best.orders),
+            (10_000, 260, 2)
+        );
+        assert_eq!(book.bids().count(), 2);
+        assert_eq!(book.best_ask().unwrap().shares, 300);
+
+        let replace = Body::ReplaceOrder(ReplaceOrder {
+            old_reference: 4,
+            new_reference: 5,
+            shares: 100,
+            price: 10_200.into(),
+        });
+        books.update(&msg(6, replace));
+        books.update(&msg(7, Body::DeleteOrder { reference: 3 }));
+        let book = books.book(1).unwrap();
+        assert_eq!(book.best_ask().unwrap().price.raw(), 10_200);
+        assert_eq!(book.asks().count(), 1);
+        assert!(book.level(Side::Buy, 9_900.into()).is_none());
+    }
+
+    #[test]
+    fn books_agree() {
+        let grid = PriceGrid::new(9_000.into(), 100.into(), 20).unwrap();
+        let mut books = BookManager::new();
+        books.set_book(2, SymbolBook::Dense(DenseBook::new(grid)));
+        books.set_book(3, SymbolBook::Depth(DepthBook::new(2)));
+        for locate in 1..=3 {
+            // references are unique across symbols
+            let r = 10 * locate as u64;
+            let bodies = [
+                add(r + 1, Side::Buy, 100, 10_000),
+                add(r + 2, Side::Buy, 200, 9_950), // off the grid
+                add(r + 3, Side::Buy, 50, 9_900),
+                add(r + 4, Side::Sell, 300, 10_100),
+                add(r + 5, Side::Sell, 10, 12_000), // outside the grid
+                Body::DeleteOrder { reference: r + 1 },
+            ];
+            for body in bodies {
+                let mut msg = msg(0, body);
+                msg.stock_locate = locate;
+                books.update(&msg);
+            }
+        }
+        let levels = |locate, side| {
+            let book = books.book(locate).unwrap();
+            let levels = match side {
+                Side::Buy => book.bids(),
+                Side::Sell => book.asks(),
+            };
+            levels
+                .map(|l| (l.price.raw(), l.shares))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(levels(1, Side::Buy), [(9_950, 200), (9_900, 50)]);
+        assert_eq!(levels(1, Side::Sell), [(10_100, 300), (12_000, 10)]);
+        for locate in 2..=3 {
+            assert_eq!(levels(locate, Side::Buy), levels(1, Side::Buy));
+            assert_eq!(levels(locate, Side::Sell), levels(1, Side::Sell));
+        }
+        let dense = books.book(2).unwrap();
+        assert_eq!(dense.best_bid().unwrap().price.raw(), 9_950);
+        assert_eq!(dense.level(Side::Sell, 12_000.into()).unwrap().orders, 1);
+    }
+
+    #[test]
+    fn tracks_halts() {
+        let mut books = BookManager::new();
+        let action = |trading_state, reason| Body::TradingAction {
+            stock: ArrayString8::from("ZXZZT   ").unwrap(),
+            trading_state,
+            reason: ArrayString4::from(reason).unwrap(),
+        };
+        books.update(&msg(1, action(TradingState::Trading, "    ")));
+        books.update(&msg(2, add(1, Side::Buy, 100, 10_000)));
+        assert!(!books.is_stale(1));
+
+        books.update(&msg(3, action(TradingState::Halted, "LUDP")));
+        books.update(&msg(4, add(2, Side::Sell, 100, 9_900)));
+        assert!(books.is_stale(1));
+        assert!(books.book(1).unwrap().is_crossed());
+
+        let cross = Body::CrossTrade(CrossTrade {
+            shares: 100,
+            stock: ArrayString8::from("ZXZZT   ").unwrap(),
+            cross_price: 9_950.into(),
+            match_number: 1,
+            cross_type: CrossType::IpoOrHalted,
+        });
+        books.update(&msg(5, Body::DeleteOrder { reference: 1 }));
+        books.update(&msg(5, cross));
+        books.update(&msg(6, action(TradingState::Trading, "    ")));
+        let status = books.status(1).unwrap();
+        assert!(!status.is_stale());
+        assert_eq!(status.last_cross, Some(CrossType::IpoOrHalted));
+        assert_eq!(status.since, 6);
+        assert!(!books.book(1).unwrap().is_crossed());
+    }
+
+    #[test]
+    fn tracks_ipo_release() {
+        let mut books = BookManager::new();
+        let stock = ArrayString8::from("ZXZZT   ").unwrap();
+        let ipo = |release_time, release_qualifier| {
+            Body::IpoQuotingPeriod(IpoQuotingPeriod {
+                stock,
+                release_time,
+                release_qualifier,
+                price: 10_000.into(),
+            })
+        };
+        let action = |trading_state, reason| Body::TradingAction {
+            stock,
+            trading_state,
+            reason: ArrayString4::from(reason).unwrap(),
+        };
+        books.update(&msg(1, action(TradingState::Halted, "IPO1")));
+        books.update(&msg(2, action(TradingState::QuotationOnly, "IPOQ")));
+        books.update(&msg(3, ipo(36_000, IpoReleaseQualifier::Anticipated)));
+        books.update(&msg(4, add(1, Side::Buy, 100, 10_100)));
+        books.update(&msg(4, add(2, Side::Sell, 100, 10_000)));
+        let status = books.status(1).unwrap();
+        assert!(status.is_stale() && status.awaits_ipo());
+        assert_eq!(status.ipo_release, Some(36_000 * 1_000_000_000));
+        assert!(books.book(1).unwrap().is_crossed());
+
+        // the orders executed in the cross have their own messages
+        for reference in [1, 2] {
+            let exec = Body::OrderExecuted {
+                reference,
+                executed: 100,
+                match_number: 1,
+            };
+            books.update(&msg(5, exec));
+        }
+        let cross = Body::CrossTrade(CrossTrade {
+            shares: 100,
+            stock,
+            cross_price: 10_050.into(),
+            match_number: 1,
+            cross_type: CrossType::IpoOrHalted,
+        });
+        books.update(&msg(5, cross));
+        books.update(&msg(6, action(TradingState::Trading, "    ")));
+        let status = books.status(1).unwrap();
+        assert!(!status.is_stale() && !status.awaits_ipo());
+        assert_eq!(status.last_cross, Some(CrossType::IpoOrHalted));
+        assert!(books.book(1).unwrap().is_empty());
+
+        // a cancelled IPO leaves the book as it is, unless clearing is asked for
+        books.update(&msg(7, add(3, Side::Buy, 100, 9_900)));
+        books.update(&msg(8, ipo(0, IpoReleaseQualifier::Cancelled)));
+        assert!(!books.book(1).unwrap().is_empty());
+        books.set_clear_on_ipo_cancel(true);
+        books.update(&msg(9, ipo(0, IpoReleaseQualifier::Cancelled)));
+        assert!(books.book(1).unwrap().is_empty());
+        assert!(books.orders().is_empty());
+        assert!(!books.update(&msg(10, Body::DeleteOrder { reference: 3 })));
+    }
+
+    #[test]
+    fn resumes_from_snapshot() {
+        let mut data = Vec::new();
+        for reference in 1..=4u64 {
+            // add orders of 100 shares at increasing prices
+            data.extend_from_slice(&[0, 36, b'A', 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+            data.extend_from_slice(&reference.to_be_bytes());
+            data.push(b'B');
+            data.extend_from_slice(&100u32.to_be_bytes());
+            data.extend_from_slice(b"ZXZZT   ");
+            data.extend_from_slice(&(10_000 + reference as u32).to_be_bytes());
+        }
+        let mut stream = MessageStream::from_reader(std::io::Cursor::new(data));
+        let mut books = BookManager::new();
+        for msg in stream.by_ref().take(2) {
+            books.update(&msg.unwrap());
+        }
+        let snapshot = books.snapshot(MessageStream::position(&stream));
+        #[cfg(feature = "serde")]
+        let snapshot: BookSnapshot =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        // a restart begins from a fresh stream
+        stream.rewind().unwrap();
+        let mut books = BookManager::resume_from(snapshot, &mut stream).unwrap();
+        for msg in stream {
+            books.update(&msg.unwrap());
+        }
+        let book = books.book(1).unwrap();
+        assert_eq!(book.bids().count(), 4);
+        assert_eq!(book.best_bid().unwrap().price.raw(), 10_004);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use crate::{Book, BookManager, Message, OrderUpdate, Price4, Result, Side};
+
+/// How a price level changed
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LevelAction {
+    /// The first order arrived at a price
+    Added,
+    /// The shares or order count at a price changed
+    Changed,
+    /// The last order at a price left the book
+    Removed,
+}
+
+/// A change to one price level of a book, carrying the new size of the level
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BookEvent {
+    pub stock_locate: u16,
+    /// Timestamp of the message which caused the change
+    pub timestamp: u64,
+    pub side: Side,
+    pub price: Price4,
+    pub action: LevelAction,
+    /// Shares resting at the level after the change, zero if removed
+    pub shares: u64,
+    /// Orders resting at the level after the change, zero if removed
+    pub orders: u32,
+}
+
+/// Iterator of level-based [`BookEvent`]s from a stream of messages.
+///
+/// Each message is applied to a [`BookManager`] and every level it touched
+/// is reported with its new size. A message yields at most two events (a
+/// replace moving an order between prices), and none if it does not change
+/// any level. Errors from the message stream are passed through.
+#[derive(Debug)]
+pub struct BookEventStream<I> {
+    messages: I,
+    books: BookManager,
+    pending: VecDeque<BookEvent>,
+}
+
+impl<I: Iterator<Item = Result<Message>>> BookEventStream<I> {
+    pub fn new(messages: I) -> BookEventStream<I> {
+        BookEventStream {
+            messages,
+            books: BookManager::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The books as of the last message applied
+    pub fn books(&self) -> &BookManager {
+        &self.books
+    }
+
+    fn apply(&mut self, msg: &Message) {
+        let Some(update) = self.books.apply(msg) else {
+            return;
+        };
+        let book = self.books.book(msg.stock_locate);
+        let mut emit = |side: Side, price: Price4, added: bool| {
+            let (action, shares, orders) = match book.and_then(|b| b.level(side, price)) {
+                Some(level) if added && level.orders == 1 => {
+                    (LevelAction::Added, level.shares, level.orders)
+                }
+                Some(level) => (LevelAction::Changed, level.shares, level.orders),
+                None => (LevelAction::Removed, 0, 0),
+            };
+            self.pending.push_back(BookEvent {
+                stock_locate: msg.stock_locate,
+                timestamp: msg.timestamp,
+                side,
+                price,
+                action,
+                shares,
+                orders,
+            });
+        };
+        // the update carries the order before the message, so the previous
+        // state of each level follows from its state now
+        match update {
+            OrderUpdate::Added { order, .. } => emit(order.side, order.price, true),
+            OrderUpdate::Executed { order, .. }
+            | OrderUpdate::Cancelled { order, .. }
+            | OrderUpdate::Deleted { order, .. } => emit(order.side, order.price, false),
+            OrderUpdate::Replaced { old, new, .. } => {
+                if (old.side, old.price) != (new.side, new.price) {
+                    emit(old.side, old.price, false);
+                    emit(new.side, new.price, true);
+                } else if old.shares != new.shares {
+                    emit(new.side, new.price, false);
+                }
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>> Iterator for BookEventStream<I> {
+    type Item = Result<BookEvent>;
+
+    fn next(&mut self) -> Option<Result<BookEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.messages.next()? {
+                Ok(msg) => self.apply(&msg),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, ReplaceOrder};
+
+    #[test]
+    fn emits_level_diffs() {
+        let messages = vec![
+            msg(1, add(1, Side::Buy, 100, 10_000)),
+            msg(2, add(2, Side::Buy, 50, 10_000)),
+            msg(
+                3,
+                Body::OrderExecuted {
+                    reference: 1,
+                    executed: 100,
+                    match_number: 1,
+                },
+            ),
+            msg(
+                4,
+                Body::ReplaceOrder(ReplaceOrder {
+                    old_reference: 2,
+                    new_reference: 3,
+                    shares: 50,
+                    price: 10_100.into(),
+                }),
+            ),
+            msg(5, Body::DeleteOrder { reference: 9 }),
+        ];
+        let events: Vec<_> = BookEventStream::new(messages.into_iter().map(Ok))
+            .map(|e| {
+                let e = e.unwrap();
+                (e.timestamp, e.price.raw(), e.action, e.shares, e.orders)
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (1, 10_000, LevelAction::Added, 100, 1),
+                (2, 10_000, LevelAction::Changed, 150, 2),
+                (3, 10_000, LevelAction::Changed, 50, 1),
+                (4, 10_000, LevelAction::Removed, 0, 0),
+                (4, 10_100, LevelAction::Added, 50, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,621 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use crate::dump::ErrorDump;
+use crate::framing::{FinishHook, BUFSIZE, MAX_BUFSIZE};
+use crate::{
+    Body, EventCode, FeedProfile, Message, MessageStream, ParserRegistry, Result, StreamPosition,
+    StreamSummary, ValidationLevel,
+};
+
+/// What a [`MessageStream`] does after a message fails to parse
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ErrorPolicy {
+    /// Return the error, then end the stream. Every later call to `next`
+    /// returns `None`.
+    #[default]
+    Halt,
+    /// Return the error, then skip the malformed message using its length
+    /// prefix and carry on with the next
+    SkipMessage,
+    /// Return the error, then scan forward byte by byte to the next
+    /// well-formed message and carry on from there. Recovers from a
+    /// corrupted length prefix, which `SkipMessage` cannot; whatever is
+    /// skipped over is not reported.
+    SkipToNextValid,
+}
+
+/// How much of the input a [`MessageStream`] includes in parse errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorContext {
+    /// Leave the input out, e.g. where logging feed contents is not allowed
+    Omit,
+    /// Up to this many bytes from the start of the failed message, as a
+    /// list of decimal values
+    Bytes(usize),
+    /// Up to this many bytes from the start of the failed message, in hex
+    Hex(usize),
+}
+
+impl Default for ErrorContext {
+    fn default() -> Self {
+        ErrorContext::Bytes(20)
+    }
+}
+
+impl ErrorContext {
+    // describe a parse error, with as much of `input` as configured
+    pub(crate) fn describe(&self, error: &str, input: &[u8]) -> String {
+        match *self {
+            ErrorContext::Omit => error.to_string(),
+            ErrorContext::Bytes(len) => {
+                let input = &input[..len.min(input.len())];
+                format!("{}, buffer context {:?}", error, input)
+            }
+            ErrorContext::Hex(len) => {
+                let input = &input[..len.min(input.len())];
+                let hex: Vec<_> = input.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("{}, buffer context {}", error, hex.join(" "))
+            }
+        }
+    }
+}
+
+/// Order and trade messages, which are thinned out by
+/// [`MessageStreamBuilder::sample_every`]
+pub(crate) const SAMPLED_TAGS: &[u8] = b"AFECXDUPQBIN";
+
+/// System event, reference data and trading status messages, which are
+/// passed through outside the window set by
+/// [`MessageStreamBuilder::between_events`]
+pub(crate) const REFERENCE_TAGS: &[u8] = b"SRHhYLVKJ";
+
+/// Window between two system events, see
+/// [`MessageStreamBuilder::between_events`]
+#[derive(Debug)]
+pub(crate) struct EventWindow {
+    start: EventCode,
+    end: EventCode,
+    pub(crate) open: bool,
+}
+
+impl EventWindow {
+    pub(crate) fn new(start: EventCode, end: EventCode) -> EventWindow {
+        EventWindow {
+            start,
+            end,
+            open: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self, msg: &Message) {
+        if let Body::SystemEvent { event } = msg.body {
+            if event == self.start {
+                self.open = true;
+            } else if event == self.end {
+                self.open = false;
+            }
+        }
+    }
+}
+
+/// Symbols selected by [`MessageStreamBuilder::symbols`]
+#[derive(Debug)]
+pub(crate) struct SymbolFilter {
+    symbols: Vec<String>,
+    // stock locates assigned to the symbols by the directory
+    pub(crate) locates: HashSet<u16>,
+}
+
+impl SymbolFilter {
+    pub(crate) fn accepts(&mut self, msg: &Message) -> bool {
+        if let Body::StockDirectory(ref dir) = msg.body {
+            if self.symbols.iter().any(|s| s == dir.stock.trim_end()) {
+                self.locates.insert(msg.stock_locate);
+            }
+        }
+        msg.stock_locate == 0 || self.locates.contains(&msg.stock_locate)
+    }
+}
+
+pub(crate) struct ProgressHook {
+    pub(crate) interval: u64,
+    // bytes consumed at which to call back next
+    pub(crate) next: u64,
+    pub(crate) callback: Box<dyn FnMut(StreamPosition) + Send>,
+}
+
+pub(crate) struct TagHook {
+    pub(crate) tag: u8,
+    pub(crate) callback: Box<dyn FnMut(&Message) + Send>,
+}
+
+/// Configures a [`MessageStream`], see [`MessageStream::builder`].
+///
+/// ```ignore
+/// let stream = itchy::MessageStream::builder()
+///     .tags(b"AFEXDU")
+///     .symbols(&["AAPL", "MSFT"])
+///     .progress(1 << 30, |pos| eprintln!("{}", pos))
+///     .open("/path/to/file.itch")?;
+/// ```
+pub struct MessageStreamBuilder {
+    buffer_size: usize,
+    max_buffer_size: usize,
+    profile: FeedProfile,
+    price_scale: Option<u32>,
+    tags: Option<Box<[bool; 256]>>,
+    symbols: Option<Vec<String>>,
+    strict: bool,
+    error_policy: ErrorPolicy,
+    error_context: ErrorContext,
+    validation: ValidationLevel,
+    progress: Option<ProgressHook>,
+    tag_hooks: Vec<TagHook>,
+    on_finish: Option<FinishHook>,
+    dump: Option<(usize, PathBuf)>,
+    sample_every: u32,
+    window: Option<(EventCode, EventCode)>,
+    parsers: Option<ParserRegistry>,
+    stop_at_end: bool,
+}
+
+impl Default for MessageStreamBuilder {
+    fn default() -> Self {
+        MessageStreamBuilder {
+            buffer_size: BUFSIZE,
+            max_buffer_size: MAX_BUFSIZE,
+            profile: FeedProfile::TotalView,
+            price_scale: None,
+            tags: None,
+            symbols: None,
+            strict: true,
+            error_policy: ErrorPolicy::Halt,
+            error_context: ErrorContext::default(),
+            validation: ValidationLevel::Basic,
+            progress: None,
+            tag_hooks: Vec::new(),
+            on_finish: None,
+            dump: None,
+            sample_every: 1,
+            window: None,
+            parsers: None,
+            stop_at_end: false,
+        }
+    }
+}
+
+impl MessageStreamBuilder {
+    pub fn new() -> MessageStreamBuilder {
+        MessageStreamBuilder::default()
+    }
+
+    /// Initial size of the parse buffer in bytes (8 KiB by default, at
+    /// least 256)
+    ///
+    /// # Panics
+    ///
+    /// If `size` is less than 256
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        assert!(size >= 256, "buffer size must be at least 256 bytes");
+        self.buffer_size = size;
+        self
+    }
+
+    /// Largest size the parse buffer grows to (1 MiB by default). The
+    /// buffer doubles whenever several reads in a row fill it completely,
+    /// as when reading from fast storage, so that each read asks for more.
+    /// A maximum no larger than [`buffer_size`](Self::buffer_size) keeps
+    /// the buffer at a fixed size.
+    pub fn max_buffer_size(mut self, size: usize) -> Self {
+        self.max_buffer_size = size;
+        self
+    }
+
+    /// The product carried by the stream (TotalView by default)
+    pub fn profile(mut self, profile: FeedProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Override the price scale of the feed profile
+    ///
+    /// # Panics
+    ///
+    /// If `scale` is zero
+    pub fn price_scale(mut self, scale: u32) -> Self {
+        assert!(scale > 0, "price scale must be non-zero");
+        self.price_scale = Some(scale);
+        self
+    }
+
+    /// Only yield messages with these tags, given as bytes or as
+    /// [`Tag`](crate::Tag)s
+    pub fn tags<T: Copy + Into<u8>>(mut self, tags: &[T]) -> Self {
+        let mut set = Box::new([false; 256]);
+        for &tag in tags {
+            set[tag.into() as usize] = true;
+        }
+        self.tags = Some(set);
+        self
+    }
+
+    /// Only yield messages for these symbols, plus messages not specific to
+    /// any symbol.
+    ///
+    /// Symbols are matched to stock locates by their Stock Directory
+    /// messages, so messages for a symbol before its directory entry are
+    /// dropped.
+    pub fn symbols<S: AsRef<str>>(mut self, symbols: &[S]) -> Self {
+        let symbols = symbols
+            .iter()
+            .map(|s| s.as_ref().trim_end().to_string())
+            .collect();
+        self.symbols = Some(symbols);
+        self
+    }
+
+    /// Whether messages outside the feed profile are errors (the default)
+    /// or silently skipped
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// What to do after a message fails to parse
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// How much of the input to include in parse errors (the first 20
+    /// bytes of the failed message by default)
+    pub fn error_context(mut self, context: ErrorContext) -> Self {
+        self.error_context = context;
+        self
+    }
+
+    /// Keep the last `messages` raw messages, and when a message fails to
+    /// parse append them to the file at `path`, in hex and decoded, along
+    /// with the input that failed. Costs a copy of every message.
+    pub fn dump_on_error<P: AsRef<Path>>(mut self, messages: usize, path: P) -> Self {
+        self.dump = Some((messages, path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// How thoroughly to check messages against the spec
+    /// ([`ValidationLevel::Basic`] by default)
+    pub fn validation(mut self, level: ValidationLevel) -> Self {
+        self.validation = level;
+        self
+    }
+
+    /// Call `callback` with the stream position each time roughly
+    /// `interval` more bytes have been parsed
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero
+    pub fn progress<F>(mut self, interval: u64, callback: F) -> Self
+    where
+        F: FnMut(StreamPosition) + Send + 'static,
+    {
+        assert!(interval > 0, "progress interval must be non-zero");
+        self.progress = Some(ProgressHook {
+            interval,
+            next: interval,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Call `callback` with each message of type `tag` as it is parsed,
+    /// before it is yielded, e.g. to capture the stock directory while
+    /// iterating over only the trades.
+    ///
+    /// Hooks see messages filtered out by [`tags`](Self::tags) and
+    /// [`symbols`](Self::symbols), but not those skipped without being
+    /// parsed. Several hooks may be set, and are called in the order they
+    /// were added.
+    pub fn on_tag<F>(mut self, tag: u8, callback: F) -> Self
+    where
+        F: FnMut(&Message) + Send + 'static,
+    {
+        self.tag_hooks.push(TagHook {
+            tag,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Call `callback` once with the summary of the stream when it is
+    /// [`finish`](MessageStream::finish)ed or dropped, e.g. to log the
+    /// throughput of each stage of a pipeline
+    pub fn on_finish<F>(mut self, callback: F) -> Self
+    where
+        F: FnOnce(&StreamSummary) + Send + 'static,
+    {
+        self.on_finish = Some(Box::new(callback));
+        self
+    }
+
+    /// Only yield one in every `n` order and trade messages, for quick
+    /// approximate exploration of large files.
+    ///
+    /// Skipped messages are not parsed. System events, reference data and
+    /// trading status messages are always passed through, so state such as
+    /// the symbol directory stays complete.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero
+    pub fn sample_every(mut self, n: u32) -> Self {
+        assert!(n > 0, "sampling interval must be non-zero");
+        self.sample_every = n;
+        self
+    }
+
+    /// Only yield order and trade messages between the `start` and `end`
+    /// system events, e.g. `StartOfMarketHours` and `EndOfMarketHours` for
+    /// regular trading hours.
+    ///
+    /// Messages outside the window are skipped without being parsed, except
+    /// system events, reference data and trading status messages, so the
+    /// symbol directory and trading states stay complete. Note that orders
+    /// added before the window opens are skipped too, so books built from
+    /// the stream only hold orders added within it.
+    pub fn between_events(mut self, start: EventCode, end: EventCode) -> Self {
+        self.window = Some((start, end));
+        self
+    }
+
+    /// Parse messages of the types in `registry` with its parsers, rather
+    /// than failing on them as unknown types
+    pub fn parsers(mut self, registry: ParserRegistry) -> Self {
+        self.parsers = Some(registry);
+        self
+    }
+
+    /// End the stream after the End of Messages system event, rather than
+    /// at the end of the input. Anything after the event is left unread
+    /// until [`finish`](MessageStream::finish), which reads the rest of the
+    /// input and reports it as trailing bytes.
+    pub fn stop_at_end_of_messages(mut self, stop: bool) -> Self {
+        self.stop_at_end = stop;
+        self
+    }
+
+    pub fn build<R: Read>(self, reader: R) -> MessageStream<R> {
+        let mut stream = MessageStream::new(reader, self.buffer_size);
+        stream.max_buffer_size = self.max_buffer_size;
+        stream.on_finish = self.on_finish;
+        stream.profile = self.profile;
+        stream.price_scale = self.price_scale;
+        stream.tags = self.tags;
+        stream.symbols = self.symbols.map(|symbols| SymbolFilter {
+            symbols,
+            locates: HashSet::new(),
+        });
+        stream.strict = self.strict;
+        stream.error_policy = self.error_policy;
+        stream.error_context = self.error_context;
+        stream.validation = self.validation;
+        stream.progress = self.progress;
+        stream.tag_hooks = self.tag_hooks;
+        stream.dump = self
+            .dump
+            .map(|(messages, path)| ErrorDump::new(messages, path));
+        stream.sample_every = self.sample_every;
+        stream.window = self.window.map(|(start, end)| EventWindow::new(start, end));
+        stream.parsers = self.parsers;
+        stream.stop_at_end = self.stop_at_end;
+        stream
+    }
+
+    pub fn open<P: AsRef<Path>>(self, path: P) -> Result<MessageStream<File>> {
+        Ok(self.build(File::open(path)?))
+    }
+
+    pub fn open_gzip<P: AsRef<Path>>(self, path: P) -> Result<MessageStream<GzDecoder<File>>> {
+        Ok(self.build(GzDecoder::new(File::open(path)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use std::sync::{Arc, Mutex};
+
+    // a system event, a stock directory entry for "ZXZZT" at locate 1, an
+    // order at each of locates 1 and 2, and another system event
+    fn session() -> Vec<u8> {
+        let mut data = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f");
+        data.extend_from_slice(&[0, 39, b'R', 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(b"ZXZZT   QN");
+        data.extend_from_slice(&100u32.to_be_bytes());
+        data.extend_from_slice(b"NCC PNN1N");
+        data.extend_from_slice(&[0, 0, 0, 0, b'N']);
+        for locate in [1u8, 2] {
+            data.extend_from_slice(&[0, 36, b'A', 0, locate, 0, 0, 0, 0, 0, 0, 0, 0]);
+            data.extend_from_slice(&(locate as u64).to_be_bytes());
+            data.push(b'B');
+            data.extend_from_slice(&100u32.to_be_bytes());
+            data.extend_from_slice(b"ZXZZT   ");
+            data.extend_from_slice(&10_000u32.to_be_bytes());
+        }
+        data.extend(hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 9953"));
+        data
+    }
+
+    #[test]
+    fn filters_messages() {
+        let data = session();
+        let stream = MessageStream::builder()
+            .buffer_size(256)
+            .symbols(&["ZXZZT"])
+            .build(&data[..]);
+        let tags: Vec<_> = stream.map(|m| m.unwrap().tag).collect();
+        assert_eq!(tags, b"SRAS");
+
+        let stream = MessageStream::builder().tags(b"A").build(&data[..]);
+        assert_eq!(stream.count(), 2);
+
+        // the directory is still read when its messages are filtered out
+        let stream = MessageStream::builder()
+            .tags(&[crate::Tag::AddOrder])
+            .symbols(&["ZXZZT"])
+            .build(&data[..]);
+        assert_eq!(stream.count(), 1);
+
+        // Add Order messages are not part of the NOII feed
+        let stream = MessageStream::builder()
+            .profile(FeedProfile::Noii)
+            .strict(false)
+            .build(&data[..]);
+        assert_eq!(stream.map(|m| m.unwrap().tag).collect::<Vec<_>>(), b"SRS");
+    }
+
+    #[test]
+    fn calls_tag_hooks() {
+        let data = session();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let directory = seen.clone();
+        let events = seen.clone();
+        let stream = MessageStream::builder()
+            .tags(b"A")
+            .on_tag(b'R', move |msg| directory.lock().unwrap().push(msg.tag))
+            .on_tag(b'S', move |msg| events.lock().unwrap().push(msg.tag))
+            .build(&data[..]);
+        assert_eq!(stream.count(), 2);
+        assert_eq!(*seen.lock().unwrap(), b"SRS");
+    }
+
+    #[test]
+    fn reports_summary_when_dropped() {
+        let mut data = session();
+        data.extend_from_slice(&[0, 12, b'S', 0, 0]);
+        let summaries = Arc::new(Mutex::new(Vec::new()));
+        let reported = summaries.clone();
+        let mut stream = MessageStream::builder()
+            .on_finish(move |summary| reported.lock().unwrap().push(summary.clone()))
+            .build(&data[..]);
+        assert_eq!(stream.by_ref().filter(|m| m.is_err()).count(), 1);
+        drop(stream);
+
+        let summaries = summaries.lock().unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!((summaries[0].messages, summaries[0].errors), (5, 1));
+        assert_eq!(summaries[0].bytes_read, data.len() as u64);
+        assert_eq!(summaries[0].trailing_data, [0, 12, b'S', 0, 0]);
+    }
+
+    #[test]
+    fn samples_order_flow() {
+        let data = session();
+        let stream = MessageStream::builder().sample_every(2).build(&data[..]);
+        let kept: Vec<_> = stream.map(|m| m.unwrap()).collect();
+        assert_eq!(kept.iter().map(|m| m.tag).collect::<Vec<_>>(), b"SRAS");
+        // the second order is kept
+        assert_eq!(kept[2].stock_locate, 2);
+    }
+
+    #[test]
+    fn filters_market_hours() {
+        use crate::orders::tests::{add, msg};
+        use crate::Side;
+
+        let event = |ts, event| Message {
+            tag: b'S',
+            ..msg(ts, Body::SystemEvent { event })
+        };
+        let order = |ts| Message {
+            tag: b'A',
+            ..msg(ts, add(ts, Side::Buy, 100, 10_000))
+        };
+        let mut data = Vec::new();
+        for m in [
+            event(0, EventCode::StartOfMessages),
+            order(1),
+            event(2, EventCode::StartOfMarketHours),
+            order(3),
+            order(4),
+            event(5, EventCode::EndOfMarketHours),
+            order(6),
+        ] {
+            m.encode(&mut data);
+        }
+        let stream = MessageStream::from_reader(&data[..])
+            .between_events(EventCode::StartOfMarketHours, EventCode::EndOfMarketHours);
+        let timestamps: Vec<_> = stream.map(|m| m.unwrap().timestamp).collect();
+        assert_eq!(timestamps, [0, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn recovers_and_reports_progress() {
+        let mut data = session();
+        // corrupt the side of the first order
+        data[14 + 41 + 21] = b'X';
+        let positions = Arc::new(Mutex::new(Vec::new()));
+        let log = positions.clone();
+        let mut stream = MessageStream::builder()
+            .error_policy(ErrorPolicy::SkipMessage)
+            .progress(50, move |pos| log.lock().unwrap().push(pos.message_index))
+            .build(&data[..]);
+        let results: Vec<_> = stream.by_ref().map(|m| m.map(|m| m.tag)).collect();
+        assert_eq!(results.len(), 5);
+        assert!(results[2].is_err());
+        assert_eq!(results[3].as_ref().unwrap(), &b'A');
+        assert_eq!(*positions.lock().unwrap(), [2, 3]);
+    }
+
+    #[test]
+    fn resyncs_after_corruption() {
+        // garbage between the directory and the first order
+        let mut data = session();
+        data.splice(55..55, [0, 1, b'z', 9, 9, 9, 9]);
+        let results = |policy| {
+            let mut stream = MessageStream::builder()
+                .error_policy(policy)
+                .build(&data[..]);
+            let results: Vec<_> = stream.by_ref().map(|m| m.ok().map(|m| m.tag)).collect();
+            assert!(stream.next().is_none());
+            results
+        };
+        assert_eq!(results(ErrorPolicy::Halt), [Some(b'S'), Some(b'R'), None]);
+        // the garbage's length prefix leads the stream astray
+        let skipped = results(ErrorPolicy::SkipMessage);
+        assert!(!skipped.contains(&Some(b'A')));
+        assert_eq!(
+            results(ErrorPolicy::SkipToNextValid),
+            [
+                Some(b'S'),
+                Some(b'R'),
+                None,
+                Some(b'A'),
+                Some(b'A'),
+                Some(b'S')
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_error_context() {
+        // a system event with an invalid event code, shorter than the
+        // default context
+        let data = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 9958");
+        let error = |context| {
+            let mut stream = MessageStream::builder()
+                .error_context(context)
+                .build(&data[..]);
+            stream.next().unwrap().unwrap_err().to_string()
+        };
+        assert!(error(ErrorContext::default()).contains("[0, 12, 83, "));
+        assert!(error(ErrorContext::Hex(3)).contains("buffer context 00 0c 53"));
+        assert!(!error(ErrorContext::Omit).contains("context"));
+    }
+}
@@ -0,0 +1,524 @@
+use std::fmt::Write as _;
+use std::io;
+use std::mem;
+use std::num::NonZeroU32;
+use std::ops::ControlFlow;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use clickhouse_rs::{Block, ClientHandle, Pool};
+
+use crate::export::PRICE4_SCALE;
+use crate::{Body, Error, Message, MessageSink, Result};
+
+// Generates a batch of rows for each table, in columns as sent over the
+// native protocol, and `Batches` holding one of each. Every table starts
+// with the timestamp, stock locate and tracking number of the message.
+macro_rules! tables {
+    ($($field:ident: $rows:ident = $table:literal order by $order:literal {
+        $($col:ident: $ty:ty = $sql:literal,)*
+    })*) => {
+        $(
+            #[derive(Debug, Default)]
+            struct $rows {
+                timestamp: Vec<u64>,
+                stock_locate: Vec<u16>,
+                tracking_number: Vec<u16>,
+                $($col: Vec<$ty>,)*
+            }
+
+            impl $rows {
+                fn header(&mut self, msg: &Message) {
+                    self.timestamp.push(msg.timestamp);
+                    self.stock_locate.push(msg.stock_locate);
+                    self.tracking_number.push(msg.tracking_number);
+                }
+
+                fn len(&self) -> usize {
+                    self.timestamp.len()
+                }
+
+                fn take_block(&mut self) -> Block {
+                    let rows = mem::take(self);
+                    Block::new()
+                        .column("timestamp", rows.timestamp)
+                        .column("stock_locate", rows.stock_locate)
+                        .column("tracking_number", rows.tracking_number)
+                        $(.column(stringify!($col), rows.$col))*
+                }
+
+                fn ddl(prefix: &str, price_scale: NonZeroU32) -> String {
+                    let mut ddl = format!(
+                        "CREATE TABLE IF NOT EXISTS {}{} (timestamp UInt64, \
+                         stock_locate UInt16, tracking_number UInt16",
+                        prefix, $table
+                    );
+                    $(
+                        ddl.push_str(concat!(", ", stringify!($col), " ", $sql));
+                        if stringify!($col) == "price" {
+                            // writing to a String cannot fail
+                            let _ = write!(ddl, " COMMENT '{} per dollar'", price_scale);
+                        }
+                    )*
+                    ddl.push_str(concat!(") ENGINE = MergeTree ORDER BY (", $order, ")"));
+                    ddl
+                }
+            }
+        )*
+
+        #[derive(Debug, Default)]
+        struct Batches {
+            $($field: $rows,)*
+        }
+
+        impl Batches {
+            fn ddl(prefix: &str, price_scale: NonZeroU32) -> Vec<String> {
+                vec![$($rows::ddl(prefix, price_scale)),*]
+            }
+
+            // take the batches of at least `rows` rows, with their tables
+            fn take(&mut self, rows: usize) -> Vec<(&'static str, Block)> {
+                let mut full = Vec::new();
+                $(
+                    if self.$field.len() >= rows.max(1) {
+                        full.push(($table, self.$field.take_block()));
+                    }
+                )*
+                full
+            }
+        }
+    };
+}
+
+tables! {
+    orders: OrderRows = "orders" order by "stock_locate, timestamp" {
+        reference: u64 = "UInt64",
+        side: String = "FixedString(1)",
+        shares: u32 = "UInt32",
+        stock: String = "String",
+        price: u32 = "UInt32",
+        mpid: Option<String> = "Nullable(String)",
+    }
+    executions: ExecutionRows = "executions" order by "stock_locate, timestamp" {
+        reference: u64 = "UInt64",
+        executed: u32 = "UInt32",
+        match_number: u64 = "UInt64",
+        printable: u8 = "UInt8",
+        price: Option<u32> = "Nullable(UInt32)",
+    }
+    cancels: CancelRows = "cancels" order by "stock_locate, timestamp" {
+        reference: u64 = "UInt64",
+        cancelled: Option<u32> = "Nullable(UInt32)",
+    }
+    replaces: ReplaceRows = "replaces" order by "stock_locate, timestamp" {
+        old_reference: u64 = "UInt64",
+        new_reference: u64 = "UInt64",
+        shares: u32 = "UInt32",
+        price: u32 = "UInt32",
+    }
+    trades: TradeRows = "trades" order by "stock_locate, timestamp" {
+        tag: String = "FixedString(1)",
+        stock: String = "String",
+        shares: u64 = "UInt64",
+        price: u32 = "UInt32",
+        match_number: u64 = "UInt64",
+        cross_type: Option<String> = "Nullable(FixedString(1))",
+    }
+    directory: DirectoryRows = "directory" order by "stock_locate, timestamp" {
+        stock: String = "String",
+        market_category: String = "FixedString(1)",
+        financial_status: String = "FixedString(1)",
+        round_lot_size: u32 = "UInt32",
+        issue_classification: String = "FixedString(1)",
+        issue_subtype: String = "String",
+        luld_ref_price_tier: String = "FixedString(1)",
+        etp_leverage_factor: u32 = "UInt32",
+    }
+    system_events: EventRows = "system_events" order by "timestamp" {
+        event: String = "FixedString(1)",
+    }
+}
+
+impl Batches {
+    // add a message to the batch of its table, returning false for message
+    // types which are not loaded
+    fn push(&mut self, msg: &Message) -> bool {
+        use Body::*;
+        match msg.body {
+            AddOrder(ref o) => {
+                let rows = &mut self.orders;
+                rows.header(msg);
+                rows.reference.push(o.reference);
+                rows.side.push(o.side.as_char().into());
+                rows.shares.push(o.shares);
+                rows.stock.push(o.stock.trim_end().into());
+                rows.price.push(o.price.raw());
+                rows.mpid.push(o.mpid.map(|mpid| mpid.trim_end().into()));
+            }
+            OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => {
+                let rows = &mut self.executions;
+                rows.header(msg);
+                rows.reference.push(reference);
+                rows.executed.push(executed);
+                rows.match_number.push(match_number);
+                rows.printable.push(1);
+                rows.price.push(None);
+            }
+            OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            } => {
+                let rows = &mut self.executions;
+                rows.header(msg);
+                rows.reference.push(reference);
+                rows.executed.push(executed);
+                rows.match_number.push(match_number);
+                rows.printable.push(printable.into());
+                rows.price.push(Some(price.raw()));
+            }
+            OrderCancelled {
+                reference,
+                cancelled,
+            } => {
+                self.cancels.header(msg);
+                self.cancels.reference.push(reference);
+                self.cancels.cancelled.push(Some(cancelled));
+            }
+            DeleteOrder { reference } => {
+                self.cancels.header(msg);
+                self.cancels.reference.push(reference);
+                self.cancels.cancelled.push(None);
+            }
+            ReplaceOrder(ref r) => {
+                let rows = &mut self.replaces;
+                rows.header(msg);
+                rows.old_reference.push(r.old_reference);
+                rows.new_reference.push(r.new_reference);
+                rows.shares.push(r.shares);
+                rows.price.push(r.price.raw());
+            }
+            NonCrossTrade(ref t) => {
+                let rows = &mut self.trades;
+                rows.header(msg);
+                rows.tag.push((msg.tag as char).into());
+                rows.stock.push(t.stock.trim_end().into());
+                rows.shares.push(t.shares.into());
+                rows.price.push(t.price.raw());
+                rows.match_number.push(t.match_number);
+                rows.cross_type.push(None);
+            }
+            CrossTrade(ref t) => {
+                let rows = &mut self.trades;
+                rows.header(msg);
+                rows.tag.push((msg.tag as char).into());
+                rows.stock.push(t.stock.trim_end().into());
+                rows.shares.push(t.shares);
+                rows.price.push(t.cross_price.raw());
+                rows.match_number.push(t.match_number);
+                rows.cross_type.push(Some(t.cross_type.as_char().into()));
+            }
+            StockDirectory(ref d) => {
+                let rows = &mut self.directory;
+                rows.header(msg);
+                rows.stock.push(d.stock.trim_end().into());
+                rows.market_category
+                    .push(d.market_category.as_char().into());
+                rows.financial_status
+                    .push(d.financial_status.as_char().into());
+                rows.round_lot_size.push(d.round_lot_size);
+                rows.issue_classification
+                    .push(d.issue_classification.as_char().into());
+                let subtype = d.issue_subtype.as_code();
+                rows.issue_subtype
+                    .push(String::from_utf8_lossy(&subtype).trim_end().into());
+                rows.luld_ref_price_tier
+                    .push(d.luld_ref_price_tier.as_char().into());
+                rows.etp_leverage_factor.push(d.etp_leverage_factor);
+            }
+            SystemEvent { event } => {
+                self.system_events.header(msg);
+                self.system_events.event.push(event.as_char().into());
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+enum Job {
+    Insert(String, Block),
+    Execute(String, SyncSender<Result<()>>),
+}
+
+fn clickhouse_error(e: clickhouse_rs::errors::Error) -> Error {
+    Error::Io(io::Error::other(e))
+}
+
+/// Bulk-loads messages into ClickHouse over its native protocol, with the
+/// `clickhouse` feature.
+///
+/// Messages are added to a batch for the table of their type, and a batch
+/// is inserted as one block once it is full. Orders (A, F), executions
+/// (E, C), cancels and deletes (X, D), replaces (U), trades (P, Q),
+/// stock directory entries (R) and system events (S) are loaded, into
+/// tables named after them which [`create_tables`] creates; other message
+/// types are counted and skipped. Prices are the raw integers of the wire
+/// format, with the [`price_scale`] in the comments of the price columns,
+/// and symbols have their padding removed.
+///
+/// Inserts are made by a background thread, with a bounded number of full
+/// batches waiting for it. Once that many are waiting, loading blocks until
+/// the database catches up, so a slow server holds back the stream rather
+/// than filling memory. Batches not yet full are inserted by
+/// [`finish`](ClickHouseLoader::finish), which must be called for the last
+/// rows to be loaded.
+///
+/// As a [`MessageSink`], insert errors stop the stream and the error is
+/// kept for [`error`](ClickHouseLoader::error).
+///
+/// [`create_tables`]: ClickHouseLoader::create_tables
+/// [`price_scale`]: ClickHouseLoader::price_scale
+pub struct ClickHouseLoader {
+    batches: Batches,
+    batch_size: usize,
+    prefix: String,
+    price_scale: NonZeroU32,
+    jobs: Option<SyncSender<Job>>,
+    worker: Option<JoinHandle<Result<u64>>>,
+    skipped: u64,
+    error: Option<Error>,
+}
+
+impl ClickHouseLoader {
+    /// Connect with the default batches of 100,000 rows, of which up to 4
+    /// wait to be inserted. The URL is that of `clickhouse_rs`, e.g.
+    /// `"tcp://localhost:9000/itch?compression=lz4"`.
+    pub fn connect(url: &str) -> Result<ClickHouseLoader> {
+        ClickHouseLoader::with_batches(url, 100_000, 4)
+    }
+
+    /// Connect, inserting batches of `batch_size` rows of which up to
+    /// `in_flight` wait to be inserted. A `batch_size` of zero is an error.
+    pub fn with_batches(
+        url: &str,
+        batch_size: usize,
+        in_flight: usize,
+    ) -> Result<ClickHouseLoader> {
+        if batch_size == 0 {
+            return Err(Error::Parse("batch size must be non-zero".into()));
+        }
+        let (jobs, queue) = mpsc::sync_channel(in_flight);
+        let (ready_tx, ready) = mpsc::sync_channel(1);
+        let url = url.to_string();
+        // like `thread::spawn`, panics if the OS cannot create a thread
+        #[allow(clippy::expect_used)]
+        let worker = thread::Builder::new()
+            .name("itchy-clickhouse".into())
+            .spawn(move || insert(url, queue, ready_tx))
+            .expect("failed to spawn clickhouse thread");
+        let mut loader = ClickHouseLoader {
+            batches: Batches::default(),
+            batch_size,
+            prefix: String::new(),
+            price_scale: PRICE4_SCALE,
+            jobs: Some(jobs),
+            worker: Some(worker),
+            skipped: 0,
+            error: None,
+        };
+        match ready.recv() {
+            Ok(Ok(())) => Ok(loader),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(loader.worker_error()),
+        }
+    }
+
+    /// Prefix the table names, e.g. with a session date
+    pub fn table_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Raw units per dollar in prices (10,000 for ITCH 5.0), see
+    /// [`MessageStream::price_scale`](crate::MessageStream::price_scale)
+    pub fn price_scale(mut self, scale: NonZeroU32) -> Self {
+        self.price_scale = scale;
+        self
+    }
+
+    /// Create any of the tables which do not exist yet
+    pub fn create_tables(&mut self) -> Result<()> {
+        for ddl in Batches::ddl(&self.prefix, self.price_scale) {
+            let (reply, done) = mpsc::sync_channel(1);
+            self.send(Job::Execute(ddl, reply))?;
+            match done.recv() {
+                Ok(result) => result?,
+                Err(_) => return Err(self.worker_error()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a message to its batch, inserting any batch that is full
+    pub fn load(&mut self, msg: &Message) -> Result<()> {
+        if !self.batches.push(msg) {
+            self.skipped += 1;
+            return Ok(());
+        }
+        self.insert_batches(self.batch_size)
+    }
+
+    /// Messages of types which are not loaded
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// The error that stopped the stream, as a sink
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Insert the remaining rows and wait for every insert to complete,
+    /// returning the number of rows inserted, or the first error met
+    pub fn finish(mut self) -> Result<u64> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.insert_batches(1)?;
+        // closing the queue ends the thread once it is drained
+        self.jobs = None;
+        match self.worker.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            _ => Err(Error::Io(io::Error::other("clickhouse thread panicked"))),
+        }
+    }
+
+    fn insert_batches(&mut self, rows: usize) -> Result<()> {
+        for (table, block) in self.batches.take(rows) {
+            let table = format!("{}{}", self.prefix, table);
+            self.send(Job::Insert(table, block))?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, job: Job) -> Result<()> {
+        let sent = match self.jobs {
+            Some(ref jobs) => jobs.send(job).is_ok(),
+            None => false,
+        };
+        if sent {
+            Ok(())
+        } else {
+            Err(self.worker_error())
+        }
+    }
+
+    // the error which ended the thread, which has closed its queue
+    fn worker_error(&mut self) -> Error {
+        self.jobs = None;
+        match self.worker.take().map(JoinHandle::join) {
+            Some(Ok(Err(e))) => e,
+            _ => Error::Io(io::Error::other("clickhouse thread exited")),
+        }
+    }
+}
+
+impl MessageSink for ClickHouseLoader {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.load(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+// the background thread: connect, then run jobs until the queue is closed,
+// returning the number of rows inserted
+fn insert(url: String, queue: Receiver<Job>, ready: SyncSender<Result<()>>) -> Result<u64> {
+    let pool = Pool::new(url);
+    let connected = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(Error::from)
+        .and_then(|runtime| {
+            let client: ClientHandle = runtime
+                .block_on(pool.get_handle())
+                .map_err(clickhouse_error)?;
+            Ok((runtime, client))
+        });
+    let (runtime, mut client) = match connected {
+        Ok(connected) => {
+            let _ = ready.send(Ok(()));
+            connected
+        }
+        Err(e) => {
+            // returned by `with_batches` instead
+            let _ = ready.send(Err(e));
+            return Ok(0);
+        }
+    };
+    let mut inserted = 0;
+    for job in queue {
+        match job {
+            Job::Insert(table, block) => {
+                let rows = block.row_count() as u64;
+                runtime
+                    .block_on(client.insert(table, block))
+                    .map_err(clickhouse_error)?;
+                inserted += rows;
+            }
+            Job::Execute(sql, reply) => {
+                let result = runtime.block_on(client.execute(sql));
+                let _ = reply.send(result.map_err(clickhouse_error));
+            }
+        }
+    }
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::tests::directory_msg;
+    use crate::orders::tests::{add, msg};
+    use crate::{LevelBreached, Side};
+
+    #[test]
+    fn batches_by_table() {
+        let mut batches = Batches::default();
+        assert!(batches.push(&directory_msg(1, "AB")));
+        for reference in 0..3 {
+            assert!(batches.push(&msg(5, add(reference, Side::Buy, 100, 10_000))));
+        }
+        assert!(batches.push(&msg(6, Body::DeleteOrder { reference: 1 })));
+        assert!(!batches.push(&msg(7, Body::Breach(LevelBreached::L1))));
+
+        let full = batches.take(3);
+        assert_eq!(full.len(), 1);
+        let (table, ref block) = full[0];
+        assert_eq!((table, block.row_count()), ("orders", 3));
+        assert_eq!(block.column_count(), 9);
+        let rest: Vec<_> = batches.take(1).into_iter().map(|(t, _)| t).collect();
+        assert_eq!(rest, ["cancels", "directory"]);
+        assert!(batches.take(1).is_empty());
+
+        let ddl = Batches::ddl("day1_", PRICE4_SCALE);
+        assert_eq!(ddl.len(), 7);
+        assert!(ddl[0].starts_with(
+            "CREATE TABLE IF NOT EXISTS day1_orders (timestamp UInt64, \
+             stock_locate UInt16, tracking_number UInt16, reference UInt64"
+        ));
+        assert!(ddl[0].contains("price UInt32 COMMENT '10000 per dollar'"));
+        assert!(ddl[6].ends_with("ENGINE = MergeTree ORDER BY (timestamp)"));
+    }
+}
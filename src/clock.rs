@@ -0,0 +1,328 @@
+//! Trading session clock utilities.
+//!
+//! ITCH timestamps are nanoseconds since midnight US Eastern time. A
+//! [`Session`] ties them to a calendar date so they can be converted to
+//! absolute UTC times, with daylight saving time handled according to the
+//! US rules in force since 2007.
+//!
+//! A [`ClockSource`] is the time that paced replay runs against: the
+//! system clock, or a [`SimulatedClock`] for deterministic tests.
+
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::TimeOfDay;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+const NANOS_PER_HOUR: u64 = 3600 * NANOS_PER_SEC;
+
+/// Start of the pre-market session, 04:00 Eastern
+pub const PRE_MARKET_OPEN: u64 = 4 * NANOS_PER_HOUR;
+/// Start of regular trading hours, 09:30 Eastern
+pub const MARKET_OPEN: u64 = 9 * NANOS_PER_HOUR + 30 * 60 * NANOS_PER_SEC;
+/// End of regular trading hours, 16:00 Eastern
+pub const MARKET_CLOSE: u64 = 16 * NANOS_PER_HOUR;
+/// End of the post-market session, 20:00 Eastern
+pub const POST_MARKET_CLOSE: u64 = 20 * NANOS_PER_HOUR;
+
+// DST changes over at 02:00 local time
+const DST_CHANGEOVER: u64 = 2 * NANOS_PER_HOUR;
+
+/// A trading day, used to interpret ITCH timestamps
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Session {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+impl Session {
+    /// Returns `None` if the date is not valid, or not in the years 1900
+    /// to 2200, whose times all fit in `i64` nanoseconds since the epoch
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Session> {
+        if !(1900..=2200).contains(&year)
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+        {
+            return None;
+        }
+        Some(Session { year, month, day })
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Days since 1970-01-01
+    pub fn epoch_days(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    /// Offset of Eastern time from UTC at the given timestamp, in seconds
+    pub fn utc_offset(&self, ts: u64) -> i32 {
+        const EST: i32 = -5 * 3600;
+        const EDT: i32 = -4 * 3600;
+        let days = self.epoch_days();
+        let dst_start = nth_sunday(self.year, 3, 2);
+        let dst_end = nth_sunday(self.year, 11, 1);
+        let dst = if days == dst_start {
+            ts >= DST_CHANGEOVER
+        } else if days == dst_end {
+            ts < DST_CHANGEOVER
+        } else {
+            days > dst_start && days < dst_end
+        };
+        if dst {
+            EDT
+        } else {
+            EST
+        }
+    }
+
+    /// Nanoseconds since the Unix epoch (UTC) for a timestamp in this session,
+    /// saturating for timestamps days past the session
+    pub fn to_utc_nanos(&self, ts: u64) -> i64 {
+        let midnight = self
+            .epoch_days()
+            .saturating_mul(86_400 * NANOS_PER_SEC as i64);
+        let local = midnight.saturating_add(i64::try_from(ts).unwrap_or(i64::MAX));
+        local.saturating_sub(self.utc_offset(ts) as i64 * NANOS_PER_SEC as i64)
+    }
+
+    /// Eastern wall-clock time of a timestamp, `None` if it is a day or
+    /// more after midnight
+    pub fn to_eastern(&self, ts: u64) -> Option<TimeOfDay> {
+        TimeOfDay::from_seconds(u32::try_from(ts / NANOS_PER_SEC).ok()?)
+    }
+
+    /// UTC wall-clock time of a timestamp
+    pub fn to_utc(&self, ts: u64) -> TimeOfDay {
+        let secs = self.to_utc_nanos(ts).div_euclid(NANOS_PER_SEC as i64);
+        TimeOfDay::wrapping(secs.rem_euclid(86_400) as u32)
+    }
+
+    /// Between 09:30 and 16:00 Eastern
+    pub fn is_market_hours(&self, ts: u64) -> bool {
+        (MARKET_OPEN..MARKET_CLOSE).contains(&ts)
+    }
+
+    /// Between 04:00 and 09:30 Eastern
+    pub fn is_pre_market(&self, ts: u64) -> bool {
+        (PRE_MARKET_OPEN..MARKET_OPEN).contains(&ts)
+    }
+
+    /// Between 16:00 and 20:00 Eastern
+    pub fn is_post_market(&self, ts: u64) -> bool {
+        (MARKET_CLOSE..POST_MARKET_CLOSE).contains(&ts)
+    }
+}
+
+/// Start of the bar of length `bar` nanoseconds containing `ts`.
+///
+/// Bars are aligned to Eastern midnight, so e.g. one minute bars start on
+/// whole minutes of Eastern wall-clock time regardless of daylight saving.
+pub fn snap(ts: u64, bar: NonZeroU64) -> u64 {
+    ts - ts % bar
+}
+
+/// Start of the bar containing `ts`, with bars aligned to `origin`
+/// (e.g. [`MARKET_OPEN`]). Timestamps before `origin` snap to earlier bars,
+/// or to zero if the bar would start before midnight.
+pub fn snap_from(ts: u64, origin: u64, bar: NonZeroU64) -> u64 {
+    if ts >= origin {
+        origin + (ts - origin) / bar * bar.get()
+    } else {
+        let bars = (origin - ts).div_ceil(bar.get());
+        origin.saturating_sub(bars.saturating_mul(bar.get()))
+    }
+}
+
+/// A source of the current time, in nanoseconds
+pub trait ClockSource {
+    fn now(&self) -> u64;
+
+    /// Wait until `now()` is at least `deadline`
+    fn sleep_until(&self, deadline: u64);
+}
+
+impl<C: ClockSource + ?Sized> ClockSource for &C {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+
+    fn sleep_until(&self, deadline: u64) {
+        (**self).sleep_until(deadline)
+    }
+}
+
+/// The system clock, in nanoseconds since the Unix epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct WallClock;
+
+impl ClockSource for WallClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    }
+
+    fn sleep_until(&self, deadline: u64) {
+        let now = self.now();
+        if deadline > now {
+            std::thread::sleep(Duration::from_nanos(deadline - now));
+        }
+    }
+}
+
+/// A clock that only moves when told to. Sleeping jumps straight to the
+/// deadline, so paced replay runs instantly and deterministically.
+///
+/// Clones share the same time, so a test can keep one to inspect or
+/// advance the clock while a replayer owns another.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedClock {
+    now: Arc<AtomicU64>,
+}
+
+impl SimulatedClock {
+    pub fn new(start: u64) -> SimulatedClock {
+        SimulatedClock {
+            now: Arc::new(AtomicU64::new(start)),
+        }
+    }
+
+    pub fn advance(&self, nanos: u64) {
+        self.now.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Move the clock to `time`, which may be earlier than the current time
+    pub fn set(&self, time: u64) {
+        self.now.store(time, Ordering::Relaxed);
+    }
+}
+
+impl ClockSource for SimulatedClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+
+    fn sleep_until(&self, deadline: u64) {
+        self.now.fetch_max(deadline, Ordering::Relaxed);
+    }
+}
+
+fn is_leap(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date
+// (see http://howardhinnant.github.io/date_algorithms.html)
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year } as i64;
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Epoch day of the nth Sunday of a month
+fn nth_sunday(year: i32, month: u8, n: i64) -> i64 {
+    let first = days_from_civil(year, month, 1);
+    // 1970-01-01 was a Thursday; weekday 0 is Sunday
+    let weekday = (first + 4).rem_euclid(7);
+    first + (7 - weekday) % 7 + 7 * (n - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = NANOS_PER_HOUR;
+
+    #[test]
+    fn utc_conversion() {
+        let winter = Session::new(2024, 1, 15).unwrap();
+        assert_eq!(winter.utc_offset(MARKET_OPEN), -5 * 3600);
+        // 2024-01-15T14:30:00Z
+        assert_eq!(
+            winter.to_utc_nanos(MARKET_OPEN),
+            1_705_329_000 * NANOS_PER_SEC as i64
+        );
+        assert_eq!(winter.to_utc(MARKET_OPEN).to_string(), "14:30:00");
+        assert!(Session::new(i32::MAX, 1, 1).is_none());
+        let last = Session::new(2200, 12, 31).unwrap();
+        assert_eq!(last.to_utc_nanos(u64::MAX), i64::MAX);
+
+        let summer = Session::new(2019, 8, 30).unwrap();
+        assert_eq!(summer.to_utc(MARKET_OPEN).to_string(), "13:30:00");
+        assert_eq!(
+            summer.to_eastern(MARKET_OPEN).unwrap().to_string(),
+            "09:30:00"
+        );
+        assert_eq!(summer.to_eastern(24 * HOUR), None);
+        assert_eq!(summer.to_utc(22 * HOUR).to_string(), "02:00:00");
+    }
+
+    #[test]
+    fn dst_transitions() {
+        // DST started on 2024-03-10 and ended on 2024-11-03
+        let start = Session::new(2024, 3, 10).unwrap();
+        assert_eq!(start.utc_offset(HOUR), -5 * 3600);
+        assert_eq!(start.utc_offset(3 * HOUR), -4 * 3600);
+        assert_eq!(Session::new(2024, 3, 9).unwrap().utc_offset(0), -5 * 3600);
+        let end = Session::new(2024, 11, 3).unwrap();
+        assert_eq!(end.utc_offset(HOUR), -4 * 3600);
+        assert_eq!(end.utc_offset(3 * HOUR), -5 * 3600);
+        assert_eq!(Session::new(2024, 11, 4).unwrap().utc_offset(0), -5 * 3600);
+    }
+
+    #[test]
+    fn session_periods_and_bars() {
+        let s = Session::new(2024, 2, 29).unwrap();
+        assert!(Session::new(2023, 2, 29).is_none());
+        assert!(s.is_pre_market(MARKET_OPEN - 1));
+        assert!(s.is_market_hours(MARKET_OPEN));
+        assert!(!s.is_market_hours(MARKET_CLOSE));
+        assert!(s.is_post_market(MARKET_CLOSE));
+
+        let minute = 60 * NANOS_PER_SEC;
+        let nonzero = |n| NonZeroU64::new(n).unwrap();
+        assert_eq!(
+            snap(MARKET_OPEN + 61 * NANOS_PER_SEC, nonzero(minute)),
+            MARKET_OPEN + minute
+        );
+        let bar = nonzero(45 * minute);
+        assert_eq!(
+            snap_from(MARKET_OPEN + 50 * minute, MARKET_OPEN, bar),
+            MARKET_OPEN + bar.get()
+        );
+        assert_eq!(
+            snap_from(MARKET_OPEN - minute, MARKET_OPEN, bar),
+            MARKET_OPEN - bar.get()
+        );
+        assert_eq!(snap_from(5, MARKET_OPEN, nonzero(u64::MAX)), 0);
+    }
+}
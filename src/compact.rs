@@ -0,0 +1,267 @@
+use crate::{AddOrder, ArrayString8, Body, Message, Price4, ReplaceOrder, Side};
+
+/// A [`Message`] in at most 48 bytes rather than 72, for holding large
+/// numbers of messages in memory, e.g. a session buffered for replay.
+///
+/// `Body` is as large as its largest variant, so every message pays for
+/// the biggest message type. Here the order flow messages which make up
+/// most of a session are stored inline, in an [`OrderMessage`], and every
+/// other message type, including attributed adds ('F'), is boxed.
+/// Conversion to and from `Message` is lossless.
+///
+/// The `compact` group of the `pipeline` benchmark compares walking a
+/// session held in each form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CompactMessage {
+    Order(OrderMessage),
+    Other(Box<Message>),
+}
+
+/// An order flow message of a [`CompactMessage`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OrderMessage {
+    pub stock_locate: u16,
+    pub tracking_number: u16,
+    pub timestamp: u64,
+    pub body: OrderBody,
+}
+
+/// The body of an [`OrderMessage`], with the fields of the corresponding
+/// [`Body`] variants
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OrderBody {
+    AddOrder {
+        reference: u64,
+        side: Side,
+        shares: u32,
+        stock: ArrayString8,
+        price: Price4,
+    },
+    DeleteOrder {
+        reference: u64,
+    },
+    OrderCancelled {
+        reference: u64,
+        cancelled: u32,
+    },
+    OrderExecuted {
+        reference: u64,
+        executed: u32,
+        match_number: u64,
+    },
+    OrderExecutedWithPrice {
+        reference: u64,
+        executed: u32,
+        match_number: u64,
+        printable: bool,
+        price: Price4,
+    },
+    ReplaceOrder(ReplaceOrder),
+}
+
+impl OrderBody {
+    /// The message type of the body
+    pub fn tag(&self) -> u8 {
+        match self {
+            OrderBody::AddOrder { .. } => b'A',
+            OrderBody::DeleteOrder { .. } => b'D',
+            OrderBody::OrderCancelled { .. } => b'X',
+            OrderBody::OrderExecuted { .. } => b'E',
+            OrderBody::OrderExecutedWithPrice { .. } => b'C',
+            OrderBody::ReplaceOrder(_) => b'U',
+        }
+    }
+
+    fn from_body(tag: u8, body: &Body) -> Option<OrderBody> {
+        let order = match *body {
+            Body::AddOrder(AddOrder {
+                reference,
+                side,
+                shares,
+                stock,
+                price,
+                mpid: None,
+            }) => OrderBody::AddOrder {
+                reference,
+                side,
+                shares,
+                stock,
+                price,
+            },
+            Body::DeleteOrder { reference } => OrderBody::DeleteOrder { reference },
+            Body::OrderCancelled {
+                reference,
+                cancelled,
+            } => OrderBody::OrderCancelled {
+                reference,
+                cancelled,
+            },
+            Body::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => OrderBody::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            },
+            Body::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            } => OrderBody::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            },
+            Body::ReplaceOrder(ref replace) => OrderBody::ReplaceOrder(replace.clone()),
+            _ => return None,
+        };
+        // a message whose tag does not match its body is kept as it is
+        (order.tag() == tag).then_some(order)
+    }
+
+    fn into_body(self) -> Body {
+        match self {
+            OrderBody::AddOrder {
+                reference,
+                side,
+                shares,
+                stock,
+                price,
+            } => Body::AddOrder(AddOrder {
+                reference,
+                side,
+                shares,
+                stock,
+                price,
+                mpid: None,
+            }),
+            OrderBody::DeleteOrder { reference } => Body::DeleteOrder { reference },
+            OrderBody::OrderCancelled {
+                reference,
+                cancelled,
+            } => Body::OrderCancelled {
+                reference,
+                cancelled,
+            },
+            OrderBody::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => Body::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            },
+            OrderBody::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            } => Body::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            },
+            OrderBody::ReplaceOrder(replace) => Body::ReplaceOrder(replace),
+        }
+    }
+}
+
+impl CompactMessage {
+    pub fn tag(&self) -> u8 {
+        match self {
+            CompactMessage::Order(order) => order.body.tag(),
+            CompactMessage::Other(msg) => msg.tag,
+        }
+    }
+
+    pub fn stock_locate(&self) -> u16 {
+        match self {
+            CompactMessage::Order(order) => order.stock_locate,
+            CompactMessage::Other(msg) => msg.stock_locate,
+        }
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            CompactMessage::Order(order) => order.timestamp,
+            CompactMessage::Other(msg) => msg.timestamp,
+        }
+    }
+
+    /// The order flow message, if this is one
+    pub fn as_order(&self) -> Option<&OrderMessage> {
+        match self {
+            CompactMessage::Order(order) => Some(order),
+            CompactMessage::Other(_) => None,
+        }
+    }
+}
+
+impl From<Message> for CompactMessage {
+    fn from(msg: Message) -> CompactMessage {
+        match OrderBody::from_body(msg.tag, &msg.body) {
+            Some(body) => CompactMessage::Order(OrderMessage {
+                stock_locate: msg.stock_locate,
+                tracking_number: msg.tracking_number,
+                timestamp: msg.timestamp,
+                body,
+            }),
+            None => CompactMessage::Other(Box::new(msg)),
+        }
+    }
+}
+
+impl From<CompactMessage> for Message {
+    fn from(msg: CompactMessage) -> Message {
+        match msg {
+            CompactMessage::Order(order) => Message {
+                tag: order.body.tag(),
+                stock_locate: order.stock_locate,
+                tracking_number: order.tracking_number,
+                timestamp: order.timestamp,
+                body: order.body.into_body(),
+            },
+            CompactMessage::Other(msg) => *msg,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+
+    #[test]
+    fn round_trips_in_48_bytes() {
+        assert_eq!(std::mem::size_of::<CompactMessage>(), 48);
+        let tagged = |tag, body| Message {
+            tag,
+            ..msg(5, body)
+        };
+        let mut attributed = add(2, Side::Sell, 100, 10_000);
+        if let Body::AddOrder(ref mut add) = attributed {
+            add.mpid = Some(crate::ArrayString4::from("NITE").unwrap());
+        }
+        let messages = [
+            tagged(b'A', add(1, Side::Buy, 100, 10_000)),
+            tagged(b'F', attributed),
+            tagged(b'D', Body::DeleteOrder { reference: 1 }),
+        ];
+        let compact: Vec<_> = messages.iter().cloned().map(CompactMessage::from).collect();
+        assert!(compact[0].as_order().is_some());
+        assert!(compact[1].as_order().is_none());
+        assert_eq!(compact[2].tag(), b'D');
+        let expanded: Vec<Message> = compact.into_iter().map(Message::from).collect();
+        assert_eq!(expanded, messages);
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use crate::{Body, Message, Price4};
+
+/// How thoroughly a [`MessageStream`] checks messages against the spec
+///
+/// [`MessageStream`]: crate::MessageStream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ValidationLevel {
+    /// Only what is needed to parse each message, which rejects unknown
+    /// codes in enumerated fields
+    #[default]
+    Basic,
+    /// Also check that alpha fields only hold allowed characters, reserved
+    /// bytes are blank, timestamps fall within the day and order prices
+    /// and sizes are plausible. A message failing a check is reported as
+    /// an error naming the field, and the stream carries on.
+    Pedantic,
+}
+
+/// Nasdaq does not accept orders priced above $199,999.9999
+const MAX_PRICE: u32 = 1_999_999_999;
+
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+
+/// A spec violation found by [`ValidationLevel::Pedantic`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Violation {
+    tag: u8,
+    /// Field name, as in the spec
+    field: &'static str,
+    reason: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "'{}' message field '{}' {}",
+            self.tag as char, self.field, self.reason
+        )
+    }
+}
+
+/// Check a parsed message, with its unframed bytes, beyond what parsing
+/// requires
+pub(crate) fn check(input: &[u8], msg: &Message) -> Option<Violation> {
+    let violation = |field, reason: String| {
+        Some(Violation {
+            tag: msg.tag,
+            field,
+            reason,
+        })
+    };
+    if msg.timestamp >= NANOS_PER_DAY {
+        return violation("Timestamp", format!("{} is after midnight", msg.timestamp));
+    }
+    if let Some(stock) = msg.body.stock() {
+        if let Some(reason) = check_symbol(stock.as_bytes()) {
+            return violation("Stock", reason);
+        }
+    }
+    match msg.body {
+        Body::AddOrder(ref add) => {
+            if let Some(mpid) = add.mpid {
+                if !mpid.bytes().all(|b| b.is_ascii_uppercase()) {
+                    return violation("Attribution", format!("{:?} is not alphabetic", mpid));
+                }
+            }
+            check_order(add.shares, add.price).and_then(|(f, r)| violation(f, r))
+        }
+        Body::ReplaceOrder(ref replace) => {
+            check_order(replace.shares, replace.price).and_then(|(f, r)| violation(f, r))
+        }
+        Body::OrderExecuted { executed: 0, .. }
+        | Body::OrderExecutedWithPrice { executed: 0, .. } => {
+            violation("Executed Shares", "is zero".into())
+        }
+        Body::OrderCancelled { cancelled: 0, .. } => {
+            violation("Cancelled Shares", "is zero".into())
+        }
+        Body::NonCrossTrade(ref trade) => {
+            check_order(trade.shares, trade.price).and_then(|(f, r)| violation(f, r))
+        }
+        Body::TradingAction { reason, .. } => {
+            // a single reserved byte follows the trading state
+            if !matches!(input.get(20), Some(b' ' | 0)) {
+                return violation("Reserved", "is not blank".into());
+            }
+            if !reason
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b' ')
+            {
+                return violation("Reason", format!("{:?} is not alphanumeric", reason));
+            }
+            None
+        }
+        Body::ParticipantPosition(ref position) => {
+            if !position.mpid.bytes().all(|b| b.is_ascii_uppercase()) {
+                return violation("MPID", format!("{:?} is not alphabetic", position.mpid));
+            }
+            None
+        }
+        Body::Imbalance(ref imbalance) => match imbalance.price_variation_indicator {
+            'L' | '1'..='9' | 'A' | 'B' | 'C' | ' ' => None,
+            other => violation(
+                "Price Variation Indicator",
+                format!("{:?} is not a documented value", other),
+            ),
+        },
+        _ => None,
+    }
+}
+
+// symbols are upper case, with suffix punctuation, left justified and
+// padded with spaces
+fn check_symbol(stock: &[u8]) -> Option<String> {
+    let len = stock.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+    if len == 0 {
+        return Some("is blank".into());
+    }
+    let allowed = |b: u8| b.is_ascii_uppercase() || b.is_ascii_digit() || b".-+=^#*".contains(&b);
+    if !stock[..len].iter().all(|&b| allowed(b)) {
+        return Some(format!(
+            "{:?} has characters other than upper case letters and suffix punctuation",
+            String::from_utf8_lossy(stock)
+        ));
+    }
+    None
+}
+
+fn check_order(shares: u32, price: Price4) -> Option<(&'static str, String)> {
+    if shares == 0 {
+        return Some(("Shares", "is zero".into()));
+    }
+    if price.raw() == 0 || price.raw() > MAX_PRICE {
+        let (whole, frac) = price.to_parts();
+        return Some(("Price", format!("{}.{:04} is out of bounds", whole, frac)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{
+        ArrayString4, ArrayString8, MessageStream, MessageStreamBuilder, Side, TradingState,
+    };
+
+    #[test]
+    fn reports_violations() {
+        let tagged = |tag, body| Message {
+            tag,
+            ..msg(1, body)
+        };
+        let halt = |stock| Body::TradingAction {
+            stock: ArrayString8::from(stock).unwrap(),
+            trading_state: TradingState::Halted,
+            reason: ArrayString4::from("LUDP").unwrap(),
+        };
+        let mut data = Vec::new();
+        for m in [
+            tagged(b'A', add(1, Side::Buy, 100, 10_000)),
+            tagged(b'A', add(2, Side::Buy, 100, 0)),
+            tagged(b'H', halt("zx zt   ")),
+            tagged(b'H', halt("ZXZZT   ")),
+        ] {
+            m.encode(&mut data);
+        }
+        // the reserved byte of the last message
+        let last = data.len() - 5;
+        data[last] = b'X';
+
+        let mut basic = MessageStream::from_reader(&data[..]);
+        assert!(basic.all(|m| m.is_ok()));
+        let pedantic = MessageStreamBuilder::new()
+            .validation(ValidationLevel::Pedantic)
+            .build(&data[..]);
+        let errors: Vec<_> = pedantic.map(|m| m.err().map(|e| e.to_string())).collect();
+        assert!(errors[0].is_none());
+        assert!(errors[1].as_ref().unwrap().contains("field 'Price'"));
+        assert!(errors[2].as_ref().unwrap().contains("field 'Stock'"));
+        assert!(errors[3].as_ref().unwrap().contains("field 'Reserved'"));
+    }
+}
@@ -0,0 +1,352 @@
+use std::iter::Peekable;
+
+use crate::{Book, OrderBook, OrderUpdate, Price4, PriceGrid, PriceLevel, Side};
+
+/// One side of a [`DenseBook`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DenseSide {
+    side: Side,
+    // (shares, orders) by grid bucket
+    levels: Vec<(u64, u32)>,
+    best: Option<usize>,
+}
+
+impl DenseSide {
+    fn new(side: Side, levels: usize) -> DenseSide {
+        DenseSide {
+            side,
+            levels: vec![(0, 0); levels],
+            best: None,
+        }
+    }
+
+    fn add(&mut self, ix: usize, shares: u64) {
+        let level = &mut self.levels[ix];
+        level.0 += shares;
+        level.1 += 1;
+        self.best = Some(match (self.side, self.best) {
+            (_, None) => ix,
+            (Side::Buy, Some(best)) => best.max(ix),
+            (Side::Sell, Some(best)) => best.min(ix),
+        });
+    }
+
+    fn remove(&mut self, ix: usize, shares: u64, terminal: bool) {
+        let level = &mut self.levels[ix];
+        level.0 = level.0.saturating_sub(shares);
+        if terminal {
+            level.1 = level.1.saturating_sub(1);
+        }
+        if level.1 > 0 || self.best != Some(ix) {
+            return;
+        }
+        *level = (0, 0);
+        // scan away from the spread for the next occupied level
+        self.best = match self.side {
+            Side::Buy => (0..ix).rev().find(|&i| self.levels[i].1 > 0),
+            Side::Sell => (ix + 1..self.levels.len()).find(|&i| self.levels[i].1 > 0),
+        };
+    }
+}
+
+/// Full-depth book storing the levels of a [`PriceGrid`] in arrays indexed
+/// by price.
+///
+/// Updates are constant time and the best prices are tracked directly, so
+/// this is the fastest book for symbols whose prices stay on a known tick
+/// grid, at the cost of memory for every level in the grid. Prices off the
+/// grid are still handled, in a sparse [`OrderBook`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenseBook {
+    grid: PriceGrid,
+    bids: DenseSide,
+    asks: DenseSide,
+    outside: OrderBook,
+}
+
+impl DenseBook {
+    pub fn new(grid: PriceGrid) -> DenseBook {
+        DenseBook {
+            grid,
+            bids: DenseSide::new(Side::Buy, grid.levels()),
+            asks: DenseSide::new(Side::Sell, grid.levels()),
+            outside: OrderBook::new(),
+        }
+    }
+
+    pub fn grid(&self) -> &PriceGrid {
+        &self.grid
+    }
+
+    fn slot(&self, price: Price4) -> Option<usize> {
+        self.grid
+            .bucket(price)
+            .filter(|&ix| self.grid.price(ix) == Some(price))
+    }
+
+    fn side(&self, side: Side) -> &DenseSide {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    fn add(&mut self, side: Side, price: Price4, shares: u64) {
+        match (self.slot(price), side) {
+            (Some(ix), Side::Buy) => self.bids.add(ix, shares),
+            (Some(ix), Side::Sell) => self.asks.add(ix, shares),
+            (None, _) => self.outside.add(side, price, shares),
+        }
+    }
+
+    fn remove(&mut self, side: Side, price: Price4, shares: u64, terminal: bool) {
+        match (self.slot(price), side) {
+            (Some(ix), Side::Buy) => self.bids.remove(ix, shares, terminal),
+            (Some(ix), Side::Sell) => self.asks.remove(ix, shares, terminal),
+            (None, _) => self.outside.remove(side, price, shares, terminal),
+        }
+    }
+
+    // grid levels of one side, best first
+    fn grid_levels(&self, side: Side) -> impl Iterator<Item = PriceLevel> + '_ {
+        let dense = self.side(side);
+        let len = dense.levels.len();
+        let range: Box<dyn Iterator<Item = usize>> = match (side, dense.best) {
+            (_, None) => Box::new(0..0),
+            (Side::Buy, Some(best)) => Box::new((0..=best).rev()),
+            (Side::Sell, Some(best)) => Box::new(best..len),
+        };
+        range.filter_map(move |ix| {
+            let (shares, orders) = dense.levels[ix];
+            if orders == 0 {
+                return None;
+            }
+            Some(PriceLevel {
+                price: self.grid.price(ix)?,
+                shares,
+                orders,
+            })
+        })
+    }
+
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        let outside: Box<dyn Iterator<Item = PriceLevel>> = match side {
+            Side::Buy => Box::new(self.outside.bids().copied()),
+            Side::Sell => Box::new(self.outside.asks().copied()),
+        };
+        Box::new(Merge {
+            side,
+            a: self.grid_levels(side).peekable(),
+            b: outside.peekable(),
+        })
+    }
+}
+
+impl Book for DenseBook {
+    fn apply(&mut self, update: &OrderUpdate) {
+        match *update {
+            OrderUpdate::Added { order, .. } => {
+                self.add(order.side, order.price, order.shares as u64);
+            }
+            OrderUpdate::Executed { order, shares, .. }
+            | OrderUpdate::Cancelled { order, shares, .. } => {
+                let terminal = update.is_terminal();
+                self.remove(order.side, order.price, shares as u64, terminal);
+            }
+            OrderUpdate::Deleted { order, .. } => {
+                self.remove(order.side, order.price, order.shares as u64, true);
+            }
+            OrderUpdate::Replaced { old, new, .. } => {
+                self.remove(old.side, old.price, old.shares as u64, true);
+                self.add(new.side, new.price, new.shares as u64);
+            }
+        }
+    }
+
+    fn best_bid(&self) -> Option<PriceLevel> {
+        self.levels(Side::Buy).next()
+    }
+
+    fn best_ask(&self) -> Option<PriceLevel> {
+        self.levels(Side::Sell).next()
+    }
+
+    fn bids(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        self.levels(Side::Buy)
+    }
+
+    fn asks(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        self.levels(Side::Sell)
+    }
+
+    fn level(&self, side: Side, price: Price4) -> Option<PriceLevel> {
+        match self.slot(price) {
+            Some(ix) => {
+                let (shares, orders) = self.side(side).levels[ix];
+                (orders > 0).then_some(PriceLevel {
+                    price,
+                    shares,
+                    orders,
+                })
+            }
+            None => self.outside.level(side, price).copied(),
+        }
+    }
+}
+
+// merges two sequences of levels of one side, best first
+struct Merge<A: Iterator, B: Iterator> {
+    side: Side,
+    a: Peekable<A>,
+    b: Peekable<B>,
+}
+
+impl<A, B> Iterator for Merge<A, B>
+where
+    A: Iterator<Item = PriceLevel>,
+    B: Iterator<Item = PriceLevel>,
+{
+    type Item = PriceLevel;
+
+    fn next(&mut self) -> Option<PriceLevel> {
+        let take_a = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) => match self.side {
+                Side::Buy => a.price > b.price,
+                Side::Sell => a.price < b.price,
+            },
+            (a, _) => a.is_some(),
+        };
+        if take_a {
+            self.a.next()
+        } else {
+            self.b.next()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, BookManager, ReplaceOrder, SymbolBook};
+
+    // a dense book over 90.0000 to 91.9900, in a manager tracking its orders
+    fn books() -> BookManager {
+        let grid = PriceGrid::new(900_000.into(), 100.into(), 200).unwrap();
+        let mut books = BookManager::new();
+        books.set_book(1, SymbolBook::Dense(DenseBook::new(grid)));
+        books
+    }
+
+    fn prices(levels: Box<dyn Iterator<Item = PriceLevel> + '_>) -> Vec<u32> {
+        levels.map(|l| l.price.raw()).collect()
+    }
+
+    #[test]
+    fn recovers_best_prices() {
+        let mut books = books();
+        books.update(&msg(1, add(1, Side::Buy, 100, 905_000)));
+        books.update(&msg(1, add(2, Side::Buy, 100, 907_000)));
+        books.update(&msg(1, add(3, Side::Sell, 100, 909_000)));
+        books.update(&msg(1, add(4, Side::Sell, 100, 912_000)));
+
+        books.update(&msg(2, Body::DeleteOrder { reference: 2 }));
+        books.update(&msg(2, Body::DeleteOrder { reference: 3 }));
+        let book = books.book(1).unwrap();
+        assert_eq!(book.best_bid().unwrap().price.raw(), 905_000);
+        assert_eq!(book.best_ask().unwrap().price.raw(), 912_000);
+
+        books.update(&msg(3, Body::DeleteOrder { reference: 1 }));
+        books.update(&msg(3, Body::DeleteOrder { reference: 4 }));
+        assert!(books.book(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_level_after_partial_execution() {
+        let mut books = books();
+        books.update(&msg(1, add(1, Side::Buy, 300, 907_000)));
+        books.update(&msg(1, add(2, Side::Buy, 100, 905_000)));
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 100,
+            match_number: 1,
+        };
+        books.update(&msg(2, exec));
+        let best = books.book(1).unwrap().best_bid().unwrap();
+        assert_eq!(
+            (best.price.raw(), best.shares, best.orders),
+            (907_000, 200, 1)
+        );
+
+        let cancel = Body::OrderCancelled {
+            reference: 1,
+            cancelled: 200,
+        };
+        books.update(&msg(3, cancel));
+        let book = books.book(1).unwrap();
+        assert!(book.level(Side::Buy, 907_000.into()).is_none());
+        assert_eq!(book.best_bid().unwrap().price.raw(), 905_000);
+    }
+
+    #[test]
+    fn replaces_across_grid_boundary() {
+        let mut books = books();
+        books.update(&msg(1, add(1, Side::Sell, 100, 919_900)));
+        let replace = |old_reference, new_reference, price: u32| {
+            Body::ReplaceOrder(ReplaceOrder {
+                old_reference,
+                new_reference,
+                shares: 100,
+                price: price.into(),
+            })
+        };
+        // off the end of the grid
+        books.update(&msg(2, replace(1, 2, 920_000)));
+        let book = books.book(1).unwrap();
+        assert!(book.level(Side::Sell, 919_900.into()).is_none());
+        assert_eq!(prices(book.asks()), [920_000]);
+
+        // back onto the grid, then between its ticks
+        books.update(&msg(3, replace(2, 3, 919_900)));
+        assert_eq!(prices(books.book(1).unwrap().asks()), [919_900]);
+        books.update(&msg(4, replace(3, 4, 919_950)));
+        let book = books.book(1).unwrap();
+        assert_eq!(prices(book.asks()), [919_950]);
+        assert_eq!(book.level(Side::Sell, 919_950.into()).unwrap().orders, 1);
+    }
+
+    #[test]
+    fn merges_levels_like_order_book() {
+        let mut dense = books();
+        let mut sparse = BookManager::new();
+        // on the grid, between ticks and outside the grid, on both sides
+        let orders = [
+            (Side::Buy, 899_000),
+            (Side::Buy, 905_000),
+            (Side::Buy, 905_050),
+            (Side::Buy, 905_000),
+            (Side::Buy, 910_000),
+            (Side::Sell, 911_000),
+            (Side::Sell, 911_050),
+            (Side::Sell, 925_000),
+            (Side::Sell, 919_900),
+        ];
+        for (reference, (side, price)) in orders.into_iter().enumerate() {
+            let message = msg(1, add(reference as u64, side, 100, price));
+            dense.update(&message);
+            sparse.update(&message);
+        }
+        let (dense, sparse) = (dense.book(1).unwrap(), sparse.book(1).unwrap());
+        assert_eq!(
+            dense.bids().collect::<Vec<_>>(),
+            sparse.bids().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            dense.asks().collect::<Vec<_>>(),
+            sparse.asks().collect::<Vec<_>>()
+        );
+        assert_eq!(prices(dense.bids()), [910_000, 905_050, 905_000, 899_000]);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use crate::{Book, OrderUpdate, Price4, PriceLevel, Side};
+
+/// One side of a [`DepthBook`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ladder {
+    side: Side,
+    depth: usize,
+    // the best `depth` levels, best first
+    top: Vec<PriceLevel>,
+    // every level behind the top, only non-empty while the top is full
+    rest: BTreeMap<Price4, PriceLevel>,
+}
+
+impl Ladder {
+    fn new(side: Side, depth: usize) -> Ladder {
+        Ladder {
+            side,
+            depth,
+            top: Vec::with_capacity(depth + 1),
+            rest: BTreeMap::new(),
+        }
+    }
+
+    fn better(&self, a: Price4, b: Price4) -> bool {
+        match self.side {
+            Side::Buy => a > b,
+            Side::Sell => a < b,
+        }
+    }
+
+    fn add(&mut self, price: Price4, shares: u64) {
+        if let Some(level) = self.top.iter_mut().find(|l| l.price == price) {
+            level.shares += shares;
+            level.orders += 1;
+            return;
+        }
+        if let Some(level) = self.rest.get_mut(&price) {
+            level.shares += shares;
+            level.orders += 1;
+            return;
+        }
+        let level = PriceLevel {
+            price,
+            shares,
+            orders: 1,
+        };
+        let ix = self
+            .top
+            .iter()
+            .position(|l| self.better(price, l.price))
+            .unwrap_or(self.top.len());
+        if ix == self.depth {
+            self.rest.insert(price, level);
+            return;
+        }
+        self.top.insert(ix, level);
+        if self.top.len() > self.depth {
+            if let Some(demoted) = self.top.pop() {
+                self.rest.insert(demoted.price, demoted);
+            }
+        }
+    }
+
+    fn remove(&mut self, price: Price4, shares: u64, terminal: bool) {
+        let reduce = |level: &mut PriceLevel| {
+            level.shares = level.shares.saturating_sub(shares);
+            if terminal {
+                level.orders = level.orders.saturating_sub(1);
+            }
+            level.orders == 0
+        };
+        if let Some(ix) = self.top.iter().position(|l| l.price == price) {
+            if reduce(&mut self.top[ix]) {
+                self.top.remove(ix);
+                let promoted = match self.side {
+                    Side::Buy => self.rest.pop_last(),
+                    Side::Sell => self.rest.pop_first(),
+                };
+                self.top.extend(promoted.map(|(_, level)| level));
+            }
+        } else if let Some(level) = self.rest.get_mut(&price) {
+            if reduce(level) {
+                self.rest.remove(&price);
+            }
+        }
+    }
+}
+
+/// Price-level book which exposes only the best `depth` levels per side.
+///
+/// The visible levels are kept in a small sorted array, so updates near the
+/// top of the book avoid tree lookups. Deeper levels are still aggregated
+/// so that, when a visible level empties, the next best is promoted into
+/// view.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthBook {
+    bids: Ladder,
+    asks: Ladder,
+}
+
+impl DepthBook {
+    /// A book exposing `depth` levels per side
+    ///
+    /// # Panics
+    ///
+    /// If `depth` is zero
+    pub fn new(depth: usize) -> DepthBook {
+        assert!(depth > 0, "book depth must be non-zero");
+        DepthBook {
+            bids: Ladder::new(Side::Buy, depth),
+            asks: Ladder::new(Side::Sell, depth),
+        }
+    }
+
+    /// Number of levels exposed per side
+    pub fn depth(&self) -> usize {
+        self.bids.depth
+    }
+
+    /// Apply an order update from an [`OrderTracker`](crate::OrderTracker)
+    pub fn apply(&mut self, update: &OrderUpdate) {
+        match *update {
+            OrderUpdate::Added { order, .. } => {
+                self.ladder_mut(order.side)
+                    .add(order.price, order.shares as u64);
+            }
+            OrderUpdate::Executed { order, shares, .. }
+            | OrderUpdate::Cancelled { order, shares, .. } => {
+                let terminal = update.is_terminal();
+                self.ladder_mut(order.side)
+                    .remove(order.price, shares as u64, terminal);
+            }
+            OrderUpdate::Deleted { order, .. } => {
+                self.ladder_mut(order.side)
+                    .remove(order.price, order.shares as u64, true);
+            }
+            OrderUpdate::Replaced { old, new, .. } => {
+                self.ladder_mut(old.side)
+                    .remove(old.price, old.shares as u64, true);
+                self.ladder_mut(new.side).add(new.price, new.shares as u64);
+            }
+        }
+    }
+
+    fn ladder_mut(&mut self, side: Side) -> &mut Ladder {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<&PriceLevel> {
+        self.bids.top.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&PriceLevel> {
+        self.asks.top.first()
+    }
+
+    /// Visible bid levels, best (highest) first
+    pub fn bids(&self) -> &[PriceLevel] {
+        &self.bids.top
+    }
+
+    /// Visible ask levels, best (lowest) first
+    pub fn asks(&self) -> &[PriceLevel] {
+        &self.asks.top
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.top.is_empty() && self.asks.top.is_empty()
+    }
+}
+
+impl Book for DepthBook {
+    fn apply(&mut self, update: &OrderUpdate) {
+        DepthBook::apply(self, update)
+    }
+
+    fn best_bid(&self) -> Option<PriceLevel> {
+        DepthBook::best_bid(self).copied()
+    }
+
+    fn best_ask(&self) -> Option<PriceLevel> {
+        DepthBook::best_ask(self).copied()
+    }
+
+    fn bids(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        Box::new(DepthBook::bids(self).iter().copied())
+    }
+
+    fn asks(&self) -> Box<dyn Iterator<Item = PriceLevel> + '_> {
+        Box::new(DepthBook::asks(self).iter().copied())
+    }
+
+    /// The level at a price, if it is among the visible levels
+    fn level(&self, side: Side, price: Price4) -> Option<PriceLevel> {
+        let levels = match side {
+            Side::Buy => DepthBook::bids(self),
+            Side::Sell => DepthBook::asks(self),
+        };
+        levels.iter().find(|l| l.price == price).copied()
+    }
+
+    fn is_empty(&self) -> bool {
+        DepthBook::is_empty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, OrderTracker};
+
+    #[test]
+    fn promotes_hidden_levels() {
+        let mut tracker = OrderTracker::new();
+        let mut book = DepthBook::new(2);
+        let mut apply = |body| {
+            let update = tracker.apply(&msg(0, body)).unwrap().unwrap();
+            book.apply(&update);
+            book.bids()
+                .iter()
+                .map(|l| l.price.raw())
+                .collect::<Vec<_>>()
+        };
+        apply(add(1, Side::Buy, 100, 9_900));
+        apply(add(2, Side::Buy, 100, 10_000));
+        assert_eq!(apply(add(3, Side::Buy, 100, 9_800)), [10_000, 9_900]);
+        assert_eq!(apply(add(4, Side::Buy, 100, 10_100)), [10_100, 10_000]);
+        assert_eq!(apply(Body::DeleteOrder { reference: 4 }), [10_000, 9_900]);
+        assert_eq!(apply(Body::DeleteOrder { reference: 2 }), [9_900, 9_800]);
+        assert_eq!(apply(Body::DeleteOrder { reference: 1 }), [9_800]);
+        assert!(book.best_ask().is_none());
+    }
+}
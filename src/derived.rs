@@ -0,0 +1,239 @@
+// Price types decoded from the fixed-point fields of a message
+
+use std::fmt;
+
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
+
+/// Opaque type representing a price to four decimal places
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Price4(u32);
+
+impl Price4 {
+    /// Number of raw units per whole currency unit
+    pub const SCALE: u32 = 10_000;
+
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+
+    /// Split into whole and fractional (in units of 1/10,000) parts
+    pub fn to_parts(self) -> (u32, u32) {
+        (self.0 / Self::SCALE, self.0 % Self::SCALE)
+    }
+
+    /// The price as the nearest `f64`.
+    ///
+    /// A `Price4` has at most ten significant digits, so the result is
+    /// closer to the exact price than to any other four decimal place price,
+    /// and formatting it with `{}` prints the exact price (without trailing
+    /// zeros, e.g. `12.5` for 12.5000). Arithmetic on the result rounds as
+    /// usual for floating point, so compare raw values where exactness matters.
+    pub fn as_f64(self) -> f64 {
+        f64::from(self.0) / f64::from(Self::SCALE)
+    }
+
+    /// The price as an `f32`, rounded from [`as_f64`](Self::as_f64).
+    ///
+    /// An `f32` holds only about seven significant digits, so prices of
+    /// 1,000 and above may not round-trip to four decimal places.
+    pub fn as_f32(self) -> f32 {
+        self.as_f64() as f32
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<Price4> for Decimal {
+    fn from(val: Price4) -> Self {
+        Self::from(val.0) / Self::from(Price4::SCALE)
+    }
+}
+
+impl From<Price4> for f64 {
+    fn from(val: Price4) -> Self {
+        val.as_f64()
+    }
+}
+
+impl From<u32> for Price4 {
+    fn from(v: u32) -> Price4 {
+        Price4(v)
+    }
+}
+
+/// Opaque type representing a price to eight decimal places
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Price8(u64);
+
+impl Price8 {
+    /// Number of raw units per whole currency unit
+    pub const SCALE: u64 = 100_000_000;
+
+    pub fn raw(self) -> u64 {
+        self.0
+    }
+
+    /// Split into whole and fractional (in units of 1/100,000,000) parts
+    pub fn to_parts(self) -> (u64, u64) {
+        (self.0 / Self::SCALE, self.0 % Self::SCALE)
+    }
+
+    /// The price as the nearest `f64`.
+    ///
+    /// Prices below 10,000,000 have at most fifteen significant digits and
+    /// print exactly with `{}`, as for [`Price4::as_f64`]; larger prices may
+    /// be rounded.
+    pub fn as_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<Price8> for Decimal {
+    fn from(val: Price8) -> Self {
+        Decimal::from(val.0) / Decimal::from(Price8::SCALE)
+    }
+}
+
+impl From<Price8> for f64 {
+    fn from(val: Price8) -> Self {
+        val.as_f64()
+    }
+}
+
+impl From<u64> for Price8 {
+    fn from(v: u64) -> Price8 {
+        Price8(v)
+    }
+}
+
+/// A price with a scale chosen at runtime, for feeds whose prices do not
+/// use the standard ITCH 5.0 decimal places
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScaledPrice {
+    raw: u64,
+    scale: u64,
+}
+
+impl ScaledPrice {
+    /// A price of `raw / scale` currency units
+    ///
+    /// # Panics
+    ///
+    /// If `scale` is zero
+    pub fn new(raw: u64, scale: u64) -> ScaledPrice {
+        assert!(scale > 0, "price scale must be non-zero");
+        ScaledPrice { raw, scale }
+    }
+
+    pub fn raw(self) -> u64 {
+        self.raw
+    }
+
+    /// Number of raw units per whole currency unit
+    pub fn scale(self) -> u64 {
+        self.scale
+    }
+
+    /// Split into whole and fractional (in units of `1 / scale`) parts
+    pub fn to_parts(self) -> (u64, u64) {
+        (self.raw / self.scale, self.raw % self.scale)
+    }
+
+    /// Number of decimal places, `None` if the scale is not a power of ten
+    pub fn decimal_places(self) -> Option<u32> {
+        let places = self.scale.ilog10();
+        (10u64.pow(places) == self.scale).then_some(places)
+    }
+}
+
+/// The exact price with the decimal places of its scale, e.g. `12.5000`
+/// for a scale of 10,000, or the nearest `f64` if the scale is not a power
+/// of ten
+impl fmt::Display for ScaledPrice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.decimal_places() {
+            Some(0) => write!(f, "{}", self.raw),
+            Some(places) => {
+                let (whole, frac) = self.to_parts();
+                write!(f, "{}.{:0width$}", whole, frac, width = places as usize)
+            }
+            None => write!(f, "{}", f64::from(*self)),
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<ScaledPrice> for Decimal {
+    fn from(val: ScaledPrice) -> Self {
+        Decimal::from(val.raw) / Decimal::from(val.scale)
+    }
+}
+
+impl From<ScaledPrice> for f64 {
+    fn from(val: ScaledPrice) -> Self {
+        val.raw as f64 / val.scale as f64
+    }
+}
+
+impl From<Price4> for ScaledPrice {
+    fn from(val: Price4) -> Self {
+        ScaledPrice::new(val.0 as u64, Price4::SCALE as u64)
+    }
+}
+
+impl From<Price8> for ScaledPrice {
+    fn from(val: Price8) -> Self {
+        ScaledPrice::new(val.0, Price8::SCALE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+    #[cfg(feature = "decimal")]
+    use std::str::FromStr;
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_price4() {
+        let p4: Decimal = Price4(12340001).into();
+        assert_eq!(p4, Decimal::from_str("1234.0001").unwrap());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_price8() {
+        let p8: Decimal = Price8(123400010002).into();
+        assert_eq!(p8, Decimal::from_str("1234.00010002").unwrap());
+    }
+
+    #[test]
+    fn test_price_parts() {
+        assert_eq!(Price4(12340001).to_parts(), (1234, 1));
+        assert_eq!(Price8(123400010002).to_parts(), (1234, 10002));
+        assert_eq!(f64::from(Price4(12345000)), 1234.5);
+        assert_eq!(f64::from(Price8(150_000_000)), 1.5);
+    }
+
+    #[test]
+    fn test_scaled_price() {
+        let price = ScaledPrice::new(1_234_500, 1_000);
+        assert_eq!(price.to_parts(), (1234, 500));
+        assert_eq!(f64::from(price), 1234.5);
+        assert_eq!(ScaledPrice::from(Price4(12345000)).to_parts(), (1234, 5000));
+
+        let mut stream = MessageStream::from_reader(&b""[..]);
+        assert_eq!(stream.scaled(Price4(12345000)), Price4(12345000).into());
+        stream.set_price_scale(100);
+        assert_eq!(f64::from(stream.scaled(Price4(12345))), 123.45);
+
+        assert_eq!(ScaledPrice::from(Price4(125000)).to_string(), "12.5000");
+        assert_eq!(ScaledPrice::new(7, 1).to_string(), "7");
+        assert_eq!(ScaledPrice::new(5, 4).to_string(), "1.25");
+    }
+}
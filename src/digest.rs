@@ -0,0 +1,109 @@
+use std::fmt;
+use std::ops::ControlFlow;
+
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::{Message, MessageSink};
+
+/// A hash over a sequence of parsed messages, see [`Digester`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest {
+    pub messages: u64,
+    pub hash: u128,
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:032x} ({} messages)", self.hash, self.messages)
+    }
+}
+
+/// Computes a stable xxh3 [`Digest`] of parsed messages.
+///
+/// Each message is hashed in its canonical wire encoding, re-encoded from
+/// the parsed fields, so captures of the same session which differ only in
+/// framing, compression or transport hash the same. The digest depends on
+/// message order, and is stable across platforms and releases.
+#[derive(Clone)]
+pub struct Digester {
+    hasher: Xxh3,
+    messages: u64,
+    buf: Vec<u8>,
+}
+
+impl Default for Digester {
+    fn default() -> Self {
+        Digester {
+            hasher: Xxh3::new(),
+            messages: 0,
+            buf: Vec::with_capacity(64),
+        }
+    }
+}
+
+impl fmt::Debug for Digester {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Digester")
+            .field("messages", &self.messages)
+            .finish()
+    }
+}
+
+impl Digester {
+    pub fn new() -> Digester {
+        Digester::default()
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        self.buf.clear();
+        msg.encode(&mut self.buf);
+        self.hasher.update(&self.buf);
+        self.messages += 1;
+    }
+
+    /// The digest of the messages seen so far
+    pub fn digest(&self) -> Digest {
+        Digest {
+            messages: self.messages,
+            hash: self.hasher.digest128(),
+        }
+    }
+}
+
+impl MessageSink for Digester {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        self.update(&msg);
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+
+    #[test]
+    fn digests_parsed_messages() {
+        let mut data = Vec::new();
+        for ts in 0..100u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let digest = MessageStream::from_reader(&data[..]).digest().unwrap();
+        assert_eq!(digest.messages, 100);
+        // the same messages, hashed one at a time
+        let mut digester = Digester::new();
+        for msg in MessageStream::from_reader(std::io::Cursor::new(data.clone())) {
+            digester.update(&msg.unwrap());
+        }
+        assert_eq!(digester.digest(), digest);
+
+        // a single changed field changes the digest
+        let last = data.len() - 1;
+        data[last] = b'C';
+        let other = MessageStream::from_reader(&data[..]).digest().unwrap();
+        assert_ne!(other.hash, digest.hash);
+    }
+}
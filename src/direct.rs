@@ -0,0 +1,125 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::path::Path;
+
+// alignment of buffers, offsets and lengths, which covers the logical block
+// size of common devices
+const ALIGN: usize = 4096;
+
+/// A file reader bypassing the page cache with `O_DIRECT`.
+///
+/// Replaying a very large archive through the page cache evicts data other
+/// users of a shared machine have cached, for no benefit if the archive is
+/// only read once. Reads are made in large aligned blocks, as direct I/O
+/// requires. With the `direct` feature.
+///
+/// Direct I/O is only used on Linux. On other platforms the file is read
+/// the same way but through the page cache, so code using this reader
+/// builds and behaves the same everywhere.
+///
+/// Not all filesystems support direct I/O; opening a file on one which does
+/// not (e.g. tmpfs) fails with `InvalidInput`.
+#[derive(Debug)]
+pub struct DirectReader {
+    file: File,
+    // over-allocated so that an aligned block fits
+    storage: Vec<u8>,
+    start: usize,
+    block_size: usize,
+    pos: usize,
+    len: usize,
+    eof: bool,
+}
+
+impl DirectReader {
+    /// Open a file, reading 1 MiB at a time
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<DirectReader> {
+        DirectReader::with_block_size(path, 1 << 20)
+    }
+
+    /// Open a file, reading `block_size` bytes at a time. The block size
+    /// must be a non-zero multiple of 4096.
+    pub fn with_block_size<P: AsRef<Path>>(path: P, block_size: usize) -> io::Result<DirectReader> {
+        if block_size == 0 || !block_size.is_multiple_of(ALIGN) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("block size must be a multiple of {}", ALIGN),
+            ));
+        }
+        let mut options = OpenOptions::new();
+        options.read(true);
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.custom_flags(libc::O_DIRECT);
+        }
+        let file = options.open(path)?;
+        let storage = vec![0; block_size + ALIGN];
+        let start = storage.as_ptr().align_offset(ALIGN);
+        Ok(DirectReader {
+            file,
+            storage,
+            start,
+            block_size,
+            pos: 0,
+            len: 0,
+            eof: false,
+        })
+    }
+}
+
+impl Read for DirectReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.len {
+            if self.eof {
+                return Ok(0);
+            }
+            let block = &mut self.storage[self.start..self.start + self.block_size];
+            // the file offset stays aligned as only the last read is short
+            self.len = loop {
+                match self.file.read(block) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    result => break result?,
+                }
+            };
+            self.pos = 0;
+            self.eof = self.len < self.block_size;
+        }
+        let block = &self.storage[self.start + self.pos..self.start + self.len];
+        let len = out.len().min(block.len());
+        out[..len].copy_from_slice(&block[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+
+    #[test]
+    fn reads_aligned_blocks() {
+        let mut data = Vec::new();
+        for ts in 0..1000u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        // temp_dir may be a tmpfs, which does not support direct I/O
+        let path = std::env::current_dir()
+            .unwrap()
+            .join(format!("itchy-direct-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let reader = DirectReader::with_block_size(&path, ALIGN);
+        std::fs::remove_file(&path).unwrap();
+        let reader = match reader {
+            Ok(reader) => reader,
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => return,
+            Err(e) => panic!("{}", e),
+        };
+        let stream = MessageStream::from_reader(reader);
+        let timestamps: Vec<_> = stream.map(|msg| msg.unwrap().timestamp).collect();
+        assert_eq!(timestamps, (0..1000).collect::<Vec<_>>());
+    }
+}
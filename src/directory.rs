@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{ArrayString8, Body, Message, MessageStream, Result, StockDirectory};
+
+/// Read-only stock directory reference data.
+///
+/// The directory is built once from the stock directory ('R') messages sent
+/// at the start of the session and then shared between threads behind an
+/// `Arc`. Since it is never mutated after construction, lookups need no locking.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolDirectory {
+    by_locate: Vec<Option<StockDirectory>>,
+    by_symbol: HashMap<ArrayString8, u16>,
+}
+
+impl SymbolDirectory {
+    pub fn builder() -> SymbolDirectoryBuilder {
+        SymbolDirectoryBuilder::default()
+    }
+
+    /// Directory attributes of the instrument with the given locate code
+    pub fn get(&self, locate: u16) -> Option<&StockDirectory> {
+        self.by_locate.get(locate as usize)?.as_ref()
+    }
+
+    /// Symbol (space-padded, as sent on the wire) for a locate code
+    pub fn symbol(&self, locate: u16) -> Option<&ArrayString8> {
+        self.get(locate).map(|dir| &dir.stock)
+    }
+
+    /// Locate code for a symbol. Trailing padding is ignored.
+    pub fn locate(&self, symbol: &str) -> Option<u16> {
+        let key = ArrayString8::from(symbol.trim_end()).ok()?;
+        self.by_symbol.get(&key).copied()
+    }
+
+    /// Iterate over `(locate, attributes)` in locate order
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &StockDirectory)> {
+        self.by_locate
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, dir)| dir.as_ref().map(|dir| (ix as u16, dir)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_symbol.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_symbol.is_empty()
+    }
+}
+
+/// Collects stock directory messages into a [`SymbolDirectory`]
+#[derive(Debug, Default)]
+pub struct SymbolDirectoryBuilder {
+    directory: SymbolDirectory,
+}
+
+impl SymbolDirectoryBuilder {
+    /// Feed a message to the builder. Returns `true` if it was a
+    /// stock directory message and was recorded.
+    pub fn update(&mut self, msg: &Message) -> bool {
+        match msg.body {
+            Body::StockDirectory(ref dir) => {
+                self.insert(msg.stock_locate, dir.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record directory attributes for a locate code, replacing any previous
+    /// entry for the locate code or the symbol
+    pub fn insert(&mut self, locate: u16, dir: StockDirectory) {
+        let dirs = &mut self.directory;
+        let ix = locate as usize;
+        if dirs.by_locate.len() <= ix {
+            dirs.by_locate.resize(ix + 1, None);
+        }
+        if let Some(old) = dirs.by_locate[ix].take() {
+            let old_symbol = trimmed(&old.stock);
+            // the symbol may have moved to another locate since
+            if dirs.by_symbol.get(&old_symbol) == Some(&locate) {
+                dirs.by_symbol.remove(&old_symbol);
+            }
+        }
+        let symbol = trimmed(&dir.stock);
+        if let Some(old_locate) = dirs.by_symbol.insert(symbol, locate) {
+            if old_locate != locate {
+                dirs.by_locate[old_locate as usize] = None;
+            }
+        }
+        dirs.by_locate[ix] = Some(dir);
+    }
+
+    pub fn build(self) -> Arc<SymbolDirectory> {
+        Arc::new(self.directory)
+    }
+}
+
+impl<R: Read> MessageStream<R> {
+    /// Read the stock directory burst at the start of a session: system
+    /// event and stock directory messages up to the first message of any
+    /// other type, which is left for the next call to `next`.
+    ///
+    /// Only messages yielded by the stream are read, so stream filters
+    /// which leave out directory messages leave the directory empty.
+    pub fn read_directory(&mut self) -> Result<Arc<SymbolDirectory>> {
+        let mut builder = SymbolDirectory::builder();
+        loop {
+            match self.peek() {
+                Some(Ok(msg)) if msg.tag == b'R' || msg.tag == b'S' => {}
+                Some(Ok(_)) | None => return Ok(builder.build()),
+                Some(Err(_)) => {}
+            }
+            if let Some(msg) = self.next() {
+                builder.update(&msg?);
+            }
+        }
+    }
+}
+
+/// Open a file and read its stock directory, returning the directory and
+/// a stream positioned at the first message after the directory burst,
+/// see [`MessageStream::read_directory`]
+pub fn preload_directory<P: AsRef<Path>>(
+    path: P,
+) -> Result<(Arc<SymbolDirectory>, MessageStream<File>)> {
+    let mut stream = MessageStream::from_file(path)?;
+    let directory = stream.read_directory()?;
+    Ok((directory, stream))
+}
+
+fn trimmed(stock: &ArrayString8) -> ArrayString8 {
+    let mut trimmed = *stock;
+    trimmed.truncate(stock.trim_end().len());
+    trimmed
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::*;
+
+    pub(crate) fn directory_msg(locate: u16, stock: &str) -> Message {
+        let mut padded = ArrayString8::from(stock).unwrap();
+        while !padded.is_full() {
+            padded.push(' ');
+        }
+        Message {
+            tag: b'R',
+            stock_locate: locate,
+            tracking_number: 0,
+            timestamp: 0,
+            body: Body::StockDirectory(StockDirectory {
+                stock: padded,
+                market_category: MarketCategory::NasdaqGlobalSelect,
+                financial_status: FinancialStatus::Normal,
+                round_lot_size: 100,
+                round_lots_only: false,
+                issue_classification: IssueClassification::CommonStock,
+                issue_subtype: IssueSubType::CommonShares,
+                authenticity: true,
+                short_sale_threshold: Some(false),
+                ipo_flag: None,
+                luld_ref_price_tier: LuldRefPriceTier::Tier1,
+                etp_flag: Some(false),
+                etp_leverage_factor: 0,
+                inverse_indicator: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn lookups() {
+        let mut builder = SymbolDirectory::builder();
+        assert!(builder.update(&directory_msg(3, "AAPL")));
+        assert!(builder.update(&directory_msg(7, "MSFT")));
+        let dir = builder.build();
+
+        assert_eq!(dir.len(), 2);
+        assert_eq!(dir.symbol(3).unwrap().as_str(), "AAPL    ");
+        assert_eq!(dir.locate("MSFT"), Some(7));
+        assert_eq!(dir.locate("MSFT    "), Some(7));
+        assert_eq!(dir.get(7).unwrap().round_lot_size, 100);
+        assert!(dir.get(4).is_none());
+        assert_eq!(dir.iter().map(|(l, _)| l).collect::<Vec<_>>(), [3, 7]);
+
+        let shared = Arc::clone(&dir);
+        std::thread::spawn(move || assert_eq!(shared.locate("AAPL"), Some(3)))
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn reissued_symbol_replaces_entry() {
+        let mut builder = SymbolDirectory::builder();
+        builder.update(&directory_msg(3, "ABC"));
+        builder.update(&directory_msg(3, "ABCD"));
+        let dir = builder.build();
+        assert_eq!(dir.locate("ABC"), None);
+        assert_eq!(dir.locate("ABCD"), Some(3));
+    }
+
+    #[test]
+    fn moved_symbol_leaves_old_locate() {
+        let mut builder = SymbolDirectory::builder();
+        builder.update(&directory_msg(1, "ABC"));
+        builder.update(&directory_msg(2, "ABC"));
+        let dir = builder.build();
+        assert_eq!(dir.locate("ABC"), Some(2));
+        assert!(dir.get(1).is_none());
+        assert_eq!(dir.len(), dir.iter().count());
+    }
+
+    #[test]
+    fn reused_locate_keeps_moved_symbol() {
+        let mut builder = SymbolDirectory::builder();
+        builder.update(&directory_msg(1, "ABC"));
+        builder.update(&directory_msg(2, "ABC"));
+        builder.update(&directory_msg(1, "XYZ"));
+        let dir = builder.build();
+        assert_eq!(dir.locate("ABC"), Some(2));
+        assert_eq!(dir.locate("XYZ"), Some(1));
+        assert_eq!(dir.len(), 2);
+        assert_eq!(dir.iter().map(|(l, _)| l).collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn reads_directory_burst() {
+        use crate::orders::tests::{add, msg};
+
+        let mut data = Vec::new();
+        let event = Message {
+            tag: b'S',
+            ..msg(
+                0,
+                Body::SystemEvent {
+                    event: EventCode::StartOfMessages,
+                },
+            )
+        };
+        event.encode(&mut data);
+        directory_msg(1, "AAPL").encode(&mut data);
+        directory_msg(2, "MSFT").encode(&mut data);
+        let order = Message {
+            tag: b'A',
+            ..msg(5, add(1, Side::Buy, 100, 10_000))
+        };
+        order.encode(&mut data);
+        directory_msg(3, "LATE").encode(&mut data);
+
+        let mut stream = MessageStream::from_reader(&data[..]);
+        let dir = stream.read_directory().unwrap();
+        assert_eq!(dir.len(), 2);
+        assert_eq!(dir.locate("MSFT"), Some(2));
+        let tags: Vec<_> = stream.map(|m| m.unwrap().tag).collect();
+        assert_eq!(tags, b"AR");
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::{parse_message, Error};
+
+// bytes of the failed input written to the dump
+const FAILED_BYTES: usize = 64;
+
+/// The last few raw messages of a stream, written out with the failed
+/// input when a parse error occurs, see
+/// [`MessageStreamBuilder::dump_on_error`](crate::MessageStreamBuilder::dump_on_error)
+#[derive(Debug)]
+pub(crate) struct ErrorDump {
+    path: PathBuf,
+    capacity: usize,
+    // message index and framed bytes, oldest first
+    frames: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl ErrorDump {
+    pub(crate) fn new(capacity: usize, path: PathBuf) -> ErrorDump {
+        ErrorDump {
+            path,
+            capacity,
+            frames: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn record(&mut self, index: u64, frame: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut buf = if self.frames.len() == self.capacity {
+            self.frames
+                .pop_front()
+                .map(|(_, buf)| buf)
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        buf.clear();
+        buf.extend_from_slice(frame);
+        self.frames.push_back((index, buf));
+    }
+
+    /// Append a report of the error to the dump file, each retained message
+    /// in hex and decoded, followed by the input that failed
+    pub(crate) fn write(&self, error: &Error, failed: &[u8]) -> io::Result<()> {
+        let mut report = format!("error: {}\n", error);
+        for (index, frame) in &self.frames {
+            let _ = writeln!(report, "message {}: {}", index, hex(frame));
+            match parse_message(frame) {
+                Ok((_, msg)) => {
+                    let _ = writeln!(report, "    {:?}", msg);
+                }
+                Err(e) => {
+                    let _ = writeln!(report, "    (does not parse: {:?})", e);
+                }
+            }
+        }
+        let failed = &failed[..failed.len().min(FAILED_BYTES)];
+        let _ = writeln!(report, "failed input: {}\n", hex(failed));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(report.as_bytes())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let hex: Vec<_> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    hex.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorPolicy, MessageStream};
+
+    #[test]
+    fn dumps_preceding_messages() {
+        let mut data = Vec::new();
+        for ts in 0..5u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        // an invalid event code in the fourth message
+        data[3 * 14 + 13] = b'Z';
+        let path = std::env::temp_dir().join(format!("itchy-dump-{}", std::process::id()));
+        let stream = MessageStream::builder()
+            .error_policy(ErrorPolicy::SkipMessage)
+            .dump_on_error(2, &path)
+            .build(&data[..]);
+        assert_eq!(stream.filter(|m| m.is_err()).count(), 1);
+        let dump = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!dump.contains("message 0:"));
+        assert!(dump.contains("message 1: 00 0c 53"));
+        assert!(dump.contains("message 2: 00 0c 53"));
+        assert!(dump.contains("StartOfMessages"));
+        assert!(dump.contains("failed input: 00 0c 53 00 00 00 00 00 00 00 00 00 03 5a"));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{ArrayString8, Body, Message};
+
+impl Message {
+    /// Append the message to `out` in wire format, with its two-byte
+    /// length prefix.
+    ///
+    /// Parsing the output gives back an identical message. Add Order
+    /// messages are written with attribution if the tag is `F`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(&[0, 0]);
+        self.encode_unframed(out);
+        let len = (out.len() - start - 2) as u16;
+        out[start..start + 2].copy_from_slice(&len.to_be_bytes());
+    }
+
+    /// Append the message to `out` in wire format, without a length prefix
+    pub(crate) fn encode_unframed(&self, out: &mut Vec<u8>) {
+        out.push(self.tag);
+        out.extend_from_slice(&self.stock_locate.to_be_bytes());
+        out.extend_from_slice(&self.tracking_number.to_be_bytes());
+        out.extend_from_slice(&self.timestamp.to_be_bytes()[2..]);
+        match self.body {
+            Body::AddOrder(ref add) => {
+                out.extend_from_slice(&add.reference.to_be_bytes());
+                out.push(add.side.as_code());
+                out.extend_from_slice(&add.shares.to_be_bytes());
+                out.extend_from_slice(&stock(&add.stock));
+                out.extend_from_slice(&add.price.raw().to_be_bytes());
+                if self.tag == b'F' {
+                    let mpid = add.mpid.as_ref().map_or("", |m| m.as_str());
+                    out.extend_from_slice(&padded::<4>(mpid));
+                }
+            }
+            Body::Breach(level) => out.push(level.as_code()),
+            Body::BrokenTrade { match_number } => {
+                out.extend_from_slice(&match_number.to_be_bytes())
+            }
+            Body::CrossTrade(ref cross) => {
+                out.extend_from_slice(&cross.shares.to_be_bytes());
+                out.extend_from_slice(&stock(&cross.stock));
+                out.extend_from_slice(&cross.cross_price.raw().to_be_bytes());
+                out.extend_from_slice(&cross.match_number.to_be_bytes());
+                out.push(cross.cross_type.as_code());
+            }
+            Body::DeleteOrder { reference } => out.extend_from_slice(&reference.to_be_bytes()),
+            Body::Imbalance(ref imb) => {
+                out.extend_from_slice(&imb.paired_shares.to_be_bytes());
+                out.extend_from_slice(&imb.imbalance_shares.to_be_bytes());
+                out.push(imb.imbalance_direction.as_code());
+                out.extend_from_slice(&stock(&imb.stock));
+                out.extend_from_slice(&imb.far_price.raw().to_be_bytes());
+                out.extend_from_slice(&imb.near_price.raw().to_be_bytes());
+                out.extend_from_slice(&imb.current_ref_price.raw().to_be_bytes());
+                out.push(imb.cross_type.as_code());
+                out.push(imb.price_variation_indicator as u8);
+            }
+            Body::IpoQuotingPeriod(ref ipo) => {
+                out.extend_from_slice(&stock(&ipo.stock));
+                out.extend_from_slice(&ipo.release_time.to_be_bytes());
+                out.push(ipo.release_qualifier.as_code());
+                out.extend_from_slice(&ipo.price.raw().to_be_bytes());
+            }
+            Body::LULDAuctionCollar {
+                stock: ref s,
+                ref_price,
+                upper_price,
+                lower_price,
+                extension,
+            } => {
+                out.extend_from_slice(&stock(s));
+                out.extend_from_slice(&ref_price.raw().to_be_bytes());
+                out.extend_from_slice(&upper_price.raw().to_be_bytes());
+                out.extend_from_slice(&lower_price.raw().to_be_bytes());
+                out.extend_from_slice(&extension.to_be_bytes());
+            }
+            Body::MwcbDeclineLevel {
+                level1,
+                level2,
+                level3,
+            } => {
+                out.extend_from_slice(&level1.raw().to_be_bytes());
+                out.extend_from_slice(&level2.raw().to_be_bytes());
+                out.extend_from_slice(&level3.raw().to_be_bytes());
+            }
+            Body::NonCrossTrade(ref trade) => {
+                out.extend_from_slice(&trade.reference.to_be_bytes());
+                out.push(trade.side.as_code());
+                out.extend_from_slice(&trade.shares.to_be_bytes());
+                out.extend_from_slice(&stock(&trade.stock));
+                out.extend_from_slice(&trade.price.raw().to_be_bytes());
+                out.extend_from_slice(&trade.match_number.to_be_bytes());
+            }
+            Body::OrderCancelled {
+                reference,
+                cancelled,
+            } => {
+                out.extend_from_slice(&reference.to_be_bytes());
+                out.extend_from_slice(&cancelled.to_be_bytes());
+            }
+            Body::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => {
+                out.extend_from_slice(&reference.to_be_bytes());
+                out.extend_from_slice(&executed.to_be_bytes());
+                out.extend_from_slice(&match_number.to_be_bytes());
+            }
+            Body::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            } => {
+                out.extend_from_slice(&reference.to_be_bytes());
+                out.extend_from_slice(&executed.to_be_bytes());
+                out.extend_from_slice(&match_number.to_be_bytes());
+                out.push(yes_no(printable));
+                out.extend_from_slice(&price.raw().to_be_bytes());
+            }
+            Body::ParticipantPosition(ref pos) => {
+                out.extend_from_slice(&padded::<4>(&pos.mpid));
+                out.extend_from_slice(&stock(&pos.stock));
+                out.push(yes_no(pos.primary_market_maker));
+                out.push(pos.market_maker_mode.as_code());
+                out.push(pos.market_participant_state.as_code());
+            }
+            Body::RegShoRestriction {
+                stock: ref s,
+                action,
+            } => {
+                out.extend_from_slice(&stock(s));
+                out.push(action.as_code());
+            }
+            Body::ReplaceOrder(ref replace) => {
+                out.extend_from_slice(&replace.old_reference.to_be_bytes());
+                out.extend_from_slice(&replace.new_reference.to_be_bytes());
+                out.extend_from_slice(&replace.shares.to_be_bytes());
+                out.extend_from_slice(&replace.price.raw().to_be_bytes());
+            }
+            Body::StockDirectory(ref dir) => {
+                out.extend_from_slice(&stock(&dir.stock));
+                out.push(dir.market_category.as_code());
+                out.push(dir.financial_status.as_code());
+                out.extend_from_slice(&dir.round_lot_size.to_be_bytes());
+                out.push(yes_no(dir.round_lots_only));
+                out.push(dir.issue_classification.as_code());
+                out.extend_from_slice(&dir.issue_subtype.as_code());
+                out.push(if dir.authenticity { b'P' } else { b'T' });
+                out.push(maybe_yes_no(dir.short_sale_threshold));
+                out.push(maybe_yes_no(dir.ipo_flag));
+                out.push(dir.luld_ref_price_tier.as_code());
+                // 'M' also parses as true, and is written back as 'Y'
+                out.push(maybe_yes_no(dir.etp_flag));
+                out.extend_from_slice(&dir.etp_leverage_factor.to_be_bytes());
+                out.push(yes_no(dir.inverse_indicator));
+            }
+            Body::SystemEvent { event } => out.push(event.as_code()),
+            Body::TradingAction {
+                stock: ref s,
+                trading_state,
+                ref reason,
+            } => {
+                out.extend_from_slice(&stock(s));
+                out.push(trading_state.as_code());
+                out.push(b' ');
+                out.extend_from_slice(&padded::<4>(reason));
+            }
+            Body::RetailPriceImprovementIndicator(ref rpi) => {
+                out.extend_from_slice(&stock(&rpi.stock));
+                out.push(rpi.interest_flag.as_code());
+            }
+            Body::Extension(ref ext) => out.extend_from_slice(ext.raw()),
+        }
+    }
+}
+
+fn stock(s: &ArrayString8) -> [u8; 8] {
+    padded(s)
+}
+
+// right-pad with spaces, as alphanumeric fields are on the wire
+fn padded<const N: usize>(s: &str) -> [u8; N] {
+    let mut field = [b' '; N];
+    field[..s.len()].copy_from_slice(s.as_bytes());
+    field
+}
+
+fn yes_no(flag: bool) -> u8 {
+    if flag {
+        b'Y'
+    } else {
+        b'N'
+    }
+}
+
+fn maybe_yes_no(flag: Option<bool>) -> u8 {
+    flag.map_or(b' ', yes_no)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::messages::tests::hex_to_bytes;
+    use crate::MessageStream;
+
+    #[test]
+    fn round_trips_wire_format() {
+        // system event, then stock directory
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f
+                     0027 5200 0100 0000 0000 0000 005a 585a 5a54 2020 2051
+                     4e00 0000 644e 4343 2050 4e4e 314e 0000 0000 4e";
+        let buf = hex_to_bytes(&code[..]);
+        let mut out = Vec::new();
+        for msg in MessageStream::from_reader(&buf[..]) {
+            msg.unwrap().encode(&mut out);
+        }
+        assert_eq!(out, buf);
+    }
+}
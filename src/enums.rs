@@ -1,7 +1,68 @@
 use nom::{bytes::streaming::take, combinator::map_opt, number::streaming::be_u8, IResult};
 
+use crate::Error;
+
+// Wire codes of each enum, from which the conversions in both directions
+// and a table of every variant are generated, so the parser, the encoder
+// and users' own code cannot disagree.
+macro_rules! codes {
+    ($name:ident { $($variant:ident => $code:literal,)* }) => {
+        impl $name {
+            /// Every variant with its code on the wire, in declaration order
+            pub const CODES: &'static [($name, u8)] = &[$(($name::$variant, $code)),*];
+
+            /// Code on the wire
+            pub const fn as_code(self) -> u8 {
+                match self {
+                    $($name::$variant => $code,)*
+                }
+            }
+
+            pub const fn from_code(code: u8) -> Option<$name> {
+                match code {
+                    $($code => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// Code on the wire, as a character
+            pub const fn as_char(self) -> char {
+                self.as_code() as char
+            }
+
+            pub const fn from_char(c: char) -> Option<$name> {
+                if c.is_ascii() {
+                    $name::from_code(c as u8)
+                } else {
+                    None
+                }
+            }
+        }
+    };
+    ($name:ident: [u8; 2] { $($variant:ident => $code:literal,)* }) => {
+        impl $name {
+            /// Every variant with its code on the wire, in declaration order
+            pub const CODES: &'static [($name, [u8; 2])] = &[$(($name::$variant, *$code)),*];
+
+            /// Code on the wire, space-padded for single letter codes
+            pub const fn as_code(self) -> [u8; 2] {
+                match self {
+                    $($name::$variant => *$code,)*
+                }
+            }
+
+            pub const fn from_code(code: [u8; 2]) -> Option<$name> {
+                match &code {
+                    $($code => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum EventCode {
     StartOfMessages,
     StartOfSystemHours,
@@ -11,8 +72,17 @@ pub enum EventCode {
     EndOfMessages,
 }
 
+codes!(EventCode {
+    StartOfMessages => b'O',
+    StartOfSystemHours => b'S',
+    StartOfMarketHours => b'Q',
+    EndOfMarketHours => b'M',
+    EndOfSystemHours => b'E',
+    EndOfMessages => b'C',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum MarketCategory {
     NasdaqGlobalSelect,
     NasdaqGlobalMarket,
@@ -25,8 +95,20 @@ pub enum MarketCategory {
     Unavailable,
 }
 
+codes!(MarketCategory {
+    NasdaqGlobalSelect => b'Q',
+    NasdaqGlobalMarket => b'G',
+    NasdaqCapitalMarket => b'S',
+    Nyse => b'N',
+    NyseMkt => b'A',
+    NyseArca => b'P',
+    BatsZExchange => b'Z',
+    InvestorsExchange => b'V',
+    Unavailable => b' ',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum FinancialStatus {
     Normal,
     Deficient,
@@ -41,8 +123,22 @@ pub enum FinancialStatus {
     Unavailable,
 }
 
+codes!(FinancialStatus {
+    Normal => b'N',
+    Deficient => b'D',
+    Delinquent => b'E',
+    Bankrupt => b'Q',
+    Suspended => b'S',
+    DeficientBankrupt => b'G',
+    DeficientDelinquent => b'H',
+    DelinquentBankrupt => b'J',
+    DeficientDelinquentBankrupt => b'K',
+    EtpSuspended => b'C',
+    Unavailable => b' ',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum IssueClassification {
     AmericanDepositaryShare,
     Bond,
@@ -62,33 +158,31 @@ pub enum IssueClassification {
     Warrant,
 }
 
+codes!(IssueClassification {
+    AmericanDepositaryShare => b'A',
+    Bond => b'B',
+    CommonStock => b'C',
+    DepositoryReceipt => b'F',
+    A144 => b'I',
+    LimitedPartnership => b'L',
+    Notes => b'N',
+    OrdinaryShare => b'O',
+    PreferredStock => b'P',
+    OtherSecurities => b'Q',
+    Right => b'R',
+    SharesOfBeneficialInterest => b'S',
+    ConvertibleDebenture => b'T',
+    Unit => b'U',
+    UnitsPerBenifInt => b'V',
+    Warrant => b'W',
+});
+
 pub(crate) fn parse_issue_classification(input: &[u8]) -> IResult<&[u8], IssueClassification> {
-    map_opt(be_u8, |v| {
-        use IssueClassification::*;
-        Some(match v {
-            b'A' => AmericanDepositaryShare,
-            b'B' => Bond,
-            b'C' => CommonStock,
-            b'F' => DepositoryReceipt,
-            b'I' => A144,
-            b'L' => LimitedPartnership,
-            b'N' => Notes,
-            b'O' => OrdinaryShare,
-            b'P' => PreferredStock,
-            b'Q' => OtherSecurities,
-            b'R' => Right,
-            b'S' => SharesOfBeneficialInterest,
-            b'T' => ConvertibleDebenture,
-            b'U' => Unit,
-            b'V' => UnitsPerBenifInt,
-            b'W' => Warrant,
-            _ => return None,
-        })
-    })(input)
+    map_opt(be_u8, IssueClassification::from_code)(input)
 }
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum IssueSubType {
     PreferredTrustSecurities,
     AlphaIndexETNs,
@@ -150,84 +244,89 @@ pub enum IssueSubType {
     NotApplicable,
 }
 
+codes!(IssueSubType: [u8; 2] {
+    PreferredTrustSecurities => b"A ",
+    AlphaIndexETNs => b"AI",
+    IndexBasedDerivative => b"B ",
+    CommonShares => b"C ",
+    CommodityBasedTrustShares => b"CB",
+    CommodityFuturesTrustShares => b"CF",
+    CommodityLinkedSecurities => b"CL",
+    CommodityIndexTrustShares => b"CM",
+    CollateralizedMortgageObligation => b"CO",
+    CurrencyTrustShares => b"CT",
+    CommodityCurrencyLinkedSecurities => b"CU",
+    CurrencyWarrants => b"CW",
+    GlobalDepositaryShares => b"D ",
+    ETFPortfolioDepositaryReceipt => b"E ",
+    EquityGoldShares => b"EG",
+    ETNEquityIndexLinkedSecurities => b"EI",
+    ExchangeTradedManagedFunds => b"EM",
+    ExchangeTradedNotes => b"EN",
+    EquityUnits => b"EU",
+    Holdrs => b"F ",
+    ETNFixedIncomeLinkedSecurities => b"FI",
+    ETNFuturesLinkedSecurities => b"FL",
+    GlobalShares => b"G ",
+    ETFIndexFundShares => b"I ",
+    InterestRate => b"IR",
+    IndexWarrant => b"IW",
+    IndexLinkedExchangeableNotes => b"IX",
+    CorporateBackedTrustSecurity => b"J ",
+    ContingentLitigationRight => b"L ",
+    Llc => b"LL",
+    EquityBasedDerivative => b"M ",
+    ManagedFundShares => b"MF",
+    ETNMultiFactorIndexLinkedSecurities => b"ML",
+    ManagedTrustSecurities => b"MT",
+    NYRegistryShares => b"N ",
+    OpenEndedMutualFund => b"O ",
+    PrivatelyHeldSecurity => b"P ",
+    PoisonPill => b"PP",
+    PartnershipUnits => b"PU",
+    ClosedEndFunds => b"Q ",
+    RegS => b"R ",
+    CommodityRedeemableCommodityLinkedSecurities => b"RC",
+    ETNRedeemableFuturesLinkedSecurities => b"RF",
+    REIT => b"RT",
+    CommodityRedeemableCurrencyLinkedSecurities => b"RU",
+    Seed => b"S ",
+    SpotRateClosing => b"SC",
+    SpotRateIntraday => b"SI",
+    TrackingStock => b"T ",
+    TrustCertificates => b"TC",
+    TrustUnits => b"TU",
+    Portal => b"U ",
+    ContingentValueRight => b"V ",
+    TrustIssuedReceipts => b"W ",
+    WorldCurrencyOption => b"WC",
+    Trust => b"X ",
+    Other => b"Y ",
+    NotApplicable => b"Z ",
+});
+
 pub(crate) fn parse_issue_subtype(input: &[u8]) -> IResult<&[u8], IssueSubType> {
     map_opt(take(2usize), |v: &[u8]| {
-        use IssueSubType::*;
-
-        Some(match v {
-            b"A " => PreferredTrustSecurities,
-            b"AI" => AlphaIndexETNs,
-            b"B " => IndexBasedDerivative,
-            b"C " => CommonShares,
-            b"CB" => CommodityBasedTrustShares,
-            b"CF" => CommodityFuturesTrustShares,
-            b"CL" => CommodityLinkedSecurities,
-            b"CM" => CommodityIndexTrustShares,
-            b"CO" => CollateralizedMortgageObligation,
-            b"CT" => CurrencyTrustShares,
-            b"CU" => CommodityCurrencyLinkedSecurities,
-            b"CW" => CurrencyWarrants,
-            b"D " => GlobalDepositaryShares,
-            b"E " => ETFPortfolioDepositaryReceipt,
-            b"EG" => EquityGoldShares,
-            b"EI" => ETNEquityIndexLinkedSecurities,
-            b"EM" => ExchangeTradedManagedFunds,
-            b"EN" => ExchangeTradedNotes,
-            b"EU" => EquityUnits,
-            b"F " => Holdrs,
-            b"FI" => ETNFixedIncomeLinkedSecurities,
-            b"FL" => ETNFuturesLinkedSecurities,
-            b"G " => GlobalShares,
-            b"I " => ETFIndexFundShares,
-            b"IR" => InterestRate,
-            b"IW" => IndexWarrant,
-            b"IX" => IndexLinkedExchangeableNotes,
-            b"J " => CorporateBackedTrustSecurity,
-            b"L " => ContingentLitigationRight,
-            b"LL" => Llc,
-            b"M " => EquityBasedDerivative,
-            b"MF" => ManagedFundShares,
-            b"ML" => ETNMultiFactorIndexLinkedSecurities,
-            b"MT" => ManagedTrustSecurities,
-            b"N " => NYRegistryShares,
-            b"O " => OpenEndedMutualFund,
-            b"P " => PrivatelyHeldSecurity,
-            b"PP" => PoisonPill,
-            b"PU" => PartnershipUnits,
-            b"Q " => ClosedEndFunds,
-            b"R " => RegS,
-            b"RC" => CommodityRedeemableCommodityLinkedSecurities,
-            b"RF" => ETNRedeemableFuturesLinkedSecurities,
-            b"RT" => REIT,
-            b"RU" => CommodityRedeemableCurrencyLinkedSecurities,
-            b"S " => Seed,
-            b"SC" => SpotRateClosing,
-            b"SI" => SpotRateIntraday,
-            b"T " => TrackingStock,
-            b"TC" => TrustCertificates,
-            b"TU" => TrustUnits,
-            b"U " => Portal,
-            b"V " => ContingentValueRight,
-            b"W " => TrustIssuedReceipts,
-            b"WC" => WorldCurrencyOption,
-            b"X " => Trust,
-            b"Y " => Other,
-            b"Z " => NotApplicable,
-            _ => return None,
-        })
+        IssueSubType::from_code([v[0], v[1]])
     })(input)
 }
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum LuldRefPriceTier {
     Tier1,
     Tier2,
     Na,
 }
 
+codes!(LuldRefPriceTier {
+    Tier1 => b'1',
+    Tier2 => b'2',
+    Na => b' ',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum MarketMakerMode {
     Normal,
     Passive,
@@ -236,8 +335,16 @@ pub enum MarketMakerMode {
     Penalty,
 }
 
+codes!(MarketMakerMode {
+    Normal => b'N',
+    Passive => b'P',
+    Syndicate => b'S',
+    Presyndicate => b'R',
+    Penalty => b'L',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum MarketParticipantState {
     Active,
     Excused,
@@ -246,16 +353,30 @@ pub enum MarketParticipantState {
     Deleted,
 }
 
+codes!(MarketParticipantState {
+    Active => b'A',
+    Excused => b'E',
+    Withdrawn => b'W',
+    Suspended => b'S',
+    Deleted => b'D',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum RegShoAction {
     None,
     Intraday,
     Extant,
 }
 
+codes!(RegShoAction {
+    None => b'0',
+    Intraday => b'1',
+    Extant => b'2',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TradingState {
     Halted,
     Paused,
@@ -263,15 +384,27 @@ pub enum TradingState {
     Trading,
 }
 
+codes!(TradingState {
+    Halted => b'H',
+    Paused => b'P',
+    QuotationOnly => b'Q',
+    Trading => b'T',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Side {
     Buy,
     Sell,
 }
 
+codes!(Side {
+    Buy => b'B',
+    Sell => b'S',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ImbalanceDirection {
     Buy,
     Sell,
@@ -279,8 +412,15 @@ pub enum ImbalanceDirection {
     InsufficientOrders,
 }
 
+codes!(ImbalanceDirection {
+    Buy => b'B',
+    Sell => b'S',
+    NoImbalance => b'N',
+    InsufficientOrders => b'O',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum CrossType {
     Opening,
     Closing,
@@ -289,26 +429,144 @@ pub enum CrossType {
     ExtendedTradingClose,
 }
 
+codes!(CrossType {
+    Opening => b'O',
+    Closing => b'C',
+    IpoOrHalted => b'H',
+    Intraday => b'I',
+    ExtendedTradingClose => b'A',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum IpoReleaseQualifier {
     Anticipated,
     Cancelled,
 }
 
+codes!(IpoReleaseQualifier {
+    Anticipated => b'A',
+    Cancelled => b'C',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum LevelBreached {
     L1,
     L2,
     L3,
 }
 
+codes!(LevelBreached {
+    L1 => b'1',
+    L2 => b'2',
+    L3 => b'3',
+});
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum InterestFlag {
     RPIAvailableBuySide,
     RPIAvailableSellSide,
     RPIAvailableBothSides,
     RPINoneAvailable,
 }
+
+codes!(InterestFlag {
+    RPIAvailableBuySide => b'B',
+    RPIAvailableSellSide => b'S',
+    RPIAvailableBothSides => b'A',
+    RPINoneAvailable => b'N',
+});
+
+/// Message type, the tag byte which starts every message
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Tag {
+    AddOrder = b'A',
+    BrokenTrade = b'B',
+    OrderExecutedWithPrice = b'C',
+    DeleteOrder = b'D',
+    OrderExecuted = b'E',
+    AddOrderWithMpid = b'F',
+    TradingAction = b'H',
+    Imbalance = b'I',
+    LULDAuctionCollar = b'J',
+    IpoQuotingPeriod = b'K',
+    ParticipantPosition = b'L',
+    RetailPriceImprovementIndicator = b'N',
+    NonCrossTrade = b'P',
+    CrossTrade = b'Q',
+    StockDirectory = b'R',
+    SystemEvent = b'S',
+    ReplaceOrder = b'U',
+    MwcbDeclineLevel = b'V',
+    Breach = b'W',
+    OrderCancelled = b'X',
+    RegShoRestriction = b'Y',
+}
+
+codes!(Tag {
+    AddOrder => b'A',
+    BrokenTrade => b'B',
+    OrderExecutedWithPrice => b'C',
+    DeleteOrder => b'D',
+    OrderExecuted => b'E',
+    AddOrderWithMpid => b'F',
+    TradingAction => b'H',
+    Imbalance => b'I',
+    LULDAuctionCollar => b'J',
+    IpoQuotingPeriod => b'K',
+    ParticipantPosition => b'L',
+    RetailPriceImprovementIndicator => b'N',
+    NonCrossTrade => b'P',
+    CrossTrade => b'Q',
+    StockDirectory => b'R',
+    SystemEvent => b'S',
+    ReplaceOrder => b'U',
+    MwcbDeclineLevel => b'V',
+    Breach => b'W',
+    OrderCancelled => b'X',
+    RegShoRestriction => b'Y',
+});
+
+impl TryFrom<u8> for Tag {
+    type Error = Error;
+
+    fn try_from(tag: u8) -> Result<Tag, Error> {
+        Tag::from_code(tag)
+            .ok_or_else(|| Error::Parse(format!("unknown message type '{}'", tag.escape_ascii())))
+    }
+}
+
+impl From<Tag> for u8 {
+    fn from(tag: Tag) -> u8 {
+        tag.as_code()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for &(status, code) in FinancialStatus::CODES {
+            assert_eq!(status.as_code(), code);
+            assert_eq!(FinancialStatus::from_char(code as char), Some(status));
+        }
+        assert_eq!(FinancialStatus::CODES.len(), 11);
+        assert_eq!(Side::from_char('B'), Some(Side::Buy));
+        assert_eq!(Side::from_char('é'), None);
+        for &(subtype, code) in IssueSubType::CODES {
+            assert_eq!(IssueSubType::from_code(code), Some(subtype));
+        }
+        assert_eq!(IssueSubType::CommonShares.as_code(), *b"C ");
+        for &(tag, code) in Tag::CODES {
+            assert_eq!(tag as u8, code);
+        }
+        assert_eq!(Tag::try_from(b'F').unwrap(), Tag::AddOrderWithMpid);
+        assert!(Tag::try_from(b'Z').is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::Read;
+
+use crate::clock::Session;
+use crate::{ArrayString, Message, MessageStream, Result};
+
+/// Session identifier of a MoldUDP64 or SoupBinTCP session
+pub type SessionId = ArrayString<10>;
+
+/// A message together with its transport-level sequencing
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Envelope {
+    pub session: SessionId,
+    /// Sequence number of the message within the session, starting at 1
+    pub sequence: u64,
+    /// Time the message was received, in nanoseconds since the Unix epoch,
+    /// if known
+    pub receive_ts: Option<u64>,
+    pub message: Message,
+}
+
+impl Envelope {
+    /// Nanoseconds between the exchange timestamp of the message and its
+    /// receipt, given the trading day the message belongs to
+    pub fn receive_latency(&self, session: &Session) -> Option<i64> {
+        let receive_ts = self.receive_ts? as i64;
+        Some(receive_ts - session.to_utc_nanos(self.message.timestamp))
+    }
+}
+
+/// Iterator wrapping messages from a [`MessageStream`] in [`Envelope`]s,
+/// see [`MessageStream::sequenced`]
+#[derive(Debug)]
+pub struct Sequenced<R> {
+    stream: MessageStream<R>,
+    session: SessionId,
+}
+
+impl<R> Sequenced<R> {
+    pub(crate) fn new(stream: MessageStream<R>, session: SessionId) -> Sequenced<R> {
+        Sequenced { stream, session }
+    }
+
+    pub fn into_inner(self) -> MessageStream<R> {
+        self.stream
+    }
+}
+
+impl<R: Read> Iterator for Sequenced<R> {
+    type Item = Result<Envelope>;
+
+    fn next(&mut self) -> Option<Result<Envelope>> {
+        let item = self.stream.next_with_position()?;
+        Some(item.map(|(position, message)| Envelope {
+            session: self.session,
+            sequence: position.message_index + 1,
+            receive_ts: None,
+            message,
+        }))
+    }
+}
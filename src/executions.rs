@@ -0,0 +1,183 @@
+use crate::{ArrayString4, ArrayString8, Message, OrderTracker, OrderUpdate, Price4, Result, Side};
+
+/// An order execution joined with the order it executed against
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnrichedExecution {
+    pub stock_locate: u16,
+    pub timestamp: u64,
+    pub stock: ArrayString8,
+    pub reference: u64,
+    /// Side of the resting order
+    pub side: Side,
+    pub shares: u32,
+    /// Execution price, which differs from the limit price for executions
+    /// with price ('C' messages)
+    pub price: Price4,
+    /// Limit price of the resting order
+    pub limit_price: Price4,
+    pub match_number: u64,
+    pub printable: bool,
+    /// Attribution of the resting order, if any
+    pub mpid: Option<ArrayString4>,
+    /// Shares left on the order after the execution
+    pub remaining: u32,
+}
+
+impl EnrichedExecution {
+    /// True unless the execution price is worse for the resting order than
+    /// its limit price, which would indicate a bad feed or a missed replace
+    pub fn is_consistent(&self) -> bool {
+        match self.side {
+            Side::Buy => self.price <= self.limit_price,
+            Side::Sell => self.price >= self.limit_price,
+        }
+    }
+}
+
+/// Iterator of [`EnrichedExecution`]s from a stream of messages.
+///
+/// Order Executed messages carry only the order reference, so the stream
+/// tracks every live order to fill in the symbol, side and limit price.
+/// Executions of unknown orders, e.g. when a capture starts mid-session,
+/// are skipped and counted. Errors from the message stream are passed
+/// through.
+#[derive(Debug)]
+pub struct ExecutionStream<I> {
+    messages: I,
+    orders: OrderTracker,
+    unknown: u64,
+}
+
+impl<I: Iterator<Item = Result<Message>>> ExecutionStream<I> {
+    pub fn new(messages: I) -> ExecutionStream<I> {
+        ExecutionStream {
+            messages,
+            orders: OrderTracker::new(),
+            unknown: 0,
+        }
+    }
+
+    /// The live orders as of the last message applied
+    pub fn orders(&self) -> &OrderTracker {
+        &self.orders
+    }
+
+    /// Number of executions skipped because their order was not known, or
+    /// had fewer shares left than were executed
+    pub fn unknown_executions(&self) -> u64 {
+        self.unknown
+    }
+
+    fn apply(&mut self, msg: &Message) -> Option<EnrichedExecution> {
+        match self.orders.apply(msg)? {
+            Ok(OrderUpdate::Executed {
+                reference,
+                order,
+                shares,
+                price,
+                match_number,
+                printable,
+            }) => Some(EnrichedExecution {
+                stock_locate: msg.stock_locate,
+                timestamp: msg.timestamp,
+                stock: order.stock,
+                reference,
+                side: order.side,
+                shares,
+                price,
+                limit_price: order.price,
+                match_number,
+                printable,
+                mpid: order.mpid,
+                remaining: order.shares.saturating_sub(shares),
+            }),
+            Ok(_) => None,
+            Err(_) => {
+                if msg.tag == b'E' || msg.tag == b'C' {
+                    self.unknown += 1;
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>> Iterator for ExecutionStream<I> {
+    type Item = Result<EnrichedExecution>;
+
+    fn next(&mut self) -> Option<Result<EnrichedExecution>> {
+        loop {
+            match self.messages.next()? {
+                Ok(msg) => {
+                    if let Some(execution) = self.apply(&msg) {
+                        return Some(Ok(execution));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Body;
+
+    #[test]
+    fn joins_executions_with_orders() {
+        let tagged = |tag, ts, body| Message {
+            tag,
+            ..msg(ts, body)
+        };
+        let messages = vec![
+            tagged(b'A', 1, add(1, Side::Sell, 100, 10_000)),
+            tagged(
+                b'E',
+                2,
+                Body::OrderExecuted {
+                    reference: 1,
+                    executed: 30,
+                    match_number: 5,
+                },
+            ),
+            tagged(
+                b'C',
+                3,
+                Body::OrderExecutedWithPrice {
+                    reference: 1,
+                    executed: 20,
+                    match_number: 6,
+                    printable: false,
+                    price: 9_900.into(),
+                },
+            ),
+            tagged(
+                b'E',
+                4,
+                Body::OrderExecuted {
+                    reference: 9,
+                    executed: 10,
+                    match_number: 7,
+                },
+            ),
+        ];
+        let mut stream = ExecutionStream::new(messages.into_iter().map(Ok));
+        let executions: Vec<_> = stream.by_ref().map(|e| e.unwrap()).collect();
+        assert_eq!(executions.len(), 2);
+        let first = executions[0];
+        assert_eq!(first.stock.as_str(), "ZXZZT   ");
+        assert_eq!(
+            (first.side, first.shares, first.remaining),
+            (Side::Sell, 30, 70)
+        );
+        assert_eq!(first.price, 10_000.into());
+        assert!(first.is_consistent());
+        // a sell executed below its limit
+        assert_eq!(executions[1].limit_price, 10_000.into());
+        assert!(!executions[1].is_consistent());
+        assert_eq!(stream.unknown_executions(), 1);
+    }
+}
@@ -0,0 +1,498 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::ops::ControlFlow;
+
+use crate::clock::Session;
+use crate::{Body, Message, MessageSink, Price4, ScaledPrice, Side};
+
+const CSV_HEADER: &str =
+    "timestamp,tag,stock_locate,tracking_number,stock,reference,side,shares,price,match_number";
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+pub(crate) const PRICE4_SCALE: NonZeroU32 = match NonZeroU32::new(Price4::SCALE) {
+    Some(scale) => scale,
+    None => NonZeroU32::MIN,
+};
+
+/// How exporters format prices
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PriceFormat {
+    /// The decimal places of the price scale, four for ITCH 5.0, e.g.
+    /// `12.5000`. See [`ScaledPrice`]'s `Display` for scales which are not a
+    /// power of ten.
+    #[default]
+    Fixed,
+    /// As the nearest `f64`, like [`Price4::as_f64`], dropping trailing
+    /// zeros, e.g. `12.5`. The printed value is still exact, see `as_f64`
+    /// for the rounding caveats when reading it back as a float.
+    Float,
+    /// The integer price in units of the price scale, as on the wire, e.g.
+    /// `125000`
+    Raw,
+}
+
+/// How exporters format timestamps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TimestampFormat {
+    /// Nanoseconds since midnight, as on the wire
+    #[default]
+    Nanos,
+    /// ISO 8601 Eastern time on the session date, with nanoseconds and the
+    /// UTC offset, e.g. `2024-01-02T09:30:00.000000000-05:00`
+    Iso8601(Session),
+}
+
+/// Number formatting shared by the exporters, [`CsvWriter`] and
+/// [`JsonWriter`].
+///
+/// The output does not depend on the locale: decimals always use a point
+/// and numbers are never grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExportOptions {
+    pub prices: PriceFormat,
+    pub timestamps: TimestampFormat,
+    /// Raw units per currency unit in `Price4` fields, 10,000 for ITCH 5.0.
+    /// See [`MessageStream::export_options`](crate::MessageStream::export_options)
+    /// for that of a stream.
+    pub price_scale: NonZeroU32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> ExportOptions {
+        ExportOptions {
+            prices: PriceFormat::default(),
+            timestamps: TimestampFormat::default(),
+            price_scale: PRICE4_SCALE,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// A `Price4` field read with the price scale
+    pub(crate) fn scaled(&self, price: Price4) -> ScaledPrice {
+        ScaledPrice::new(price.raw() as u64, self.price_scale.get() as u64)
+    }
+
+    // writing to a String cannot fail
+    fn push_price(&self, out: &mut String, price: Price4) {
+        let _ = match self.prices {
+            PriceFormat::Fixed => write!(out, "{}", self.scaled(price)),
+            PriceFormat::Float => write!(out, "{}", f64::from(self.scaled(price))),
+            PriceFormat::Raw => write!(out, "{}", price.raw()),
+        };
+    }
+
+    fn push_timestamp(&self, out: &mut String, ts: u64) {
+        let _ = match self.timestamps {
+            TimestampFormat::Nanos => write!(out, "{}", ts),
+            TimestampFormat::Iso8601(session) => {
+                let secs = ts / NANOS_PER_SEC;
+                let offset = session.utc_offset(ts);
+                write!(
+                    out,
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}{}{:02}:{:02}",
+                    session.year(),
+                    session.month(),
+                    session.day(),
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60,
+                    ts % NANOS_PER_SEC,
+                    if offset < 0 { '-' } else { '+' },
+                    offset.unsigned_abs() / 3600,
+                    offset.unsigned_abs() / 60 % 60
+                )
+            }
+        };
+    }
+
+    // whether timestamps are written as strings rather than numbers
+    fn quote_timestamps(&self) -> bool {
+        self.timestamps != TimestampFormat::Nanos
+    }
+}
+
+/// Writes messages as CSV, one row per message.
+///
+/// Columns which do not apply to a message type are left empty. Order
+/// replaces report the new reference, and executions the executed shares.
+/// As a [`MessageSink`], write errors stop the stream and are kept for
+/// [`finish`](CsvWriter::finish).
+#[derive(Debug)]
+pub struct CsvWriter<W: Write> {
+    writer: W,
+    row: String,
+    header_written: bool,
+    options: ExportOptions,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(writer: W) -> CsvWriter<W> {
+        CsvWriter {
+            writer,
+            row: String::new(),
+            header_written: false,
+            options: ExportOptions::default(),
+            error: None,
+        }
+    }
+
+    /// How to format prices and timestamps (by default, prices with exactly
+    /// four decimal places and timestamps in nanoseconds)
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// How to format prices (exactly four decimal places by default)
+    pub fn price_format(mut self, format: PriceFormat) -> Self {
+        self.options.prices = format;
+        self
+    }
+
+    /// Write one message, preceded by the header row if this is the first
+    pub fn write(&mut self, msg: &Message) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(self.writer, "{}", CSV_HEADER)?;
+            self.header_written = true;
+        }
+        let Fields {
+            stock,
+            reference,
+            side,
+            shares,
+            price,
+            match_number,
+        } = Fields::of(&msg.body);
+        self.row.clear();
+        self.options.push_timestamp(&mut self.row, msg.timestamp);
+        // writing to a String cannot fail
+        let _ = write!(
+            self.row,
+            ",{},{},{},",
+            msg.tag as char, msg.stock_locate, msg.tracking_number
+        );
+        self.row.push_str(stock.map_or("", |s| s.trim_end()));
+        self.row.push(',');
+        push_opt(&mut self.row, reference);
+        self.row.push_str(match side {
+            Some(Side::Buy) => "B,",
+            Some(Side::Sell) => "S,",
+            None => ",",
+        });
+        push_opt(&mut self.row, shares);
+        if let Some(price) = price {
+            self.options.push_price(&mut self.row, price);
+        }
+        self.row.push(',');
+        if let Some(match_number) = match_number {
+            let _ = write!(self.row, "{}", match_number);
+        }
+        self.row.push('\n');
+        self.writer.write_all(self.row.as_bytes())
+    }
+
+    /// Flush the writer and return it, or the first error met as a sink
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> MessageSink for CsvWriter<W> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.write(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+fn push_opt<T: std::fmt::Display>(row: &mut String, value: Option<T>) {
+    if let Some(value) = value {
+        let _ = write!(row, "{}", value);
+    }
+    row.push(',');
+}
+
+/// Writes messages as JSON lines, one object per message, with the fields
+/// of [`CsvWriter`]'s columns.
+///
+/// Fields which do not apply to a message type are left out. Prices are
+/// JSON numbers in every [`PriceFormat`], and timestamps are numbers in
+/// nanoseconds or ISO 8601 strings. As a [`MessageSink`], write errors stop
+/// the stream and are kept for [`finish`](JsonWriter::finish).
+#[derive(Debug)]
+pub struct JsonWriter<W: Write> {
+    writer: W,
+    line: String,
+    options: ExportOptions,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W) -> JsonWriter<W> {
+        JsonWriter {
+            writer,
+            line: String::new(),
+            options: ExportOptions::default(),
+            error: None,
+        }
+    }
+
+    /// How to format prices and timestamps (by default, prices with exactly
+    /// four decimal places and timestamps in nanoseconds)
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn write(&mut self, msg: &Message) -> io::Result<()> {
+        let fields = Fields::of(&msg.body);
+        self.line.clear();
+        self.line.push_str("{\"timestamp\":");
+        if self.options.quote_timestamps() {
+            self.line.push('"');
+            self.options.push_timestamp(&mut self.line, msg.timestamp);
+            self.line.push('"');
+        } else {
+            self.options.push_timestamp(&mut self.line, msg.timestamp);
+        }
+        self.line.push_str(",\"tag\":");
+        push_json_str(&mut self.line, &(msg.tag as char).to_string());
+        // writing to a String cannot fail
+        let _ = write!(
+            self.line,
+            ",\"stock_locate\":{},\"tracking_number\":{}",
+            msg.stock_locate, msg.tracking_number
+        );
+        if let Some(stock) = fields.stock {
+            self.line.push_str(",\"stock\":");
+            push_json_str(&mut self.line, stock.trim_end());
+        }
+        if let Some(reference) = fields.reference {
+            let _ = write!(self.line, ",\"reference\":{}", reference);
+        }
+        if let Some(side) = fields.side {
+            let side = match side {
+                Side::Buy => "B",
+                Side::Sell => "S",
+            };
+            let _ = write!(self.line, ",\"side\":\"{}\"", side);
+        }
+        if let Some(shares) = fields.shares {
+            let _ = write!(self.line, ",\"shares\":{}", shares);
+        }
+        if let Some(price) = fields.price {
+            self.line.push_str(",\"price\":");
+            self.options.push_price(&mut self.line, price);
+        }
+        if let Some(match_number) = fields.match_number {
+            let _ = write!(self.line, ",\"match_number\":{}", match_number);
+        }
+        self.line.push_str("}\n");
+        self.writer.write_all(self.line.as_bytes())
+    }
+
+    /// Flush the writer and return it, or the first error met as a sink
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> MessageSink for JsonWriter<W> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.write(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+fn push_json_str(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// the columns of a row which depend on the message type
+#[derive(Default)]
+pub(crate) struct Fields<'a> {
+    pub(crate) stock: Option<&'a str>,
+    pub(crate) reference: Option<u64>,
+    pub(crate) side: Option<Side>,
+    pub(crate) shares: Option<u64>,
+    pub(crate) price: Option<Price4>,
+    pub(crate) match_number: Option<u64>,
+}
+
+impl<'a> Fields<'a> {
+    pub(crate) fn of(body: &'a Body) -> Fields<'a> {
+        let mut fields = Fields {
+            stock: body.stock().map(|s| s.as_str()),
+            ..Default::default()
+        };
+        match *body {
+            Body::AddOrder(ref add) => {
+                fields.reference = Some(add.reference);
+                fields.side = Some(add.side);
+                fields.shares = Some(add.shares as u64);
+                fields.price = Some(add.price);
+            }
+            Body::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => {
+                fields.reference = Some(reference);
+                fields.shares = Some(executed as u64);
+                fields.match_number = Some(match_number);
+            }
+            Body::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                price,
+                ..
+            } => {
+                fields.reference = Some(reference);
+                fields.shares = Some(executed as u64);
+                fields.price = Some(price);
+                fields.match_number = Some(match_number);
+            }
+            Body::OrderCancelled {
+                reference,
+                cancelled,
+            } => {
+                fields.reference = Some(reference);
+                fields.shares = Some(cancelled as u64);
+            }
+            Body::DeleteOrder { reference } => fields.reference = Some(reference),
+            Body::ReplaceOrder(ref replace) => {
+                fields.reference = Some(replace.new_reference);
+                fields.shares = Some(replace.shares as u64);
+                fields.price = Some(replace.price);
+            }
+            Body::NonCrossTrade(ref trade) => {
+                fields.reference = Some(trade.reference);
+                fields.side = Some(trade.side);
+                fields.shares = Some(trade.shares as u64);
+                fields.price = Some(trade.price);
+                fields.match_number = Some(trade.match_number);
+            }
+            Body::CrossTrade(ref cross) => {
+                fields.shares = Some(cross.shares);
+                fields.price = Some(cross.cross_price);
+                fields.match_number = Some(cross.match_number);
+            }
+            Body::BrokenTrade { match_number } => fields.match_number = Some(match_number),
+            _ => {}
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drive;
+    use crate::orders::tests::{add, msg};
+
+    #[test]
+    fn writes_csv() {
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 40,
+            match_number: 7,
+        };
+        let tagged = |tag, msg: Message| Ok(Message { tag, ..msg });
+        let messages = vec![
+            tagged(b'A', msg(5, add(1, Side::Buy, 100, 123_456))),
+            tagged(b'E', msg(6, exec)),
+        ];
+        let mut csv = CsvWriter::new(Vec::new());
+        drive(messages, &mut csv).unwrap();
+        let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "5,A,1,0,ZXZZT,1,B,100,12.3456,");
+        assert_eq!(lines[2], "6,E,1,0,,1,,40,,7");
+
+        let mut csv = CsvWriter::new(Vec::new()).price_format(PriceFormat::Float);
+        let add = Message {
+            tag: b'A',
+            ..msg(5, add(1, Side::Buy, 100, 123_450))
+        };
+        csv.write(&add).unwrap();
+        let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+        assert_eq!(csv.lines().nth(1).unwrap(), "5,A,1,0,ZXZZT,1,B,100,12.345,");
+
+        let mut stream = crate::MessageStream::from_reader(&b""[..]);
+        stream.set_price_scale(100);
+        let mut csv = CsvWriter::new(Vec::new()).options(stream.export_options());
+        csv.write(&add).unwrap();
+        let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "5,A,1,0,ZXZZT,1,B,100,1234.50,"
+        );
+    }
+
+    #[test]
+    fn writes_json_with_options() {
+        let session = Session::new(2024, 7, 1).unwrap();
+        let options = ExportOptions {
+            prices: PriceFormat::Raw,
+            timestamps: TimestampFormat::Iso8601(session),
+            ..ExportOptions::default()
+        };
+        let add = Message {
+            tag: b'A',
+            ..msg(34_200_000_000_005, add(1, Side::Sell, 100, 123_450))
+        };
+        let mut json = JsonWriter::new(Vec::new()).options(options);
+        json.write(&add).unwrap();
+        let line = String::from_utf8(json.finish().unwrap()).unwrap();
+        assert_eq!(
+            line,
+            "{\"timestamp\":\"2024-07-01T09:30:00.000000005-04:00\",\"tag\":\"A\",\
+             \"stock_locate\":1,\"tracking_number\":0,\"stock\":\"ZXZZT\",\"reference\":1,\
+             \"side\":\"S\",\"shares\":100,\"price\":123450}\n"
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["price"], 123_450);
+
+        let mut csv = CsvWriter::new(Vec::new()).options(options);
+        csv.write(&add).unwrap();
+        let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "2024-07-01T09:30:00.000000005-04:00,A,1,0,ZXZZT,1,S,100,123450,"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+/// The NASDAQ data product a stream carries.
+///
+/// All profiles use the same length-prefixed framing. Messages outside the
+/// profile are skipped by [`MessageStream`](crate::MessageStream) and reported
+/// as errors, without attempting to parse their bodies.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FeedProfile {
+    /// Nasdaq TotalView-ITCH 5.0, the full depth-of-book feed
+    #[default]
+    TotalView,
+    /// Net Order Imbalance Indicator feed: system, directory, trading action
+    /// and NOII messages only
+    Noii,
+    /// Nasdaq Basic, the best bid and offer and last sale feed. It shares
+    /// the administrative messages of TotalView, which are decoded. Its
+    /// quotation and trade messages have their own layouts, which are not:
+    /// they are skipped by their length prefix, or can be decoded with a
+    /// [`ParserRegistry`](crate::ParserRegistry).
+    Basic,
+}
+
+const TOTALVIEW_TAGS: &[u8] = b"SRHYLVWKJAFECXDUPQBINO";
+const NOII_TAGS: &[u8] = b"SRHI";
+const BASIC_TAGS: &[u8] = b"SRHYVWKJ";
+
+impl FeedProfile {
+    /// Message types which may appear in this feed
+    pub fn tags(self) -> &'static [u8] {
+        match self {
+            FeedProfile::TotalView => TOTALVIEW_TAGS,
+            FeedProfile::Noii => NOII_TAGS,
+            FeedProfile::Basic => BASIC_TAGS,
+        }
+    }
+
+    pub fn allows(self, tag: u8) -> bool {
+        self.tags().contains(&tag)
+    }
+
+    /// Whether a frame of a type outside the profile, of `len` bytes after
+    /// the length prefix, is a message of the product rather than corrupt
+    /// input. TotalView and NOII only carry TotalView-ITCH 5.0 layouts,
+    /// while Nasdaq Basic has message types of its own.
+    pub(crate) fn recognizes(self, tag: u8, len: usize) -> bool {
+        match self {
+            FeedProfile::TotalView | FeedProfile::Noii => {
+                crate::version::length_v50(tag) == Some(len)
+            }
+            FeedProfile::Basic => len > 0,
+        }
+    }
+
+    /// Number of raw units per dollar in four-decimal price fields
+    pub fn price_scale(self) -> u32 {
+        match self {
+            FeedProfile::TotalView | FeedProfile::Noii | FeedProfile::Basic => crate::Price4::SCALE,
+        }
+    }
+}
+
+impl fmt::Display for FeedProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FeedProfile::TotalView => "TotalView-ITCH",
+            FeedProfile::Noii => "NOII",
+            FeedProfile::Basic => "Nasdaq Basic",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::{Body, MessageStream, MessageStreamBuilder};
+
+    #[test]
+    fn skips_messages_outside_profile() {
+        // broken trade, then system event
+        let code = b"0013 4200 0000 0028 6aab 3b3a 9900 0000 0000 0000 01
+                     000c 5300 0000 0028 6aab 3b3a 994f";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::from_reader(&buf[..]);
+        stream.set_profile(FeedProfile::Noii);
+        let err = stream.next().unwrap().unwrap_err();
+        assert_eq!(err.position().unwrap().message_index, 0);
+        let msg = stream.next().unwrap().unwrap();
+        assert!(matches!(msg.body, Body::SystemEvent { .. }));
+        assert!(stream.next().is_none());
+        assert_eq!(stream.message_count(), 2);
+
+        let stream = MessageStream::from_reader(&buf[..]);
+        assert_eq!(stream.profile(), FeedProfile::TotalView);
+        assert_eq!(stream.filter(|m| m.is_ok()).count(), 2);
+    }
+
+    #[test]
+    fn skips_basic_messages_by_length() {
+        // a message of a type only Nasdaq Basic has, then a system event
+        let code = b"0008 7100 0000 0000 0000
+                     000c 5300 0000 0028 6aab 3b3a 994f";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::from_reader(&buf[..]);
+        stream.set_profile(FeedProfile::Basic);
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().is_none());
+
+        let mut stream = MessageStreamBuilder::new()
+            .profile(FeedProfile::Basic)
+            .strict(false)
+            .build(&buf[..]);
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().is_none());
+        assert_eq!(stream.message_count(), 2);
+        // the skipped frame was not parsed
+        assert_eq!(stream.summary().messages, 1);
+
+        // as corrupt input, the same frame stops a TotalView stream
+        let stream = MessageStream::from_reader(&buf[..]);
+        assert_eq!(stream.filter(|m| m.is_ok()).count(), 0);
+    }
+}
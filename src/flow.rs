@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use crate::symbol::padded_mpid;
+use crate::{ArrayString4, Message, Order, OrderTracker, OrderUpdate};
+
+/// Order flow counters for a symbol or market participant
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FlowStats {
+    /// Orders entered, including replacements
+    pub orders: u64,
+    /// Executions against resting orders
+    pub trades: u64,
+    /// Orders removed by a delete or a cancel of all remaining shares
+    pub cancelled_orders: u64,
+    /// Orders which left the book (filled, cancelled or replaced)
+    pub removed_orders: u64,
+    /// Total time spent on the book by removed orders, in nanoseconds
+    pub resting_time: u64,
+}
+
+impl FlowStats {
+    /// Orders entered per execution, `None` if there were no executions
+    pub fn order_to_trade_ratio(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.orders as f64 / self.trades as f64)
+    }
+
+    /// Fraction of orders entered which were cancelled
+    pub fn cancel_rate(&self) -> Option<f64> {
+        (self.orders > 0).then(|| self.cancelled_orders as f64 / self.orders as f64)
+    }
+
+    /// Mean time on the book of removed orders, in nanoseconds
+    pub fn average_resting_time(&self) -> Option<f64> {
+        (self.removed_orders > 0).then(|| self.resting_time as f64 / self.removed_orders as f64)
+    }
+
+    fn remove(&mut self, order: &Order, timestamp: u64) {
+        self.removed_orders += 1;
+        self.resting_time += timestamp.saturating_sub(order.timestamp);
+    }
+}
+
+/// Order-to-trade ratios, cancel rates and resting times per symbol and
+/// per MPID.
+///
+/// A replace counts as removal of the old order and entry of a new one.
+/// Only orders added with attribution ('F' messages) are counted per MPID.
+#[derive(Debug, Clone, Default)]
+pub struct OrderFlow {
+    tracker: OrderTracker,
+    symbols: BTreeMap<u16, FlowStats>,
+    mpids: BTreeMap<ArrayString4, FlowStats>,
+}
+
+impl OrderFlow {
+    pub fn new() -> OrderFlow {
+        OrderFlow::default()
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        let Some(Ok(update)) = self.tracker.apply(msg) else {
+            return;
+        };
+        let order = match update {
+            OrderUpdate::Added { order, .. }
+            | OrderUpdate::Executed { order, .. }
+            | OrderUpdate::Cancelled { order, .. }
+            | OrderUpdate::Deleted { order, .. } => order,
+            OrderUpdate::Replaced { old, .. } => old,
+        };
+        let symbol = self.symbols.entry(order.stock_locate).or_default();
+        let mpid = order.mpid.map(|mpid| self.mpids.entry(mpid).or_default());
+        for stats in std::iter::once(symbol).chain(mpid) {
+            match update {
+                OrderUpdate::Added { .. } => stats.orders += 1,
+                OrderUpdate::Executed { .. } => stats.trades += 1,
+                OrderUpdate::Cancelled { .. } | OrderUpdate::Deleted { .. } => {
+                    if update.is_terminal() {
+                        stats.cancelled_orders += 1;
+                    }
+                }
+                OrderUpdate::Replaced { .. } => stats.orders += 1,
+            }
+            if update.is_terminal() {
+                stats.remove(&order, msg.timestamp);
+            }
+        }
+    }
+
+    /// Statistics for a stock locate
+    pub fn symbol(&self, locate: u16) -> Option<&FlowStats> {
+        self.symbols.get(&locate)
+    }
+
+    /// Statistics for an MPID, ignoring trailing padding
+    pub fn mpid(&self, mpid: &str) -> Option<&FlowStats> {
+        self.mpids.get(&padded_mpid(mpid)?)
+    }
+
+    /// Statistics for every symbol seen, by stock locate
+    pub fn symbols(&self) -> &BTreeMap<u16, FlowStats> {
+        &self.symbols
+    }
+
+    /// Statistics for every MPID seen
+    pub fn mpids(&self) -> &BTreeMap<ArrayString4, FlowStats> {
+        &self.mpids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, Side};
+
+    #[test]
+    fn flow_ratios() {
+        let mut flow = OrderFlow::new();
+        let mut attributed = add(2, Side::Sell, 100, 10_100);
+        if let Body::AddOrder(ref mut add) = attributed {
+            add.mpid = Some(ArrayString4::from("GSCO").unwrap());
+        }
+        flow.update(&msg(0, add(1, Side::Buy, 100, 10_000)));
+        flow.update(&msg(10, attributed));
+        flow.update(&msg(10, add(3, Side::Buy, 100, 9_900)));
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 100,
+            match_number: 1,
+        };
+        flow.update(&msg(100, exec));
+        flow.update(&msg(110, Body::DeleteOrder { reference: 2 }));
+
+        let stats = flow.symbol(1).unwrap();
+        assert_eq!(stats.order_to_trade_ratio(), Some(3.0));
+        assert_eq!(stats.cancel_rate(), Some(1.0 / 3.0));
+        assert_eq!(stats.average_resting_time(), Some(100.0));
+
+        let gsco = flow.mpid("GSCO").unwrap();
+        assert_eq!(flow.mpid("GSCO  "), Some(gsco));
+        assert!(flow.mpid("GSCOX").is_none());
+        assert_eq!((gsco.orders, gsco.cancelled_orders), (1, 1));
+        assert_eq!(gsco.order_to_trade_ratio(), None);
+        assert_eq!(flow.mpids().len(), 1);
+    }
+}
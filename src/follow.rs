@@ -0,0 +1,160 @@
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reads a file as another process writes it, like `tail -f`, for
+/// processing a capture in near real time. See [`MessageStream::follow`].
+///
+/// At the end of the file, the reader waits for more data, polling every
+/// `poll_interval`, rather than reporting the end. If the file at the path
+/// is replaced, as when a capture is rotated, reading moves on to the new
+/// file once the old one is exhausted; if it is truncated, reading starts
+/// again from the beginning. A file removed without a replacement is waited
+/// for. A message left incomplete by the writer at rotation is joined to
+/// the start of the next file, so writers should rotate between messages.
+///
+/// Reads block until data arrives, unless an `idle_timeout` is set, after
+/// which the reader reports the end of the file.
+///
+/// [`MessageStream::follow`]: crate::MessageStream::follow
+#[derive(Debug)]
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    poll_interval: Duration,
+    idle_timeout: Option<Duration>,
+    reopened: u32,
+}
+
+impl FollowReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FollowReader> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        Ok(FollowReader {
+            path,
+            file,
+            pos: 0,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            idle_timeout: None,
+            reopened: 0,
+        })
+    }
+
+    /// How often to check for new data at the end of the file (100 ms by
+    /// default)
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// End reading after this long without new data
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Number of times the file has been rotated or truncated
+    pub fn reopened(&self) -> u32 {
+        self.reopened
+    }
+
+    // move to a replaced or truncated file, true if reading should resume
+    fn check(&mut self) -> io::Result<bool> {
+        let Ok(current) = fs::metadata(&self.path) else {
+            // removed, and not yet replaced
+            return Ok(false);
+        };
+        if !same_file(&current, &self.file.metadata()?) {
+            self.file = File::open(&self.path)?;
+        } else if current.len() < self.pos {
+            self.file.seek(SeekFrom::Start(0))?;
+        } else {
+            return Ok(false);
+        }
+        self.pos = 0;
+        self.reopened += 1;
+        Ok(true)
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.created().ok() == b.created().ok()
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        loop {
+            let ct = self.file.read(buf)?;
+            if ct > 0 || buf.is_empty() {
+                self.pos += ct as u64;
+                return Ok(ct);
+            }
+            if self.check()? {
+                continue;
+            }
+            if self
+                .idle_timeout
+                .is_some_and(|timeout| start.elapsed() >= timeout)
+            {
+                return Ok(0);
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::MessageStream;
+    use std::io::Write;
+
+    #[test]
+    fn follows_growth_and_rotation() {
+        let frame = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f");
+        let dir = std::env::temp_dir().join(format!("itchy-follow-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("live.itch");
+        fs::write(&path, frame.repeat(2)).unwrap();
+
+        let writer = {
+            let (path, dir, frame) = (path.clone(), dir.clone(), frame.clone());
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+                file.write_all(&frame).unwrap();
+                thread::sleep(Duration::from_millis(50));
+                fs::rename(&path, dir.join("live.itch.1")).unwrap();
+                fs::write(&path, frame.repeat(3)).unwrap();
+            })
+        };
+        let reader = FollowReader::open(&path)
+            .unwrap()
+            .poll_interval(Duration::from_millis(5))
+            .idle_timeout(Duration::from_millis(500));
+        let mut stream = MessageStream::from_reader(reader);
+        let ct = stream
+            .by_ref()
+            .map(|m| m.unwrap().tag)
+            .filter(|&tag| tag == b'S')
+            .count();
+        writer.join().unwrap();
+        assert_eq!(ct, 6);
+        assert_eq!(stream.get_ref().reopened(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
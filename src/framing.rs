@@ -0,0 +1,1348 @@
+// Framing: reading length-prefixed messages from a byte stream into a
+// reusable buffer, with the error policies, filters and positioning of a
+// `MessageStream`
+
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use flate2::read::GzDecoder;
+use nom::{error::ErrorKind, Err};
+
+use crate::builder::{
+    EventWindow, ProgressHook, SymbolFilter, TagHook, REFERENCE_TAGS, SAMPLED_TAGS,
+};
+use crate::dump::ErrorDump;
+use crate::export::PRICE4_SCALE;
+use crate::messages::parse_message;
+use crate::*;
+
+// Default size of buffer for parsing
+pub(crate) const BUFSIZE: usize = 8 * 1024;
+// Default cap on the buffer as it grows
+pub(crate) const MAX_BUFSIZE: usize = 1 << 20;
+// Double the buffer after this many reads in a row fill it
+const GROW_AFTER: u32 = 4;
+
+/// Represents an iterable stream of ITCH protocol messages
+pub struct MessageStream<R> {
+    reader: R,
+    buffer: Box<[u8]>,
+    bufstart: usize,
+    bufend: usize,
+    bytes_read: u64,
+    read_calls: u64,
+    // the buffer grows up to this size while reads keep filling it
+    pub(crate) max_buffer_size: usize,
+    full_reads: u32,
+    message_ct: u64, // messages read so far
+    parsed: u64,     // messages parsed so far, without skipped frames or errors
+    errors: u64,     // errors yielded so far
+    // the reader has reported the end of its input
+    eof: bool,
+    started: Instant,
+    // called with the summary on `finish` or drop
+    pub(crate) on_finish: Option<FinishHook>,
+    finished: bool,
+    in_error_state: bool,
+    // looking for the next well-formed frame after an error
+    resyncing: bool,
+    peeked: Option<Peeked>,
+    pub(crate) profile: FeedProfile,
+    pub(crate) price_scale: Option<u32>,
+    pub(crate) tags: Option<Box<[bool; 256]>>,
+    pub(crate) symbols: Option<SymbolFilter>,
+    pub(crate) strict: bool,
+    pub(crate) error_policy: ErrorPolicy,
+    pub(crate) error_context: ErrorContext,
+    pub(crate) validation: ValidationLevel,
+    pub(crate) progress: Option<ProgressHook>,
+    pub(crate) tag_hooks: Vec<TagHook>,
+    pub(crate) dump: Option<ErrorDump>,
+    // keep one in this many order flow messages
+    pub(crate) sample_every: u32,
+    sampled: u32,
+    // only yield order flow between two system events
+    pub(crate) window: Option<EventWindow>,
+    pub(crate) parsers: Option<ParserRegistry>,
+    // end iterating after the End of Messages event
+    pub(crate) stop_at_end: bool,
+    // the End of Messages event has been read
+    ended: bool,
+    // reader offset corresponding to `origin_bytes` consumed bytes, moved by seeking
+    origin_offset: u64,
+    origin_bytes: u64,
+    #[cfg(feature = "metrics")]
+    metrics: Option<std::sync::Arc<dyn MetricsRegistry>>,
+}
+
+pub(crate) type FinishHook = Box<dyn FnOnce(&StreamSummary) + Send>;
+
+/// An item parsed ahead by `peek()`
+struct Peeked {
+    item: Option<Result<Message>>,
+    len: usize,      // bytes consumed by the item
+    message_ct: u64, // message count before the item
+    parsed: u64,     // parsed count before the item
+}
+
+/// Summary of a stream at the end of iteration, see [`MessageStream::finish`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StreamSummary {
+    /// Number of messages successfully parsed, not counting frames skipped
+    /// without parsing them or messages reported as errors
+    pub messages: u64,
+    /// Number of errors returned by the stream
+    pub errors: u64,
+    /// Total bytes read from the underlying reader
+    pub bytes_read: u64,
+    /// Number of reads from the underlying reader
+    pub read_calls: u64,
+    /// Time since the stream was created
+    pub duration: Duration,
+    /// Number of bytes after the last complete message which could not be
+    /// decoded, once the end of the input has been read (zero before)
+    pub trailing_bytes: usize,
+    /// The undecoded trailing bytes, e.g. a message truncated by capture rotation
+    pub trailing_data: Vec<u8>,
+}
+
+impl StreamSummary {
+    /// True if the stream ended exactly on a message boundary
+    pub fn is_complete(&self) -> bool {
+        self.trailing_bytes == 0
+    }
+}
+
+/// Location of a message within a stream, see [`MessageStream::position`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StreamPosition {
+    /// Zero-based index of the message in the stream
+    pub message_index: u64,
+    /// Offset of the start of the message (its length prefix) in the underlying reader
+    pub byte_offset: u64,
+}
+
+impl fmt::Display for StreamPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "message {} (byte offset {})",
+            self.message_index, self.byte_offset
+        )
+    }
+}
+
+/// A position in a seekable stream, see [`MessageStream::checkpoint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Checkpoint {
+    pub(crate) offset: u64,
+    pub(crate) message_ct: u64,
+}
+
+impl Checkpoint {
+    /// Byte offset of the next message in the underlying reader
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+/// A checkpoint at the start of the message at a position
+impl From<StreamPosition> for Checkpoint {
+    fn from(position: StreamPosition) -> Checkpoint {
+        Checkpoint {
+            offset: position.byte_offset,
+            message_ct: position.message_index,
+        }
+    }
+}
+
+impl MessageStream<File> {
+    /// Configure a stream, see [`MessageStreamBuilder`]
+    pub fn builder() -> MessageStreamBuilder {
+        MessageStreamBuilder::new()
+    }
+
+    /// Parse an in-memory capture in place, without copying it through a
+    /// buffer, see [`SliceStream`]
+    pub fn from_slice(data: &[u8]) -> SliceStream<'_> {
+        SliceStream::new(data)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<MessageStream<File>> {
+        let reader = File::open(path)?;
+        Ok(MessageStream::from_reader(reader))
+    }
+}
+
+impl MessageStream<GzDecoder<File>> {
+    pub fn from_gzip<P: AsRef<Path>>(path: P) -> Result<MessageStream<GzDecoder<File>>> {
+        let file = File::open(path)?;
+        let reader = GzDecoder::new(file);
+        Ok(MessageStream::from_reader(reader))
+    }
+}
+
+impl MessageStream<FollowReader> {
+    /// Keep reading a file as it grows, following it through rotation and
+    /// truncation, so that iteration waits for new messages rather than
+    /// ending. See [`FollowReader`], which can also be configured and
+    /// passed to [`from_reader`](MessageStream::from_reader).
+    pub fn follow<P: AsRef<Path>>(path: P) -> Result<MessageStream<FollowReader>> {
+        Ok(MessageStream::from_reader(FollowReader::open(path)?))
+    }
+}
+
+impl MessageStream<Prefetch> {
+    /// Open a gzipped file, decompressing on a background thread so that
+    /// decompression overlaps with parsing. See [`Prefetch`].
+    pub fn from_gzip_prefetched<P: AsRef<Path>>(path: P) -> Result<MessageStream<Prefetch>> {
+        let file = File::open(path)?;
+        let reader = Prefetch::new(GzDecoder::new(file));
+        Ok(MessageStream::from_reader(reader))
+    }
+}
+
+impl<I> MessageStream<PacketReader<I>>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    /// Parse messages from discrete packets, e.g. messages already extracted
+    /// by another framing layer, with or without their length prefix. See
+    /// [`PacketReader`].
+    pub fn from_packets<P>(packets: P) -> MessageStream<PacketReader<I>>
+    where
+        P: IntoIterator<IntoIter = I>,
+    {
+        MessageStream::from_reader(PacketReader::new(packets))
+    }
+}
+
+#[cfg(feature = "direct")]
+impl MessageStream<DirectReader> {
+    /// Open an uncompressed file with direct I/O, bypassing the page cache.
+    /// See [`DirectReader`].
+    pub fn from_file_direct<P: AsRef<Path>>(path: P) -> Result<MessageStream<DirectReader>> {
+        Ok(MessageStream::from_reader(DirectReader::open(path)?))
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl MessageStream<SeekableReader<File>> {
+    /// Open a file in the zstd seekable format, which can be seeked into
+    /// by decompressed offset. See [`SeekableReader`].
+    pub fn from_zstd_seekable<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<MessageStream<SeekableReader<File>>> {
+        Ok(MessageStream::from_reader(SeekableReader::open(path)?))
+    }
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+impl MessageStream<UringReader> {
+    /// Open an uncompressed file, reading ahead with io_uring. See
+    /// [`UringReader`].
+    pub fn from_file_uring<P: AsRef<Path>>(path: P) -> Result<MessageStream<UringReader>> {
+        Ok(MessageStream::from_reader(UringReader::open(path)?))
+    }
+}
+
+impl<R> fmt::Debug for MessageStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MessageStream {{ read_calls: {}, bytes_read: {}, buffer_size: {}, buffer_pos: {}, message_ct: {} }}",
+            self.read_calls,
+            self.bytes_read,
+            self.buffer.len(),
+            self.bytes_read - (self.bufend - self.bufstart) as u64,
+            self.message_ct
+        )
+    }
+}
+
+impl<R> MessageStream<R> {
+    /// Summary of the stream so far, as [`finish`](Self::finish) returns
+    /// at the end. A peeked message is not counted until it is returned by
+    /// `next()`, and there are no trailing bytes until the end of the input
+    /// has been read.
+    pub fn summary(&self) -> StreamSummary {
+        let pending = self
+            .peeked
+            .as_ref()
+            .is_some_and(|p| matches!(p.item, Some(Ok(_))));
+        let trailing_data = if self.eof && !pending {
+            self.buffer[self.bufstart..self.bufend].to_vec()
+        } else {
+            Vec::new()
+        };
+        let messages = match self.peeked {
+            Some(ref p) => p.parsed,
+            None => self.parsed,
+        };
+        StreamSummary {
+            messages,
+            errors: self.errors,
+            bytes_read: self.bytes_read,
+            read_calls: self.read_calls,
+            duration: self.started.elapsed(),
+            trailing_bytes: trailing_data.len(),
+            trailing_data,
+        }
+    }
+}
+
+// a stream dropped without `finish` reports its summary anyway
+impl<R> Drop for MessageStream<R> {
+    fn drop(&mut self) {
+        // the summary copies the trailing bytes, so only build it if wanted
+        #[cfg(feature = "tracing")]
+        let traced = tracing::enabled!(tracing::Level::DEBUG);
+        #[cfg(not(feature = "tracing"))]
+        let traced = false;
+        if self.finished || (self.on_finish.is_none() && !traced) {
+            return;
+        }
+        let summary = self.summary();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            messages = summary.messages,
+            errors = summary.errors,
+            bytes_read = summary.bytes_read,
+            duration_ms = summary.duration.as_millis() as u64,
+            "message stream dropped"
+        );
+        if let Some(on_finish) = self.on_finish.take() {
+            on_finish(&summary);
+        }
+    }
+}
+
+impl<R: Read> MessageStream<R> {
+    pub fn from_reader(reader: R) -> MessageStream<R> {
+        MessageStreamBuilder::new().build(reader)
+    }
+
+    pub(crate) fn new(reader: R, buffer_size: usize) -> MessageStream<R> {
+        MessageStream {
+            reader,
+            buffer: vec![0; buffer_size].into_boxed_slice(),
+            bufstart: 0,
+            bufend: 0,
+            bytes_read: 0,
+            read_calls: 0,
+            max_buffer_size: buffer_size,
+            full_reads: 0,
+            message_ct: 0,
+            parsed: 0,
+            errors: 0,
+            eof: false,
+            started: Instant::now(),
+            on_finish: None,
+            finished: false,
+            in_error_state: false,
+            resyncing: false,
+            peeked: None,
+            profile: FeedProfile::TotalView,
+            price_scale: None,
+            tags: None,
+            symbols: None,
+            strict: true,
+            error_policy: ErrorPolicy::Halt,
+            error_context: ErrorContext::default(),
+            validation: ValidationLevel::Basic,
+            progress: None,
+            tag_hooks: Vec::new(),
+            dump: None,
+            sample_every: 1,
+            sampled: 0,
+            window: None,
+            parsers: None,
+            stop_at_end: false,
+            ended: false,
+            origin_offset: 0,
+            origin_bytes: 0,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    fn fetch_more_bytes(&mut self) -> Result<usize> {
+        self.read_calls += 1;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("refill", read_calls = self.read_calls).entered();
+        let bufsize = self.buffer.len();
+        if self.full_reads >= GROW_AFTER && bufsize < self.max_buffer_size {
+            // the reader keeps up with whatever is asked of it, so ask for more
+            let mut buffer = vec![0; (bufsize * 2).min(self.max_buffer_size)];
+            buffer[..self.bufend - self.bufstart]
+                .copy_from_slice(&self.buffer[self.bufstart..self.bufend]);
+            self.buffer = buffer.into_boxed_slice();
+            self.bufend -= self.bufstart;
+            self.bufstart = 0;
+            self.full_reads = 0;
+        } else if self.bufend == bufsize {
+            // we need more data from the reader, but first,
+            // copy the remnants back to the beginning of the buffer
+            // (this should only be a few bytes)
+            if self.bufstart == 0 {
+                return Err(Error::Parse(format!(
+                    "message does not fit in the {} byte buffer",
+                    bufsize
+                )));
+            }
+            self.buffer.copy_within(self.bufstart.., 0);
+            self.bufend -= self.bufstart;
+            self.bufstart = 0;
+        }
+        let space = self.buffer.len() - self.bufend;
+        let n = self.reader.read(&mut self.buffer[self.bufend..])?;
+        if n == space {
+            self.full_reads += 1;
+        } else {
+            self.full_reads = 0;
+        }
+        Ok(n)
+    }
+
+    /// Current size of the parse buffer, which grows while the reader
+    /// fills every read, see
+    /// [`MessageStreamBuilder::max_buffer_size`](crate::MessageStreamBuilder::max_buffer_size)
+    pub fn buffer_size(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Total bytes read from the underlying reader. Counters are 64 bits
+    /// on every target, so they do not wrap on captures over 4 GiB.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Number of messages parsed so far, including any filtered out, from
+    /// the start of the reader or the position last restored
+    pub fn message_count(&self) -> u64 {
+        self.message_ct
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Consume the stream and summarize it.
+    ///
+    /// This is intended to be called once iteration has ended. If the input
+    /// was truncated mid-message, the partial message is reported as trailing
+    /// bytes rather than only as an "Unexpected EOF" error. A stream stopped
+    /// at the End of Messages event reads the rest of its input, all of
+    /// which is trailing.
+    pub fn finish(mut self) -> StreamSummary {
+        if self.ended && self.stop_at_end {
+            self.read_rest();
+        }
+        let summary = self.summary();
+        if let Some(on_finish) = self.on_finish.take() {
+            on_finish(&summary);
+        }
+        self.finished = true;
+        summary
+    }
+
+    // append the rest of the input to the unconsumed bytes
+    fn read_rest(&mut self) {
+        let mut rest = self.buffer[self.bufstart..self.bufend].to_vec();
+        let buffered = rest.len();
+        self.read_calls += 1;
+        // on an error, what was read before it is still reported
+        let _result = self.reader.read_to_end(&mut rest);
+        #[cfg(feature = "tracing")]
+        if let Err(ref e) = _result {
+            tracing::warn!(error = %e, "read error after End of Messages");
+        }
+        self.bytes_read += (rest.len() - buffered) as u64;
+        self.eof = true;
+        self.bufstart = 0;
+        self.bufend = rest.len();
+        self.buffer = rest.into_boxed_slice();
+    }
+
+    /// Whether the End of Messages system event has been read, which marks
+    /// a complete session. A stream ending without it was cut short.
+    pub fn ended_cleanly(&self) -> bool {
+        self.ended
+    }
+
+    /// Set the product carried by the stream (TotalView by default)
+    pub fn set_profile(&mut self, profile: FeedProfile) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> FeedProfile {
+        self.profile
+    }
+
+    /// Override the price scale of the feed profile, for variant feeds
+    /// which reuse the ITCH 5.0 layouts with different decimal places
+    ///
+    /// # Panics
+    ///
+    /// If `scale` is zero
+    pub fn set_price_scale(&mut self, scale: u32) {
+        assert!(scale > 0, "price scale must be non-zero");
+        self.price_scale = Some(scale);
+    }
+
+    /// Number of raw units per whole currency unit in `Price4` fields
+    pub fn price_scale(&self) -> u32 {
+        self.price_scale.unwrap_or(self.profile.price_scale())
+    }
+
+    /// Interpret a `Price4` field from this stream using its price scale
+    pub fn scaled(&self, price: Price4) -> ScaledPrice {
+        ScaledPrice::new(price.raw() as u64, self.price_scale() as u64)
+    }
+
+    /// Default [`ExportOptions`] with the price scale of this stream, for
+    /// the exporters of its messages
+    pub fn export_options(&self) -> ExportOptions {
+        ExportOptions {
+            price_scale: NonZeroU32::new(self.price_scale()).unwrap_or(PRICE4_SCALE),
+            ..ExportOptions::default()
+        }
+    }
+
+    /// Only yield one in every `n` order and trade messages, skipping the
+    /// rest without parsing them. See [`MessageStreamBuilder::sample_every`].
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero
+    pub fn set_sample_every(&mut self, n: u32) {
+        assert!(n > 0, "sampling interval must be non-zero");
+        self.sample_every = n;
+    }
+
+    /// Only yield order and trade messages between two system events, see
+    /// [`MessageStreamBuilder::between_events`]
+    pub fn between_events(mut self, start: EventCode, end: EventCode) -> Self {
+        self.window = Some(EventWindow::new(start, end));
+        self
+    }
+
+    /// Report counters for this stream to the given registry
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: std::sync::Arc<dyn MetricsRegistry>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Returns a reference to the next item without consuming it.
+    ///
+    /// The parsed item is cached, so the following call to `next()`
+    /// returns it without parsing again.
+    pub fn peek(&mut self) -> Option<&Result<Message>> {
+        if self.peeked.is_none() {
+            let (message_ct, parsed) = (self.message_ct, self.parsed);
+            let before = self.consumed();
+            let item = self.parse_next();
+            self.peeked = Some(Peeked {
+                item,
+                len: (self.consumed() - before) as usize,
+                message_ct,
+                parsed,
+            });
+        }
+        self.peeked.as_ref().and_then(|p| p.item.as_ref())
+    }
+
+    /// Position of the next message to be returned by `next()`.
+    ///
+    /// A peeked item is not considered consumed. Messages are numbered in
+    /// the order they are read, so positions are deterministic for a given input.
+    ///
+    /// Through a `&mut MessageStream`, this is shadowed by `Iterator::position`;
+    /// call it as `MessageStream::position(&stream)` in that case.
+    pub fn position(&self) -> StreamPosition {
+        let message_ct = match self.peeked {
+            Some(ref p) => p.message_ct,
+            None => self.message_ct,
+        };
+        StreamPosition {
+            message_index: message_ct,
+            byte_offset: self.origin_offset + self.consumed() - self.origin_bytes,
+        }
+    }
+
+    /// Like `next()`, but also returns the position of the message
+    pub fn next_with_position(&mut self) -> Option<Result<(StreamPosition, Message)>> {
+        // `&mut Self` is an iterator too, so avoid `Iterator::position`
+        let position = MessageStream::position(self);
+        self.next().map(|item| item.map(|msg| (position, msg)))
+    }
+
+    /// Wrap messages in [`Envelope`]s with sequence numbers synthesized from
+    /// their position in the stream, for consistency with live sessions
+    pub fn sequenced(self, session: SessionId) -> Sequenced<R> {
+        Sequenced::new(self, session)
+    }
+
+    /// Parse the stream on a background thread, fanning every message out
+    /// to `n` consumers so that several analyses can share one pass over a
+    /// file. See [`TeeReceiver`].
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero, or the OS cannot create a thread
+    pub fn tee(self, n: usize) -> Vec<TeeReceiver>
+    where
+        R: Send + 'static,
+    {
+        tee::spawn(self, n)
+    }
+
+    /// Consume the stream, computing a [`Digest`] of its messages for
+    /// comparing captures of the same session from different sources
+    #[cfg(feature = "digest")]
+    pub fn digest(self) -> Result<Digest> {
+        let mut digester = Digester::new();
+        for msg in self {
+            digester.update(&msg?);
+        }
+        Ok(digester.digest())
+    }
+
+    // attach the current position to an error
+    fn positioned(&self, error: Error) -> Error {
+        Error::Stream {
+            position: self.position(),
+            source: Box::new(error),
+        }
+    }
+
+    // bytes consumed from the reader so far, excluding any peeked item
+    fn consumed(&self) -> u64 {
+        let peeked = self.peeked.as_ref().map_or(0, |p| p.len);
+        self.bytes_read - (self.bufend - self.bufstart + peeked) as u64
+    }
+
+    fn parse_next(&mut self) -> Option<Result<Message>> {
+        let item = loop {
+            if self.ended && self.stop_at_end {
+                return None;
+            }
+            let item = self.parse_frame()?;
+            if let Ok(Some(ref msg)) = item {
+                for hook in &mut self.tag_hooks {
+                    if hook.tag == msg.tag {
+                        (hook.callback)(msg);
+                    }
+                }
+            }
+            match item {
+                Ok(Some(msg)) if !self.accepts(&msg) => continue,
+                Ok(Some(msg)) => break Ok(msg),
+                // a frame skipped without being parsed
+                Ok(None) => continue,
+                Err(e) => break Err(e),
+            }
+        };
+        if let Some(ref mut hook) = self.progress {
+            let consumed = self.bytes_read - (self.bufend - self.bufstart) as u64;
+            if consumed >= hook.next {
+                hook.next = consumed + hook.interval;
+                let position = StreamPosition {
+                    message_index: self.message_ct,
+                    byte_offset: self.origin_offset + consumed - self.origin_bytes,
+                };
+                (hook.callback)(position);
+            }
+        }
+        Some(item)
+    }
+
+    // position an error in the input and count it as a parse error
+    fn parse_error(&self, error: Error) -> Error {
+        #[cfg(feature = "metrics")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_parse_error();
+        }
+        self.positioned(error)
+    }
+
+    // position an error in the frame at the start of the buffer, then
+    // carry on as the error policy says
+    fn recover(&mut self, error: Error, frame_len: usize) -> Error {
+        let error = self.parse_error(error);
+        if let Some(ref dump) = self.dump {
+            // a failure to write the dump should not hide the parse error
+            let _ = dump.write(&error, &self.buffer[self.bufstart..self.bufend]);
+        }
+        match self.error_policy {
+            ErrorPolicy::Halt => self.in_error_state = true,
+            ErrorPolicy::SkipMessage => self.bufstart += frame_len,
+            ErrorPolicy::SkipToNextValid => {
+                self.bufstart += 1;
+                self.resyncing = true;
+            }
+        }
+        error
+    }
+
+    // move to the next offset at which a well-formed frame starts, false if
+    // more input is needed to find it
+    fn resync(&mut self) -> bool {
+        while self.bufstart < self.bufend {
+            match frame_at(&self.buffer[self.bufstart..self.bufend]) {
+                Some(true) => {
+                    self.resyncing = false;
+                    return true;
+                }
+                Some(false) => self.bufstart += 1,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    // whether a parsed message passes the tag and symbol filters. The
+    // symbol filter sees every message, so that it learns the locates of
+    // its symbols even if directory messages are not yielded.
+    fn accepts(&mut self, msg: &Message) -> bool {
+        let symbol = match self.symbols {
+            Some(ref mut symbols) => symbols.accepts(msg),
+            None => true,
+        };
+        symbol && self.tags.as_ref().is_none_or(|tags| tags[msg.tag as usize])
+    }
+
+    // parse a complete frame of a type in the parser registry
+    fn parse_extension(&mut self, len: usize) -> Result<Option<Message>> {
+        let frame = &self.buffer[self.bufstart..self.bufstart + len];
+        let parsed = match (frame, &self.parsers) {
+            (
+                [_, _, tag, l0, l1, t0, t1, ts0, ts1, ts2, ts3, ts4, ts5, body @ ..],
+                Some(parsers),
+            ) => parsers.parse(*tag, body).map(|ext| {
+                ext.map(|ext| Message {
+                    tag: *tag,
+                    stock_locate: u16::from_be_bytes([*l0, *l1]),
+                    tracking_number: u16::from_be_bytes([*t0, *t1]),
+                    timestamp: u64::from_be_bytes([0, 0, *ts0, *ts1, *ts2, *ts3, *ts4, *ts5]),
+                    body: Body::Extension(ext),
+                })
+            }),
+            _ => None,
+        };
+        let parsed = parsed.unwrap_or_else(|| {
+            Err(Error::Parse(format!(
+                "truncated extension message of {} bytes",
+                len - 2
+            )))
+        });
+        if let Some(ref mut dump) = self.dump {
+            dump.record(self.message_ct, frame);
+        }
+        let result = match parsed {
+            Ok(msg) => {
+                #[cfg(feature = "metrics")]
+                if let Some(ref metrics) = self.metrics {
+                    metrics.record_message(msg.tag);
+                }
+                self.in_error_state = false;
+                self.parsed += 1;
+                Ok(Some(msg))
+            }
+            Err(e) => Err(self.parse_error(e)),
+        };
+        // the frame is skipped whether or not it parses
+        self.bufstart += len;
+        self.message_ct += 1;
+        result
+    }
+
+    // parse the next frame, `Ok(None)` if it was skipped
+    fn parse_frame(&mut self) -> Option<Result<Option<Message>>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("parse", message_ct = self.message_ct).entered();
+        loop {
+            'parse: {
+                if self.resyncing && !self.resync() {
+                    break 'parse;
+                }
+                let buf = &self.buffer[self.bufstart..self.bufend];
+                if let Some(&tag) = buf.get(2) {
+                    if self.parsers.as_ref().is_some_and(|p| p.contains(tag)) {
+                        let len = 2 + u16::from_be_bytes([buf[0], buf[1]]) as usize;
+                        if buf.len() < len {
+                            break 'parse;
+                        }
+                        return Some(self.parse_extension(len));
+                    }
+                    if !self.profile.allows(tag) {
+                        // skip the frame without parsing it, the body layout may be unknown
+                        let len = 2 + u16::from_be_bytes([buf[0], buf[1]]) as usize;
+                        if !self.profile.recognizes(tag, len - 2) {
+                            // not a message of any feed, so the input is corrupt
+                            if self.in_error_state {
+                                return None;
+                            }
+                            if self.error_policy == ErrorPolicy::SkipMessage && buf.len() < len {
+                                break 'parse;
+                            }
+                            let error =
+                                Error::Parse(self.error_context.describe(
+                                    &format!("unknown message type {:?}", tag as char),
+                                    buf,
+                                ));
+                            return Some(Err(self.recover(error, len)));
+                        }
+                        if buf.len() < len {
+                            break 'parse;
+                        }
+                        if !self.strict {
+                            self.bufstart += len;
+                            self.message_ct += 1;
+                            return Some(Ok(None));
+                        }
+                        let error = self.parse_error(Error::Parse(format!(
+                            "'{}' message is not part of the {} feed",
+                            tag as char, self.profile
+                        )));
+                        self.bufstart += len;
+                        self.message_ct += 1;
+                        return Some(Err(error));
+                    }
+                    let outside_window = self.window.as_ref().is_some_and(|w| !w.open);
+                    if outside_window && !REFERENCE_TAGS.contains(&tag) {
+                        let len = 2 + u16::from_be_bytes([buf[0], buf[1]]) as usize;
+                        if buf.len() < len {
+                            break 'parse;
+                        }
+                        self.bufstart += len;
+                        self.message_ct += 1;
+                        return Some(Ok(None));
+                    }
+                    if self.sample_every > 1 && SAMPLED_TAGS.contains(&tag) {
+                        let len = 2 + u16::from_be_bytes([buf[0], buf[1]]) as usize;
+                        if buf.len() < len {
+                            break 'parse;
+                        }
+                        self.sampled += 1;
+                        if !self.sampled.is_multiple_of(self.sample_every) {
+                            // still counted, so positions match the unsampled stream
+                            self.bufstart += len;
+                            self.message_ct += 1;
+                            return Some(Ok(None));
+                        }
+                    }
+                }
+                match parse_message(buf) {
+                    Ok((rest, msg)) => {
+                        if self.validation == ValidationLevel::Pedantic {
+                            let input = &buf[2..buf.len() - rest.len()];
+                            if let Some(violation) = conformance::check(input, &msg) {
+                                let error = self.parse_error(Error::Parse(violation.to_string()));
+                                self.bufstart = self.bufend - rest.len();
+                                self.message_ct += 1;
+                                return Some(Err(error));
+                            }
+                        }
+                        // TODO could this logic be sped up? Or is it already pretty fast?
+                        // it should just consist of pointer arithmetic
+                        if let Some(ref mut dump) = self.dump {
+                            dump.record(self.message_ct, &buf[..buf.len() - rest.len()]);
+                        }
+                        self.bufstart = self.bufend - rest.len();
+                        self.message_ct += 1;
+                        self.parsed += 1;
+                        self.in_error_state = false;
+                        if let Some(ref mut window) = self.window {
+                            window.update(&msg);
+                        }
+                        if let Body::SystemEvent {
+                            event: EventCode::EndOfMessages,
+                        } = msg.body
+                        {
+                            self.ended = true;
+                        }
+                        #[cfg(feature = "metrics")]
+                        if let Some(ref metrics) = self.metrics {
+                            metrics.record_message(msg.tag);
+                        }
+                        return Some(Ok(Some(msg)));
+                    }
+                    Err(Err::Error(e)) | Err(Err::Failure(e)) => {
+                        // skip the malformed frame once it is complete, if asked to
+                        let frame_len = match *buf {
+                            [a, b, ..] if self.error_policy == ErrorPolicy::SkipMessage => {
+                                Some(2 + u16::from_be_bytes([a, b]) as usize)
+                            }
+                            _ => None,
+                        };
+                        if let Some(len) = frame_len {
+                            if buf.len() < len {
+                                break 'parse;
+                            }
+                        }
+                        // We need to inform user of error, but don't want to get
+                        // stuck in an infinite loop if error is ignored
+                        // (but obviously shouldn't fail silently on error either)
+                        // therefore track if we already in an 'error state' and bail if so
+                        if self.in_error_state {
+                            return None;
+                        } else if e.code != ErrorKind::Eof {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(
+                                message_ct = self.message_ct,
+                                bytes_read = self.bytes_read,
+                                code = ?e.code,
+                                "parse error"
+                            );
+                            let error = Error::Parse(self.error_context.describe(
+                                &format!("{:?}", e.code),
+                                &self.buffer[self.bufstart..self.bufend],
+                            ));
+                            return Some(Err(self.recover(error, frame_len.unwrap_or(0))));
+                        }
+                    }
+                    Err(Err::Incomplete(_)) => {
+                        // fall through to below... necessary to appease borrow checker
+                    }
+                }
+            }
+            match self.fetch_more_bytes() {
+                Ok(0) => {
+                    self.eof = true;
+                    // Are we part-way through a parse? If not, assume we are done.
+                    // Otherwise, unless nothing well-formed followed the last error,
+                    // the input ends mid-message
+                    if self.bufstart == self.bufend || self.in_error_state || self.resyncing {
+                        return None;
+                    }
+                    self.in_error_state = true;
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        message_ct = self.message_ct,
+                        trailing_bytes = self.bufend - self.bufstart,
+                        "unexpected EOF"
+                    );
+                    return Some(Err(self.parse_error(Error::Parse("Unexpected EOF".into()))));
+                }
+                Ok(ct) => {
+                    self.bufend += ct;
+                    self.bytes_read += ct as u64;
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(
+                        bytes = ct,
+                        bytes_read = self.bytes_read,
+                        message_ct = self.message_ct,
+                        "read more bytes"
+                    );
+                    #[cfg(feature = "metrics")]
+                    if let Some(ref metrics) = self.metrics {
+                        metrics.record_bytes(ct);
+                    }
+                    // parse again, in a loop rather than by recursion, since
+                    // resyncing may read through any amount of input
+                }
+                Err(e) => {
+                    if self.in_error_state {
+                        return None;
+                    }
+                    self.in_error_state = true;
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(message_ct = self.message_ct, error = %e, "read error");
+                    // a message too large for the buffer is a parse error
+                    let error = match e {
+                        Error::Parse(_) => self.parse_error(e),
+                        e => self.positioned(e),
+                    };
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> MessageStream<R> {
+    /// Record the current position so it can later be returned to with
+    /// [`restore`](Self::restore). A peeked item is not considered consumed.
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        let position = self.reader.stream_position()?;
+        let message_ct = match self.peeked {
+            Some(ref p) => p.message_ct,
+            None => self.message_ct,
+        };
+        Ok(Checkpoint {
+            offset: position - (self.bytes_read - self.consumed()),
+            message_ct,
+        })
+    }
+
+    /// Return to a position previously recorded with [`checkpoint`](Self::checkpoint)
+    pub fn restore(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(checkpoint.offset))?;
+        self.reset(checkpoint.offset, checkpoint.message_ct);
+        Ok(())
+    }
+
+    /// Capture the position of the next message and the state of the
+    /// stream's filters, e.g. to [`save`](StreamState::save) it and
+    /// [`resume`](Self::resume) after a restart
+    pub fn state(&mut self) -> Result<StreamState> {
+        if self.peeked.is_some() {
+            // the filters have already seen the peeked message
+            return Err(Error::Parse(
+                "cannot capture the state of a stream with a peeked message".into(),
+            ));
+        }
+        let locates = self.symbols.as_ref().map(|symbols| {
+            let mut locates: Vec<_> = symbols.locates.iter().copied().collect();
+            locates.sort_unstable();
+            locates
+        });
+        Ok(StreamState {
+            checkpoint: self.checkpoint()?,
+            sampled: self.sampled,
+            window_open: self.window.as_ref().map(|w| w.open),
+            locates,
+        })
+    }
+
+    /// Continue from a state captured with [`state`](Self::state).
+    ///
+    /// The stream must read the same data, and be configured in the same
+    /// way, as the one the state was captured from.
+    pub fn resume(&mut self, state: &StreamState) -> Result<()> {
+        self.restore(state.checkpoint)?;
+        self.sampled = state.sampled;
+        if let (Some(window), Some(open)) = (self.window.as_mut(), state.window_open) {
+            window.open = open;
+        }
+        if let (Some(symbols), Some(locates)) = (self.symbols.as_mut(), state.locates.as_ref()) {
+            symbols.locates = locates.iter().copied().collect();
+        }
+        Ok(())
+    }
+
+    /// Seek back to the beginning of the underlying reader
+    pub fn rewind(&mut self) -> Result<()> {
+        self.reader.rewind()?;
+        self.reset(0, 0);
+        Ok(())
+    }
+
+    fn reset(&mut self, offset: u64, message_ct: u64) {
+        // `bytes_read` keeps counting, so discard the buffer by marking it consumed
+        self.bufstart = 0;
+        self.bufend = 0;
+        self.origin_offset = offset;
+        self.origin_bytes = self.bytes_read;
+        self.message_ct = message_ct;
+        self.eof = false;
+        self.in_error_state = false;
+        self.resyncing = false;
+        self.peeked = None;
+        self.ended = false;
+    }
+}
+
+// Whether a well-formed frame starts the input: its length prefix matches
+// the length of its message type, it parses, and the header of the frame
+// after it, if already buffered, is also plausible. `None` if more input
+// is needed to tell.
+fn frame_at(buf: &[u8]) -> Option<bool> {
+    let plausible = |buf: &[u8]| {
+        let len = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+        version::length_v50(buf[2]) == Some(len)
+    };
+    if buf.len() < 3 {
+        return None;
+    }
+    if !plausible(buf) {
+        return Some(false);
+    }
+    let len = 2 + u16::from_be_bytes([buf[0], buf[1]]) as usize;
+    if buf.len() < len {
+        return None;
+    }
+    if parse_message(&buf[..len]).is_err() {
+        return Some(false);
+    }
+    Some(buf.len() < len + 3 || plausible(&buf[len..]))
+}
+
+impl<R: Read> Iterator for MessageStream<R> {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        let item = match self.peeked.take() {
+            Some(peeked) => peeked.item,
+            None => self.parse_next(),
+        };
+        if let Some(Err(_)) = item {
+            self.errors += 1;
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::parse_unframed;
+    use crate::messages::tests::hex_to_bytes;
+
+    #[test]
+    fn test_parse_empty_buffer() {
+        let buf: &[u8] = &[];
+        let mut stream = MessageStream::from_reader(buf);
+        assert!(stream.next().is_none()); // stops iterating immediately
+    }
+
+    #[test]
+    fn test_parse_invalid_buffer_fails() {
+        let buf: &[u8] = &[0, 0xc, 0x53, 0, 0, 0, 0x28, 0x6a];
+        let mut stream = MessageStream::from_reader(buf);
+        assert!(stream.next().unwrap().is_err()); // first time gives error
+        assert!(stream.next().is_none()); // then it stops iterating
+    }
+
+    #[test]
+    fn test_parse_one_message() {
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::from_reader(&buf[..]);
+        assert!(stream.next().unwrap().is_ok()); // first time ok
+        assert!(stream.next().is_none()); // then it stops iterating
+    }
+
+    #[test]
+    fn stops_at_end_of_messages() {
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f 000c 5300 0000 0028 6aab 3b3a 9943 0000";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::builder()
+            .stop_at_end_of_messages(true)
+            .build(&buf[..]);
+        assert_eq!(stream.by_ref().filter(|m| m.is_ok()).count(), 2);
+        assert!(stream.ended_cleanly());
+        assert_eq!(stream.finish().trailing_data, [0, 0]);
+
+        // the input after the event is counted whatever the buffer holds
+        let mut data = buf[..28].to_vec();
+        data.extend([0; 1000]);
+        let mut stream = MessageStream::builder()
+            .buffer_size(256)
+            .stop_at_end_of_messages(true)
+            .build(&data[..]);
+        assert_eq!(stream.by_ref().count(), 2);
+        let summary = stream.finish();
+        assert_eq!(summary.trailing_bytes, 1000);
+        assert_eq!(summary.bytes_read, 1028);
+
+        let mut stream = MessageStream::from_reader(&buf[..14]);
+        assert_eq!(stream.by_ref().count(), 1);
+        assert!(!stream.ended_cleanly());
+    }
+
+    #[test]
+    fn grows_buffer_while_reads_fill_it() {
+        let data = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f").repeat(5000);
+        let builder = || {
+            MessageStream::builder()
+                .buffer_size(256)
+                .max_buffer_size(4096)
+        };
+
+        let mut stream = builder().build(&data[..]);
+        assert_eq!(stream.by_ref().count(), 5000);
+        assert_eq!(stream.buffer_size(), 4096);
+        assert!(format!("{:?}", stream).contains("buffer_size: 4096"));
+
+        // a reader which never fills a read gains nothing from a larger buffer
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(self.0.len()).min(100);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let mut stream = builder().build(Trickle(&data));
+        assert_eq!(stream.by_ref().count(), 5000);
+        assert_eq!(stream.buffer_size(), 256);
+    }
+
+    #[test]
+    fn test_finish_truncated() {
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f 000c 5300 00";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::from_reader(&buf[..]);
+        assert!(stream.next().unwrap().is_ok());
+        // the rest of the message may still arrive
+        assert!(stream.summary().is_complete());
+        let err = stream.next().unwrap().unwrap_err(); // unexpected EOF
+        assert_eq!(
+            err.position(),
+            Some(StreamPosition {
+                message_index: 1,
+                byte_offset: 14
+            })
+        );
+        assert!(stream.next().is_none());
+        let summary = stream.finish();
+        assert_eq!(summary.messages, 1);
+        assert_eq!(summary.bytes_read, 19);
+        assert_eq!(summary.trailing_bytes, 5);
+        assert_eq!(summary.trailing_data, [0, 0xc, 0x53, 0, 0]);
+        assert!(!summary.is_complete());
+    }
+
+    #[test]
+    fn test_peek() {
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f
+                     000c 5300 0000 0028 6aab 3b3a 9953";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::from_reader(&buf[..]);
+        let peeked = stream.peek().unwrap().as_ref().unwrap().clone();
+        assert_eq!(stream.peek().unwrap().as_ref().unwrap(), &peeked);
+        assert_eq!(stream.summary().messages, 0);
+        assert_eq!(stream.next().unwrap().unwrap(), peeked);
+        assert_eq!(stream.summary().messages, 1);
+        let second = stream.next().unwrap().unwrap();
+        assert_eq!(
+            second.body,
+            Body::SystemEvent {
+                event: EventCode::StartOfSystemHours
+            }
+        );
+        assert!(stream.peek().is_none());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f
+                     000c 5300 0000 0028 6aab 3b3a 9953
+                     000c 5300 0000 0028 6aab 3b3a 9951";
+        let buf = hex_to_bytes(&code[..]);
+        let mut stream = MessageStream::from_reader(std::io::Cursor::new(buf));
+        let first = stream.next().unwrap().unwrap();
+        let second = stream.peek().unwrap().as_ref().unwrap().clone();
+
+        let checkpoint = stream.checkpoint().unwrap();
+        assert_eq!(checkpoint.offset(), 14);
+        assert_eq!(stream.next().unwrap().unwrap(), second);
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().is_none());
+
+        stream.restore(checkpoint).unwrap();
+        assert_eq!(stream.position().byte_offset, 14);
+        let (position, msg) = stream.next_with_position().unwrap().unwrap();
+        assert_eq!(msg, second);
+        assert_eq!(
+            position,
+            StreamPosition {
+                message_index: 1,
+                byte_offset: 14
+            }
+        );
+        assert_eq!(stream.position().byte_offset, 28);
+        stream.rewind().unwrap();
+        assert_eq!(stream.next().unwrap().unwrap(), first);
+        assert_eq!(stream.count(), 2);
+    }
+
+    #[test]
+    fn test_sequenced() {
+        let code = b"000c 5300 0000 0028 6aab 3b3a 994f
+                     000c 5300 0000 0028 6aab 3b3a 9953";
+        let buf = hex_to_bytes(&code[..]);
+        let session = SessionId::from("TEST      ").unwrap();
+        let stream = MessageStream::from_reader(&buf[..]).sequenced(session);
+        let sequences: Vec<_> = stream.map(|e| e.unwrap().sequence).collect();
+        assert_eq!(sequences, [1, 2]);
+
+        let mut envelope = MessageStream::from_reader(&buf[..])
+            .sequenced(session)
+            .next()
+            .unwrap()
+            .unwrap();
+        let day = clock::Session::new(2024, 1, 15).unwrap();
+        assert_eq!(envelope.receive_latency(&day), None);
+        envelope.receive_ts = Some(day.to_utc_nanos(envelope.message.timestamp) as u64 + 1_500);
+        assert_eq!(envelope.receive_latency(&day), Some(1_500));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_full_parse() {
+        // Download sample data from ftp://emi.nasdaq.com/ITCH/
+        let mut stream = MessageStream::from_file("sample-data/20190830.PSX_ITCH_50").unwrap();
+        let stream_size = stream.get_ref().metadata().unwrap().len();
+
+        let mut ct = 0;
+        while let Some(msg) = stream.next() {
+            match msg {
+                Err(e) => panic!("Message {} failed to parse: {}", ct, e),
+                Ok(msg) => {
+                    let progress =
+                        (stream.bytes_read() as f32 / stream_size as f32 * 100.0).round();
+                    if ct % 1_000_000 == 0 {
+                        println!("Processed {}M messages ({}%)", ct / 1000000, progress);
+                        println!("{:?}", msg)
+                    }
+                }
+            }
+            ct += 1;
+        }
+        assert_eq!(ct, 40030397)
+    }
+
+    #[test]
+    fn arbitrary_input_does_not_panic() {
+        // xorshift, so failures are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut data = Vec::new();
+        for _ in 0..2000 {
+            let tag = b"SRHYLVWKJhAFECXDUPQBINO"[next() as usize % 23];
+            let len = match next() % 8 {
+                // a frame of the right length for its type, with random contents
+                0..=5 => version::length_v50(tag).unwrap_or(12),
+                _ => 1 + next() as usize % 64,
+            };
+            data.extend_from_slice(&(len as u16).to_be_bytes());
+            data.push(tag);
+            data.extend((1..len).map(|_| next() as u8));
+        }
+        for policy in [
+            ErrorPolicy::Halt,
+            ErrorPolicy::SkipMessage,
+            ErrorPolicy::SkipToNextValid,
+        ] {
+            for buffer_size in [256, 4096] {
+                let stream = MessageStream::builder()
+                    .buffer_size(buffer_size)
+                    .error_policy(policy)
+                    .build(&data[..]);
+                assert!(stream.take(10_000).count() > 0);
+            }
+        }
+        for start in 0..data.len().min(4096) {
+            let _ = parse_message(&data[start..]);
+            let _ = parse_unframed(&data[start..]);
+        }
+    }
+}
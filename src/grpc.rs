@@ -0,0 +1,719 @@
+//! A gRPC service streaming messages to remote clients, with the `grpc`
+//! feature.
+//!
+//! The service is defined in `proto/itchy.proto`, from which clients in
+//! other languages can be generated. [`ItchyServer`] serves it with
+//! [`tonic`]:
+//!
+//! ```ignore
+//! use itchy::grpc::ItchyServer;
+//!
+//! tonic::transport::Server::builder()
+//!     .add_service(ItchyServer::from_file("/path/to/file.itch"))
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+//!
+//! Each request opens the feed anew, with the symbol and message type
+//! filters of the request applied by a [`MessageStreamBuilder`], and is
+//! served by a thread of its own. Reading the feed pauses while a slow
+//! client's buffer is full.
+
+use std::convert::Infallible;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{http, Body, BoxFuture, Service, StdError};
+use tonic::server::{Grpc, NamedService};
+use tonic::Status;
+
+use crate::{Message, MessageStream, MessageStreamBuilder, Result};
+
+/// Path of the streaming method
+pub const STREAM_PATH: &str = "/itchy.v1.Itchy/Stream";
+
+/// The messages of `proto/itchy.proto`
+pub mod proto {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct StreamRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub symbols: Vec<String>,
+        #[prost(string, tag = "2")]
+        pub tags: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Message {
+        #[prost(uint32, tag = "1")]
+        pub tag: u32,
+        #[prost(uint32, tag = "2")]
+        pub stock_locate: u32,
+        #[prost(uint32, tag = "3")]
+        pub tracking_number: u32,
+        #[prost(uint64, tag = "4")]
+        pub timestamp: u64,
+        #[prost(
+            oneof = "Body",
+            tags = "5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25"
+        )]
+        pub body: Option<Body>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Body {
+        #[prost(message, tag = "5")]
+        AddOrder(AddOrder),
+        #[prost(uint32, tag = "6")]
+        Breach(u32),
+        #[prost(message, tag = "7")]
+        BrokenTrade(BrokenTrade),
+        #[prost(message, tag = "8")]
+        CrossTrade(CrossTrade),
+        #[prost(message, tag = "9")]
+        DeleteOrder(DeleteOrder),
+        #[prost(message, tag = "10")]
+        Imbalance(Imbalance),
+        #[prost(message, tag = "11")]
+        IpoQuotingPeriod(IpoQuotingPeriod),
+        #[prost(message, tag = "12")]
+        LuldAuctionCollar(LuldAuctionCollar),
+        #[prost(message, tag = "13")]
+        MwcbDeclineLevel(MwcbDeclineLevel),
+        #[prost(message, tag = "14")]
+        NonCrossTrade(NonCrossTrade),
+        #[prost(message, tag = "15")]
+        OrderCancelled(OrderCancelled),
+        #[prost(message, tag = "16")]
+        OrderExecuted(OrderExecuted),
+        #[prost(message, tag = "17")]
+        OrderExecutedWithPrice(OrderExecutedWithPrice),
+        #[prost(message, tag = "18")]
+        ParticipantPosition(ParticipantPosition),
+        #[prost(message, tag = "19")]
+        RegShoRestriction(RegShoRestriction),
+        #[prost(message, tag = "20")]
+        ReplaceOrder(ReplaceOrder),
+        #[prost(message, tag = "21")]
+        StockDirectory(StockDirectory),
+        #[prost(uint32, tag = "22")]
+        SystemEvent(u32),
+        #[prost(message, tag = "23")]
+        TradingAction(TradingAction),
+        #[prost(message, tag = "24")]
+        RetailPriceImprovement(RetailPriceImprovement),
+        #[prost(bytes, tag = "25")]
+        Extension(Vec<u8>),
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct AddOrder {
+        #[prost(uint64, tag = "1")]
+        pub reference: u64,
+        #[prost(uint32, tag = "2")]
+        pub side: u32,
+        #[prost(uint32, tag = "3")]
+        pub shares: u32,
+        #[prost(string, tag = "4")]
+        pub stock: String,
+        #[prost(uint32, tag = "5")]
+        pub price: u32,
+        #[prost(string, optional, tag = "6")]
+        pub mpid: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct BrokenTrade {
+        #[prost(uint64, tag = "1")]
+        pub match_number: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CrossTrade {
+        #[prost(uint64, tag = "1")]
+        pub shares: u64,
+        #[prost(string, tag = "2")]
+        pub stock: String,
+        #[prost(uint32, tag = "3")]
+        pub cross_price: u32,
+        #[prost(uint64, tag = "4")]
+        pub match_number: u64,
+        #[prost(uint32, tag = "5")]
+        pub cross_type: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct DeleteOrder {
+        #[prost(uint64, tag = "1")]
+        pub reference: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Imbalance {
+        #[prost(uint64, tag = "1")]
+        pub paired_shares: u64,
+        #[prost(uint64, tag = "2")]
+        pub imbalance_shares: u64,
+        #[prost(uint32, tag = "3")]
+        pub imbalance_direction: u32,
+        #[prost(string, tag = "4")]
+        pub stock: String,
+        #[prost(uint32, tag = "5")]
+        pub far_price: u32,
+        #[prost(uint32, tag = "6")]
+        pub near_price: u32,
+        #[prost(uint32, tag = "7")]
+        pub current_ref_price: u32,
+        #[prost(uint32, tag = "8")]
+        pub cross_type: u32,
+        #[prost(uint32, tag = "9")]
+        pub price_variation_indicator: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct IpoQuotingPeriod {
+        #[prost(string, tag = "1")]
+        pub stock: String,
+        #[prost(uint32, tag = "2")]
+        pub release_time: u32,
+        #[prost(uint32, tag = "3")]
+        pub release_qualifier: u32,
+        #[prost(uint32, tag = "4")]
+        pub price: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct LuldAuctionCollar {
+        #[prost(string, tag = "1")]
+        pub stock: String,
+        #[prost(uint32, tag = "2")]
+        pub ref_price: u32,
+        #[prost(uint32, tag = "3")]
+        pub upper_price: u32,
+        #[prost(uint32, tag = "4")]
+        pub lower_price: u32,
+        #[prost(uint32, tag = "5")]
+        pub extension: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MwcbDeclineLevel {
+        #[prost(uint64, tag = "1")]
+        pub level1: u64,
+        #[prost(uint64, tag = "2")]
+        pub level2: u64,
+        #[prost(uint64, tag = "3")]
+        pub level3: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct NonCrossTrade {
+        #[prost(uint64, tag = "1")]
+        pub reference: u64,
+        #[prost(uint32, tag = "2")]
+        pub side: u32,
+        #[prost(uint32, tag = "3")]
+        pub shares: u32,
+        #[prost(string, tag = "4")]
+        pub stock: String,
+        #[prost(uint32, tag = "5")]
+        pub price: u32,
+        #[prost(uint64, tag = "6")]
+        pub match_number: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct OrderCancelled {
+        #[prost(uint64, tag = "1")]
+        pub reference: u64,
+        #[prost(uint32, tag = "2")]
+        pub cancelled: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct OrderExecuted {
+        #[prost(uint64, tag = "1")]
+        pub reference: u64,
+        #[prost(uint32, tag = "2")]
+        pub executed: u32,
+        #[prost(uint64, tag = "3")]
+        pub match_number: u64,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct OrderExecutedWithPrice {
+        #[prost(uint64, tag = "1")]
+        pub reference: u64,
+        #[prost(uint32, tag = "2")]
+        pub executed: u32,
+        #[prost(uint64, tag = "3")]
+        pub match_number: u64,
+        #[prost(bool, tag = "4")]
+        pub printable: bool,
+        #[prost(uint32, tag = "5")]
+        pub price: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ParticipantPosition {
+        #[prost(string, tag = "1")]
+        pub mpid: String,
+        #[prost(string, tag = "2")]
+        pub stock: String,
+        #[prost(bool, tag = "3")]
+        pub primary_market_maker: bool,
+        #[prost(uint32, tag = "4")]
+        pub market_maker_mode: u32,
+        #[prost(uint32, tag = "5")]
+        pub market_participant_state: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RegShoRestriction {
+        #[prost(string, tag = "1")]
+        pub stock: String,
+        #[prost(uint32, tag = "2")]
+        pub action: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ReplaceOrder {
+        #[prost(uint64, tag = "1")]
+        pub old_reference: u64,
+        #[prost(uint64, tag = "2")]
+        pub new_reference: u64,
+        #[prost(uint32, tag = "3")]
+        pub shares: u32,
+        #[prost(uint32, tag = "4")]
+        pub price: u32,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct StockDirectory {
+        #[prost(string, tag = "1")]
+        pub stock: String,
+        #[prost(uint32, tag = "2")]
+        pub market_category: u32,
+        #[prost(uint32, tag = "3")]
+        pub financial_status: u32,
+        #[prost(uint32, tag = "4")]
+        pub round_lot_size: u32,
+        #[prost(bool, tag = "5")]
+        pub round_lots_only: bool,
+        #[prost(uint32, tag = "6")]
+        pub issue_classification: u32,
+        #[prost(string, tag = "7")]
+        pub issue_subtype: String,
+        #[prost(bool, tag = "8")]
+        pub authenticity: bool,
+        #[prost(bool, optional, tag = "9")]
+        pub short_sale_threshold: Option<bool>,
+        #[prost(bool, optional, tag = "10")]
+        pub ipo_flag: Option<bool>,
+        #[prost(uint32, tag = "11")]
+        pub luld_ref_price_tier: u32,
+        #[prost(bool, optional, tag = "12")]
+        pub etp_flag: Option<bool>,
+        #[prost(uint32, tag = "13")]
+        pub etp_leverage_factor: u32,
+        #[prost(bool, tag = "14")]
+        pub inverse_indicator: bool,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TradingAction {
+        #[prost(string, tag = "1")]
+        pub stock: String,
+        #[prost(uint32, tag = "2")]
+        pub trading_state: u32,
+        #[prost(string, tag = "3")]
+        pub reason: String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct RetailPriceImprovement {
+        #[prost(string, tag = "1")]
+        pub stock: String,
+        #[prost(uint32, tag = "2")]
+        pub interest_flag: u32,
+    }
+}
+
+fn text(s: &str) -> String {
+    s.trim_end().to_string()
+}
+
+impl From<&Message> for proto::Message {
+    fn from(msg: &Message) -> proto::Message {
+        use crate::Body::*;
+        use proto::Body as P;
+        let body = match msg.body {
+            AddOrder(ref o) => P::AddOrder(proto::AddOrder {
+                reference: o.reference,
+                side: o.side.as_code().into(),
+                shares: o.shares,
+                stock: text(&o.stock),
+                price: o.price.raw(),
+                mpid: o.mpid.as_ref().map(|mpid| text(mpid)),
+            }),
+            Breach(level) => P::Breach(level.as_code().into()),
+            BrokenTrade { match_number } => P::BrokenTrade(proto::BrokenTrade { match_number }),
+            CrossTrade(ref t) => P::CrossTrade(proto::CrossTrade {
+                shares: t.shares,
+                stock: text(&t.stock),
+                cross_price: t.cross_price.raw(),
+                match_number: t.match_number,
+                cross_type: t.cross_type.as_code().into(),
+            }),
+            DeleteOrder { reference } => P::DeleteOrder(proto::DeleteOrder { reference }),
+            Imbalance(ref i) => P::Imbalance(proto::Imbalance {
+                paired_shares: i.paired_shares,
+                imbalance_shares: i.imbalance_shares,
+                imbalance_direction: i.imbalance_direction.as_code().into(),
+                stock: text(&i.stock),
+                far_price: i.far_price.raw(),
+                near_price: i.near_price.raw(),
+                current_ref_price: i.current_ref_price.raw(),
+                cross_type: i.cross_type.as_code().into(),
+                price_variation_indicator: i.price_variation_indicator.into(),
+            }),
+            IpoQuotingPeriod(ref q) => P::IpoQuotingPeriod(proto::IpoQuotingPeriod {
+                stock: text(&q.stock),
+                release_time: q.release_time,
+                release_qualifier: q.release_qualifier.as_code().into(),
+                price: q.price.raw(),
+            }),
+            LULDAuctionCollar {
+                ref stock,
+                ref_price,
+                upper_price,
+                lower_price,
+                extension,
+            } => P::LuldAuctionCollar(proto::LuldAuctionCollar {
+                stock: text(stock),
+                ref_price: ref_price.raw(),
+                upper_price: upper_price.raw(),
+                lower_price: lower_price.raw(),
+                extension,
+            }),
+            MwcbDeclineLevel {
+                level1,
+                level2,
+                level3,
+            } => P::MwcbDeclineLevel(proto::MwcbDeclineLevel {
+                level1: level1.raw(),
+                level2: level2.raw(),
+                level3: level3.raw(),
+            }),
+            NonCrossTrade(ref t) => P::NonCrossTrade(proto::NonCrossTrade {
+                reference: t.reference,
+                side: t.side.as_code().into(),
+                shares: t.shares,
+                stock: text(&t.stock),
+                price: t.price.raw(),
+                match_number: t.match_number,
+            }),
+            OrderCancelled {
+                reference,
+                cancelled,
+            } => P::OrderCancelled(proto::OrderCancelled {
+                reference,
+                cancelled,
+            }),
+            OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => P::OrderExecuted(proto::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            }),
+            OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            } => P::OrderExecutedWithPrice(proto::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price: price.raw(),
+            }),
+            ParticipantPosition(ref p) => P::ParticipantPosition(proto::ParticipantPosition {
+                mpid: text(&p.mpid),
+                stock: text(&p.stock),
+                primary_market_maker: p.primary_market_maker,
+                market_maker_mode: p.market_maker_mode.as_code().into(),
+                market_participant_state: p.market_participant_state.as_code().into(),
+            }),
+            RegShoRestriction { ref stock, action } => {
+                P::RegShoRestriction(proto::RegShoRestriction {
+                    stock: text(stock),
+                    action: action.as_code().into(),
+                })
+            }
+            ReplaceOrder(ref r) => P::ReplaceOrder(proto::ReplaceOrder {
+                old_reference: r.old_reference,
+                new_reference: r.new_reference,
+                shares: r.shares,
+                price: r.price.raw(),
+            }),
+            StockDirectory(ref d) => P::StockDirectory(proto::StockDirectory {
+                stock: text(&d.stock),
+                market_category: d.market_category.as_code().into(),
+                financial_status: d.financial_status.as_code().into(),
+                round_lot_size: d.round_lot_size,
+                round_lots_only: d.round_lots_only,
+                issue_classification: d.issue_classification.as_code().into(),
+                issue_subtype: String::from_utf8_lossy(&d.issue_subtype.as_code()).into_owned(),
+                authenticity: d.authenticity,
+                short_sale_threshold: d.short_sale_threshold,
+                ipo_flag: d.ipo_flag,
+                luld_ref_price_tier: d.luld_ref_price_tier.as_code().into(),
+                etp_flag: d.etp_flag,
+                etp_leverage_factor: d.etp_leverage_factor,
+                inverse_indicator: d.inverse_indicator,
+            }),
+            SystemEvent { event } => P::SystemEvent(event.as_code().into()),
+            TradingAction {
+                ref stock,
+                trading_state,
+                ref reason,
+            } => P::TradingAction(proto::TradingAction {
+                stock: text(stock),
+                trading_state: trading_state.as_code().into(),
+                reason: text(reason),
+            }),
+            RetailPriceImprovementIndicator(ref r) => {
+                P::RetailPriceImprovement(proto::RetailPriceImprovement {
+                    stock: text(&r.stock),
+                    interest_flag: r.interest_flag.as_code().into(),
+                })
+            }
+            Extension(ref e) => P::Extension(e.raw().to_vec()),
+        };
+        proto::Message {
+            tag: msg.tag.into(),
+            stock_locate: msg.stock_locate.into(),
+            tracking_number: msg.tracking_number.into(),
+            timestamp: msg.timestamp,
+            body: Some(body),
+        }
+    }
+}
+
+type Feed = Box<dyn Iterator<Item = Result<Message>> + Send>;
+type OpenFeed = dyn Fn(MessageStreamBuilder) -> Result<Feed> + Send + Sync;
+type MessageStreamOf = ReceiverStream<std::result::Result<proto::Message, Status>>;
+
+/// The `itchy.v1.Itchy` service, to be added to a
+/// [`tonic::transport::Server`]
+#[derive(Clone)]
+pub struct ItchyServer {
+    open: Arc<OpenFeed>,
+    buffer: usize,
+}
+
+impl ItchyServer {
+    /// Serve the feed opened by `open` from a builder configured with the
+    /// filters of each request, e.g. a file, or a live feed decoded from a
+    /// socket
+    pub fn new<F, R>(open: F) -> ItchyServer
+    where
+        F: Fn(MessageStreamBuilder) -> Result<MessageStream<R>> + Send + Sync + 'static,
+        R: Read + Send + 'static,
+    {
+        ItchyServer {
+            open: Arc::new(move |builder| Ok(Box::new(open(builder)?) as Feed)),
+            buffer: 1024,
+        }
+    }
+
+    /// Serve a file, read from the start for each request
+    pub fn from_file<P: Into<PathBuf>>(path: P) -> ItchyServer {
+        let path = path.into();
+        ItchyServer::new(move |builder| builder.open(&path))
+    }
+
+    /// Messages buffered for each client before reading pauses (1024 by
+    /// default)
+    ///
+    /// # Panics
+    ///
+    /// If `messages` is zero
+    pub fn buffer(mut self, messages: usize) -> Self {
+        assert!(messages > 0, "buffer must hold at least one message");
+        self.buffer = messages;
+        self
+    }
+
+    // `Status` is the error type tonic's services return
+    #[allow(clippy::result_large_err)]
+    fn stream(
+        &self,
+        request: proto::StreamRequest,
+    ) -> std::result::Result<MessageStreamOf, Status> {
+        let mut builder = MessageStream::builder();
+        if !request.symbols.is_empty() {
+            builder = builder.symbols(&request.symbols);
+        }
+        if !request.tags.is_empty() {
+            builder = builder.tags(request.tags.as_bytes());
+        }
+        let feed = (self.open)(builder).map_err(|e| Status::unavailable(e.to_string()))?;
+        let (tx, rx) = mpsc::channel(self.buffer);
+        std::thread::Builder::new()
+            .name("itchy-grpc".into())
+            .spawn(move || {
+                for item in feed {
+                    let item = item
+                        .map(|msg| proto::Message::from(&msg))
+                        .map_err(|e| Status::data_loss(e.to_string()));
+                    let failed = item.is_err();
+                    // stop once the client has gone, or after an error,
+                    // which ends the response
+                    if tx.blocking_send(item).is_err() || failed {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+// the streaming method, as tonic's server expects it
+struct StreamMethod(ItchyServer);
+
+impl Service<tonic::Request<proto::StreamRequest>> for StreamMethod {
+    type Response = tonic::Response<MessageStreamOf>;
+    type Error = Status;
+    type Future = std::future::Ready<std::result::Result<Self::Response, Status>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<std::result::Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: tonic::Request<proto::StreamRequest>) -> Self::Future {
+        std::future::ready(
+            self.0
+                .stream(request.into_inner())
+                .map(tonic::Response::new),
+        )
+    }
+}
+
+impl<B> Service<http::Request<B>> for ItchyServer
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Infallible>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<std::result::Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if request.uri().path() != STREAM_PATH {
+            let status = Status::unimplemented(request.uri().path().to_string());
+            return Box::pin(async move { Ok(status.into_http()) });
+        }
+        let method = StreamMethod(self.clone());
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::default());
+            Ok(grpc.server_streaming(method, request).await)
+        })
+    }
+}
+
+impl NamedService for ItchyServer {
+    const NAME: &'static str = "itchy.v1.Itchy";
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+
+    use super::*;
+    use crate::directory::tests::directory_msg;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn streams_filtered_messages() {
+        let mut data = Vec::new();
+        let messages = [
+            directory_msg(1, "AB"),
+            directory_msg(2, "ABC"),
+            Message {
+                tag: b'A',
+                stock_locate: 2,
+                ..msg(5, add(1, Side::Buy, 100, 10_000))
+            },
+            Message {
+                tag: b'A',
+                ..msg(6, add(2, Side::Sell, 200, 10_100))
+            },
+        ];
+        for m in &messages {
+            m.encode(&mut data);
+        }
+        let server = ItchyServer::new(move |builder| Ok(builder.build(Cursor::new(data.clone()))));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let received = runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(
+                Server::builder()
+                    .add_service(server)
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+            let channel = Channel::from_shared(format!("http://{}", addr))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut client = tonic::client::Grpc::new(channel);
+            client.ready().await.unwrap();
+            let request = proto::StreamRequest {
+                symbols: vec!["AB".into()],
+                tags: "A".into(),
+            };
+            let mut stream = client
+                .server_streaming(
+                    tonic::Request::new(request),
+                    http::uri::PathAndQuery::from_static(STREAM_PATH),
+                    ProstCodec::<proto::StreamRequest, proto::Message>::default(),
+                )
+                .await
+                .unwrap()
+                .into_inner();
+            let mut received = Vec::new();
+            while let Some(msg) = stream.message().await.unwrap() {
+                received.push(msg);
+            }
+            received
+        });
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0], proto::Message::from(&messages[3]));
+        let Some(proto::Body::AddOrder(ref order)) = received[0].body else {
+            panic!("expected an add order");
+        };
+        assert_eq!((order.stock.as_str(), order.price), ("ZXZZT", 10_100));
+    }
+}
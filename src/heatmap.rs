@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::num::{NonZeroU32, NonZeroU64};
+
+use crate::clock::snap;
+use crate::export::PRICE4_SCALE;
+use crate::{Book, BookManager, Message, Price4};
+
+/// Evenly spaced price buckets, used as the columns of a [`Heatmap`] and the
+/// levels of a [`DenseBook`](crate::DenseBook)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PriceGrid {
+    low: Price4,
+    tick: Price4,
+    levels: usize,
+}
+
+impl PriceGrid {
+    /// `levels` buckets of width `tick`, the first starting at `low`.
+    /// Returns `None` if `tick` is zero or the grid reaches past the
+    /// largest `Price4`.
+    pub fn new(low: Price4, tick: Price4, levels: usize) -> Option<PriceGrid> {
+        if tick.raw() == 0 {
+            return None;
+        }
+        let span = u32::try_from(levels).ok()?.checked_mul(tick.raw())?;
+        low.raw().checked_add(span)?;
+        Some(PriceGrid { low, tick, levels })
+    }
+
+    /// Lowest price of the first bucket
+    pub fn low(&self) -> Price4 {
+        self.low
+    }
+
+    /// Width of each bucket
+    pub fn tick(&self) -> Price4 {
+        self.tick
+    }
+
+    /// Number of buckets
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    /// Bucket containing a price, `None` if it is outside the grid
+    pub fn bucket(&self, price: Price4) -> Option<usize> {
+        let offset = price.raw().checked_sub(self.low.raw())?;
+        let ix = offset.checked_div(self.tick.raw())? as usize;
+        (ix < self.levels).then_some(ix)
+    }
+
+    /// Lowest price of a bucket, `None` if it is outside the grid
+    pub fn price(&self, bucket: usize) -> Option<Price4> {
+        if bucket >= self.levels {
+            return None;
+        }
+        let offset = u32::try_from(bucket).ok()?.checked_mul(self.tick.raw())?;
+        self.low.raw().checked_add(offset).map(Price4::from)
+    }
+}
+
+/// A dense time × price matrix of resting shares for one symbol.
+///
+/// Each row is a snapshot of the book; each column the shares resting on
+/// either side within one bucket of the [`PriceGrid`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    grid: PriceGrid,
+    price_scale: NonZeroU32,
+    timestamps: Vec<u64>,
+    cells: Vec<u64>,
+}
+
+impl Heatmap {
+    pub fn new(grid: PriceGrid) -> Heatmap {
+        Heatmap {
+            grid,
+            price_scale: PRICE4_SCALE,
+            timestamps: Vec::new(),
+            cells: Vec::new(),
+        }
+    }
+
+    /// Raw units per dollar in prices (10,000 for ITCH 5.0), used to name
+    /// the columns written by `write_parquet`
+    pub fn price_scale(mut self, scale: NonZeroU32) -> Self {
+        self.price_scale = scale;
+        self
+    }
+
+    /// Append a snapshot of a book taken at `timestamp`
+    pub fn sample<B: Book + ?Sized>(&mut self, timestamp: u64, book: Option<&B>) {
+        let start = self.cells.len();
+        self.cells.resize(start + self.grid.levels, 0);
+        let row = &mut self.cells[start..];
+        for level in book.into_iter().flat_map(|b| b.bids().chain(b.asks())) {
+            if let Some(ix) = self.grid.bucket(level.price) {
+                row[ix] += level.shares;
+            }
+        }
+        self.timestamps.push(timestamp);
+    }
+
+    pub fn grid(&self) -> &PriceGrid {
+        &self.grid
+    }
+
+    /// Sample times, one per row
+    pub fn timestamps(&self) -> &[u64] {
+        &self.timestamps
+    }
+
+    pub fn rows(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// A row of the matrix, lowest price first
+    pub fn row(&self, ix: usize) -> &[u64] {
+        let levels = self.grid.levels;
+        &self.cells[ix * levels..(ix + 1) * levels]
+    }
+
+    /// Write the matrix in NumPy `.npy` format, as `uint64` with shape
+    /// `(rows, levels)`
+    pub fn write_npy<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header = format!(
+            "{{'descr': '<u8', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.rows(),
+            self.grid.levels
+        );
+        // magic, version and header length take 10 bytes; pad the whole
+        // preamble to a multiple of 64, ending with a newline
+        let padding = 63 - (10 + header.len()) % 64;
+        header.extend(std::iter::repeat_n(' ', padding));
+        header.push('\n');
+        writer.write_all(b"\x93NUMPY\x01\x00")?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        for cell in &self.cells {
+            writer.write_all(&cell.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Write the matrix as a Parquet file with a `timestamp` column and a
+    /// `uint64` column per bucket, named by its lowest price, e.g. `99.0000`
+    /// with the ITCH 5.0 price scale
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W: Write + Send>(&self, writer: W) -> io::Result<()> {
+        use std::sync::Arc;
+
+        use arrow_array::{ArrayRef, RecordBatch, UInt64Array};
+        use arrow_schema::{DataType, Field, Schema};
+        use parquet::arrow::ArrowWriter;
+
+        use crate::ScaledPrice;
+
+        let levels = self.grid.levels;
+        let mut fields = vec![Field::new("timestamp", DataType::UInt64, false)];
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(UInt64Array::from(self.timestamps.clone()))];
+        for ix in 0..levels {
+            let Some(price) = self.grid.price(ix) else {
+                break;
+            };
+            let name = ScaledPrice::new(price.raw() as u64, self.price_scale.get() as u64);
+            fields.push(Field::new(name.to_string(), DataType::UInt64, false));
+            let column: UInt64Array = self
+                .cells
+                .iter()
+                .skip(ix)
+                .step_by(levels)
+                .copied()
+                .collect();
+            columns.push(Arc::new(column));
+        }
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .map_err(io::Error::other)?;
+        let mut parquet =
+            ArrowWriter::try_new(writer, batch.schema(), None).map_err(io::Error::other)?;
+        parquet.write(&batch).map_err(io::Error::other)?;
+        parquet.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// Samples reconstructed books at a fixed interval into a [`Heatmap`] per
+/// symbol.
+///
+/// Samples are taken at whole multiples of the interval (in nanoseconds
+/// since midnight) and reflect every message timestamped before the sample.
+#[derive(Debug, Clone)]
+pub struct HeatmapExporter {
+    books: BookManager,
+    interval: NonZeroU64,
+    price_scale: NonZeroU32,
+    next_sample: Option<u64>,
+    heatmaps: BTreeMap<u16, Heatmap>,
+}
+
+impl HeatmapExporter {
+    /// Sample every `interval` nanoseconds
+    pub fn new(interval: NonZeroU64) -> HeatmapExporter {
+        HeatmapExporter {
+            books: BookManager::new(),
+            interval,
+            price_scale: PRICE4_SCALE,
+            next_sample: None,
+            heatmaps: BTreeMap::new(),
+        }
+    }
+
+    /// Raw units per dollar in the prices of the heatmaps, see
+    /// [`Heatmap::price_scale`]
+    pub fn price_scale(mut self, scale: NonZeroU32) -> Self {
+        self.price_scale = scale;
+        self
+    }
+
+    /// Record a heatmap for a stock locate
+    pub fn add_symbol(&mut self, locate: u16, grid: PriceGrid) {
+        let heatmap = Heatmap::new(grid).price_scale(self.price_scale);
+        self.heatmaps.insert(locate, heatmap);
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        let next = self
+            .next_sample
+            .get_or_insert_with(|| snap(msg.timestamp, self.interval) + self.interval.get());
+        while *next <= msg.timestamp {
+            for (locate, heatmap) in &mut self.heatmaps {
+                heatmap.sample(*next, self.books.book(*locate));
+            }
+            *next += self.interval.get();
+        }
+        self.books.update(msg);
+    }
+
+    pub fn heatmap(&self, locate: u16) -> Option<&Heatmap> {
+        self.heatmaps.get(&locate)
+    }
+
+    /// The recorded heatmaps, by stock locate
+    pub fn into_heatmaps(self) -> BTreeMap<u16, Heatmap> {
+        self.heatmaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, Side};
+
+    #[test]
+    fn samples_book() {
+        let grid = PriceGrid::new(9_900.into(), 100.into(), 3).unwrap();
+        let mut exporter = HeatmapExporter::new(NonZeroU64::new(1_000).unwrap());
+        exporter.add_symbol(1, grid);
+        exporter.update(&msg(500, add(1, Side::Buy, 100, 9_950)));
+        exporter.update(&msg(600, add(2, Side::Sell, 200, 10_100)));
+        exporter.update(&msg(2_500, Body::DeleteOrder { reference: 1 }));
+        exporter.update(&msg(3_000, add(3, Side::Sell, 1, 20_000)));
+
+        let heatmap = exporter.heatmap(1).unwrap();
+        assert_eq!(heatmap.timestamps(), [1_000, 2_000, 3_000]);
+        assert_eq!(heatmap.row(0), [100, 0, 200]);
+        assert_eq!(heatmap.row(2), [0, 0, 200]);
+
+        let mut npy = Vec::new();
+        heatmap.write_npy(&mut npy).unwrap();
+        let data = npy.len() - 9 * 8;
+        assert_eq!(data % 64, 0);
+        assert!(npy.starts_with(b"\x93NUMPY"));
+        assert_eq!(npy[data - 1], b'\n');
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn writes_parquet() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::UInt64Type;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+
+        let grid = PriceGrid::new(9_900.into(), 100.into(), 2).unwrap();
+        let mut heatmap = Heatmap::new(grid).price_scale(NonZeroU32::new(100).unwrap());
+        heatmap.sample::<crate::OrderBook>(1_000, None);
+        heatmap.sample::<crate::OrderBook>(2_000, None);
+        let path = std::env::temp_dir().join(format!("itchy-heatmap-{}", std::process::id()));
+        heatmap
+            .write_parquet(std::fs::File::create(&path).unwrap())
+            .unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let batch = ParquetRecordBatchReader::try_new(file, 1024)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let names: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        assert_eq!(names, ["timestamp", "99.00", "100.00"]);
+        let timestamps = batch.column(0).as_primitive::<UInt64Type>();
+        assert_eq!(timestamps.values(), &[1_000, 2_000]);
+    }
+
+    #[test]
+    fn validates_grid() {
+        assert!(PriceGrid::new(100.into(), 0.into(), 3).is_none());
+        assert!(PriceGrid::new(u32::MAX.into(), 1.into(), 1).is_none());
+        assert!(PriceGrid::new(0.into(), 2.into(), 1 << 31).is_none());
+
+        let grid = PriceGrid::new((u32::MAX - 2).into(), 1.into(), 2).unwrap();
+        assert_eq!(grid.price(1), Some((u32::MAX - 1).into()));
+        assert_eq!(grid.price(2), None);
+        assert_eq!(grid.bucket(u32::MAX.into()), None);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+
+use crate::{Packet, PacketSource, Result};
+
+/// Network impairments applied by [`Impaired`]. Rates are probabilities
+/// per packet, between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impairment {
+    pub drop_rate: f64,
+    pub duplicate_rate: f64,
+    /// Probability that a packet is held back and delivered after the next one
+    pub reorder_rate: f64,
+    /// Maximum random delay added to receive timestamps, in nanoseconds
+    pub jitter: u64,
+    /// Seed for the random number generator, so runs are reproducible
+    pub seed: u64,
+}
+
+impl Default for Impairment {
+    fn default() -> Self {
+        Impairment {
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+            reorder_rate: 0.0,
+            jitter: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+/// A [`PacketSource`] which drops, duplicates, reorders and delays the
+/// packets of another, for testing gap detection and recovery.
+#[derive(Debug)]
+pub struct Impaired<S> {
+    source: S,
+    config: Impairment,
+    rng: u64,
+    held: Option<Packet>,
+    ready: VecDeque<Packet>,
+    dropped: u64,
+    duplicated: u64,
+    reordered: u64,
+}
+
+impl<S: PacketSource> Impaired<S> {
+    pub fn new(source: S, config: Impairment) -> Impaired<S> {
+        Impaired {
+            source,
+            config,
+            rng: config.seed,
+            held: None,
+            ready: VecDeque::new(),
+            dropped: 0,
+            duplicated: 0,
+            reordered: 0,
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn duplicated(&self) -> u64 {
+        self.duplicated
+    }
+
+    pub fn reordered(&self) -> u64 {
+        self.reordered
+    }
+
+    // splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        // uniform in [0, 1) from the top 53 bits
+        let sample = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        rate > 0.0 && sample < rate
+    }
+}
+
+impl<S: PacketSource> PacketSource for Impaired<S> {
+    fn next_packet(&mut self) -> Option<Result<Packet>> {
+        loop {
+            if let Some(packet) = self.ready.pop_front() {
+                return Some(Ok(packet));
+            }
+            let mut packet = match self.source.next_packet() {
+                Some(Ok(packet)) => packet,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return self.held.take().map(Ok),
+            };
+            if self.chance(self.config.drop_rate) {
+                self.dropped += 1;
+                continue;
+            }
+            if self.config.jitter > 0 {
+                let delay = self.next_u64() % (self.config.jitter + 1);
+                packet.receive_ts = packet.receive_ts.map(|ts| ts + delay);
+            }
+            if self.held.is_none() && self.chance(self.config.reorder_rate) {
+                self.reordered += 1;
+                self.held = Some(packet);
+                continue;
+            }
+            if self.chance(self.config.duplicate_rate) {
+                self.duplicated += 1;
+                self.ready.push_back(packet.clone());
+            }
+            self.ready.push_back(packet);
+            self.ready.extend(self.held.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packets(ct: u8) -> std::vec::IntoIter<Packet> {
+        (0..ct)
+            .map(|i| Packet {
+                data: vec![i],
+                receive_ts: Some(1_000),
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn drain<S: PacketSource>(mut source: S) -> Vec<Packet> {
+        std::iter::from_fn(|| source.next_packet())
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn applies_impairments() {
+        let config = Impairment {
+            reorder_rate: 1.0,
+            ..Default::default()
+        };
+        let data: Vec<_> = drain(Impaired::new(packets(5), config))
+            .iter()
+            .map(|p| p.data[0])
+            .collect();
+        assert_eq!(data, [1, 0, 3, 2, 4]);
+
+        let config = Impairment {
+            drop_rate: 0.5,
+            duplicate_rate: 0.5,
+            jitter: 100,
+            seed: 7,
+            ..Default::default()
+        };
+        let mut impaired = Impaired::new(packets(200), config);
+        let out = drain(&mut impaired);
+        assert!(impaired.dropped() > 50 && impaired.duplicated() > 20);
+        assert_eq!(
+            out.len() as u64,
+            200 - impaired.dropped() + impaired.duplicated()
+        );
+        assert!(out
+            .iter()
+            .all(|p| (1_000..=1_100).contains(&p.receive_ts.unwrap())));
+        // the same seed gives the same impairments
+        assert_eq!(drain(Impaired::new(packets(200), config)), out);
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{self, prelude::*, BufReader};
+
+use crate::{Error, Result};
+
+/// Location of the first message at or after a timestamp boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IndexEntry {
+    /// Start of the time window, in nanoseconds since midnight
+    pub timestamp: u64,
+    /// Byte offset of the first message in the window
+    pub offset: u64,
+    /// Number of messages before this offset
+    pub message_ct: u64,
+}
+
+/// Index of byte offsets at regular timestamp intervals.
+///
+/// Building the index only reads the framing (length prefix and
+/// timestamp) of each message, so it is much cheaper than a full parse.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimeIndex {
+    entries: Vec<IndexEntry>,
+    end: u64,
+}
+
+impl TimeIndex {
+    /// Scan a stream of length-prefixed messages, recording an entry for
+    /// every window of `interval` nanoseconds which contains messages. An
+    /// `interval` of zero is an error.
+    pub fn build<R: Read>(reader: R, interval: u64) -> Result<TimeIndex> {
+        if interval == 0 {
+            return Err(Error::Parse("index interval must be non-zero".into()));
+        }
+        let mut reader = BufReader::new(reader);
+        let mut entries: Vec<IndexEntry> = Vec::new();
+        let mut offset = 0;
+        let mut message_ct = 0;
+        let mut frame = [0; u16::MAX as usize];
+        loop {
+            let mut len = [0; 2];
+            match reader.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            let len = u16::from_be_bytes(len) as usize;
+            match reader.read_exact(&mut frame[..len]) {
+                Ok(()) => {}
+                // leave truncated trailing data to be reported by the parser
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            if let Some(ts) = frame_timestamp(&frame[..len]) {
+                let window = ts - ts % interval;
+                if entries.last().is_none_or(|e| e.timestamp < window) {
+                    entries.push(IndexEntry {
+                        timestamp: window,
+                        offset,
+                        message_ct,
+                    });
+                }
+            }
+            offset += 2 + len as u64;
+            message_ct += 1;
+        }
+        Ok(TimeIndex {
+            entries,
+            end: offset,
+        })
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Byte offset just past the last complete message
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// The indexed windows as `(entry, end_offset)` pairs
+    pub fn windows(&self) -> impl Iterator<Item = (IndexEntry, u64)> + '_ {
+        self.entries.iter().enumerate().map(|(ix, entry)| {
+            let end = self.entries.get(ix + 1).map_or(self.end, |e| e.offset);
+            (*entry, end)
+        })
+    }
+
+    /// The last entry at or before `timestamp`
+    pub fn seek(&self, timestamp: u64) -> Option<&IndexEntry> {
+        let ix = self.entries.partition_point(|e| e.timestamp <= timestamp);
+        ix.checked_sub(1).map(|ix| &self.entries[ix])
+    }
+}
+
+// Timestamp of a message without its length prefix
+fn frame_timestamp(frame: &[u8]) -> Option<u64> {
+    let ts = frame.get(5..11)?;
+    Some(ts.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system_event(ts: u64) -> Vec<u8> {
+        let mut out = vec![0, 12, b'S', 0, 0, 0, 0];
+        out.extend_from_slice(&ts.to_be_bytes()[2..]);
+        out.push(b'O');
+        out
+    }
+
+    #[test]
+    fn build_and_seek() {
+        let data: Vec<u8> = [5, 7, 12, 25, 26]
+            .into_iter()
+            .flat_map(system_event)
+            .collect();
+        let index = TimeIndex::build(&data[..], 10).unwrap();
+        assert!(TimeIndex::build(&data[..], 0).is_err());
+        let starts: Vec<_> = index.entries().iter().map(|e| e.timestamp).collect();
+        assert_eq!(starts, [0, 10, 20]);
+        assert_eq!(index.entries()[1].offset, 28);
+        assert_eq!(index.entries()[2].message_ct, 3);
+        assert_eq!(index.end(), 70);
+        assert_eq!(index.seek(15).unwrap().timestamp, 10);
+        let windows: Vec<_> = index.windows().map(|(e, end)| (e.offset, end)).collect();
+        assert_eq!(windows, [(0, 28), (28, 42), (42, 70)]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::num::{NonZeroU32, NonZeroU64};
+use std::ops::ControlFlow;
+
+use crate::clock::{snap, Session};
+use crate::export::PRICE4_SCALE;
+use crate::{ArrayString8, Body, Book, BookManager, Message, MessageSink};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Writes per-interval metrics of a stream in the InfluxDB line protocol,
+/// for plotting in e.g. Grafana while a feed is consumed.
+///
+/// At the end of each interval (one second of feed time by default) it
+/// writes the number of messages of each type in the interval:
+///
+/// ```text
+/// itchy_messages,tag=A count=1520i 1700000000000000000
+/// ```
+///
+/// and the top of book of each symbol whose book changed in the interval,
+/// with prices in dollars. Sides with no orders are left out, and the
+/// spread needs both:
+///
+/// ```text
+/// itchy_quote,symbol=AAPL bid=189.5,bid_shares=300i,ask=189.51,ask_shares=200i,spread=0.01 1700000000000000000
+/// ```
+///
+/// Points are timestamped in nanoseconds since the Unix epoch, using the
+/// session date to convert from feed time. Symbols are learnt from the
+/// stock directory messages. With the `metrics` feature.
+///
+/// As a [`MessageSink`], write errors stop the stream and are kept for
+/// [`finish`](LineProtocolExporter::finish).
+#[derive(Debug)]
+pub struct LineProtocolExporter<W: Write> {
+    writer: W,
+    session: Session,
+    interval: NonZeroU64,
+    price_scale: NonZeroU32,
+    next_point: Option<u64>,
+    books: BookManager,
+    symbols: HashMap<u16, ArrayString8>,
+    messages: [u64; 256],
+    changed: BTreeSet<u16>,
+    lines: String,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> LineProtocolExporter<W> {
+    pub fn new(writer: W, session: Session) -> LineProtocolExporter<W> {
+        LineProtocolExporter {
+            writer,
+            session,
+            interval: NonZeroU64::new(NANOS_PER_SEC).unwrap_or(NonZeroU64::MIN),
+            price_scale: PRICE4_SCALE,
+            next_point: None,
+            books: BookManager::new(),
+            symbols: HashMap::new(),
+            messages: [0; 256],
+            changed: BTreeSet::new(),
+            lines: String::new(),
+            error: None,
+        }
+    }
+
+    /// Length of the intervals in nanoseconds (one second by default)
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero
+    pub fn interval(mut self, interval: u64) -> Self {
+        assert!(interval > 0, "interval must be non-zero");
+        self.interval = NonZeroU64::new(interval).unwrap_or(NonZeroU64::MIN);
+        self
+    }
+
+    /// Raw units per dollar in prices (10,000 for ITCH 5.0), see
+    /// [`MessageStream::price_scale`](crate::MessageStream::price_scale)
+    pub fn price_scale(mut self, scale: NonZeroU32) -> Self {
+        self.price_scale = scale;
+        self
+    }
+
+    pub fn update(&mut self, msg: &Message) -> io::Result<()> {
+        let interval = self.interval.get();
+        let mut next = *self
+            .next_point
+            .get_or_insert_with(|| snap(msg.timestamp, self.interval) + interval);
+        while next <= msg.timestamp {
+            self.write_points(next)?;
+            next += interval;
+        }
+        self.next_point = Some(next);
+
+        self.messages[msg.tag as usize] += 1;
+        if let Body::StockDirectory(ref dir) = msg.body {
+            self.symbols.insert(msg.stock_locate, dir.stock);
+        }
+        if self.books.update(msg) {
+            self.changed.insert(msg.stock_locate);
+        }
+        Ok(())
+    }
+
+    pub fn books(&self) -> &BookManager {
+        &self.books
+    }
+
+    /// Write the points of the interval in progress, flush the writer and
+    /// return it, or the first error met as a sink
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        if let Some(next) = self.next_point {
+            self.write_points(next)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    // write the points of the interval ending at `ts`, and start the next
+    fn write_points(&mut self, ts: u64) -> io::Result<()> {
+        let time = self.session.to_utc_nanos(ts);
+        let scale = f64::from(self.price_scale.get());
+        self.lines.clear();
+        // writing to a String cannot fail
+        for (tag, count) in self.messages.iter_mut().enumerate() {
+            if *count > 0 {
+                let tag = tag as u8;
+                let _ = write!(self.lines, "itchy_messages,tag=");
+                push_tag(&mut self.lines, &tag.escape_ascii().to_string());
+                let _ = writeln!(self.lines, " count={}i {}", count, time);
+                *count = 0;
+            }
+        }
+        for locate in std::mem::take(&mut self.changed) {
+            let (Some(symbol), Some(book)) = (self.symbols.get(&locate), self.books.book(locate))
+            else {
+                continue;
+            };
+            let (bid, ask) = (book.best_bid(), book.best_ask());
+            if bid.is_none() && ask.is_none() {
+                continue;
+            }
+            self.lines.push_str("itchy_quote,symbol=");
+            push_tag(&mut self.lines, symbol.trim_end());
+            let mut sep = ' ';
+            if let Some(bid) = bid {
+                let _ = write!(
+                    self.lines,
+                    "{}bid={},bid_shares={}i",
+                    sep,
+                    f64::from(bid.price.raw()) / scale,
+                    bid.shares
+                );
+                sep = ',';
+            }
+            if let Some(ask) = ask {
+                let _ = write!(
+                    self.lines,
+                    "{}ask={},ask_shares={}i",
+                    sep,
+                    f64::from(ask.price.raw()) / scale,
+                    ask.shares
+                );
+            }
+            if let (Some(bid), Some(ask)) = (bid, ask) {
+                // negative while the book is crossed
+                let spread = i64::from(ask.price.raw()) - i64::from(bid.price.raw());
+                let spread = spread as f64 / scale;
+                let _ = write!(self.lines, ",spread={}", spread);
+            }
+            let _ = writeln!(self.lines, " {}", time);
+        }
+        self.writer.write_all(self.lines.as_bytes())
+    }
+}
+
+// tag values escape commas, equals signs and spaces
+fn push_tag(lines: &mut String, value: &str) {
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            lines.push('\\');
+        }
+        lines.push(c);
+    }
+}
+
+impl<W: Write> MessageSink for LineProtocolExporter<W> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.update(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::tests::directory_msg;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn writes_points_per_interval() {
+        let session = Session::new(2024, 1, 2).unwrap();
+        let mut exporter = LineProtocolExporter::new(Vec::new(), session);
+        let second = NANOS_PER_SEC;
+        let add_order = |ts, reference, side, shares, price| Message {
+            tag: b'A',
+            ..msg(ts, add(reference, side, shares, price))
+        };
+        let mut directory = directory_msg(1, "ZXZZT");
+        directory.timestamp = 100;
+        exporter.update(&directory).unwrap();
+        exporter
+            .update(&add_order(200, 1, Side::Buy, 100, 99_500))
+            .unwrap();
+        exporter
+            .update(&add_order(300, 2, Side::Sell, 200, 100_000))
+            .unwrap();
+        // nothing happens in the second interval
+        exporter
+            .update(&add_order(2 * second + 5, 3, Side::Buy, 50, 99_000))
+            .unwrap();
+        let lines = String::from_utf8(exporter.finish().unwrap()).unwrap();
+
+        let utc = |ts| session.to_utc_nanos(ts);
+        let expected = format!(
+            "itchy_messages,tag=A count=2i {t1}\n\
+             itchy_messages,tag=R count=1i {t1}\n\
+             itchy_quote,symbol=ZXZZT bid=9.95,bid_shares=100i,ask=10,ask_shares=200i,spread=0.05 {t1}\n\
+             itchy_messages,tag=A count=1i {t3}\n\
+             itchy_quote,symbol=ZXZZT bid=9.95,bid_shares=100i,ask=10,ask_shares=200i,spread=0.05 {t3}\n",
+            t1 = utc(second),
+            t3 = utc(3 * second),
+        );
+        assert_eq!(lines, expected);
+    }
+}
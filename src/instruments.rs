@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::clock::Session;
+use crate::{
+    ArrayString8, IssueClassification, IssueSubType, MarketCategory, StockDirectory,
+    SymbolDirectory,
+};
+
+/// An instrument identifier that stays the same across trading days,
+/// assigned by a [`LocateMapper`] in order of first appearance
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstrumentId(pub u32);
+
+/// An instrument's symbol and locate code on one day
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Listing {
+    pub session: Session,
+    pub stock: ArrayString8,
+    pub stock_locate: u16,
+}
+
+// directory attributes that a ticker change leaves alone
+type Attributes = (
+    MarketCategory,
+    IssueClassification,
+    IssueSubType,
+    u32,
+    Option<bool>,
+    u32,
+    bool,
+);
+
+fn attributes(dir: &StockDirectory) -> Attributes {
+    (
+        dir.market_category,
+        dir.issue_classification,
+        dir.issue_subtype,
+        dir.round_lot_size,
+        dir.etp_flag,
+        dir.etp_leverage_factor,
+        dir.inverse_indicator,
+    )
+}
+
+/// Maps the locate codes of many days' captures onto stable
+/// [`InstrumentId`]s, for studies spanning several capture files.
+///
+/// Locate codes are reassigned every day, so days are linked by symbol. A
+/// symbol seen before keeps its instrument. A new symbol is taken to be a
+/// ticker change when exactly one instrument listed the previous day has
+/// disappeared with the same market category, issue classification and
+/// subtype, round lot size and ETP attributes, and the new listing is not
+/// flagged as an IPO. Anything else is a new instrument. Known ticker
+/// changes, e.g. from a corporate actions file, can be given with
+/// [`rename`](Self::rename) to take precedence over the heuristic.
+#[derive(Debug, Clone, Default)]
+pub struct LocateMapper {
+    days: BTreeMap<Session, HashMap<u16, InstrumentId>>,
+    // trimmed symbol to the instrument last listed under it
+    by_symbol: HashMap<ArrayString8, InstrumentId>,
+    // indexed by instrument id
+    listings: Vec<Vec<Listing>>,
+    attributes: Vec<Attributes>,
+    // pending ticker changes, new symbol to old
+    renames: HashMap<ArrayString8, ArrayString8>,
+}
+
+impl LocateMapper {
+    pub fn new() -> LocateMapper {
+        LocateMapper::default()
+    }
+
+    /// Record a known ticker change, applied when `to` first appears in a
+    /// later day's directory
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let (Some(from), Some(to)) = (trimmed(from), trimmed(to)) {
+            self.renames.insert(to, from);
+        }
+    }
+
+    /// Add a day's directory. Days must be added in date order.
+    ///
+    /// # Panics
+    ///
+    /// If the session is not after every session already added
+    pub fn add_day(&mut self, session: Session, directory: &SymbolDirectory) {
+        if let Some((&last, _)) = self.days.last_key_value() {
+            assert!(session > last, "days must be added in date order");
+        }
+        let entries: Vec<_> = directory
+            .iter()
+            .filter_map(|(locate, dir)| Some((locate, trimmed(&dir.stock)?, dir)))
+            .collect();
+        let mut today = HashMap::new();
+        let mut listed = HashSet::new();
+        // known ticker changes first, so the old symbol cannot claim the
+        // instrument if it is still listed
+        for &(locate, symbol, _) in &entries {
+            if let Some(old) = self.renames.remove(&symbol) {
+                if let Some(id) = self.by_symbol.remove(&old) {
+                    if listed.insert(id) {
+                        self.by_symbol.insert(symbol, id);
+                        today.insert(locate, id);
+                    }
+                }
+            }
+        }
+        let mut unmatched = Vec::new();
+        for &(locate, symbol, dir) in &entries {
+            if today.contains_key(&locate) {
+                continue;
+            }
+            match self.by_symbol.get(&symbol) {
+                Some(&id) if listed.insert(id) => {
+                    today.insert(locate, id);
+                }
+                _ => unmatched.push((locate, symbol, dir)),
+            }
+        }
+
+        // instruments listed on the previous day that have not reappeared
+        let mut gone: HashMap<Attributes, Vec<InstrumentId>> = HashMap::new();
+        if let Some(previous) = self.days.values().next_back() {
+            for &id in previous.values() {
+                if !listed.contains(&id) {
+                    gone.entry(self.attributes[id.0 as usize])
+                        .or_default()
+                        .push(id);
+                }
+            }
+        }
+        let mut candidates: HashMap<Attributes, usize> = HashMap::new();
+        for (_, _, dir) in &unmatched {
+            if dir.ipo_flag != Some(true) {
+                *candidates.entry(attributes(dir)).or_default() += 1;
+            }
+        }
+
+        for (locate, symbol, dir) in unmatched {
+            let key = attributes(dir);
+            let renamed = match gone.get(&key) {
+                Some(ids) if ids.len() == 1 && candidates.get(&key) == Some(&1) => {
+                    Some(ids[0]).filter(|_| dir.ipo_flag != Some(true))
+                }
+                _ => None,
+            };
+            let id = match renamed {
+                Some(id) => {
+                    let old = self.listings[id.0 as usize].last().map(|l| l.stock);
+                    if let Some(old) = old.as_deref().and_then(trimmed) {
+                        self.by_symbol.remove(&old);
+                    }
+                    id
+                }
+                None => {
+                    let id = InstrumentId(self.listings.len() as u32);
+                    self.listings.push(Vec::new());
+                    self.attributes.push(key);
+                    id
+                }
+            };
+            self.by_symbol.insert(symbol, id);
+            today.insert(locate, id);
+        }
+
+        for &(locate, _, dir) in &entries {
+            if let Some(&id) = today.get(&locate) {
+                self.attributes[id.0 as usize] = attributes(dir);
+                self.listings[id.0 as usize].push(Listing {
+                    session,
+                    stock: dir.stock,
+                    stock_locate: locate,
+                });
+            }
+        }
+        self.days.insert(session, today);
+    }
+
+    /// Instrument with the given locate code on a day
+    pub fn instrument(&self, session: Session, locate: u16) -> Option<InstrumentId> {
+        self.days.get(&session)?.get(&locate).copied()
+    }
+
+    /// Locate code of an instrument on a day
+    pub fn locate(&self, session: Session, id: InstrumentId) -> Option<u16> {
+        let listings = self.listings.get(id.0 as usize)?;
+        let ix = listings
+            .binary_search_by_key(&session, |l| l.session)
+            .ok()?;
+        Some(listings[ix].stock_locate)
+    }
+
+    /// Every day an instrument was listed, in date order
+    pub fn listings(&self, id: InstrumentId) -> &[Listing] {
+        self.listings.get(id.0 as usize).map_or(&[], |l| l)
+    }
+
+    /// Days added so far, in date order
+    pub fn sessions(&self) -> impl Iterator<Item = Session> + '_ {
+        self.days.keys().copied()
+    }
+
+    /// Number of distinct instruments
+    pub fn len(&self) -> usize {
+        self.listings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listings.is_empty()
+    }
+}
+
+fn trimmed(symbol: &str) -> Option<ArrayString8> {
+    let symbol = symbol.trim_end();
+    if symbol.is_empty() {
+        return None;
+    }
+    ArrayString8::from(symbol).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::tests::directory_msg;
+    use crate::{Body, IssueClassification};
+
+    fn directory(entries: &[(u16, &str)]) -> SymbolDirectory {
+        let mut builder = SymbolDirectory::builder();
+        for &(locate, stock) in entries {
+            builder.update(&directory_msg(locate, stock));
+        }
+        (*builder.build()).clone()
+    }
+
+    #[test]
+    fn links_days() {
+        let day = |d| Session::new(2024, 3, d).unwrap();
+        let mut mapper = LocateMapper::new();
+        mapper.add_day(day(4), &directory(&[(1, "AAPL"), (2, "FB"), (3, "MSFT")]));
+        // locates move, and FB becomes META
+        mapper.add_day(day(5), &directory(&[(5, "AAPL"), (6, "META"), (7, "MSFT")]));
+
+        // a new listing with a different classification, MSFT is gone, and
+        // a known ticker change is given
+        let mut builder = SymbolDirectory::builder();
+        let mut fund = directory_msg(9, "QQQ");
+        if let Body::StockDirectory(ref mut dir) = fund.body {
+            dir.issue_classification = IssueClassification::Unit;
+        }
+        builder.update(&fund);
+        builder.update(&directory_msg(2, "AAPL"));
+        builder.update(&directory_msg(8, "FBX"));
+        mapper.rename("META", "FBX");
+        mapper.add_day(day(6), &builder.build());
+
+        let aapl = mapper.instrument(day(4), 1).unwrap();
+        assert_eq!(mapper.instrument(day(5), 5), Some(aapl));
+        assert_eq!(mapper.locate(day(6), aapl), Some(2));
+        let fb = mapper.instrument(day(4), 2).unwrap();
+        assert_eq!(mapper.instrument(day(5), 6), Some(fb));
+        assert_eq!(mapper.instrument(day(6), 8), Some(fb));
+        let history: Vec<_> = mapper
+            .listings(fb)
+            .iter()
+            .map(|l| l.stock.trim_end())
+            .collect();
+        assert_eq!(history, ["FB", "META", "FBX"]);
+        // MSFT is not mistaken for the fund
+        assert_ne!(mapper.instrument(day(6), 9), mapper.instrument(day(5), 7));
+        assert_eq!(mapper.len(), 4);
+    }
+}
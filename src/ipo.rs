@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{ArrayString8, Body, IpoReleaseQualifier, Message, Price4, Symbol};
+
+/// Wall-clock time of day, as used for IPO release times
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+impl TimeOfDay {
+    /// Time `secs` seconds after midnight, `None` if that is a day or more
+    pub fn from_seconds(secs: u32) -> Option<TimeOfDay> {
+        (secs < 86_400).then(|| TimeOfDay::wrapping(secs))
+    }
+
+    // time of day `secs` seconds after some midnight
+    pub(crate) fn wrapping(secs: u32) -> TimeOfDay {
+        let secs = secs % 86_400;
+        TimeOfDay {
+            hours: (secs / 3600) as u8,
+            minutes: (secs / 60 % 60) as u8,
+            seconds: (secs % 60) as u8,
+        }
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            self.hours, self.minutes, self.seconds
+        )
+    }
+}
+
+/// Current IPO quoting state of a single symbol
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IpoListing {
+    pub stock: ArrayString8,
+    /// Release time in seconds since midnight
+    pub release_time: u32,
+    pub price: Price4,
+    /// Qualifier of the latest message for this symbol
+    pub qualifier: IpoReleaseQualifier,
+    /// Timestamp of the latest message for this symbol
+    pub updated: u64,
+    /// Number of 'K' messages seen for this symbol
+    pub revisions: u32,
+}
+
+impl IpoListing {
+    /// The release time as a time of day, `None` if it is out of range
+    pub fn release_time_of_day(&self) -> Option<TimeOfDay> {
+        TimeOfDay::from_seconds(self.release_time)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.qualifier == IpoReleaseQualifier::Cancelled
+    }
+}
+
+/// IPO calendar for a session, collected from IPO Quoting Period ('K') messages.
+///
+/// Repeated messages for a symbol are merged: an anticipated release updates
+/// the release time and price, while a cancellation keeps the last announced
+/// time and price but marks the listing as cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct IpoCalendar {
+    listings: BTreeMap<ArrayString8, IpoListing>,
+}
+
+impl IpoCalendar {
+    pub fn new() -> IpoCalendar {
+        IpoCalendar::default()
+    }
+
+    /// Apply a message. Returns `true` if it was an IPO quoting period message.
+    pub fn update(&mut self, msg: &Message) -> bool {
+        let ipo = match msg.body {
+            Body::IpoQuotingPeriod(ref ipo) => ipo,
+            _ => return false,
+        };
+        let listing = self
+            .listings
+            .entry(ipo.stock)
+            .or_insert_with(|| IpoListing {
+                stock: ipo.stock,
+                release_time: ipo.release_time,
+                price: ipo.price,
+                qualifier: ipo.release_qualifier,
+                updated: msg.timestamp,
+                revisions: 0,
+            });
+        if ipo.release_qualifier == IpoReleaseQualifier::Anticipated {
+            listing.release_time = ipo.release_time;
+            listing.price = ipo.price;
+        }
+        listing.qualifier = ipo.release_qualifier;
+        listing.updated = msg.timestamp;
+        listing.revisions += 1;
+        true
+    }
+
+    /// Listing for a symbol. Trailing padding is ignored.
+    pub fn get(&self, symbol: &str) -> Option<&IpoListing> {
+        self.listings.get(&Symbol::new(symbol)?.padded())
+    }
+
+    /// All listings, ordered by symbol
+    pub fn iter(&self) -> impl Iterator<Item = &IpoListing> {
+        self.listings.values()
+    }
+
+    /// Listings which have not been cancelled, ordered by release time
+    pub fn anticipated(&self) -> Vec<&IpoListing> {
+        let mut out: Vec<_> = self.iter().filter(|l| !l.is_cancelled()).collect();
+        out.sort_by_key(|l| (l.release_time, l.stock));
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.listings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IpoQuotingPeriod;
+
+    fn ipo(
+        timestamp: u64,
+        stock: &str,
+        release_time: u32,
+        qualifier: IpoReleaseQualifier,
+    ) -> Message {
+        Message {
+            tag: b'K',
+            stock_locate: 0,
+            tracking_number: 0,
+            timestamp,
+            body: Body::IpoQuotingPeriod(IpoQuotingPeriod {
+                stock: ArrayString8::from(stock).unwrap(),
+                release_time,
+                release_qualifier: qualifier,
+                price: 100_000.into(),
+            }),
+        }
+    }
+
+    #[test]
+    fn time_of_day() {
+        assert_eq!(
+            TimeOfDay::from_seconds(35100).unwrap().to_string(),
+            "09:45:00"
+        );
+        assert_eq!(
+            TimeOfDay::from_seconds(57599).unwrap().to_string(),
+            "15:59:59"
+        );
+        assert_eq!(TimeOfDay::from_seconds(86_400), None);
+        assert_eq!(TimeOfDay::from_seconds(u32::MAX), None);
+    }
+
+    #[test]
+    fn merges_cancellations() {
+        use IpoReleaseQualifier::*;
+        let mut cal = IpoCalendar::new();
+        cal.update(&ipo(1, "ZWZZT   ", 35100, Anticipated));
+        cal.update(&ipo(2, "ZWZZT   ", 36000, Anticipated));
+        cal.update(&ipo(3, "ABCD    ", 34200, Anticipated));
+        cal.update(&ipo(4, "ZWZZT   ", 0, Cancelled));
+        assert_eq!(cal.len(), 2);
+
+        let zw = cal.get("ZWZZT").unwrap();
+        assert_eq!(cal.get("ZWZZT   "), Some(zw));
+        assert!(cal.get("ZWZZ").is_none());
+        assert!(zw.is_cancelled());
+        assert_eq!(zw.release_time_of_day().unwrap().to_string(), "10:00:00");
+        assert_eq!(zw.revisions, 3);
+        assert_eq!(zw.updated, 4);
+
+        let live = cal.anticipated();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].stock.as_str(), "ABCD    ");
+    }
+}
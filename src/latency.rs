@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use crate::Message;
+
+/// Distribution of inter-message timestamp deltas, in nanoseconds
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LatencySummary {
+    /// Number of deltas seen, including any not kept in the sample
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub p50: u64,
+    pub p99: u64,
+    pub p999: u64,
+}
+
+/// Online accumulator of the gaps between consecutive message timestamps,
+/// overall and per symbol, for characterizing how bursty a feed is.
+///
+/// Percentiles are estimated from a uniform reservoir sample of bounded
+/// size, so memory stays fixed however long the stream; the minimum,
+/// maximum and count are exact. Can be queried at any point of a replay.
+/// Deltas are measured between messages in stream order, so a timestamp
+/// earlier than the one before it counts as a gap of zero.
+#[derive(Debug, Clone)]
+pub struct LatencyStats {
+    overall: Reservoir,
+    symbols: HashMap<u16, Reservoir>,
+    symbol_capacity: usize,
+    last: Option<u64>,
+    last_by_symbol: HashMap<u16, u64>,
+    // xorshift state, fixed so results are reproducible
+    rng: u64,
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        LatencyStats::with_capacity(100_000, 1_000)
+    }
+}
+
+impl LatencyStats {
+    /// Keep up to 100,000 samples overall and 1,000 per symbol
+    pub fn new() -> LatencyStats {
+        LatencyStats::default()
+    }
+
+    /// Keep up to `overall` samples across all messages, and
+    /// `per_symbol` for each symbol
+    ///
+    /// # Panics
+    ///
+    /// If either is zero
+    pub fn with_capacity(overall: usize, per_symbol: usize) -> LatencyStats {
+        assert!(
+            overall > 0 && per_symbol > 0,
+            "reservoirs must be non-empty"
+        );
+        LatencyStats {
+            overall: Reservoir::new(overall),
+            symbols: HashMap::new(),
+            symbol_capacity: per_symbol,
+            last: None,
+            last_by_symbol: HashMap::new(),
+            rng: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        let ts = msg.timestamp;
+        if let Some(last) = self.last.replace(ts) {
+            let r = self.next_random();
+            self.overall.add(ts.saturating_sub(last), r);
+        }
+        // market-wide messages are not attributed to a symbol
+        if msg.stock_locate == 0 {
+            return;
+        }
+        if let Some(last) = self.last_by_symbol.insert(msg.stock_locate, ts) {
+            let r = self.next_random();
+            let capacity = self.symbol_capacity;
+            self.symbols
+                .entry(msg.stock_locate)
+                .or_insert_with(|| Reservoir::new(capacity))
+                .add(ts.saturating_sub(last), r);
+        }
+    }
+
+    /// Deltas between consecutive messages of the whole stream
+    pub fn overall(&self) -> Option<LatencySummary> {
+        self.overall.summary()
+    }
+
+    /// Deltas between consecutive messages for one symbol
+    pub fn symbol(&self, locate: u16) -> Option<LatencySummary> {
+        self.symbols.get(&locate)?.summary()
+    }
+
+    /// Summaries of every symbol with at least two messages
+    pub fn symbols(&self) -> impl Iterator<Item = (u16, LatencySummary)> + '_ {
+        self.symbols
+            .iter()
+            .filter_map(|(&locate, r)| Some((locate, r.summary()?)))
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
+
+// a uniform sample of a stream of values (Vitter's algorithm R)
+#[derive(Debug, Clone)]
+struct Reservoir {
+    samples: Vec<u64>,
+    capacity: usize,
+    count: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Reservoir {
+    fn new(capacity: usize) -> Reservoir {
+        Reservoir {
+            samples: Vec::new(),
+            capacity,
+            count: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+
+    fn add(&mut self, value: u64, random: u64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.samples.len() < self.capacity {
+            self.samples.push(value);
+        } else {
+            let slot = (random % self.count) as usize;
+            if slot < self.capacity {
+                self.samples[slot] = value;
+            }
+        }
+    }
+
+    fn summary(&self) -> Option<LatencySummary> {
+        if self.count == 0 {
+            return None;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        // nearest rank
+        let percentile = |p: f64| {
+            let rank = (p * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Some(LatencySummary {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            p50: percentile(0.5),
+            p99: percentile(0.99),
+            p999: percentile(0.999),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn summarizes_deltas() {
+        let mut stats = LatencyStats::with_capacity(100, 10);
+        for i in 0..=1000u64 {
+            // two symbols alternating, at 10ns intervals
+            let m = Message {
+                stock_locate: 1 + (i % 2) as u16,
+                ..msg(i * 10, add(i, Side::Buy, 100, 10_000))
+            };
+            stats.update(&m);
+        }
+        let overall = stats.overall().unwrap();
+        assert_eq!((overall.count, overall.min, overall.max), (1000, 10, 10));
+        assert_eq!(overall.p999, 10);
+        let symbol = stats.symbol(1).unwrap();
+        assert_eq!((symbol.count, symbol.p50), (500, 20));
+        assert_eq!(stats.symbols().count(), 2);
+        assert!(stats.symbol(3).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+use std::str;
+
+use crate::version::length_v50;
+use crate::{decode_message, Error, FeedProfile, Message, Price4, Result, Side};
+
+/// A message left as raw bytes, with fields decoded on demand.
+///
+/// Accessors return `None` for fields the message type does not have.
+/// For consumers which only look at one or two fields of most messages
+/// this avoids building the full [`Body`](crate::Body).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LazyMessage<'a> {
+    // the message without its length prefix
+    bytes: &'a [u8],
+}
+
+impl<'a> LazyMessage<'a> {
+    /// Wrap the bytes of one message, without its length prefix. Fails if
+    /// the message type is unknown or the length does not match it.
+    pub fn new(bytes: &'a [u8]) -> Result<LazyMessage<'a>> {
+        let Some(&tag) = bytes.first() else {
+            return Err(Error::Parse("empty message".into()));
+        };
+        match length_v50(tag) {
+            Some(len) if FeedProfile::TotalView.allows(tag) && len == bytes.len() => {
+                Ok(LazyMessage { bytes })
+            }
+            Some(_) if FeedProfile::TotalView.allows(tag) => Err(Error::Parse(format!(
+                "'{}' message of {} bytes",
+                tag as char,
+                bytes.len()
+            ))),
+            _ => Err(Error::Parse(format!(
+                "unknown message type '{}'",
+                tag as char
+            ))),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Parse the whole message
+    pub fn parse(&self) -> Result<Message> {
+        decode_message(self.bytes)
+    }
+
+    pub fn tag(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    pub fn stock_locate(&self) -> u16 {
+        self.u16_at(1)
+    }
+
+    pub fn tracking_number(&self) -> u16 {
+        self.u16_at(3)
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        let mut ts = [0; 8];
+        ts[2..].copy_from_slice(&self.bytes[5..11]);
+        u64::from_be_bytes(ts)
+    }
+
+    /// The stock symbol, with its space padding
+    pub fn stock(&self) -> Option<&'a str> {
+        let at = match self.tag() {
+            b'H' | b'J' | b'K' | b'N' | b'R' | b'Y' => 11,
+            b'L' => 15,
+            b'Q' => 19,
+            b'A' | b'F' | b'P' => 24,
+            b'I' => 28,
+            _ => return None,
+        };
+        str::from_utf8(&self.bytes[at..at + 8]).ok()
+    }
+
+    /// The order reference. For Order Replace messages this is the
+    /// reference of the replaced order.
+    pub fn reference(&self) -> Option<u64> {
+        match self.tag() {
+            b'A' | b'F' | b'E' | b'C' | b'X' | b'D' | b'U' | b'P' => Some(self.u64_at(11)),
+            _ => None,
+        }
+    }
+
+    pub fn side(&self) -> Option<Side> {
+        match self.tag() {
+            b'A' | b'F' | b'P' => match self.bytes[19] {
+                b'B' => Some(Side::Buy),
+                b'S' => Some(Side::Sell),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Shares added, executed, cancelled, replaced or crossed
+    pub fn shares(&self) -> Option<u64> {
+        let at = match self.tag() {
+            b'Q' => return Some(self.u64_at(11)),
+            b'E' | b'C' | b'X' => 19,
+            b'A' | b'F' | b'P' => 20,
+            b'U' => 27,
+            _ => return None,
+        };
+        Some(self.u32_at(at) as u64)
+    }
+
+    /// The order, execution, trade or cross price
+    pub fn price(&self) -> Option<Price4> {
+        let at = match self.tag() {
+            b'Q' => 27,
+            b'U' => 31,
+            b'A' | b'F' | b'C' | b'P' => 32,
+            _ => return None,
+        };
+        Some(self.u32_at(at).into())
+    }
+
+    pub fn match_number(&self) -> Option<u64> {
+        let at = match self.tag() {
+            b'B' => 11,
+            b'E' | b'C' => 23,
+            b'Q' => 31,
+            b'P' => 36,
+            _ => return None,
+        };
+        Some(self.u64_at(at))
+    }
+
+    fn u16_at(&self, at: usize) -> u16 {
+        u16::from_be_bytes(self.array_at(at))
+    }
+
+    fn u32_at(&self, at: usize) -> u32 {
+        u32::from_be_bytes(self.array_at(at))
+    }
+
+    fn u64_at(&self, at: usize) -> u64 {
+        u64::from_be_bytes(self.array_at(at))
+    }
+
+    fn array_at<const N: usize>(&self, at: usize) -> [u8; N] {
+        let mut array = [0; N];
+        array.copy_from_slice(&self.bytes[at..at + N]);
+        array
+    }
+}
+
+/// Iterator over the messages in a buffer of length-prefixed messages,
+/// see [`lazy_messages`]
+#[derive(Debug, Clone)]
+pub struct LazyMessages<'a> {
+    data: &'a [u8],
+    failed: bool,
+}
+
+/// Iterate over the messages in an in-memory capture (e.g. a memory-mapped
+/// file) without parsing them
+pub fn lazy_messages(data: &[u8]) -> LazyMessages<'_> {
+    LazyMessages {
+        data,
+        failed: false,
+    }
+}
+
+impl<'a> Iterator for LazyMessages<'a> {
+    type Item = Result<LazyMessage<'a>>;
+
+    fn next(&mut self) -> Option<Result<LazyMessage<'a>>> {
+        if self.data.is_empty() || self.failed {
+            return None;
+        }
+        let item = match *self.data {
+            [a, b, ref rest @ ..] if rest.len() >= u16::from_be_bytes([a, b]) as usize => {
+                let (bytes, rest) = rest.split_at(u16::from_be_bytes([a, b]) as usize);
+                self.data = rest;
+                LazyMessage::new(bytes)
+            }
+            _ => Err(Error::Parse("Unexpected EOF".into())),
+        };
+        self.failed = item.is_err();
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Body;
+
+    #[test]
+    fn reads_fields_lazily() {
+        let mut data = Vec::new();
+        Message {
+            tag: b'A',
+            ..msg(5, add(7, Side::Sell, 100, 123_456))
+        }
+        .encode(&mut data);
+        let exec = Body::OrderExecuted {
+            reference: 7,
+            executed: 40,
+            match_number: 9,
+        };
+        Message {
+            tag: b'E',
+            ..msg(6, exec)
+        }
+        .encode(&mut data);
+
+        let messages: Vec<_> = lazy_messages(&data).map(|m| m.unwrap()).collect();
+        let (add, exec) = (messages[0], messages[1]);
+        assert_eq!(add.timestamp(), 5);
+        assert_eq!(add.stock(), Some("ZXZZT   "));
+        assert_eq!(add.side(), Some(Side::Sell));
+        assert_eq!(add.price(), Some(123_456.into()));
+        assert_eq!(add.match_number(), None);
+        assert_eq!(exec.reference(), Some(7));
+        assert_eq!(exec.shares(), Some(40));
+        assert_eq!(exec.match_number(), Some(9));
+        assert_eq!(exec.parse().unwrap().timestamp, 6);
+
+        assert!(lazy_messages(&data[..data.len() - 1])
+            .nth(1)
+            .unwrap()
+            .is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::{
+    AddOrder, Body, Book, BookManager, Message, NonCrossTrade, OrderUpdate, Price4, ReplaceOrder,
+    Result, Side,
+};
+
+// message types copied through unchanged: system events, the stock
+// directory, halts and other trading status, and trades
+const KEPT_TAGS: &[u8] = b"SRHhYVWKJPQB";
+
+/// Reduces a full-depth stream to a Level 1 feed of trades, top of book
+/// and trading status, for consumers which never need depth.
+///
+/// The output is valid ITCH 5.0, so it can be read back with a
+/// [`MessageStream`](crate::MessageStream) and built into books as usual:
+///
+/// - System events, stock directory, trading action, Reg SHO, MWCB, IPO
+///   and LULD messages, and trades ('P', 'Q' and 'B'), are copied through.
+/// - Printable executions of displayed orders become non-cross trades
+///   ('P') with an order reference of 0, the side of the resting order, and
+///   the execution price and match number.
+/// - The best bid and best ask of each symbol are each represented by a
+///   single synthetic order, added ('A') when the side has orders, replaced
+///   ('U') whenever the price or size at the top changes, and deleted ('D')
+///   when the side empties. Its size is the total at the best price,
+///   capped at `u32::MAX`.
+/// - Every other message, including all orders, is dropped.
+///
+/// Synthetic messages take the locate, tracking number and timestamp of
+/// the message which changed the book, and synthetic order references
+/// count up from 1.
+#[derive(Debug, Clone, Default)]
+pub struct Level1Downsampler {
+    books: BookManager,
+    // the synthetic order at the top of each side: reference, price and size
+    tops: HashMap<(u16, Side), (u64, Price4, u32)>,
+    next_reference: u64,
+}
+
+impl Level1Downsampler {
+    pub fn new() -> Level1Downsampler {
+        Level1Downsampler::default()
+    }
+
+    /// Append the Level 1 messages for a message of the full feed to `out`.
+    /// Messages must be passed in stream order.
+    pub fn convert(&mut self, msg: &Message, out: &mut Vec<Message>) {
+        if KEPT_TAGS.contains(&msg.tag) {
+            out.push(msg.clone());
+        }
+        let Some(update) = self.books.apply(msg) else {
+            return;
+        };
+        let derived = |tag, body| Message {
+            tag,
+            stock_locate: msg.stock_locate,
+            tracking_number: msg.tracking_number,
+            timestamp: msg.timestamp,
+            body,
+        };
+        let order = match update {
+            OrderUpdate::Executed {
+                order,
+                shares,
+                price,
+                match_number,
+                printable: true,
+                ..
+            } => {
+                let trade = NonCrossTrade {
+                    reference: 0,
+                    side: order.side,
+                    shares,
+                    stock: order.stock,
+                    price,
+                    match_number,
+                };
+                out.push(derived(b'P', Body::NonCrossTrade(trade)));
+                order
+            }
+            OrderUpdate::Added { order, .. }
+            | OrderUpdate::Executed { order, .. }
+            | OrderUpdate::Cancelled { order, .. }
+            | OrderUpdate::Deleted { order, .. } => order,
+            OrderUpdate::Replaced { new, .. } => new,
+        };
+
+        let book = self.books.book(msg.stock_locate);
+        let tops = [
+            (Side::Buy, book.and_then(|book| book.best_bid())),
+            (Side::Sell, book.and_then(|book| book.best_ask())),
+        ];
+        for (side, best) in tops {
+            let best =
+                best.map(|level| (level.price, u32::try_from(level.shares).unwrap_or(u32::MAX)));
+            let key = (msg.stock_locate, side);
+            let current = self.tops.get(&key).copied();
+            match (current, best) {
+                (None, None) => {}
+                (Some((_, price, shares)), Some(best)) if (price, shares) == best => {}
+                (None, Some((price, shares))) => {
+                    let reference = self.reference();
+                    let add = AddOrder {
+                        reference,
+                        side,
+                        shares,
+                        stock: order.stock,
+                        price,
+                        mpid: None,
+                    };
+                    out.push(derived(b'A', Body::AddOrder(add)));
+                    self.tops.insert(key, (reference, price, shares));
+                }
+                (Some((old_reference, ..)), Some((price, shares))) => {
+                    let new_reference = self.reference();
+                    let replace = ReplaceOrder {
+                        old_reference,
+                        new_reference,
+                        shares,
+                        price,
+                    };
+                    out.push(derived(b'U', Body::ReplaceOrder(replace)));
+                    self.tops.insert(key, (new_reference, price, shares));
+                }
+                (Some((reference, ..)), None) => {
+                    out.push(derived(b'D', Body::DeleteOrder { reference }));
+                    self.tops.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Convert a stream of messages, writing the Level 1 feed out as
+    /// length-prefixed ITCH 5.0. Returns the number of messages written.
+    pub fn rewrite<I, W>(&mut self, messages: I, mut writer: W) -> Result<u64>
+    where
+        I: IntoIterator<Item = Result<Message>>,
+        W: Write,
+    {
+        let mut converted = Vec::new();
+        let mut buf = Vec::new();
+        let mut count = 0;
+        for msg in messages {
+            converted.clear();
+            self.convert(&msg?, &mut converted);
+            for msg in &converted {
+                buf.clear();
+                msg.encode(&mut buf);
+                writer.write_all(&buf)?;
+                count += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    fn reference(&mut self) -> u64 {
+        self.next_reference += 1;
+        self.next_reference
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::MessageStream;
+
+    #[test]
+    fn tracks_top_of_book() {
+        let tagged = |tag, ts, body| {
+            Ok(Message {
+                tag,
+                ..msg(ts, body)
+            })
+        };
+        let messages = vec![
+            tagged(b'A', 1, add(1, Side::Buy, 100, 10_000)),
+            tagged(b'A', 2, add(2, Side::Sell, 200, 10_100)),
+            // behind the best bid
+            tagged(b'A', 3, add(3, Side::Buy, 300, 9_900)),
+            tagged(
+                b'E',
+                4,
+                Body::OrderExecuted {
+                    reference: 2,
+                    executed: 50,
+                    match_number: 9,
+                },
+            ),
+            tagged(b'D', 5, Body::DeleteOrder { reference: 1 }),
+            tagged(b'D', 6, Body::DeleteOrder { reference: 2 }),
+        ];
+        let mut full = BookManager::new();
+        for msg in &messages {
+            full.update(msg.as_ref().unwrap());
+        }
+
+        let mut data = Vec::new();
+        let count = Level1Downsampler::new()
+            .rewrite(messages, &mut data)
+            .unwrap();
+        let level1: Vec<_> = MessageStream::from_reader(&data[..])
+            .map(|m| m.unwrap())
+            .collect();
+        assert_eq!(count, 6);
+        let tags: Vec<_> = level1.iter().map(|m| m.tag).collect();
+        assert_eq!(tags, b"AAPUUD");
+        let Body::NonCrossTrade(ref trade) = level1[2].body else {
+            panic!("not a trade: {:?}", level1[2]);
+        };
+        assert_eq!((trade.side, trade.shares), (Side::Sell, 50));
+        assert_eq!(trade.price, 10_100.into());
+
+        let mut books = BookManager::new();
+        for msg in &level1 {
+            books.update(msg);
+        }
+        let (book, full) = (books.book(1).unwrap(), full.book(1).unwrap());
+        assert_eq!(book.best_bid(), full.best_bid());
+        assert_eq!(book.best_ask(), full.best_ask());
+    }
+}
@@ -0,0 +1,848 @@
+// Message bodies and the nom parsers that decode them
+
+use core::str;
+
+use nom::branch::alt;
+use nom::bytes::streaming::take;
+use nom::character::streaming::char;
+use nom::combinator::{map, map_opt};
+use nom::{
+    error::ErrorKind,
+    number::streaming::{be_u16, be_u32, be_u64, be_u8},
+    Err, IResult, Needed,
+};
+
+use crate::enums::parse_issue_subtype;
+use crate::*;
+
+fn char2bool(input: &[u8]) -> IResult<&[u8], bool> {
+    alt((map(char('Y'), |_| true), map(char('N'), |_| false)))(input)
+}
+
+fn maybe_char2bool(input: &[u8]) -> IResult<&[u8], Option<bool>> {
+    alt((
+        map(char('Y'), |_| Some(true)),
+        map(char('N'), |_| Some(false)),
+        map(char(' '), |_| None),
+    ))(input)
+}
+
+fn parse_etp_flag(input: &[u8]) -> IResult<&[u8], Option<bool>> {
+    alt((
+        map(char('Y'), |_| Some(true)),
+        map(char('N'), |_| Some(false)),
+        map(char(' '), |_| None),
+        map(char('M'), |_| Some(true)),
+    ))(input)
+}
+
+pub(crate) fn stock(input: &[u8]) -> IResult<&[u8], ArrayString8> {
+    alpha(input)
+}
+
+// a fixed-width alpha field, which fails to parse unless it is UTF-8
+fn alpha<const N: usize>(input: &[u8]) -> IResult<&[u8], ArrayString<N>> {
+    map_opt(take(N), |s: &[u8]| {
+        ArrayString::from(str::from_utf8(s).ok()?).ok()
+    })(input)
+}
+
+#[inline]
+fn be_u48(i: &[u8]) -> IResult<&[u8], u64> {
+    if i.len() < 6 {
+        IResult::Err(Err::Incomplete(Needed::new(6)))
+    } else {
+        let res = ((i[0] as u64) << 40)
+            + ((i[1] as u64) << 32)
+            + ((i[2] as u64) << 24)
+            + ((i[3] as u64) << 16)
+            + ((i[4] as u64) << 8)
+            + i[5] as u64;
+        IResult::Ok((&i[6..], res))
+    }
+}
+
+/// An ITCH protocol message. Refer to the protocol spec for interpretation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Message {
+    /// Message Type
+    pub tag: u8,
+    /// Integer identifying the underlying instrument updated daily
+    pub stock_locate: u16,
+    /// NASDAQ internal tracking number
+    pub tracking_number: u16,
+    /// Nanoseconds since midnight
+    pub timestamp: u64,
+    /// Body of one of the supported message types
+    pub body: Body,
+}
+
+impl Message {
+    /// Sort key of `(timestamp, tracking_number, tag)`.
+    ///
+    /// Sorting by this key gives a deterministic order for messages
+    /// merged from several streams.
+    pub fn key(&self) -> (u64, u16, u8) {
+        (self.timestamp, self.tracking_number, self.tag)
+    }
+}
+
+/// The message body. Refer to the protocol spec for interpretation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Body {
+    AddOrder(AddOrder),
+    Breach(LevelBreached),
+    BrokenTrade {
+        match_number: u64,
+    },
+    CrossTrade(CrossTrade),
+    DeleteOrder {
+        reference: u64,
+    },
+    Imbalance(ImbalanceIndicator),
+    IpoQuotingPeriod(IpoQuotingPeriod),
+    LULDAuctionCollar {
+        stock: ArrayString8,
+        ref_price: Price4,
+        upper_price: Price4,
+        lower_price: Price4,
+        extension: u32,
+    },
+    MwcbDeclineLevel {
+        level1: Price8,
+        level2: Price8,
+        level3: Price8,
+    },
+    NonCrossTrade(NonCrossTrade),
+    OrderCancelled {
+        reference: u64,
+        cancelled: u32,
+    },
+    OrderExecuted {
+        reference: u64,
+        executed: u32,
+        match_number: u64,
+    },
+    OrderExecutedWithPrice {
+        reference: u64,
+        executed: u32,
+        match_number: u64,
+        printable: bool,
+        price: Price4,
+    },
+    ParticipantPosition(MarketParticipantPosition),
+    RegShoRestriction {
+        stock: ArrayString8,
+        action: RegShoAction,
+    },
+    ReplaceOrder(ReplaceOrder),
+    StockDirectory(StockDirectory),
+    SystemEvent {
+        event: EventCode,
+    },
+    TradingAction {
+        stock: ArrayString8,
+        trading_state: TradingState,
+        reason: ArrayString4,
+    },
+    RetailPriceImprovementIndicator(RetailPriceImprovementIndicator),
+    /// A message type outside the specification, see [`ParserRegistry`](crate::ParserRegistry)
+    Extension(Extension),
+}
+
+impl Message {
+    /// The message type, or `None` for an extension type outside the
+    /// specification
+    pub fn kind(&self) -> Option<Tag> {
+        Tag::from_code(self.tag)
+    }
+}
+
+impl Body {
+    /// The message type of the body, or `None` for an extension. Add
+    /// orders with an MPID are 'F' messages.
+    pub fn tag(&self) -> Option<Tag> {
+        use Body::*;
+        Some(match self {
+            AddOrder(v) if v.mpid.is_some() => Tag::AddOrderWithMpid,
+            AddOrder(_) => Tag::AddOrder,
+            Breach(_) => Tag::Breach,
+            BrokenTrade { .. } => Tag::BrokenTrade,
+            CrossTrade(_) => Tag::CrossTrade,
+            DeleteOrder { .. } => Tag::DeleteOrder,
+            Imbalance(_) => Tag::Imbalance,
+            IpoQuotingPeriod(_) => Tag::IpoQuotingPeriod,
+            LULDAuctionCollar { .. } => Tag::LULDAuctionCollar,
+            MwcbDeclineLevel { .. } => Tag::MwcbDeclineLevel,
+            NonCrossTrade(_) => Tag::NonCrossTrade,
+            OrderCancelled { .. } => Tag::OrderCancelled,
+            OrderExecuted { .. } => Tag::OrderExecuted,
+            OrderExecutedWithPrice { .. } => Tag::OrderExecutedWithPrice,
+            ParticipantPosition(_) => Tag::ParticipantPosition,
+            RegShoRestriction { .. } => Tag::RegShoRestriction,
+            ReplaceOrder(_) => Tag::ReplaceOrder,
+            StockDirectory(_) => Tag::StockDirectory,
+            SystemEvent { .. } => Tag::SystemEvent,
+            TradingAction { .. } => Tag::TradingAction,
+            RetailPriceImprovementIndicator(_) => Tag::RetailPriceImprovementIndicator,
+            Extension(_) => return None,
+        })
+    }
+
+    /// The stock symbol carried in the body, for message types which have one
+    pub fn stock(&self) -> Option<&ArrayString8> {
+        use Body::*;
+        match self {
+            AddOrder(v) => Some(&v.stock),
+            CrossTrade(v) => Some(&v.stock),
+            Imbalance(v) => Some(&v.stock),
+            IpoQuotingPeriod(v) => Some(&v.stock),
+            LULDAuctionCollar { stock, .. } => Some(stock),
+            NonCrossTrade(v) => Some(&v.stock),
+            ParticipantPosition(v) => Some(&v.stock),
+            RegShoRestriction { stock, .. } => Some(stock),
+            StockDirectory(v) => Some(&v.stock),
+            TradingAction { stock, .. } => Some(stock),
+            RetailPriceImprovementIndicator(v) => Some(&v.stock),
+            Breach(_)
+            | BrokenTrade { .. }
+            | DeleteOrder { .. }
+            | MwcbDeclineLevel { .. }
+            | OrderCancelled { .. }
+            | OrderExecuted { .. }
+            | OrderExecutedWithPrice { .. }
+            | ReplaceOrder(_)
+            | SystemEvent { .. }
+            | Extension(_) => None,
+        }
+    }
+}
+
+/// Parse a single message without its length prefix, e.g. from a
+/// transport packet which frames messages itself
+pub(crate) fn decode_message(input: &[u8]) -> Result<Message> {
+    let Some(&tag) = input.first() else {
+        return Err(Error::Parse("empty message".into()));
+    };
+    if !FeedProfile::TotalView.allows(tag) {
+        return Err(Error::Parse(format!(
+            "unknown message type '{}'",
+            tag as char
+        )));
+    }
+    match parse_unframed(input) {
+        Ok((_, msg)) => Ok(msg),
+        Err(Err::Incomplete(_)) => Err(Error::Parse(format!(
+            "truncated '{}' message of {} bytes",
+            tag as char,
+            input.len()
+        ))),
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => Err(Error::Parse(format!(
+            "{:?} in '{}' message",
+            e.code, tag as char
+        ))),
+    }
+}
+
+pub(crate) fn parse_message(input: &[u8]) -> IResult<&[u8], Message> {
+    let (input, _length) = be_u16(input)?;
+    parse_unframed(input)
+}
+
+pub(crate) fn parse_unframed(input: &[u8]) -> IResult<&[u8], Message> {
+    let (input, tag) = be_u8(input)?;
+    let (input, stock_locate) = be_u16(input)?;
+    let (input, tracking_number) = be_u16(input)?;
+    let (input, timestamp) = be_u48(input)?;
+    let (input, body) = match tag {
+        b'A' => {
+            let (input, add_order) = parse_add_order(input, false)?;
+            (input, Body::AddOrder(add_order))
+        }
+        b'B' => map(be_u64, |match_number| Body::BrokenTrade { match_number })(input)?,
+        b'C' => {
+            let (input, reference) = be_u64(input)?;
+            let (input, executed) = be_u32(input)?;
+            let (input, match_number) = be_u64(input)?;
+            let (input, printable) = char2bool(input)?;
+            let (input, price) = be_u32(input)?;
+            (
+                input,
+                Body::OrderExecutedWithPrice {
+                    reference,
+                    executed,
+                    match_number,
+                    printable,
+                    price: price.into(),
+                },
+            )
+        }
+        b'D' => map(be_u64, |reference| Body::DeleteOrder { reference })(input)?,
+        b'E' => {
+            let (input, reference) = be_u64(input)?;
+            let (input, executed) = be_u32(input)?;
+            let (input, match_number) = be_u64(input)?;
+            (
+                input,
+                Body::OrderExecuted {
+                    reference,
+                    executed,
+                    match_number,
+                },
+            )
+        }
+        b'F' => {
+            let (input, add_order) = parse_add_order(input, true)?;
+            (input, Body::AddOrder(add_order))
+        }
+        b'H' => parse_trading_action(input)?,
+        b'I' => map(parse_imbalance_indicator, Body::Imbalance)(input)?,
+        b'J' => {
+            let (input, stock) = stock(input)?;
+            let (input, ref_p) = be_u32(input)?;
+            let (input, upper_p) = be_u32(input)?;
+            let (input, lower_p) = be_u32(input)?;
+            let (input, extension) = be_u32(input)?;
+            (
+                input,
+                Body::LULDAuctionCollar {
+                    stock,
+                    ref_price: ref_p.into(),
+                    upper_price: upper_p.into(),
+                    lower_price: lower_p.into(),
+                    extension,
+                },
+            )
+        }
+        b'K' => map(parse_ipo_quoting_period, Body::IpoQuotingPeriod)(input)?,
+        b'L' => map(parse_participant_position, Body::ParticipantPosition)(input)?,
+        b'N' => map(
+            parse_retail_price_improvement_indicator,
+            Body::RetailPriceImprovementIndicator,
+        )(input)?,
+        b'P' => map(parse_noncross_trade, Body::NonCrossTrade)(input)?,
+        b'Q' => map(parse_cross_trade, Body::CrossTrade)(input)?,
+        b'R' => map(parse_stock_directory, Body::StockDirectory)(input)?,
+        b'S' => parse_system_event(input)?,
+        b'U' => map(parse_replace_order, Body::ReplaceOrder)(input)?,
+        b'V' => {
+            let (input, l1) = be_u64(input)?;
+            let (input, l2) = be_u64(input)?;
+            let (input, l3) = be_u64(input)?;
+            (
+                input,
+                Body::MwcbDeclineLevel {
+                    level1: l1.into(),
+                    level2: l2.into(),
+                    level3: l3.into(),
+                },
+            )
+        }
+        b'W' => map(map_opt(be_u8, LevelBreached::from_code), Body::Breach)(input)?,
+        b'X' => {
+            let (input, reference) = be_u64(input)?;
+            let (input, cancelled) = be_u32(input)?;
+            (
+                input,
+                Body::OrderCancelled {
+                    reference,
+                    cancelled,
+                },
+            )
+        }
+        b'Y' => parse_reg_sho_restriction(input)?,
+        _ => return Err(Err::Error(nom::error::Error::new(input, ErrorKind::Tag))),
+    };
+
+    Ok((
+        input,
+        Message {
+            tag,
+            stock_locate,
+            tracking_number,
+            timestamp,
+            body,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StockDirectory {
+    pub stock: ArrayString8,
+    pub market_category: MarketCategory,
+    pub financial_status: FinancialStatus,
+    pub round_lot_size: u32,
+    pub round_lots_only: bool,
+    pub issue_classification: IssueClassification,
+    pub issue_subtype: IssueSubType,
+    pub authenticity: bool,
+    pub short_sale_threshold: Option<bool>,
+    pub ipo_flag: Option<bool>,
+    pub luld_ref_price_tier: LuldRefPriceTier,
+    pub etp_flag: Option<bool>,
+    pub etp_leverage_factor: u32,
+    pub inverse_indicator: bool,
+}
+
+pub(crate) fn parse_system_event(input: &[u8]) -> IResult<&[u8], Body> {
+    let (input, event_code) = map_opt(be_u8, EventCode::from_code)(input)?;
+
+    Ok((input, Body::SystemEvent { event: event_code }))
+}
+
+pub(crate) fn parse_stock_directory(input: &[u8]) -> IResult<&[u8], StockDirectory> {
+    let (input, stock) = stock(input)?;
+    let (input, market_category) = map_opt(be_u8, MarketCategory::from_code)(input)?;
+    let (input, financial_status) = map_opt(be_u8, FinancialStatus::from_code)(input)?;
+    let (input, round_lot_size) = be_u32(input)?;
+    let (input, round_lots_only) = char2bool(input)?;
+    let (input, issue_classification) = parse_issue_classification(input)?;
+    let (input, issue_subtype) = parse_issue_subtype(input)?;
+    let (input, authenticity) = alt((map(char('P'), |_| true), map(char('T'), |_| false)))(input)?;
+    let (input, short_sale_threshold) = maybe_char2bool(input)?;
+    let (input, ipo_flag) = maybe_char2bool(input)?;
+    let (input, luld_ref_price_tier) = map_opt(be_u8, LuldRefPriceTier::from_code)(input)?;
+    let (input, etp_flag) = parse_etp_flag(input)?;
+    let (input, etp_leverage_factor) = be_u32(input)?;
+    let (input, inverse_indicator) = char2bool(input)?;
+
+    Ok((
+        input,
+        StockDirectory {
+            stock,
+            market_category,
+            financial_status,
+            round_lot_size,
+            round_lots_only,
+            issue_classification,
+            issue_subtype,
+            authenticity,
+            short_sale_threshold,
+            ipo_flag,
+            luld_ref_price_tier,
+            etp_flag,
+            etp_leverage_factor,
+            inverse_indicator,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MarketParticipantPosition {
+    pub mpid: ArrayString4,
+    pub stock: ArrayString8,
+    pub primary_market_maker: bool,
+    pub market_maker_mode: MarketMakerMode,
+    pub market_participant_state: MarketParticipantState,
+}
+
+pub(crate) fn parse_participant_position(
+    input: &[u8],
+) -> IResult<&[u8], MarketParticipantPosition> {
+    let (input, mpid) = alpha(input)?;
+    let (input, stock) = stock(input)?;
+    let (input, primary_market_maker) = char2bool(input)?;
+    let (input, market_maker_mode) = map_opt(be_u8, MarketMakerMode::from_code)(input)?;
+    let (input, market_participant_state) =
+        map_opt(be_u8, MarketParticipantState::from_code)(input)?;
+
+    Ok((
+        input,
+        MarketParticipantPosition {
+            mpid,
+            stock,
+            primary_market_maker,
+            market_maker_mode,
+            market_participant_state,
+        },
+    ))
+}
+
+pub(crate) fn parse_reg_sho_restriction(input: &[u8]) -> IResult<&[u8], Body> {
+    let (input, stock) = stock(input)?;
+    let (input, action) = map_opt(be_u8, RegShoAction::from_code)(input)?;
+
+    Ok((input, Body::RegShoRestriction { stock, action }))
+}
+
+pub(crate) fn parse_trading_action(input: &[u8]) -> IResult<&[u8], Body> {
+    let (input, stock) = stock(input)?;
+    let (input, trading_state) = map_opt(be_u8, TradingState::from_code)(input)?;
+    let (input, _) = be_u8(input)?; // skip reserved byte
+    let (input, reason) = alpha(input)?;
+
+    Ok((
+        input,
+        Body::TradingAction {
+            stock,
+            trading_state,
+            reason,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AddOrder {
+    pub reference: u64,
+    pub side: Side,
+    pub shares: u32,
+    pub stock: ArrayString8,
+    pub price: Price4,
+    pub mpid: Option<ArrayString4>,
+}
+
+pub(crate) fn parse_add_order(input: &[u8], attribution: bool) -> IResult<&[u8], AddOrder> {
+    let (input, reference) = be_u64(input)?;
+    let (input, side) = map_opt(be_u8, Side::from_code)(input)?;
+    let (input, shares) = be_u32(input)?;
+    let (input, stock) = stock(input)?;
+    let (input, price) = be_u32(input)?;
+    let (input, mpid) = match attribution {
+        true => map(alpha, Some)(input),
+        false => Ok((input, None)),
+    }?;
+
+    Ok((
+        input,
+        AddOrder {
+            reference,
+            side,
+            shares,
+            stock,
+            price: price.into(),
+            mpid,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplaceOrder {
+    pub old_reference: u64,
+    pub new_reference: u64,
+    pub shares: u32,
+    pub price: Price4,
+}
+
+pub(crate) fn parse_replace_order(input: &[u8]) -> IResult<&[u8], ReplaceOrder> {
+    let (input, old_reference) = be_u64(input)?;
+    let (input, new_reference) = be_u64(input)?;
+    let (input, shares) = be_u32(input)?;
+    let (input, price) = be_u32(input)?;
+
+    Ok((
+        input,
+        ReplaceOrder {
+            old_reference,
+            new_reference,
+            shares,
+            price: price.into(),
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImbalanceIndicator {
+    pub paired_shares: u64,
+    pub imbalance_shares: u64,
+    pub imbalance_direction: ImbalanceDirection,
+    pub stock: ArrayString8,
+    pub far_price: Price4,
+    pub near_price: Price4,
+    pub current_ref_price: Price4,
+    pub cross_type: CrossType,
+    pub price_variation_indicator: char, // TODO encode as enum somehow
+}
+
+pub(crate) fn parse_imbalance_indicator(input: &[u8]) -> IResult<&[u8], ImbalanceIndicator> {
+    let (input, paired_shares) = be_u64(input)?;
+    let (input, imbalance_shares) = be_u64(input)?;
+    let (input, imbalance_direction) = map_opt(be_u8, ImbalanceDirection::from_code)(input)?;
+    let (input, stock) = stock(input)?;
+    let (input, far_price) = be_u32(input)?;
+    let (input, near_price) = be_u32(input)?;
+    let (input, current_ref_price) = be_u32(input)?;
+    let (input, cross_type) = alt((
+        map(char('O'), |_| CrossType::Opening),
+        map(char('C'), |_| CrossType::Closing),
+        map(char('H'), |_| CrossType::IpoOrHalted),
+        map(char('A'), |_| CrossType::ExtendedTradingClose),
+    ))(input)?;
+    let (input, price_variation_indicator) = be_u8(input)?;
+
+    Ok((
+        input,
+        ImbalanceIndicator {
+            paired_shares,
+            imbalance_shares,
+            imbalance_direction,
+            stock,
+            far_price: far_price.into(),
+            near_price: near_price.into(),
+            current_ref_price: current_ref_price.into(),
+            cross_type,
+            price_variation_indicator: price_variation_indicator as char,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrossTrade {
+    pub shares: u64,
+    pub stock: ArrayString8,
+    pub cross_price: Price4,
+    pub match_number: u64,
+    pub cross_type: CrossType,
+}
+
+pub(crate) fn parse_cross_trade(input: &[u8]) -> IResult<&[u8], CrossTrade> {
+    let (input, shares) = be_u64(input)?;
+    let (input, stock) = stock(input)?;
+    let (input, price) = be_u32(input)?;
+    let (input, match_number) = be_u64(input)?;
+    let (input, cross_type) = map_opt(be_u8, CrossType::from_code)(input)?;
+
+    Ok((
+        input,
+        CrossTrade {
+            shares,
+            stock,
+            cross_price: price.into(),
+            match_number,
+            cross_type,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RetailPriceImprovementIndicator {
+    pub stock: ArrayString8,
+    pub interest_flag: InterestFlag,
+}
+
+pub(crate) fn parse_retail_price_improvement_indicator(
+    input: &[u8],
+) -> IResult<&[u8], RetailPriceImprovementIndicator> {
+    let (input, stock) = stock(input)?;
+    let (input, interest_flag) = map_opt(be_u8, InterestFlag::from_code)(input)?;
+
+    Ok((
+        input,
+        RetailPriceImprovementIndicator {
+            stock,
+            interest_flag,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NonCrossTrade {
+    pub reference: u64,
+    pub side: Side,
+    pub shares: u32,
+    pub stock: ArrayString8,
+    pub price: Price4,
+    pub match_number: u64,
+}
+
+pub(crate) fn parse_noncross_trade(input: &[u8]) -> IResult<&[u8], NonCrossTrade> {
+    let (input, reference) = be_u64(input)?;
+    let (input, side) = map_opt(be_u8, Side::from_code)(input)?;
+    let (input, shares) = be_u32(input)?;
+    let (input, stock) = stock(input)?;
+    let (input, price) = be_u32(input)?;
+    let (input, match_number) = be_u64(input)?;
+
+    Ok((
+        input,
+        NonCrossTrade {
+            reference,
+            side,
+            shares,
+            stock,
+            price: price.into(),
+            match_number,
+        },
+    ))
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IpoQuotingPeriod {
+    pub stock: ArrayString8,
+    pub release_time: u32,
+    pub release_qualifier: IpoReleaseQualifier,
+    pub price: Price4,
+}
+
+pub(crate) fn parse_ipo_quoting_period(input: &[u8]) -> IResult<&[u8], IpoQuotingPeriod> {
+    let (input, stock) = stock(input)?;
+    let (input, release_time) = be_u32(input)?;
+    let (input, release_qualifier) = map_opt(be_u8, IpoReleaseQualifier::from_code)(input)?;
+    let (input, price) = be_u32(input)?;
+
+    Ok((
+        input,
+        IpoQuotingPeriod {
+            stock,
+            release_time,
+            release_qualifier,
+            price: price.into(),
+        },
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn hex_to_bytes(bytes: &[u8]) -> Vec<u8> {
+        fn h2b(h: u8) -> Option<u8> {
+            match h {
+                v @ b'0'..=b'9' => Some(v - b'0'),
+                v @ b'a'..=b'f' => Some(v - b'a' + 10),
+                b' ' | b'\n' => None,
+                _ => panic!("Invalid hex: {}", h as char),
+            }
+        }
+        bytes
+            .iter()
+            .filter_map(|b| h2b(*b))
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|slice| (slice[0] << 4) + slice[1])
+            .collect()
+    }
+
+    #[test]
+    fn system_event() {
+        let code = b"4f";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_system_event(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn stock_directory() {
+        let code = b"41 2020 2020 2020 204e 2000
+                     0000 644e 435a 2050 4e20 314e 0000 0000 4e";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_stock_directory(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn market_participant_position() {
+        let code = b"41 44 41 4d 42 42 52 59 20 20 20 20 59 4e 41";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_participant_position(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn add_order() {
+        let code = b"00 00 00 00 00 00 05 84 42 00 00 00 64 5a 58 5a 5a 54 20 20 20 00 00 27 10";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_add_order(&bytes[..], false).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn add_order_with_attr() {
+        // same code as in add_order test with 4 additional `10` bytes
+        let code = b"00 00 00 00 00 00 05 84 42 00 00 00 64 5a 58 5a 5a 54 20 20 20 00 00 27 10 10 10 10 10";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_add_order(&bytes[..], true).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn check_sizeof() {
+        assert_eq!(std::mem::size_of::<Message>(), 72)
+    }
+
+    #[test]
+    fn test_imbalance() {
+        let code = b"00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 4f 48 49 42 42 20 20 20 20
+                     00 00 00 00 00 00 00 00 00 00 00 00 43 20";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_imbalance_indicator(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn test_cross_trade() {
+        let code = b"00 00 00 00 00 00 00 00 45 53 53 41 20 20 20 20 00 00
+                    00 00 00 00 00 00 00 00 03 c0 43";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_cross_trade(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn test_retail_price_improvement_indicator() {
+        let code = b"45 53 53 41 20 20 20 20 4e";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_retail_price_improvement_indicator(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn test_noncross_trade() {
+        let code = b"00 00 00 00 00 00 00 00 42 00 00 0b b8 4e 55 47 54 20
+                     20 20 20 00 01 93 e8 00 00 00 00 00 00 41 7f";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_noncross_trade(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn test_ipo_release() {
+        let code = b"5a 57 5a 5a 54 20 20 20 00 00 89 1c 41 00 01 86 a0";
+        let bytes = hex_to_bytes(&code[..]);
+        let (rest, _) = parse_ipo_quoting_period(&bytes[..]).unwrap();
+        assert_eq!(rest.len(), 0);
+    }
+
+    #[test]
+    fn test_message_key_ordering() {
+        let msg = |timestamp, tracking_number, tag| Message {
+            tag,
+            stock_locate: 0,
+            tracking_number,
+            timestamp,
+            body: Body::Breach(LevelBreached::L1),
+        };
+        let mut msgs = [msg(2, 0, b'W'), msg(1, 5, b'W'), msg(1, 2, b'W')];
+        msgs.sort_by_key(Message::key);
+        assert_eq!(
+            msgs.iter().map(Message::key).collect::<Vec<_>>(),
+            [(1, 2, b'W'), (1, 5, b'W'), (2, 0, b'W')]
+        );
+        assert!(Price4::from(100) < Price4::from(200));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let msg = Message {
+            tag: 123,
+            stock_locate: 234,
+            tracking_number: 321,
+            timestamp: 3333,
+            body: Body::Breach(LevelBreached::L1),
+        };
+        let blob = serde_json::to_string(&msg).unwrap();
+        let msg_2 = serde_json::from_str(&blob).unwrap();
+        assert_eq!(msg, msg_2);
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::WatchdogEvent;
+
+/// Receives counter updates from a [`MessageStream`](crate::MessageStream).
+///
+/// Methods take `&self` so that a registry can be shared (e.g. behind an
+/// `Arc`) between the stream and whatever exports the values.
+pub trait MetricsRegistry: Send + Sync {
+    /// A message with the given tag was decoded
+    fn record_message(&self, tag: u8);
+    /// Bytes were read from the underlying reader
+    fn record_bytes(&self, ct: usize);
+    /// A parse error was reported
+    fn record_parse_error(&self);
+    /// Messages were missed, as detected from sequence numbers
+    fn record_gap(&self, missed: u64);
+    /// A [`Watchdog`](crate::Watchdog) saw the health of a feed change
+    fn record_feed_event(&self, _event: &WatchdogEvent) {}
+}
+
+/// Lock-free counters rendered in the Prometheus text exposition format
+#[derive(Debug)]
+pub struct PrometheusMetrics {
+    messages: [AtomicU64; 256],
+    bytes: AtomicU64,
+    parse_errors: AtomicU64,
+    gaps: AtomicU64,
+    missed: AtomicU64,
+    feed_stale: AtomicU64,
+    feed_down: AtomicU64,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        PrometheusMetrics {
+            messages: std::array::from_fn(|_| AtomicU64::new(0)),
+            bytes: AtomicU64::new(0),
+            parse_errors: AtomicU64::new(0),
+            gaps: AtomicU64::new(0),
+            missed: AtomicU64::new(0),
+            feed_stale: AtomicU64::new(0),
+            feed_down: AtomicU64::new(0),
+        }
+    }
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> PrometheusMetrics {
+        PrometheusMetrics::default()
+    }
+
+    /// Number of messages decoded with the given tag
+    pub fn messages(&self, tag: u8) -> u64 {
+        self.messages[tag as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn parse_errors(&self) -> u64 {
+        self.parse_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn gaps(&self) -> u64 {
+        self.gaps.load(Ordering::Relaxed)
+    }
+
+    /// Render all counters in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP itchy_messages_total Messages decoded, by message type\n");
+        out.push_str("# TYPE itchy_messages_total counter\n");
+        for (tag, ct) in self.messages.iter().enumerate() {
+            let ct = ct.load(Ordering::Relaxed);
+            if ct > 0 {
+                let _ = writeln!(
+                    out,
+                    "itchy_messages_total{{tag=\"{}\"}} {}",
+                    (tag as u8).escape_ascii(),
+                    ct
+                );
+            }
+        }
+        let counters = [
+            (
+                "itchy_bytes_total",
+                "Bytes read from the source",
+                &self.bytes,
+            ),
+            (
+                "itchy_parse_errors_total",
+                "Parse errors reported",
+                &self.parse_errors,
+            ),
+            ("itchy_gaps_total", "Sequence gaps detected", &self.gaps),
+            (
+                "itchy_missed_messages_total",
+                "Messages missing in sequence gaps",
+                &self.missed,
+            ),
+            (
+                "itchy_feed_stale_total",
+                "Times a watched feed went without data or heartbeats",
+                &self.feed_stale,
+            ),
+            (
+                "itchy_feed_down_total",
+                "Times a watched feed went without packets",
+                &self.feed_down,
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+        out
+    }
+}
+
+impl MetricsRegistry for PrometheusMetrics {
+    fn record_message(&self, tag: u8) {
+        self.messages[tag as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_bytes(&self, ct: usize) {
+        self.bytes.fetch_add(ct as u64, Ordering::Relaxed);
+    }
+
+    fn record_parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_gap(&self, missed: u64) {
+        self.gaps.fetch_add(1, Ordering::Relaxed);
+        self.missed.fetch_add(missed, Ordering::Relaxed);
+    }
+
+    fn record_feed_event(&self, event: &WatchdogEvent) {
+        match event {
+            WatchdogEvent::FeedStale { .. } => self.feed_stale.fetch_add(1, Ordering::Relaxed),
+            WatchdogEvent::FeedDown { .. } => self.feed_down.fetch_add(1, Ordering::Relaxed),
+            WatchdogEvent::Recovered { .. } => return,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+    use std::sync::Arc;
+
+    #[test]
+    fn counts_stream_activity() {
+        let buf: &[u8] = &[
+            0, 0xc, 0x53, 0, 0, 0, 0, 0x28, 0x6a, 0xab, 0x3b, 0x3a, 0x99, 0x4f, 0, 0xc, 0x53,
+        ];
+        let metrics = Arc::new(PrometheusMetrics::new());
+        let mut stream = MessageStream::from_reader(buf);
+        stream.set_metrics(metrics.clone());
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().unwrap().is_err());
+
+        assert_eq!(metrics.messages(b'S'), 1);
+        assert_eq!(metrics.bytes(), 17);
+        assert_eq!(metrics.parse_errors(), 1);
+        let text = metrics.render();
+        assert!(text.contains("itchy_messages_total{tag=\"S\"} 1\n"));
+        assert!(text.contains("itchy_bytes_total 17\n"));
+    }
+
+    #[test]
+    fn counts_skipped_errors() {
+        use crate::messages::tests::hex_to_bytes;
+        use crate::{ErrorPolicy, MessageStreamBuilder};
+
+        // a frame of an unknown type, then a system event
+        let buf = hex_to_bytes(
+            b"000c 7a00 0000 0028 6aab 3b3a 994f
+              000c 5300 0000 0028 6aab 3b3a 994f",
+        );
+        let metrics = Arc::new(PrometheusMetrics::new());
+        let mut stream = MessageStreamBuilder::new()
+            .error_policy(ErrorPolicy::SkipMessage)
+            .build(&buf[..]);
+        stream.set_metrics(metrics.clone());
+        let results: Vec<_> = stream.map(|m| m.is_ok()).collect();
+        assert_eq!(results, [false, true]);
+        assert_eq!(metrics.parse_errors(), 1);
+
+        // a Nasdaq Basic frame longer than the largest buffer
+        let mut buf = hex_to_bytes(b"ffff 7100");
+        buf.resize(1024, 0);
+        let mut stream = MessageStreamBuilder::new()
+            .profile(crate::FeedProfile::Basic)
+            .buffer_size(256)
+            .max_buffer_size(256)
+            .build(&buf[..]);
+        stream.set_metrics(metrics.clone());
+        let err = stream.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("does not fit"), "{}", err);
+        assert_eq!(metrics.parse_errors(), 2);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{decode_message, Envelope, Error, Result, SessionId};
+
+const HEADER_LEN: usize = 20;
+const END_OF_SESSION: u16 = 0xffff;
+
+/// A MoldUDP64 downstream packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MoldPacket<'a> {
+    pub session: SessionId,
+    /// Sequence number of the first message in the packet
+    pub sequence: u64,
+    /// Number of messages in the packet
+    pub count: u16,
+    blocks: &'a [u8],
+}
+
+impl<'a> MoldPacket<'a> {
+    pub fn parse(packet: &'a [u8]) -> Result<MoldPacket<'a>> {
+        if packet.len() < HEADER_LEN {
+            return Err(Error::Parse(format!(
+                "MoldUDP64 packet of {} bytes is shorter than its header",
+                packet.len()
+            )));
+        }
+        let session = std::str::from_utf8(&packet[..10])
+            .ok()
+            .and_then(|s| SessionId::from(s).ok())
+            .ok_or_else(|| Error::Parse("invalid MoldUDP64 session".into()))?;
+        let mut sequence = [0; 8];
+        sequence.copy_from_slice(&packet[10..18]);
+        let mold = MoldPacket {
+            session,
+            sequence: u64::from_be_bytes(sequence),
+            count: u16::from_be_bytes([packet[18], packet[19]]),
+            blocks: &packet[HEADER_LEN..],
+        };
+        if mold
+            .sequence
+            .checked_add(mold.message_count() as u64)
+            .is_none()
+        {
+            return Err(Error::Parse(format!(
+                "MoldUDP64 sequence number {} out of range",
+                mold.sequence
+            )));
+        }
+        Ok(mold)
+    }
+
+    /// A heartbeat carries no messages
+    pub fn is_heartbeat(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn is_end_of_session(&self) -> bool {
+        self.count == END_OF_SESSION
+    }
+
+    /// Sequence number expected in the next packet of the session
+    pub fn next_sequence(&self) -> u64 {
+        // parsed packets cannot overflow, but the fields are public
+        self.sequence.saturating_add(self.message_count() as u64)
+    }
+
+    fn message_count(&self) -> u16 {
+        if self.is_end_of_session() {
+            0
+        } else {
+            self.count
+        }
+    }
+
+    /// The raw message blocks, without their length prefixes
+    pub fn messages(&self) -> impl Iterator<Item = Result<&'a [u8]>> + 'a {
+        let mut rest = self.blocks;
+        let mut remaining = self.message_count();
+        std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            remaining -= 1;
+            let block = match *rest {
+                [hi, lo, ref tail @ ..] if tail.len() >= u16::from_be_bytes([hi, lo]) as usize => {
+                    let (block, tail) = tail.split_at(u16::from_be_bytes([hi, lo]) as usize);
+                    rest = tail;
+                    Ok(block)
+                }
+                _ => {
+                    remaining = 0;
+                    Err(Error::Parse("truncated MoldUDP64 message block".into()))
+                }
+            };
+            Some(block)
+        })
+    }
+
+    /// Decode the messages in the packet, numbering them from the packet
+    /// sequence number
+    pub fn envelopes(
+        &self,
+        receive_ts: Option<u64>,
+    ) -> impl Iterator<Item = Result<Envelope>> + 'a {
+        let session = self.session;
+        let sequence = self.sequence;
+        self.messages().enumerate().map(move |(ix, block)| {
+            Ok(Envelope {
+                session,
+                sequence: sequence + ix as u64,
+                receive_ts,
+                message: decode_message(block?)?,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::Body;
+
+    /// A MoldUDP64 packet holding the given messages
+    pub(crate) fn mold_packet(session: &str, sequence: u64, messages: &[&[u8]]) -> Vec<u8> {
+        let mut packet = format!("{:<10}", session).into_bytes();
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        packet.extend_from_slice(&(messages.len() as u16).to_be_bytes());
+        for msg in messages {
+            packet.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+            packet.extend_from_slice(msg);
+        }
+        packet
+    }
+
+    #[test]
+    fn decodes_packet() {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        let packet = mold_packet("SESSION1", 41, &[&event, &event]);
+        let mold = MoldPacket::parse(&packet).unwrap();
+        assert_eq!(mold.session.as_str(), "SESSION1  ");
+        assert_eq!(mold.next_sequence(), 43);
+        let envelopes: Vec<_> = mold.envelopes(Some(7)).collect::<Result<_>>().unwrap();
+        assert_eq!(envelopes.len(), 2);
+        assert_eq!(envelopes[1].sequence, 42);
+        assert_eq!(envelopes[1].receive_ts, Some(7));
+        assert!(matches!(
+            envelopes[0].message.body,
+            Body::SystemEvent { .. }
+        ));
+
+        let truncated = MoldPacket::parse(&packet[..packet.len() - 1]).unwrap();
+        assert!(truncated.envelopes(None).nth(1).unwrap().is_err());
+        let end = mold_packet("SESSION1", 43, &[]);
+        let mut end = MoldPacket::parse(&end).unwrap();
+        end.count = END_OF_SESSION;
+        assert!(end.is_end_of_session());
+        assert_eq!(end.messages().count(), 0);
+
+        let wrapping = mold_packet("SESSION1", u64::MAX, &[&event]);
+        assert!(MoldPacket::parse(&wrapping).is_err());
+    }
+}
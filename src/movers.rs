@@ -0,0 +1,199 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::trades::DEFAULT_ROUND_LOT;
+use crate::{ArrayString8, Body, LotKind, Message, OrderTracker, OrderUpdate, Price4};
+
+/// Measure of activity used to rank symbols
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Activity {
+    /// Number of messages referring to the symbol
+    Messages,
+    /// Number of shares executed, including non-displayed and cross trades
+    Volume,
+    /// Traded value of executed shares
+    Notional,
+}
+
+/// Activity counters for a single symbol
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SymbolActivity {
+    pub stock_locate: u16,
+    pub stock: Option<ArrayString8>,
+    pub messages: u64,
+    pub volume: u64,
+    /// Traded value in units of 1/10,000 of a dollar (the `Price4` scale)
+    pub notional: u128,
+    /// Shares executed in trades smaller than a round lot
+    pub odd_lot_volume: u64,
+    /// Shares executed in trades of more than a round lot which are not a
+    /// whole number of round lots
+    pub mixed_lot_volume: u64,
+}
+
+impl SymbolActivity {
+    /// Traded value in dollars
+    pub fn notional_f64(&self) -> f64 {
+        self.notional as f64 / Price4::SCALE as f64
+    }
+
+    fn rank(&self, by: Activity) -> u128 {
+        match by {
+            Activity::Messages => self.messages as u128,
+            Activity::Volume => self.volume as u128,
+            Activity::Notional => self.notional,
+        }
+    }
+
+    /// Shares executed in trades of whole round lots
+    pub fn round_lot_volume(&self) -> u64 {
+        self.volume - self.odd_lot_volume - self.mixed_lot_volume
+    }
+
+    fn trade(&mut self, shares: u64, price: Price4, round_lot: u32) {
+        match LotKind::classify(shares, round_lot) {
+            LotKind::Odd => self.odd_lot_volume += shares,
+            LotKind::Mixed => self.mixed_lot_volume += shares,
+            LotKind::Round => {}
+        }
+        self.volume += shares;
+        self.notional += shares as u128 * price.raw() as u128;
+    }
+}
+
+/// Online accumulator of the most active symbols.
+///
+/// Can be updated message by message and queried at any point of a replay.
+/// Volume is split by [`LotKind`] using each symbol's round lot from its
+/// stock directory message, 100 shares until one is seen.
+#[derive(Debug, Clone, Default)]
+pub struct TopMovers {
+    orders: OrderTracker,
+    symbols: HashMap<u16, SymbolActivity>,
+    round_lots: HashMap<u16, u32>,
+}
+
+impl TopMovers {
+    pub fn new() -> TopMovers {
+        TopMovers::default()
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        // market-wide messages are not attributed to a symbol
+        if msg.stock_locate == 0 {
+            return;
+        }
+        let update = self.orders.apply(msg).and_then(Result::ok);
+        if let Body::StockDirectory(ref directory) = msg.body {
+            self.round_lots
+                .insert(msg.stock_locate, directory.round_lot_size);
+        }
+        let round_lot = self
+            .round_lots
+            .get(&msg.stock_locate)
+            .copied()
+            .unwrap_or(DEFAULT_ROUND_LOT);
+        let activity = self
+            .symbols
+            .entry(msg.stock_locate)
+            .or_insert_with(|| SymbolActivity {
+                stock_locate: msg.stock_locate,
+                ..Default::default()
+            });
+        activity.messages += 1;
+        if let Some(stock) = msg.body.stock() {
+            activity.stock.get_or_insert(*stock);
+        }
+        match msg.body {
+            Body::NonCrossTrade(ref trade) => {
+                activity.trade(trade.shares as u64, trade.price, round_lot)
+            }
+            Body::CrossTrade(ref cross) => {
+                activity.trade(cross.shares, cross.cross_price, round_lot)
+            }
+            _ => {}
+        }
+        if let Some(OrderUpdate::Executed {
+            shares,
+            price,
+            printable,
+            ..
+        }) = update
+        {
+            // non-printable executions are reported again in a cross trade
+            if printable {
+                activity.trade(shares as u64, price, round_lot);
+            }
+        }
+    }
+
+    /// Activity of a single symbol, by stock locate
+    pub fn get(&self, locate: u16) -> Option<&SymbolActivity> {
+        self.symbols.get(&locate)
+    }
+
+    /// The `n` most active symbols, most active first. Ties are broken by locate.
+    pub fn top(&self, n: usize, by: Activity) -> Vec<&SymbolActivity> {
+        let mut all: Vec<_> = self.symbols.values().collect();
+        let key = |a: &&SymbolActivity| (Reverse(a.rank(by)), a.stock_locate);
+        if n < all.len() {
+            all.select_nth_unstable_by_key(n, key);
+            all.truncate(n);
+        }
+        all.sort_unstable_by_key(key);
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::add;
+    use crate::Side;
+
+    fn msg(locate: u16, body: Body) -> Message {
+        Message {
+            tag: 0,
+            stock_locate: locate,
+            tracking_number: 0,
+            timestamp: 0,
+            body,
+        }
+    }
+
+    #[test]
+    fn ranks_symbols() {
+        let mut movers = TopMovers::new();
+        movers.update(&msg(1, add(1, Side::Buy, 100, 10_000)));
+        movers.update(&msg(1, add(2, Side::Buy, 100, 10_000)));
+        movers.update(&msg(1, add(3, Side::Buy, 100, 10_000)));
+        movers.update(&msg(2, add(4, Side::Sell, 500, 20_000)));
+        let exec = Body::OrderExecuted {
+            reference: 4,
+            executed: 300,
+            match_number: 1,
+        };
+        movers.update(&msg(2, exec));
+        let mixed = Body::OrderExecuted {
+            reference: 4,
+            executed: 150,
+            match_number: 2,
+        };
+        movers.update(&msg(2, mixed));
+
+        let by_msgs: Vec<_> = movers
+            .top(5, Activity::Messages)
+            .iter()
+            .map(|a| a.stock_locate)
+            .collect();
+        assert_eq!(by_msgs, [1, 2]);
+        let by_volume = movers.top(1, Activity::Volume);
+        assert_eq!(by_volume.len(), 1);
+        assert_eq!(by_volume[0].stock_locate, 2);
+        assert_eq!(by_volume[0].volume, 450);
+        assert_eq!(by_volume[0].round_lot_volume(), 300);
+        assert_eq!(by_volume[0].mixed_lot_volume, 150);
+        assert_eq!(movers.get(2).unwrap().notional_f64(), 900.0);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Body, LevelBreached, Message, Price8};
+
+/// Market-wide circuit breaker decline levels for the session
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeclineLevels {
+    pub level1: Price8,
+    pub level2: Price8,
+    pub level3: Price8,
+    /// Timestamp of the message that set these levels
+    pub timestamp: u64,
+}
+
+/// A single breach of a decline level
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Breach {
+    pub level: LevelBreached,
+    pub timestamp: u64,
+}
+
+/// Market-wide circuit breaker (MWCB) state, derived from the
+/// Decline Level ('V') and Breach ('W') messages of a session
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CircuitBreakerState {
+    levels: Option<DeclineLevels>,
+    breaches: Vec<Breach>,
+}
+
+impl CircuitBreakerState {
+    pub fn new() -> CircuitBreakerState {
+        CircuitBreakerState::default()
+    }
+
+    /// Apply a message. Returns `true` if the state changed.
+    pub fn update(&mut self, msg: &Message) -> bool {
+        match msg.body {
+            Body::MwcbDeclineLevel {
+                level1,
+                level2,
+                level3,
+            } => {
+                self.levels = Some(DeclineLevels {
+                    level1,
+                    level2,
+                    level3,
+                    timestamp: msg.timestamp,
+                });
+                true
+            }
+            Body::Breach(level) => {
+                self.breaches.push(Breach {
+                    level,
+                    timestamp: msg.timestamp,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The most recently disseminated decline levels, if any
+    pub fn levels(&self) -> Option<&DeclineLevels> {
+        self.levels.as_ref()
+    }
+
+    /// All breaches seen so far, in the order they were received
+    pub fn breaches(&self) -> &[Breach] {
+        &self.breaches
+    }
+
+    /// The most severe level breached so far
+    pub fn highest_breach(&self) -> Option<LevelBreached> {
+        self.breaches.iter().map(|b| b.level).max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(timestamp: u64, body: Body) -> Message {
+        Message {
+            tag: 0,
+            stock_locate: 0,
+            tracking_number: 0,
+            timestamp,
+            body,
+        }
+    }
+
+    #[test]
+    fn tracks_levels_and_breaches() {
+        let mut state = CircuitBreakerState::new();
+        assert!(state.levels().is_none());
+        assert!(state.highest_breach().is_none());
+
+        let levels = Body::MwcbDeclineLevel {
+            level1: 100.into(),
+            level2: 200.into(),
+            level3: 300.into(),
+        };
+        assert!(state.update(&msg(10, levels)));
+        assert!(state.update(&msg(20, Body::Breach(LevelBreached::L2))));
+        assert!(state.update(&msg(30, Body::Breach(LevelBreached::L1))));
+        assert!(!state.update(&msg(40, Body::DeleteOrder { reference: 1 })));
+
+        assert_eq!(state.levels().unwrap().level2, 200.into());
+        assert_eq!(state.levels().unwrap().timestamp, 10);
+        assert_eq!(state.breaches().len(), 2);
+        assert_eq!(state.breaches()[0].timestamp, 20);
+        assert_eq!(state.highest_breach(), Some(LevelBreached::L2));
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{ArrayString4, ArrayString8, Body, Message, Price4, Side};
+
+/// A live order, as reconstructed from order messages
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Order {
+    pub stock_locate: u16,
+    pub stock: ArrayString8,
+    pub side: Side,
+    pub price: Price4,
+    /// Shares remaining on the book
+    pub shares: u32,
+    /// Attribution, for orders added with an 'F' message
+    pub mpid: Option<ArrayString4>,
+    /// Timestamp at which the order (or its replacement) was added
+    pub timestamp: u64,
+}
+
+/// The effect of an order message on an [`OrderTracker`].
+///
+/// Each variant carries the state of the affected order *before* the
+/// message was applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderUpdate {
+    Added {
+        reference: u64,
+        order: Order,
+    },
+    Executed {
+        reference: u64,
+        order: Order,
+        shares: u32,
+        /// Execution price; differs from the order price for 'C' messages
+        price: Price4,
+        match_number: u64,
+        printable: bool,
+    },
+    Cancelled {
+        reference: u64,
+        order: Order,
+        shares: u32,
+    },
+    Deleted {
+        reference: u64,
+        order: Order,
+    },
+    Replaced {
+        old_reference: u64,
+        new_reference: u64,
+        old: Order,
+        new: Order,
+    },
+}
+
+impl OrderUpdate {
+    /// True if the order is no longer live after this update
+    pub fn is_terminal(&self) -> bool {
+        match *self {
+            OrderUpdate::Added { .. } => false,
+            OrderUpdate::Executed { order, shares, .. }
+            | OrderUpdate::Cancelled { order, shares, .. } => shares >= order.shares,
+            OrderUpdate::Deleted { .. } | OrderUpdate::Replaced { .. } => true,
+        }
+    }
+}
+
+/// An order message inconsistent with the tracked state
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderError {
+    /// The message refers to an order which is not live
+    UnknownReference { reference: u64 },
+    /// More shares were executed or cancelled than remained on the order
+    Overfill {
+        reference: u64,
+        remaining: u32,
+        requested: u32,
+    },
+    /// An order was added with a reference which is already live
+    DuplicateReference { reference: u64 },
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OrderError::UnknownReference { reference } => {
+                write!(f, "unknown order reference {}", reference)
+            }
+            OrderError::Overfill {
+                reference,
+                remaining,
+                requested,
+            } => write!(
+                f,
+                "order {} has {} shares remaining but {} were removed",
+                reference, remaining, requested
+            ),
+            OrderError::DuplicateReference { reference } => {
+                write!(f, "order reference {} is already live", reference)
+            }
+        }
+    }
+}
+
+/// Tracks the lifetime of every live order by reference number
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct OrderTracker {
+    orders: HashMap<u64, Order>,
+}
+
+impl OrderTracker {
+    pub fn new() -> OrderTracker {
+        OrderTracker::default()
+    }
+
+    /// Apply a message. Returns `None` for messages which do not affect orders.
+    ///
+    /// On error the message is still applied as far as possible: an overfill
+    /// removes the order and a duplicate add replaces the existing order.
+    pub fn apply(&mut self, msg: &Message) -> Option<Result<OrderUpdate, OrderError>> {
+        let update = match msg.body {
+            Body::AddOrder(ref add) => {
+                let order = Order {
+                    stock_locate: msg.stock_locate,
+                    stock: add.stock,
+                    side: add.side,
+                    price: add.price,
+                    shares: add.shares,
+                    mpid: add.mpid,
+                    timestamp: msg.timestamp,
+                };
+                if self.orders.insert(add.reference, order).is_some() {
+                    return Some(Err(OrderError::DuplicateReference {
+                        reference: add.reference,
+                    }));
+                }
+                Ok(OrderUpdate::Added {
+                    reference: add.reference,
+                    order,
+                })
+            }
+            Body::OrderExecuted {
+                reference,
+                executed,
+                match_number,
+            } => self
+                .reduce(reference, executed)
+                .map(|order| OrderUpdate::Executed {
+                    reference,
+                    order,
+                    shares: executed,
+                    price: order.price,
+                    match_number,
+                    printable: true,
+                }),
+            Body::OrderExecutedWithPrice {
+                reference,
+                executed,
+                match_number,
+                printable,
+                price,
+            } => self
+                .reduce(reference, executed)
+                .map(|order| OrderUpdate::Executed {
+                    reference,
+                    order,
+                    shares: executed,
+                    price,
+                    match_number,
+                    printable,
+                }),
+            Body::OrderCancelled {
+                reference,
+                cancelled,
+            } => self
+                .reduce(reference, cancelled)
+                .map(|order| OrderUpdate::Cancelled {
+                    reference,
+                    order,
+                    shares: cancelled,
+                }),
+            Body::DeleteOrder { reference } => match self.orders.remove(&reference) {
+                Some(order) => Ok(OrderUpdate::Deleted { reference, order }),
+                None => Err(OrderError::UnknownReference { reference }),
+            },
+            Body::ReplaceOrder(ref replace) => {
+                let Some(old) = self.orders.remove(&replace.old_reference) else {
+                    return Some(Err(OrderError::UnknownReference {
+                        reference: replace.old_reference,
+                    }));
+                };
+                let new = Order {
+                    price: replace.price,
+                    shares: replace.shares,
+                    timestamp: msg.timestamp,
+                    ..old
+                };
+                if self.orders.insert(replace.new_reference, new).is_some() {
+                    return Some(Err(OrderError::DuplicateReference {
+                        reference: replace.new_reference,
+                    }));
+                }
+                Ok(OrderUpdate::Replaced {
+                    old_reference: replace.old_reference,
+                    new_reference: replace.new_reference,
+                    old,
+                    new,
+                })
+            }
+            _ => return None,
+        };
+        Some(update)
+    }
+
+    // Remove shares from an order, returning its state before the reduction
+    fn reduce(&mut self, reference: u64, shares: u32) -> Result<Order, OrderError> {
+        let Some(order) = self.orders.get_mut(&reference) else {
+            return Err(OrderError::UnknownReference { reference });
+        };
+        let before = *order;
+        if shares > order.shares {
+            self.orders.remove(&reference);
+            return Err(OrderError::Overfill {
+                reference,
+                remaining: before.shares,
+                requested: shares,
+            });
+        }
+        order.shares -= shares;
+        if order.shares == 0 {
+            self.orders.remove(&reference);
+        }
+        Ok(before)
+    }
+
+    /// Stop tracking the orders of a stock locate, returning them
+    pub fn remove_symbol(&mut self, locate: u16) -> Vec<(u64, Order)> {
+        let references: Vec<u64> = self
+            .iter()
+            .filter(|(_, order)| order.stock_locate == locate)
+            .map(|(reference, _)| reference)
+            .collect();
+        references
+            .into_iter()
+            .filter_map(|reference| Some((reference, self.orders.remove(&reference)?)))
+            .collect()
+    }
+
+    pub fn get(&self, reference: u64) -> Option<&Order> {
+        self.orders.get(&reference)
+    }
+
+    /// All live orders, in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &Order)> {
+        self.orders.iter().map(|(r, o)| (*r, o))
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{AddOrder, ReplaceOrder};
+
+    pub(crate) fn msg(timestamp: u64, body: Body) -> Message {
+        Message {
+            tag: 0,
+            stock_locate: 1,
+            tracking_number: 0,
+            timestamp,
+            body,
+        }
+    }
+
+    pub(crate) fn add(reference: u64, side: Side, shares: u32, price: u32) -> Body {
+        Body::AddOrder(AddOrder {
+            reference,
+            side,
+            shares,
+            stock: ArrayString8::from("ZXZZT   ").unwrap(),
+            price: price.into(),
+            mpid: None,
+        })
+    }
+
+    #[test]
+    fn order_lifecycle() {
+        let mut tracker = OrderTracker::new();
+        tracker.apply(&msg(1, add(1, Side::Buy, 100, 5000)));
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 40,
+            match_number: 9,
+        };
+        let update = tracker.apply(&msg(2, exec)).unwrap().unwrap();
+        assert!(!update.is_terminal());
+        assert_eq!(tracker.get(1).unwrap().shares, 60);
+
+        let replace = Body::ReplaceOrder(ReplaceOrder {
+            old_reference: 1,
+            new_reference: 2,
+            shares: 70,
+            price: 5100.into(),
+        });
+        tracker.apply(&msg(3, replace)).unwrap().unwrap();
+        assert!(tracker.get(1).is_none());
+        let replaced = tracker.get(2).unwrap();
+        assert_eq!((replaced.shares, replaced.price.raw()), (70, 5100));
+        assert_eq!(replaced.side, Side::Buy);
+
+        let cancel = Body::OrderCancelled {
+            reference: 2,
+            cancelled: 70,
+        };
+        assert!(tracker
+            .apply(&msg(4, cancel))
+            .unwrap()
+            .unwrap()
+            .is_terminal());
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn order_errors() {
+        let mut tracker = OrderTracker::new();
+        assert_eq!(
+            tracker.apply(&msg(1, Body::DeleteOrder { reference: 3 })),
+            Some(Err(OrderError::UnknownReference { reference: 3 }))
+        );
+        tracker.apply(&msg(1, add(3, Side::Sell, 10, 1)));
+        assert_eq!(
+            tracker.apply(&msg(1, add(3, Side::Sell, 10, 1))),
+            Some(Err(OrderError::DuplicateReference { reference: 3 }))
+        );
+        let cancel = Body::OrderCancelled {
+            reference: 3,
+            cancelled: 11,
+        };
+        assert!(matches!(
+            tracker.apply(&msg(2, cancel)),
+            Some(Err(OrderError::Overfill { remaining: 10, .. }))
+        ));
+        assert!(tracker.is_empty());
+        assert_eq!(
+            tracker.apply(&msg(3, Body::BrokenTrade { match_number: 1 })),
+            None
+        );
+    }
+}
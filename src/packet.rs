@@ -0,0 +1,330 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::UdpSocket;
+
+use crate::clock::{ClockSource, WallClock};
+use crate::{Envelope, MoldPacket, Result};
+
+/// A datagram together with the time it was received
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Packet {
+    pub data: Vec<u8>,
+    /// Capture time in nanoseconds since the Unix epoch, if known
+    pub receive_ts: Option<u64>,
+}
+
+/// A source of packets, such as a capture file or a socket.
+///
+/// Implementations attach whatever receive timestamp the source provides,
+/// which decoders pass through to each message.
+pub trait PacketSource {
+    /// The next packet, or `None` once the source is exhausted
+    fn next_packet(&mut self) -> Option<Result<Packet>>;
+}
+
+impl<S: PacketSource + ?Sized> PacketSource for &mut S {
+    fn next_packet(&mut self) -> Option<Result<Packet>> {
+        (**self).next_packet()
+    }
+}
+
+impl PacketSource for std::vec::IntoIter<Packet> {
+    fn next_packet(&mut self) -> Option<Result<Packet>> {
+        self.next().map(Ok)
+    }
+}
+
+/// Reads datagrams from a bound socket, blocking until one arrives.
+///
+/// Packets are stamped with the system clock when `recv` returns, which
+/// includes scheduling delay; capture files carry kernel or NIC timestamps.
+impl PacketSource for UdpSocket {
+    fn next_packet(&mut self) -> Option<Result<Packet>> {
+        thread_local! {
+            // the socket holds no state of ours, so the largest datagram is
+            // received into a buffer per thread and only its bytes copied out
+            static SCRATCH: RefCell<Vec<u8>> = RefCell::new(vec![0; u16::MAX as usize]);
+        }
+        SCRATCH.with(|scratch| {
+            let mut buf = scratch.borrow_mut();
+            let len = match self.recv(&mut buf) {
+                Ok(len) => len,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let receive_ts = Some(WallClock.now());
+            Some(Ok(Packet {
+                data: buf[..len].to_vec(),
+                receive_ts,
+            }))
+        })
+    }
+}
+
+/// Decodes MoldUDP64 packets from a [`PacketSource`] into [`Envelope`]s
+/// carrying each packet's receive timestamp.
+///
+/// Retransmitted (already seen) messages are dropped, and gaps in the
+/// sequence numbers are counted. Iteration ends at an end of session packet.
+///
+/// With [`record_to`](MoldReceiver::record_to), the receiver also captures
+/// the feed as it decodes it.
+pub struct MoldReceiver<S> {
+    source: S,
+    pending: VecDeque<Result<Envelope>>,
+    expected: Option<u64>,
+    gaps: u64,
+    missed: u64,
+    ended: bool,
+    recorder: Option<Box<dyn Write + Send>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<std::sync::Arc<dyn crate::MetricsRegistry>>,
+}
+
+impl<S: PacketSource> MoldReceiver<S> {
+    pub fn new(source: S) -> MoldReceiver<S> {
+        MoldReceiver {
+            source,
+            pending: VecDeque::new(),
+            expected: None,
+            gaps: 0,
+            missed: 0,
+            ended: false,
+            recorder: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Append each message block to `writer` as it is decoded, with its
+    /// length prefix, which makes a capture in the usual length-prefixed
+    /// format. Only messages the receiver yields are recorded, once each
+    /// and in order, so the capture holds exactly the bytes decoded.
+    ///
+    /// Each message is passed to the writer in a single write, so a
+    /// [`RotatingWriter`](crate::RotatingWriter) never splits one across
+    /// files. The writer is flushed at the end of the session, and a write
+    /// error is returned from the iterator in place of the packet's messages.
+    pub fn record_to<W: Write + Send + 'static>(mut self, writer: W) -> Self {
+        self.recorder = Some(Box::new(writer));
+        self
+    }
+
+    /// Report sequence gaps to the given registry
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: std::sync::Arc<dyn crate::MetricsRegistry>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Number of sequence gaps seen
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Number of messages missing in sequence gaps
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Sequence number of the next message expected, once a packet has been seen
+    pub fn expected(&self) -> Option<u64> {
+        self.expected
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    fn receive(&mut self, packet: &Packet) -> Result<()> {
+        let mold = MoldPacket::parse(&packet.data)?;
+        let expected = self.expected.unwrap_or(mold.sequence);
+        if mold.sequence > expected {
+            let missed = mold.sequence - expected;
+            self.gaps += 1;
+            self.missed += missed;
+            #[cfg(feature = "metrics")]
+            if let Some(ref metrics) = self.metrics {
+                metrics.record_gap(missed);
+            }
+        }
+        self.expected = Some(expected.max(mold.next_sequence()));
+        self.ended = mold.is_end_of_session();
+        let skip = expected.saturating_sub(mold.sequence) as usize;
+        if let Some(ref mut recorder) = self.recorder {
+            let mut buf = Vec::new();
+            for block in mold.messages().skip(skip).flatten() {
+                buf.clear();
+                buf.extend_from_slice(&(block.len() as u16).to_be_bytes());
+                buf.extend_from_slice(block);
+                recorder.write_all(&buf)?;
+            }
+            if self.ended {
+                recorder.flush()?;
+            }
+        }
+        self.pending
+            .extend(mold.envelopes(packet.receive_ts).skip(skip));
+        Ok(())
+    }
+}
+
+impl<S: PacketSource> Iterator for MoldReceiver<S> {
+    type Item = Result<Envelope>;
+
+    fn next(&mut self) -> Option<Result<Envelope>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if self.ended {
+                return None;
+            }
+            let packet = match self.source.next_packet()? {
+                Ok(packet) => packet,
+                Err(e) => return Some(Err(e)),
+            };
+            if let Err(e) = self.receive(&packet) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Adapts an iterator of byte packets, each holding one or more messages,
+/// into a reader of length-prefixed messages for [`MessageStream`].
+///
+/// Packets which start with a zero byte are taken to be length-prefixed
+/// already, as no ITCH message is 256 bytes or longer. Any other packet is
+/// taken to be a single message without its prefix, as extracted by e.g. a
+/// MoldUDP64 decoder, and is prefixed with its length.
+///
+/// [`MessageStream`]: crate::MessageStream
+#[derive(Debug)]
+pub struct PacketReader<I: Iterator> {
+    packets: I,
+    current: Option<I::Item>,
+    prefix: [u8; 2],
+    // bytes of the current packet, including any prefix, read so far
+    pos: usize,
+}
+
+impl<I> PacketReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    pub fn new<P: IntoIterator<IntoIter = I>>(packets: P) -> PacketReader<I> {
+        PacketReader {
+            packets: packets.into_iter(),
+            current: None,
+            prefix: [0; 2],
+            pos: 0,
+        }
+    }
+
+    // length of the prefix added to the current packet
+    fn prefix_len(&self) -> usize {
+        match self.current.as_ref().map(|p| p.as_ref().first()) {
+            Some(Some(&0)) | Some(None) | None => 0,
+            Some(Some(_)) => 2,
+        }
+    }
+}
+
+impl<I> Read for PacketReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let prefix_len = self.prefix_len();
+            if let Some(ref packet) = self.current {
+                let data = packet.as_ref();
+                if self.pos < prefix_len {
+                    let prefix = &self.prefix[self.pos..];
+                    let len = out.len().min(prefix.len());
+                    out[..len].copy_from_slice(&prefix[..len]);
+                    self.pos += len;
+                    return Ok(len);
+                }
+                let rest = &data[self.pos - prefix_len..];
+                if !rest.is_empty() {
+                    let len = out.len().min(rest.len());
+                    out[..len].copy_from_slice(&rest[..len]);
+                    self.pos += len;
+                    return Ok(len);
+                }
+            }
+            let Some(packet) = self.packets.next() else {
+                return Ok(0);
+            };
+            let len = packet.as_ref().len();
+            if packet.as_ref().first().is_some_and(|&b| b != 0) {
+                let len = u16::try_from(len).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "packet too long for a message")
+                })?;
+                self.prefix = len.to_be_bytes();
+            }
+            self.current = Some(packet);
+            self.pos = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::mold::tests::mold_packet;
+
+    fn packet(sequence: u64, count: usize, receive_ts: u64) -> Packet {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        let messages = vec![&event[..]; count];
+        Packet {
+            data: mold_packet("SESSION", sequence, &messages),
+            receive_ts: Some(receive_ts),
+        }
+    }
+
+    #[test]
+    fn passes_through_receive_timestamps() {
+        let packets = vec![
+            packet(1, 2, 100),
+            packet(2, 2, 200), // retransmission overlapping the first packet
+            packet(6, 1, 300), // messages 4 and 5 are missing
+            packet(7, 0, 400), // heartbeat
+        ];
+        let mut receiver = MoldReceiver::new(packets.into_iter());
+        let received: Vec<_> = receiver
+            .by_ref()
+            .map(|e| e.map(|e| (e.sequence, e.receive_ts.unwrap())))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(received, [(1, 100), (2, 100), (3, 200), (6, 300)]);
+        assert_eq!((receiver.gaps(), receiver.missed()), (1, 2));
+        assert_eq!(receiver.expected(), Some(7));
+    }
+
+    #[test]
+    fn reads_packets_with_and_without_prefix() {
+        let framed = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f");
+        let mut two = framed.clone();
+        two.extend_from_slice(&framed);
+        let packets: Vec<&[u8]> = vec![&framed[2..], &two, &[], &framed];
+        let stream = crate::MessageStream::from_packets(packets);
+        let tags: Vec<_> = stream.map(|m| m.unwrap().tag).collect();
+        assert_eq!(tags, b"SSSS");
+    }
+
+    #[test]
+    fn receives_datagrams_without_spare_capacity() {
+        let mut socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let data = packet(1, 1, 0).data;
+        sender.send_to(&data, socket.local_addr().unwrap()).unwrap();
+        let received = socket.next_packet().unwrap().unwrap();
+        assert_eq!(received.data, data);
+        assert_eq!(received.data.capacity(), data.len());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use crate::{Error, Message, MessageStream, Result, TimeIndex};
+
+/// Run a map-reduce style analysis over a file on all available cores.
+///
+/// The file is split into time windows of `window` nanoseconds using a
+/// [`TimeIndex`]. Each window is parsed on a worker thread and folded into
+/// its own accumulator, starting from `A::default()`. The per-window results
+/// are then combined with `reduce` in time order, so the result is
+/// deterministic as long as `reduce` is associative.
+///
+/// ```ignore
+/// let volume = itchy::analyze_parallel(
+///     "/path/to/file.itch",
+///     60 * 1_000_000_000,
+///     |total: &mut u64, msg| if let itchy::Body::OrderExecuted { executed, .. } = msg.body {
+///         *total += executed as u64
+///     },
+///     |a, b| a + b,
+/// )?;
+/// ```
+pub fn analyze_parallel<P, A, F, G>(path: P, window: u64, fold: F, reduce: G) -> Result<A>
+where
+    P: AsRef<Path>,
+    A: Default + Send,
+    F: Fn(&mut A, Message) + Sync,
+    G: Fn(A, A) -> A,
+{
+    let path = path.as_ref();
+    let index = TimeIndex::build(File::open(path)?, window)?;
+    let windows: Vec<_> = index.windows().collect();
+    run_parallel(
+        &windows,
+        |&(entry, end)| fold_window(path, entry.offset, end, &fold),
+        reduce,
+    )
+}
+
+// Process tasks on all available cores, combining their results with
+// `reduce` in task order
+pub(crate) fn run_parallel<T, A, W, G>(tasks: &[T], work: W, reduce: G) -> Result<A>
+where
+    T: Sync,
+    A: Default + Send,
+    W: Fn(&T) -> Result<A> + Sync,
+    G: Fn(A, A) -> A,
+{
+    let results: Vec<Mutex<Option<Result<A>>>> = tasks.iter().map(|_| Mutex::new(None)).collect();
+    let next_task = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+
+    thread::scope(|scope| {
+        for _ in 0..workers.min(tasks.len()) {
+            scope.spawn(|| loop {
+                let ix = next_task.fetch_add(1, Ordering::Relaxed);
+                let Some(task) = tasks.get(ix) else {
+                    break;
+                };
+                let result = work(task);
+                *results[ix].lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+            });
+        }
+    });
+
+    let mut acc = A::default();
+    for result in results {
+        // every task is processed before the scope exits
+        let task_acc = result
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .ok_or_else(|| Error::Parse("parallel task was not processed".into()))??;
+        acc = reduce(acc, task_acc);
+    }
+    Ok(acc)
+}
+
+fn fold_window<A, F>(path: &Path, start: u64, end: u64, fold: &F) -> Result<A>
+where
+    A: Default,
+    F: Fn(&mut A, Message),
+{
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut acc = A::default();
+    for msg in MessageStream::from_reader(file.take(end - start)) {
+        fold(&mut acc, msg?);
+    }
+    Ok(acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_messages_per_window() {
+        let mut data = Vec::new();
+        for ts in 0..100u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&(ts * 1000).to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let path = std::env::temp_dir().join(format!("itchy-parallel-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        let count = analyze_parallel(&path, 7_000, |ct: &mut u64, _| *ct += 1, |a, b| a + b);
+        let timestamps = analyze_parallel(
+            &path,
+            7_000,
+            |ts: &mut Vec<u64>, msg| ts.push(msg.timestamp),
+            |mut a, b| {
+                a.extend(b);
+                a
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(count.unwrap(), 100);
+        let timestamps = timestamps.unwrap();
+        assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(timestamps.len(), 100);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use crate::symbol::padded_mpid;
+use crate::{ArrayString4, Body, MarketParticipantPosition, Message, OrderTracker, OrderUpdate};
+
+/// Share volumes attributed to a market participant
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ParticipantVolume {
+    /// Attributed orders entered, including replacements
+    pub orders: u64,
+    pub added: u64,
+    pub executed: u64,
+    pub cancelled: u64,
+}
+
+impl ParticipantVolume {
+    fn apply(&mut self, update: &OrderUpdate) {
+        match *update {
+            OrderUpdate::Added { order, .. } => {
+                self.orders += 1;
+                self.added += order.shares as u64;
+            }
+            OrderUpdate::Executed { shares, .. } => self.executed += shares as u64,
+            OrderUpdate::Cancelled { shares, .. } => self.cancelled += shares as u64,
+            OrderUpdate::Deleted { order, .. } => self.cancelled += order.shares as u64,
+            OrderUpdate::Replaced { old, new, .. } => {
+                self.orders += 1;
+                self.cancelled += old.shares as u64;
+                self.added += new.shares as u64;
+            }
+        }
+    }
+}
+
+/// A participant's activity in a single symbol
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ParticipantSymbol {
+    pub volume: ParticipantVolume,
+    /// Latest market participant position ('L' message), if any
+    pub position: Option<MarketParticipantPosition>,
+}
+
+/// Activity of a single MPID
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Participant {
+    pub mpid: ArrayString4,
+    pub total: ParticipantVolume,
+    /// Per-symbol activity, by stock locate
+    pub symbols: BTreeMap<u16, ParticipantSymbol>,
+}
+
+/// Aggregates activity by MPID.
+///
+/// Attributed ('F') orders are joined by reference to the executions,
+/// cancels, deletes and replaces which follow them. Participant positions
+/// are recorded for every MPID seen, even without attributed orders.
+/// A replace counts as cancelling the remaining shares of the old order and
+/// adding the shares of the new one.
+#[derive(Debug, Clone, Default)]
+pub struct MpidAggregator {
+    tracker: OrderTracker,
+    participants: BTreeMap<ArrayString4, Participant>,
+}
+
+impl MpidAggregator {
+    pub fn new() -> MpidAggregator {
+        MpidAggregator::default()
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        if let Body::ParticipantPosition(ref position) = msg.body {
+            self.participant(position.mpid)
+                .symbols
+                .entry(msg.stock_locate)
+                .or_default()
+                .position = Some(position.clone());
+            return;
+        }
+        let Some(Ok(update)) = self.tracker.apply(msg) else {
+            return;
+        };
+        let order = match update {
+            OrderUpdate::Added { order, .. }
+            | OrderUpdate::Executed { order, .. }
+            | OrderUpdate::Cancelled { order, .. }
+            | OrderUpdate::Deleted { order, .. } => order,
+            OrderUpdate::Replaced { old, .. } => old,
+        };
+        let Some(mpid) = order.mpid else {
+            return;
+        };
+        let participant = self.participant(mpid);
+        participant.total.apply(&update);
+        participant
+            .symbols
+            .entry(order.stock_locate)
+            .or_default()
+            .volume
+            .apply(&update);
+    }
+
+    fn participant(&mut self, mpid: ArrayString4) -> &mut Participant {
+        self.participants
+            .entry(mpid)
+            .or_insert_with(|| Participant {
+                mpid,
+                total: ParticipantVolume::default(),
+                symbols: BTreeMap::new(),
+            })
+    }
+
+    /// Activity for an MPID, ignoring trailing padding
+    pub fn get(&self, mpid: &str) -> Option<&Participant> {
+        self.participants.get(&padded_mpid(mpid)?)
+    }
+
+    /// All participants seen, ordered by MPID
+    pub fn iter(&self) -> impl Iterator<Item = &Participant> {
+        self.participants.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.participants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{ArrayString8, MarketMakerMode, MarketParticipantState, ReplaceOrder, Side};
+
+    fn attributed(reference: u64, shares: u32) -> Body {
+        let mut body = add(reference, Side::Buy, shares, 10_000);
+        if let Body::AddOrder(ref mut add) = body {
+            add.mpid = Some(ArrayString4::from("NITE").unwrap());
+        }
+        body
+    }
+
+    #[test]
+    fn aggregates_by_mpid() {
+        let mut agg = MpidAggregator::new();
+        agg.update(&msg(
+            0,
+            Body::ParticipantPosition(MarketParticipantPosition {
+                mpid: ArrayString4::from("NITE").unwrap(),
+                stock: ArrayString8::from("ZXZZT   ").unwrap(),
+                primary_market_maker: true,
+                market_maker_mode: MarketMakerMode::Normal,
+                market_participant_state: MarketParticipantState::Active,
+            }),
+        ));
+        agg.update(&msg(1, attributed(1, 300)));
+        agg.update(&msg(1, add(2, Side::Buy, 500, 10_000)));
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 100,
+            match_number: 1,
+        };
+        agg.update(&msg(2, exec));
+        let replace = Body::ReplaceOrder(ReplaceOrder {
+            old_reference: 1,
+            new_reference: 3,
+            shares: 50,
+            price: 10_100.into(),
+        });
+        agg.update(&msg(3, replace));
+        agg.update(&msg(4, Body::DeleteOrder { reference: 3 }));
+
+        assert_eq!(agg.len(), 1);
+        let nite = agg.get("NITE").unwrap();
+        assert_eq!(agg.get("NITE "), Some(nite));
+        assert!(agg.get("NITEX").is_none());
+        let expected = ParticipantVolume {
+            orders: 2,
+            added: 350,
+            executed: 100,
+            cancelled: 250,
+        };
+        assert_eq!(nite.total, expected);
+        assert_eq!(nite.symbols[&1].volume, expected);
+        assert!(
+            nite.symbols[&1]
+                .position
+                .as_ref()
+                .unwrap()
+                .primary_market_maker
+        );
+    }
+}
@@ -0,0 +1,505 @@
+//! Reading packet captures (pcap and pcapng) of the multicast feed.
+//!
+//! [`PcapReader`] strips the link, IP and UDP headers from each captured
+//! frame and yields the UDP payloads as a [`PacketSource`], so a capture
+//! can be decoded with a [`MoldReceiver`]:
+//!
+//! ```ignore
+//! let receiver = itchy::pcap::open("/path/to/capture.pcap").unwrap();
+//! for envelope in receiver {
+//!     println!("{:?}", envelope.unwrap().message)
+//! }
+//! ```
+//!
+//! Ethernet (with VLAN tags), Linux cooked and raw IP captures are
+//! supported. Fragmented IP datagrams and non-UDP traffic are skipped.
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader};
+use std::path::Path;
+
+use crate::{Error, MoldReceiver, Packet, PacketSource, Result};
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+// longest record or block read, so a corrupt length cannot exhaust memory
+const MAX_RECORD_LEN: usize = 1 << 24;
+
+/// Open a capture file and decode the MoldUDP64 packets in it
+pub fn open<P: AsRef<Path>>(path: P) -> Result<MoldReceiver<PcapReader<BufReader<File>>>> {
+    let reader = PcapReader::new(BufReader::new(File::open(path)?))?;
+    Ok(MoldReceiver::new(reader))
+}
+
+// Timestamp resolution of a pcapng interface
+#[derive(Debug, Clone, Copy)]
+enum Resolution {
+    Decimal(u8),
+    Binary(u8),
+}
+
+impl Resolution {
+    // None if the timestamp does not fit in nanoseconds
+    fn to_nanos(self, ts: u64) -> Option<u64> {
+        match self {
+            Resolution::Decimal(p) if p <= 9 => ts.checked_mul(10u64.checked_pow(9 - p as u32)?),
+            Resolution::Decimal(p) => Some(ts / 10u64.checked_pow(p as u32 - 9)?),
+            Resolution::Binary(p) => ((ts as u128 * 1_000_000_000) >> p).try_into().ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    linktype: u32,
+    resolution: Resolution,
+}
+
+// A captured link-layer frame
+struct Frame {
+    linktype: u32,
+    receive_ts: Option<u64>,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap { linktype: u32, nanos: bool },
+    PcapNg { interfaces: Vec<Interface> },
+}
+
+/// Reads UDP payloads from a pcap or pcapng capture
+#[derive(Debug)]
+pub struct PcapReader<R> {
+    reader: R,
+    format: Format,
+    big_endian: bool,
+    port: Option<u16>,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the file header, detecting the capture format
+    pub fn new(mut reader: R) -> Result<PcapReader<R>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        let le = u32::from_le_bytes(magic);
+        let be = u32::from_be_bytes(magic);
+        if le == PCAPNG_SECTION_HEADER {
+            let mut pcap = PcapReader {
+                reader,
+                format: Format::PcapNg {
+                    interfaces: Vec::new(),
+                },
+                big_endian: false,
+                port: None,
+            };
+            pcap.section_header()?;
+            return Ok(pcap);
+        }
+        let (big_endian, nanos) = match (le, be) {
+            (PCAP_MAGIC_MICROS, _) => (false, false),
+            (PCAP_MAGIC_NANOS, _) => (false, true),
+            (_, PCAP_MAGIC_MICROS) => (true, false),
+            (_, PCAP_MAGIC_NANOS) => (true, true),
+            _ => return Err(Error::Parse("not a pcap or pcapng file".into())),
+        };
+        let mut header = [0; 20];
+        reader.read_exact(&mut header)?;
+        let mut pcap = PcapReader {
+            reader,
+            format: Format::Pcap { linktype: 0, nanos },
+            big_endian,
+            port: None,
+        };
+        let linktype = pcap.u32_at(&header, 16);
+        pcap.format = Format::Pcap { linktype, nanos };
+        Ok(pcap)
+    }
+
+    /// Only yield datagrams sent to the given UDP port
+    pub fn filter_port(&mut self, port: u16) {
+        self.port = Some(port);
+    }
+
+    fn u16_at(&self, bytes: &[u8], at: usize) -> u16 {
+        let b = [bytes[at], bytes[at + 1]];
+        if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        }
+    }
+
+    fn u32_at(&self, bytes: &[u8], at: usize) -> u32 {
+        let b = [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]];
+        if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        }
+    }
+
+    // Read the remainder of a pcapng section header block, whose type has
+    // already been read. Sets the byte order of the section.
+    fn section_header(&mut self) -> Result<()> {
+        let mut head = [0; 8];
+        self.reader.read_exact(&mut head)?;
+        let magic = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+        self.big_endian = match magic {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(Error::Parse("invalid pcapng byte order magic".into())),
+        };
+        let total = self.u32_at(&head, 0) as usize;
+        // skip the rest of the block: version, section length, options and
+        // trailing length
+        let rest = total
+            .checked_sub(12)
+            .ok_or_else(|| Error::Parse("invalid pcapng section header".into()))?;
+        io::copy(&mut (&mut self.reader).take(rest as u64), &mut io::sink())?;
+        if let Format::PcapNg { ref mut interfaces } = self.format {
+            interfaces.clear();
+        }
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Frame>> {
+        match self.format {
+            Format::Pcap { linktype, nanos } => {
+                let mut header = [0; 16];
+                if !read_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let secs = self.u32_at(&header, 0) as u64;
+                let frac = self.u32_at(&header, 4) as u64;
+                let len = check_len(self.u32_at(&header, 8) as usize)?;
+                let mut data = vec![0; len];
+                self.reader.read_exact(&mut data)?;
+                let ts = secs * 1_000_000_000 + if nanos { frac } else { frac * 1000 };
+                Ok(Some(Frame {
+                    linktype,
+                    receive_ts: Some(ts),
+                    data,
+                }))
+            }
+            Format::PcapNg { .. } => loop {
+                let mut kind = [0; 4];
+                if !read_or_eof(&mut self.reader, &mut kind)? {
+                    return Ok(None);
+                }
+                if u32::from_le_bytes(kind) == PCAPNG_SECTION_HEADER {
+                    self.section_header()?;
+                    continue;
+                }
+                let kind = self.u32_at(&kind, 0);
+                let mut len = [0; 4];
+                self.reader.read_exact(&mut len)?;
+                let total = self.u32_at(&len, 0) as usize;
+                let body_len = check_len(total)?
+                    .checked_sub(12)
+                    .ok_or_else(|| Error::Parse("invalid pcapng block length".into()))?;
+                let mut body = vec![0; body_len + 4];
+                self.reader.read_exact(&mut body)?;
+                body.truncate(body_len);
+                if let Some(frame) = self.pcapng_block(kind, body)? {
+                    return Ok(Some(frame));
+                }
+            },
+        }
+    }
+
+    fn pcapng_block(&mut self, kind: u32, body: Vec<u8>) -> Result<Option<Frame>> {
+        let invalid = || Error::Parse("truncated pcapng block".into());
+        match kind {
+            // interface description
+            1 => {
+                if body.len() < 8 {
+                    return Err(invalid());
+                }
+                let linktype = self.u16_at(&body, 0) as u32;
+                let mut resolution = Resolution::Decimal(6);
+                let mut at = 8;
+                while at + 4 <= body.len() {
+                    let code = self.u16_at(&body, at);
+                    let len = self.u16_at(&body, at + 2) as usize;
+                    if code == 0 {
+                        break;
+                    }
+                    if code == 9 && len >= 1 && at + 4 < body.len() {
+                        let v = body[at + 4];
+                        // 10^20 does not fit in a u64
+                        if v & 0x80 == 0 && v > 19 {
+                            return Err(Error::Parse(format!(
+                                "unsupported pcapng timestamp resolution 10^-{}",
+                                v
+                            )));
+                        }
+                        resolution = if v & 0x80 == 0 {
+                            Resolution::Decimal(v)
+                        } else {
+                            Resolution::Binary(v & 0x7f)
+                        };
+                    }
+                    at += 4 + len.div_ceil(4) * 4;
+                }
+                if let Format::PcapNg { ref mut interfaces } = self.format {
+                    interfaces.push(Interface {
+                        linktype,
+                        resolution,
+                    });
+                }
+                Ok(None)
+            }
+            // enhanced packet
+            6 => {
+                if body.len() < 20 {
+                    return Err(invalid());
+                }
+                let interface = self.interface(self.u32_at(&body, 0))?;
+                let ts = ((self.u32_at(&body, 4) as u64) << 32) | self.u32_at(&body, 8) as u64;
+                let len = self.u32_at(&body, 12) as usize;
+                let data = body.get(20..20 + len).ok_or_else(invalid)?.to_vec();
+                let receive_ts = interface
+                    .resolution
+                    .to_nanos(ts)
+                    .ok_or_else(|| Error::Parse(format!("pcapng timestamp {} out of range", ts)))?;
+                Ok(Some(Frame {
+                    linktype: interface.linktype,
+                    receive_ts: Some(receive_ts),
+                    data,
+                }))
+            }
+            // simple packet, without a timestamp
+            3 => {
+                let interface = self.interface(0)?;
+                let data = body.get(4..).ok_or_else(invalid)?.to_vec();
+                Ok(Some(Frame {
+                    linktype: interface.linktype,
+                    receive_ts: None,
+                    data,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn interface(&self, id: u32) -> Result<Interface> {
+        match self.format {
+            Format::PcapNg { ref interfaces } => interfaces.get(id as usize).copied(),
+            Format::Pcap { .. } => None,
+        }
+        .ok_or_else(|| Error::Parse(format!("packet for undefined pcapng interface {}", id)))
+    }
+}
+
+impl<R: Read> PacketSource for PcapReader<R> {
+    fn next_packet(&mut self) -> Option<Result<Packet>> {
+        loop {
+            let frame = match self.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            let Some((port, payload)) = udp_payload(frame.linktype, &frame.data) else {
+                continue;
+            };
+            if self.port.is_some_and(|p| p != port) {
+                continue;
+            }
+            return Some(Ok(Packet {
+                data: payload.to_vec(),
+                receive_ts: frame.receive_ts,
+            }));
+        }
+    }
+}
+
+// Fill the buffer, returning false on a clean EOF before any bytes are read
+fn read_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn check_len(len: usize) -> Result<usize> {
+    if len > MAX_RECORD_LEN {
+        return Err(Error::Parse(format!(
+            "capture record of {} bytes is too long",
+            len
+        )));
+    }
+    Ok(len)
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
+// The destination port and payload of a UDP datagram in a link-layer frame
+fn udp_payload(linktype: u32, frame: &[u8]) -> Option<(u16, &[u8])> {
+    let ip = match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = be16(frame, 12)?;
+            let mut at = 14;
+            // 802.1Q and 802.1ad tags
+            while ethertype == 0x8100 || ethertype == 0x88a8 {
+                ethertype = be16(frame, at + 2)?;
+                at += 4;
+            }
+            frame.get(at..)?
+        }
+        LINKTYPE_LINUX_SLL => frame.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => frame.get(20..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => frame,
+        _ => return None,
+    };
+    let udp = match ip.first()? >> 4 {
+        4 => {
+            let header_len = (ip[0] & 0xf) as usize * 4;
+            let total_len = be16(ip, 2)? as usize;
+            let fragment = be16(ip, 6)?;
+            // skip fragments (more fragments flag or non-zero offset)
+            if *ip.get(9)? != 17 || fragment & 0x3fff != 0 {
+                return None;
+            }
+            ip.get(header_len..total_len)?
+        }
+        6 => {
+            if *ip.get(6)? != 17 {
+                return None;
+            }
+            let payload_len = be16(ip, 4)? as usize;
+            ip.get(40..40 + payload_len)?
+        }
+        _ => return None,
+    };
+    let port = be16(udp, 2)?;
+    let len = be16(udp, 4)? as usize;
+    Some((port, udp.get(8..len)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::mold::tests::mold_packet;
+
+    // An Ethernet/IPv4/UDP frame carrying a payload to the given port
+    fn frame(port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());
+        let mut ip = vec![0x45, 0, 0, 0, 0, 0, 0x40, 0, 64, 17, 0, 0];
+        ip.extend_from_slice(&[10, 0, 0, 1, 233, 54, 12, 111]);
+        let total = (20 + 8 + payload.len()) as u16;
+        ip[2..4].copy_from_slice(&total.to_be_bytes());
+        frame.extend(ip);
+        frame.extend_from_slice(&1234u16.to_be_bytes());
+        frame.extend_from_slice(&port.to_be_bytes());
+        frame.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        // Ethernet padding beyond the IP datagram
+        frame.extend_from_slice(&[0; 4]);
+        frame
+    }
+
+    fn mold() -> Vec<u8> {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        mold_packet("SESSION", 1, &[&event])
+    }
+
+    #[test]
+    fn reads_pcap() {
+        let mut pcap = Vec::new();
+        for v in [
+            PCAP_MAGIC_NANOS,
+            0x0004_0002,
+            0,
+            0,
+            65535,
+            LINKTYPE_ETHERNET,
+        ] {
+            pcap.extend_from_slice(&v.to_le_bytes());
+        }
+        for (port, ts) in [(26477, 5u32), (9999, 6)] {
+            let frame = frame(port, &mold());
+            for v in [100, ts, frame.len() as u32, frame.len() as u32] {
+                pcap.extend_from_slice(&v.to_le_bytes());
+            }
+            pcap.extend(frame);
+        }
+        let mut reader = PcapReader::new(&pcap[..]).unwrap();
+        reader.filter_port(26477);
+        let envelopes: Vec<_> = MoldReceiver::new(reader).collect::<Result<_>>().unwrap();
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].receive_ts, Some(100_000_000_005));
+        assert_eq!(envelopes[0].session.as_str(), "SESSION   ");
+
+        // a corrupt record length is an error, not an allocation
+        for v in [0, 0, u32::MAX, u32::MAX] {
+            pcap.extend_from_slice(&v.to_le_bytes());
+        }
+        let mut reader = PcapReader::new(&pcap[..]).unwrap();
+        let last = std::iter::from_fn(|| reader.next_packet()).last().unwrap();
+        assert!(matches!(last, Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn reads_pcapng() {
+        fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+            let total = (12 + body.len()) as u32;
+            let mut block = kind.to_le_bytes().to_vec();
+            block.extend_from_slice(&total.to_le_bytes());
+            block.extend_from_slice(body);
+            block.extend_from_slice(&total.to_le_bytes());
+            block
+        }
+        let mut shb = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        shb.extend_from_slice(&[1, 0, 0, 0]);
+        shb.extend_from_slice(&u64::MAX.to_le_bytes());
+        // interface with nanosecond resolution
+        let mut idb = vec![1, 0, 0, 0, 0, 0, 0, 0];
+        idb.extend_from_slice(&[9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]);
+        let mut frame = frame(26477, &mold());
+        let mut epb = Vec::new();
+        for v in [0, 0, 1_000, frame.len() as u32, frame.len() as u32] {
+            epb.extend_from_slice(&v.to_le_bytes());
+        }
+        frame.resize(frame.len().div_ceil(4) * 4, 0);
+        epb.extend(frame);
+
+        let mut capture = block(PCAPNG_SECTION_HEADER, &shb);
+        capture.extend(block(1, &idb));
+        capture.extend(block(6, &epb));
+        let mut reader = PcapReader::new(&capture[..]).unwrap();
+        let packet = reader.next_packet().unwrap().unwrap();
+        assert_eq!(packet.receive_ts, Some(1_000));
+        assert_eq!(packet.data, mold());
+        assert!(reader.next_packet().is_none());
+
+        // a resolution finer than 10^-19 is rejected rather than overflowing
+        idb[12] = 20;
+        let mut capture = block(PCAPNG_SECTION_HEADER, &shb);
+        capture.extend(block(1, &idb));
+        let mut reader = PcapReader::new(&capture[..]).unwrap();
+        assert!(matches!(reader.next_packet(), Some(Err(Error::Parse(_)))));
+    }
+}
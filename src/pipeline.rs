@@ -0,0 +1,237 @@
+//! Multi-threaded replay of a capture.
+//!
+//! A [`Pipeline`] runs each stage of processing a capture on a dedicated
+//! thread: reading the file, decompressing it, parsing messages and
+//! dispatching them to a [`MessageSink`]. Stages are connected by bounded
+//! single-producer single-consumer queues, so a slow stage applies
+//! backpressure rather than letting memory grow. With the `affinity`
+//! feature each stage can be pinned to a CPU core.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+use flate2::read::GzDecoder;
+
+use crate::{Message, MessageSink, MessageStream, Prefetch, Result};
+
+/// A stage of a [`Pipeline`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Read,
+    Decompress,
+    Parse,
+    Dispatch,
+}
+
+/// Configures and runs a thread-per-stage pipeline, see the
+/// [module documentation](self).
+///
+/// ```ignore
+/// let mut books = itchy::BookManager::new();
+/// let count = itchy::pipeline::Pipeline::new()
+///     .pin(Stage::Parse, 2)
+///     .pin(Stage::Dispatch, 3)
+///     .run_file("/path/to/file.itch.gz", &mut books)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    buffers: usize,
+    buffer_size: usize,
+    batch_size: usize,
+    queue_depth: usize,
+    cores: [Option<usize>; 4],
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline {
+            buffers: 4,
+            buffer_size: 1 << 20,
+            batch_size: 1024,
+            queue_depth: 16,
+            cores: [None; 4],
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Number and size of the byte buffers in flight between the read,
+    /// decompress and parse stages (4 of 1 MiB by default)
+    ///
+    /// # Panics
+    ///
+    /// If either is zero
+    pub fn buffers(mut self, buffers: usize, buffer_size: usize) -> Self {
+        assert!(buffers > 0 && buffer_size > 0, "buffers must be non-empty");
+        self.buffers = buffers;
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Messages per batch passed from the parse to the dispatch stage, and
+    /// the number of batches in flight (1024 and 16 by default)
+    ///
+    /// # Panics
+    ///
+    /// If either is zero
+    pub fn batches(mut self, batch_size: usize, queue_depth: usize) -> Self {
+        assert!(
+            batch_size > 0 && queue_depth > 0,
+            "batches must be non-empty"
+        );
+        self.batch_size = batch_size;
+        self.queue_depth = queue_depth;
+        self
+    }
+
+    /// Pin the thread running a stage to a CPU core
+    #[cfg(feature = "affinity")]
+    pub fn pin(mut self, stage: Stage, core: usize) -> Self {
+        self.cores[stage as usize] = Some(core);
+        self
+    }
+
+    /// Run the pipeline over a file, decompressing it if it is gzipped.
+    /// Returns the number of messages dispatched, like [`drive`](crate::drive).
+    pub fn run_file<P, S>(&self, path: P, sink: S) -> Result<u64>
+    where
+        P: AsRef<Path>,
+        S: MessageSink + Send,
+    {
+        let mut file = BufReader::new(File::open(path)?);
+        let gzipped = file.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        self.run(file, gzipped, sink)
+    }
+
+    /// Run the pipeline over a reader, decompressing it on its own stage
+    /// if `gzipped`
+    pub fn run<R, S>(&self, reader: R, gzipped: bool, mut sink: S) -> Result<u64>
+    where
+        R: Read + Send + 'static,
+        S: MessageSink + Send,
+    {
+        let spawn = |reader: Box<dyn Read + Send>, stage: Stage, name: &str| {
+            let core = self.cores[stage as usize];
+            Prefetch::spawn(reader, self.buffers, self.buffer_size, name, move || {
+                pin(core)
+            })
+        };
+        let mut input = spawn(Box::new(reader), Stage::Read, "itchy-read");
+        if gzipped {
+            input = spawn(
+                Box::new(GzDecoder::new(input)),
+                Stage::Decompress,
+                "itchy-decompress",
+            );
+        }
+        let (batches, parsed) = mpsc::sync_channel::<Result<Vec<Message>>>(self.queue_depth);
+        thread::scope(|scope| {
+            let parse_core = self.cores[Stage::Parse as usize];
+            let batch_size = self.batch_size;
+            // like `thread::spawn`, panics if the OS cannot create a thread
+            #[allow(clippy::expect_used)]
+            thread::Builder::new()
+                .name("itchy-parse".into())
+                .spawn_scoped(scope, move || {
+                    pin(parse_core);
+                    let mut batch = Vec::with_capacity(batch_size);
+                    for msg in MessageStream::from_reader(input) {
+                        match msg {
+                            Ok(msg) => batch.push(msg),
+                            Err(e) => {
+                                let _ = batches.send(Ok(batch));
+                                let _ = batches.send(Err(e));
+                                return;
+                            }
+                        }
+                        if batch.len() == batch_size {
+                            let full =
+                                std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+                            // the dispatcher has stopped
+                            if batches.send(Ok(full)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    let _ = batches.send(Ok(batch));
+                })
+                .expect("failed to spawn parse thread");
+
+            let dispatch_core = self.cores[Stage::Dispatch as usize];
+            #[allow(clippy::expect_used)]
+            thread::Builder::new()
+                .name("itchy-dispatch".into())
+                .spawn_scoped(scope, move || {
+                    pin(dispatch_core);
+                    let mut accepted = 0;
+                    for batch in parsed {
+                        for msg in batch? {
+                            accepted += 1;
+                            if sink.accept(msg).is_break() {
+                                return Ok(accepted);
+                            }
+                        }
+                    }
+                    Ok(accepted)
+                })
+                .expect("failed to spawn dispatch thread")
+                .join()
+                .expect("dispatch thread panicked")
+        })
+    }
+}
+
+#[cfg(feature = "affinity")]
+fn pin(core: Option<usize>) {
+    if let Some(id) = core {
+        core_affinity::set_for_current(core_affinity::CoreId { id });
+    }
+}
+
+#[cfg(not(feature = "affinity"))]
+fn pin(_: Option<usize>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Write};
+    use std::ops::ControlFlow;
+
+    #[test]
+    fn runs_stages() {
+        let mut data = Vec::new();
+        for ts in 0..1000u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let mut gz = GzEncoder::new(Vec::new(), Compression::fast());
+        gz.write_all(&data).unwrap();
+        let gz = gz.finish().unwrap();
+
+        let pipeline = Pipeline::new().buffers(2, 100).batches(16, 2);
+        let mut timestamps = Vec::new();
+        let sink = crate::sink_fn(|msg: Message| {
+            timestamps.push(msg.timestamp);
+            ControlFlow::Continue(())
+        });
+        assert_eq!(pipeline.run(Cursor::new(gz), true, sink).unwrap(), 1000);
+        assert_eq!(timestamps, (0..1000).collect::<Vec<_>>());
+
+        // the sink can stop the pipeline early
+        let stop = crate::sink_fn(|msg: Message| match msg.timestamp {
+            99 => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        });
+        assert_eq!(pipeline.run(Cursor::new(data), false, stop).unwrap(), 100);
+    }
+}
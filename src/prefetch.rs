@@ -0,0 +1,181 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+/// A reader which reads ahead from another on a background thread.
+///
+/// Wrapping a decompressor such as `GzDecoder` in a `Prefetch` lets
+/// decompression overlap with parsing. The background thread fills a fixed
+/// ring of buffers and blocks once they are all waiting to be consumed, so
+/// it never runs more than `buffers` buffers ahead of the parser.
+///
+/// The thread exits when the reader reaches EOF or fails, or when the
+/// `Prefetch` is dropped.
+#[derive(Debug)]
+pub struct Prefetch {
+    filled: Receiver<io::Result<Vec<u8>>>,
+    recycle: SyncSender<Vec<u8>>,
+    current: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Prefetch {
+    /// Prefetch with the default ring of 4 buffers of 1 MiB
+    pub fn new<R: Read + Send + 'static>(reader: R) -> Prefetch {
+        Prefetch::with_buffers(reader, 4, 1 << 20)
+    }
+
+    /// Prefetch into a ring of `buffers` buffers of `buffer_size` bytes each
+    ///
+    /// # Panics
+    ///
+    /// If either is zero, or the OS cannot create a thread
+    pub fn with_buffers<R: Read + Send + 'static>(
+        reader: R,
+        buffers: usize,
+        buffer_size: usize,
+    ) -> Prefetch {
+        Prefetch::spawn(reader, buffers, buffer_size, "itchy-prefetch", || {})
+    }
+
+    // prefetch on a thread with the given name, which calls `on_start` first
+    pub(crate) fn spawn<R, F>(
+        reader: R,
+        buffers: usize,
+        buffer_size: usize,
+        name: &str,
+        on_start: F,
+    ) -> Prefetch
+    where
+        R: Read + Send + 'static,
+        F: FnOnce() + Send + 'static,
+    {
+        assert!(
+            buffers > 0 && buffer_size > 0,
+            "prefetch ring must be non-empty"
+        );
+        let (filled_tx, filled) = mpsc::sync_channel(buffers);
+        let (recycle, empty) = mpsc::sync_channel(buffers);
+        for _ in 0..buffers {
+            // cannot fail, the receiver is held below
+            let _ = recycle.send(vec![0; buffer_size]);
+        }
+        // like `thread::spawn`, panics if the OS cannot create a thread
+        #[allow(clippy::expect_used)]
+        thread::Builder::new()
+            .name(name.into())
+            .spawn(move || {
+                on_start();
+                prefetch(reader, empty, filled_tx)
+            })
+            .expect("failed to spawn prefetch thread");
+        Prefetch {
+            filled,
+            recycle,
+            current: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+fn prefetch<R: Read>(
+    mut reader: R,
+    empty: Receiver<Vec<u8>>,
+    filled: SyncSender<io::Result<Vec<u8>>>,
+) {
+    // stops once the consumer is dropped, as both channels disconnect
+    while let Ok(mut buf) = empty.recv() {
+        let capacity = buf.capacity();
+        buf.resize(capacity, 0);
+        let mut len = 0;
+        while len < buf.len() {
+            match reader.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    buf.truncate(len);
+                    if len > 0 {
+                        let _ = filled.send(Ok(buf));
+                    }
+                    let _ = filled.send(Err(e));
+                    return;
+                }
+            }
+        }
+        buf.truncate(len);
+        if len == 0 || filled.send(Ok(buf)).is_err() {
+            return;
+        }
+    }
+}
+
+impl Read for Prefetch {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.current.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.filled.recv() {
+                Ok(Ok(buf)) => {
+                    let used = std::mem::replace(&mut self.current, buf);
+                    if used.capacity() > 0 {
+                        let _ = self.recycle.send(used);
+                    }
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e);
+                }
+                // the thread exits without sending at EOF
+                Err(_) => self.done = true,
+            }
+        }
+        let len = out.len().min(self.current.len() - self.pos);
+        out[..len].copy_from_slice(&self.current[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+
+    #[test]
+    fn prefetches_reader() {
+        let mut data = Vec::new();
+        for ts in 0..1000u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let stream = MessageStream::from_reader(Prefetch::with_buffers(
+            io::Cursor::new(data.clone()),
+            2,
+            100,
+        ));
+        let timestamps: Vec<_> = stream.map(|msg| msg.unwrap().timestamp).collect();
+        assert_eq!(timestamps, (0..1000).collect::<Vec<_>>());
+
+        // errors are passed through after the data read before them
+        let failing = io::Cursor::new(data).take(30).chain(FailingReader);
+        let mut prefetch = Prefetch::with_buffers(failing, 2, 16);
+        let mut out = Vec::new();
+        let err = prefetch.read_to_end(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(out.len(), 30);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("failed"))
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! The types most programs need, for a glob import:
+//!
+//! ```ignore
+//! use itchy::prelude::*;
+//! ```
+
+pub use crate::{
+    ArrayString4, ArrayString8, Body, Error, ErrorPolicy, EventCode, Message, MessageStream,
+    MessageStreamBuilder, Price4, Price8, ResultIterExt, Side, StockDirectory, Symbol,
+};
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::trades::DEFAULT_ROUND_LOT;
+use crate::{Body, Book, BookManager, LotKind, Message, OrderUpdate, Price4, Price8};
+
+/// Reference prices of one symbol, see [`Prices`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SymbolPrices {
+    pub stock_locate: u16,
+    /// Price of the last trade of at least a round lot, including crosses
+    pub last_trade: Option<Price4>,
+    /// Price of the last cross in which shares traded
+    pub last_cross: Option<Price4>,
+    /// Midpoint of the best bid and ask, while both sides are quoted and
+    /// the book is not crossed. Halfway between two ticks needs a fifth
+    /// decimal place, so this is a [`Price8`].
+    pub mid: Option<Price8>,
+    /// Timestamp of the message which last changed any of the prices
+    pub timestamp: u64,
+}
+
+/// Tracks the last trade price, last cross price and midpoint of every
+/// symbol, answering "what is the price of X as of this message".
+///
+/// The last trade price follows the consolidated tape's last sale rules
+/// as far as a single venue's feed allows: printable executions,
+/// non-displayed trades ('P') and crosses update it, while odd lot trades,
+/// smaller than the symbol's round lot from its stock directory message
+/// (100 shares by default), and crosses in which nothing traded do not.
+/// Broken trades ('B') are not reversed.
+///
+/// The midpoint comes from a full-depth book of each symbol, so the
+/// tracker keeps one; see [`books`](Prices::books).
+#[derive(Debug, Clone, Default)]
+pub struct Prices {
+    books: BookManager,
+    prices: HashMap<u16, SymbolPrices>,
+    round_lots: HashMap<u16, u32>,
+}
+
+impl Prices {
+    pub fn new() -> Prices {
+        Prices::default()
+    }
+
+    /// Apply a message, returning the symbol's prices if it changed any
+    /// of them
+    pub fn update(&mut self, msg: &Message) -> Option<SymbolPrices> {
+        let locate = msg.stock_locate;
+        let mut changed = false;
+        let trade = match msg.body {
+            Body::StockDirectory(ref directory) => {
+                self.round_lots.insert(locate, directory.round_lot_size);
+                None
+            }
+            Body::NonCrossTrade(ref trade) => Some((trade.price, trade.shares as u64)),
+            Body::CrossTrade(ref cross) if cross.shares > 0 => {
+                changed |=
+                    self.update_price(msg, |prices| prices.last_cross = Some(cross.cross_price));
+                Some((cross.cross_price, cross.shares))
+            }
+            _ => None,
+        };
+        let trade = trade.or_else(|| match self.books.apply(msg)? {
+            OrderUpdate::Executed {
+                shares,
+                price,
+                printable: true,
+                ..
+            } => Some((price, shares as u64)),
+            _ => None,
+        });
+        let round_lot = self
+            .round_lots
+            .get(&locate)
+            .copied()
+            .unwrap_or(DEFAULT_ROUND_LOT);
+        if let Some((price, shares)) = trade {
+            if LotKind::classify(shares, round_lot) != LotKind::Odd {
+                changed |= self.update_price(msg, |prices| prices.last_trade = Some(price));
+            }
+        }
+        let mid = self.books.book(locate).and_then(|book| {
+            let (bid, ask) = (book.best_bid()?.price, book.best_ask()?.price);
+            (bid <= ask).then(|| Price8::from((bid.raw() as u64 + ask.raw() as u64) * 5_000))
+        });
+        changed |= self.update_price(msg, |prices| prices.mid = mid);
+        if changed {
+            self.prices.get(&locate).copied()
+        } else {
+            None
+        }
+    }
+
+    /// The prices of a symbol, once any is known
+    pub fn get(&self, locate: u16) -> Option<&SymbolPrices> {
+        self.prices.get(&locate)
+    }
+
+    pub fn last_trade(&self, locate: u16) -> Option<Price4> {
+        self.get(locate)?.last_trade
+    }
+
+    pub fn mid(&self, locate: u16) -> Option<Price8> {
+        self.get(locate)?.mid
+    }
+
+    /// The books the midpoints are taken from
+    pub fn books(&self) -> &BookManager {
+        &self.books
+    }
+
+    // apply `f` to the symbol's prices, stamping them and returning true
+    // if they changed
+    fn update_price(&mut self, msg: &Message, f: impl FnOnce(&mut SymbolPrices)) -> bool {
+        let locate = msg.stock_locate;
+        let current = self.prices.get(&locate).copied().unwrap_or(SymbolPrices {
+            stock_locate: locate,
+            ..SymbolPrices::default()
+        });
+        let mut updated = current;
+        f(&mut updated);
+        if updated == current {
+            return false;
+        }
+        updated.timestamp = msg.timestamp;
+        self.prices.insert(locate, updated);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn tracks_trades_crosses_and_mid() {
+        let mut prices = Prices::new();
+        let tagged = |tag, ts, body| Message {
+            tag,
+            ..msg(ts, body)
+        };
+        assert!(prices
+            .update(&tagged(b'A', 1, add(1, Side::Buy, 500, 10_000)))
+            .is_none());
+        let quoted = prices
+            .update(&tagged(b'A', 2, add(2, Side::Sell, 500, 10_001)))
+            .unwrap();
+        assert_eq!(quoted.mid, Some(Price8::from(100_005_000)));
+
+        // an odd lot leaves the last trade alone
+        let odd = Body::OrderExecuted {
+            reference: 2,
+            executed: 50,
+            match_number: 1,
+        };
+        assert!(prices.update(&tagged(b'E', 3, odd)).is_none());
+        let round = Body::OrderExecuted {
+            reference: 2,
+            executed: 100,
+            match_number: 2,
+        };
+        let traded = prices.update(&tagged(b'E', 4, round)).unwrap();
+        assert_eq!(
+            (traded.last_trade, traded.timestamp),
+            (Some(10_001.into()), 4)
+        );
+
+        let cross = Body::CrossTrade(crate::CrossTrade {
+            shares: 10_000,
+            stock: crate::ArrayString8::from("ZXZZT   ").unwrap(),
+            cross_price: 10_002.into(),
+            match_number: 3,
+            cross_type: crate::CrossType::Closing,
+        });
+        prices.update(&tagged(b'Q', 5, cross)).unwrap();
+        let symbol = prices.get(1).unwrap();
+        assert_eq!(symbol.last_cross, Some(10_002.into()));
+        assert_eq!(symbol.last_trade, Some(10_002.into()));
+        assert_eq!(symbol.mid, Some(Price8::from(100_005_000)));
+    }
+}
@@ -0,0 +1,137 @@
+//! Parsers for the individual parts of an ITCH 5.0 message.
+//!
+//! These are the parsers [`MessageStream`](crate::MessageStream) is built
+//! from, for use with framing of your own: an exchange simulator, a
+//! hardware capture or a transport other than MoldUDP64 or SoupBinTCP.
+//!
+//! [`parse_message`] and [`parse_unframed`] parse a whole message. The
+//! body parsers expect the bytes following the 11-byte header (message
+//! type, stock locate, tracking number and timestamp) of the message type
+//! they are named for. All parsers are streaming: input that ends early
+//! gives [`nom::Err::Incomplete`] rather than an error.
+
+use crate::{
+    AddOrder, ArrayString8, Body, CrossTrade, ImbalanceIndicator, IpoQuotingPeriod,
+    IssueClassification, IssueSubType, MarketParticipantPosition, Message, NonCrossTrade,
+    ReplaceOrder, RetailPriceImprovementIndicator, StockDirectory,
+};
+
+pub use nom::IResult;
+
+/// A message with its two-byte length prefix
+pub fn parse_message(input: &[u8]) -> IResult<&[u8], Message> {
+    crate::messages::parse_message(input)
+}
+
+/// A message without a length prefix, starting at the message type
+pub fn parse_unframed(input: &[u8]) -> IResult<&[u8], Message> {
+    crate::messages::parse_unframed(input)
+}
+
+/// Add Order ('A') body, without attribution
+pub fn parse_add_order(input: &[u8]) -> IResult<&[u8], AddOrder> {
+    crate::messages::parse_add_order(input, false)
+}
+
+/// Add Order with MPID Attribution ('F') body
+pub fn parse_add_order_with_mpid(input: &[u8]) -> IResult<&[u8], AddOrder> {
+    crate::messages::parse_add_order(input, true)
+}
+
+/// Cross Trade ('Q') body
+pub fn parse_cross_trade(input: &[u8]) -> IResult<&[u8], CrossTrade> {
+    crate::messages::parse_cross_trade(input)
+}
+
+/// Net Order Imbalance Indicator ('I') body
+pub fn parse_imbalance_indicator(input: &[u8]) -> IResult<&[u8], ImbalanceIndicator> {
+    crate::messages::parse_imbalance_indicator(input)
+}
+
+/// IPO Quoting Period Update ('K') body
+pub fn parse_ipo_quoting_period(input: &[u8]) -> IResult<&[u8], IpoQuotingPeriod> {
+    crate::messages::parse_ipo_quoting_period(input)
+}
+
+/// Non-Cross Trade ('P') body
+pub fn parse_noncross_trade(input: &[u8]) -> IResult<&[u8], NonCrossTrade> {
+    crate::messages::parse_noncross_trade(input)
+}
+
+/// Market Participant Position ('L') body
+pub fn parse_participant_position(input: &[u8]) -> IResult<&[u8], MarketParticipantPosition> {
+    crate::messages::parse_participant_position(input)
+}
+
+/// Reg SHO Short Sale Price Test Restricted Indicator ('Y') body
+pub fn parse_reg_sho_restriction(input: &[u8]) -> IResult<&[u8], Body> {
+    crate::messages::parse_reg_sho_restriction(input)
+}
+
+/// Order Replace ('U') body
+pub fn parse_replace_order(input: &[u8]) -> IResult<&[u8], ReplaceOrder> {
+    crate::messages::parse_replace_order(input)
+}
+
+/// Retail Price Improvement Indicator ('N') body
+pub fn parse_retail_price_improvement_indicator(
+    input: &[u8],
+) -> IResult<&[u8], RetailPriceImprovementIndicator> {
+    crate::messages::parse_retail_price_improvement_indicator(input)
+}
+
+/// Stock Directory ('R') body
+pub fn parse_stock_directory(input: &[u8]) -> IResult<&[u8], StockDirectory> {
+    crate::messages::parse_stock_directory(input)
+}
+
+/// System Event ('S') body
+pub fn parse_system_event(input: &[u8]) -> IResult<&[u8], Body> {
+    crate::messages::parse_system_event(input)
+}
+
+/// Stock Trading Action ('H') body
+pub fn parse_trading_action(input: &[u8]) -> IResult<&[u8], Body> {
+    crate::messages::parse_trading_action(input)
+}
+
+/// An eight character, space-padded stock symbol
+pub fn parse_stock(input: &[u8]) -> IResult<&[u8], ArrayString8> {
+    crate::messages::stock(input)
+}
+
+/// A one character issue classification code
+pub fn parse_issue_classification(input: &[u8]) -> IResult<&[u8], IssueClassification> {
+    crate::enums::parse_issue_classification(input)
+}
+
+/// A two character issue subtype code
+pub fn parse_issue_subtype(input: &[u8]) -> IResult<&[u8], IssueSubType> {
+    crate::enums::parse_issue_subtype(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{Message, Side};
+
+    #[test]
+    fn parses_bodies() {
+        let mut buf = Vec::new();
+        Message {
+            tag: b'A',
+            ..msg(5, add(7, Side::Sell, 300, 12_500))
+        }
+        .encode(&mut buf);
+        let (rest, order) = parse_add_order(&buf[13..]).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            (order.reference, order.side, order.shares),
+            (7, Side::Sell, 300)
+        );
+        assert!(parse_add_order(&buf[13..20]).unwrap_err().is_incomplete());
+        let (_, parsed) = parse_message(&buf).unwrap();
+        assert_eq!(parsed.timestamp, 5);
+    }
+}
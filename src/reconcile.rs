@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::{ArrayString8, Body, CrossType, EventCode, Message, OrderTracker, OrderUpdate, Result};
+
+/// End-of-day totals for one symbol, see [`Reconciler`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SymbolReconciliation {
+    pub stock: Option<ArrayString8>,
+    pub adds: u64,
+    pub added_shares: u64,
+    pub executions: u64,
+    pub executed_shares: u64,
+    pub cancels: u64,
+    pub cancelled_shares: u64,
+    pub deletes: u64,
+    pub replaces: u64,
+    /// Orders still live at the end of the session
+    pub live_orders: u64,
+    /// Shares still live at the end of the session
+    pub live_shares: u64,
+    pub opening_cross_shares: u64,
+    pub closing_cross_shares: u64,
+    /// Shares crossed in IPO and halt release crosses
+    pub halt_cross_shares: u64,
+    /// Shares crossed in intraday and extended trading close crosses
+    pub other_cross_shares: u64,
+}
+
+/// End-of-day reconciliation report produced by [`Reconciler`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconciliationReport {
+    /// Per-symbol totals, keyed by stock locate
+    pub symbols: BTreeMap<u16, SymbolReconciliation>,
+    /// Timestamp of the End of Messages event, `None` if the capture
+    /// ended before it
+    pub end_of_messages: Option<u64>,
+}
+
+impl ReconciliationReport {
+    /// True if the capture reached the End of Messages event
+    pub fn is_complete(&self) -> bool {
+        self.end_of_messages.is_some()
+    }
+
+    /// Write the report as CSV, one row per symbol, with a header row
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "stock_locate,stock,adds,added_shares,executions,executed_shares,\
+             cancels,cancelled_shares,deletes,replaces,live_orders,live_shares,\
+             opening_cross_shares,closing_cross_shares,halt_cross_shares,other_cross_shares"
+        )?;
+        for (locate, s) in &self.symbols {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                locate,
+                s.stock.as_ref().map_or("", |s| s.trim_end()),
+                s.adds,
+                s.added_shares,
+                s.executions,
+                s.executed_shares,
+                s.cancels,
+                s.cancelled_shares,
+                s.deletes,
+                s.replaces,
+                s.live_orders,
+                s.live_shares,
+                s.opening_cross_shares,
+                s.closing_cross_shares,
+                s.halt_cross_shares,
+                s.other_cross_shares
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates per-symbol order and cross totals over a session, for
+/// checking a capture against the statistics published by the exchange.
+///
+/// Messages after the End of Messages event are ignored. With the `serde`
+/// feature the report can also be written as JSON.
+#[derive(Debug, Clone, Default)]
+pub struct Reconciler {
+    tracker: OrderTracker,
+    symbols: BTreeMap<u16, SymbolReconciliation>,
+    end_of_messages: Option<u64>,
+}
+
+impl Reconciler {
+    pub fn new() -> Reconciler {
+        Reconciler::default()
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        if self.end_of_messages.is_some() {
+            return;
+        }
+        let stats = self.symbols.entry(msg.stock_locate);
+        match msg.body {
+            Body::SystemEvent {
+                event: EventCode::EndOfMessages,
+            } => {
+                self.end_of_messages = Some(msg.timestamp);
+                return;
+            }
+            Body::SystemEvent { .. } => return,
+            Body::StockDirectory(ref dir) => {
+                stats.or_default().stock = Some(dir.stock);
+                return;
+            }
+            Body::CrossTrade(ref cross) => {
+                let stats = stats.or_default();
+                let total = match cross.cross_type {
+                    CrossType::Opening => &mut stats.opening_cross_shares,
+                    CrossType::Closing => &mut stats.closing_cross_shares,
+                    CrossType::IpoOrHalted => &mut stats.halt_cross_shares,
+                    CrossType::Intraday | CrossType::ExtendedTradingClose => {
+                        &mut stats.other_cross_shares
+                    }
+                };
+                *total += cross.shares;
+                return;
+            }
+            _ => {}
+        }
+        let Some(Ok(update)) = self.tracker.apply(msg) else {
+            return;
+        };
+        let stats = stats.or_default();
+        match update {
+            OrderUpdate::Added { order, .. } => {
+                stats.stock.get_or_insert(order.stock);
+                stats.adds += 1;
+                stats.added_shares += order.shares as u64;
+            }
+            OrderUpdate::Executed { shares, .. } => {
+                stats.executions += 1;
+                stats.executed_shares += shares as u64;
+            }
+            OrderUpdate::Cancelled { shares, .. } => {
+                stats.cancels += 1;
+                stats.cancelled_shares += shares as u64;
+            }
+            OrderUpdate::Deleted { .. } => stats.deletes += 1,
+            OrderUpdate::Replaced { .. } => stats.replaces += 1,
+        }
+    }
+
+    /// The report as of the last message, with live orders counted from
+    /// the orders still resting
+    pub fn report(&self) -> ReconciliationReport {
+        let mut symbols = self.symbols.clone();
+        for (_, order) in self.tracker.iter() {
+            let stats = symbols.entry(order.stock_locate).or_default();
+            stats.live_orders += 1;
+            stats.live_shares += order.shares as u64;
+        }
+        ReconciliationReport {
+            symbols,
+            end_of_messages: self.end_of_messages,
+        }
+    }
+}
+
+/// Run a [`Reconciler`] over a whole stream of messages
+pub fn reconcile<I>(messages: I) -> Result<ReconciliationReport>
+where
+    I: IntoIterator<Item = Result<Message>>,
+{
+    let mut reconciler = Reconciler::new();
+    for msg in messages {
+        reconciler.update(&msg?);
+    }
+    Ok(reconciler.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{CrossTrade, Side};
+
+    #[test]
+    fn reconciles_session() {
+        let exec = Body::OrderExecuted {
+            reference: 1,
+            executed: 30,
+            match_number: 1,
+        };
+        let cross = Body::CrossTrade(CrossTrade {
+            shares: 500,
+            stock: ArrayString8::from("ZXZZT   ").unwrap(),
+            cross_price: 10_000.into(),
+            match_number: 2,
+            cross_type: CrossType::Closing,
+        });
+        let end = Body::SystemEvent {
+            event: EventCode::EndOfMessages,
+        };
+        let messages = vec![
+            msg(1, add(1, Side::Buy, 100, 10_000)),
+            msg(2, add(2, Side::Sell, 50, 10_100)),
+            msg(3, exec),
+            msg(4, Body::DeleteOrder { reference: 2 }),
+            msg(5, cross),
+            msg(6, end),
+            msg(7, add(3, Side::Sell, 50, 10_100)),
+        ];
+        let report = reconcile(messages.into_iter().map(Ok)).unwrap();
+        assert_eq!(report.end_of_messages, Some(6));
+        let stats = &report.symbols[&1];
+        assert_eq!((stats.adds, stats.added_shares), (2, 150));
+        assert_eq!((stats.executions, stats.executed_shares), (1, 30));
+        assert_eq!((stats.live_orders, stats.live_shares), (1, 70));
+        assert_eq!(stats.closing_cross_shares, 500);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("1,ZXZZT,2,150,1,30,0,0,1,0,1,70,0,500,0,0")
+        );
+    }
+}
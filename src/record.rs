@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A capture file which moves on to a new file once it reaches a size
+/// limit, for recording a live feed with
+/// [`MoldReceiver::record_to`](crate::MoldReceiver::record_to).
+///
+/// Files are numbered after the first, before the extension, so that
+/// `capture.itch` is followed by `capture.1.itch`, `capture.2.itch` and so
+/// on. Files only change between writes, so a write holding whole
+/// messages never splits them across files, and each file can be read on
+/// its own. A single write larger than the limit still goes to one file.
+#[derive(Debug)]
+pub struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    file: BufWriter<File>,
+    written: u64,
+    paths: Vec<PathBuf>,
+}
+
+impl RotatingWriter {
+    pub fn create<P: AsRef<Path>>(path: P, max_bytes: u64) -> io::Result<RotatingWriter> {
+        let path = path.as_ref().to_path_buf();
+        let file = BufWriter::new(File::create(&path)?);
+        Ok(RotatingWriter {
+            paths: vec![path.clone()],
+            path,
+            max_bytes,
+            file,
+            written: 0,
+        })
+    }
+
+    /// The files written so far, in order
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut name = self.path.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", self.paths.len()));
+        if let Some(extension) = self.path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        let path = self.path.with_file_name(name);
+        self.file = BufWriter::new(File::create(&path)?);
+        self.paths.push(path);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::mold::tests::mold_packet;
+    use crate::{MessageStream, MoldReceiver, Packet};
+
+    #[test]
+    fn records_decoded_messages_across_files() {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        let packet = |sequence, count| Packet {
+            data: mold_packet("SESSION", sequence, &vec![&event[..]; count]),
+            receive_ts: None,
+        };
+        let dir = std::env::temp_dir().join(format!("itchy-record-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // room for two 14 byte framed messages per file
+        let writer = RotatingWriter::create(dir.join("capture.itch"), 30).unwrap();
+        let packets = vec![packet(1, 2), packet(2, 2), packet(4, 1), packet(5, 0)];
+        let receiver = MoldReceiver::new(packets.into_iter()).record_to(writer);
+        let sequences: Vec<_> = receiver.map(|e| e.unwrap().sequence).collect();
+        assert_eq!(sequences, [1, 2, 3, 4]);
+
+        let files = ["capture.itch", "capture.1.itch"];
+        let recorded: Vec<_> = files
+            .iter()
+            .map(|name| {
+                MessageStream::from_file(dir.join(name))
+                    .unwrap()
+                    .map(|m| m.unwrap().tag)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(recorded, [b"SS", b"SS"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
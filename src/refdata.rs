@@ -0,0 +1,213 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::{Body, Message, Result, StockDirectory};
+
+/// A field of a Stock Directory message
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DirectoryField {
+    Stock,
+    MarketCategory,
+    FinancialStatus,
+    RoundLotSize,
+    RoundLotsOnly,
+    IssueClassification,
+    IssueSubType,
+    Authenticity,
+    ShortSaleThreshold,
+    IpoFlag,
+    LuldRefPriceTier,
+    EtpFlag,
+    EtpLeverageFactor,
+    InverseIndicator,
+}
+
+impl DirectoryField {
+    /// Fields that differ between two directory entries
+    pub fn diff(before: &StockDirectory, after: &StockDirectory) -> Vec<DirectoryField> {
+        use DirectoryField::*;
+        let checks = [
+            (Stock, before.stock != after.stock),
+            (
+                MarketCategory,
+                before.market_category != after.market_category,
+            ),
+            (
+                FinancialStatus,
+                before.financial_status != after.financial_status,
+            ),
+            (RoundLotSize, before.round_lot_size != after.round_lot_size),
+            (
+                RoundLotsOnly,
+                before.round_lots_only != after.round_lots_only,
+            ),
+            (
+                IssueClassification,
+                before.issue_classification != after.issue_classification,
+            ),
+            (IssueSubType, before.issue_subtype != after.issue_subtype),
+            (Authenticity, before.authenticity != after.authenticity),
+            (
+                ShortSaleThreshold,
+                before.short_sale_threshold != after.short_sale_threshold,
+            ),
+            (IpoFlag, before.ipo_flag != after.ipo_flag),
+            (
+                LuldRefPriceTier,
+                before.luld_ref_price_tier != after.luld_ref_price_tier,
+            ),
+            (EtpFlag, before.etp_flag != after.etp_flag),
+            (
+                EtpLeverageFactor,
+                before.etp_leverage_factor != after.etp_leverage_factor,
+            ),
+            (
+                InverseIndicator,
+                before.inverse_indicator != after.inverse_indicator,
+            ),
+        ];
+        checks
+            .into_iter()
+            .filter_map(|(field, changed)| changed.then_some(field))
+            .collect()
+    }
+}
+
+/// A directory entry re-issued during the session with different contents,
+/// e.g. a ticker change or a new financial status
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReferenceDataChange {
+    pub stock_locate: u16,
+    pub timestamp: u64,
+    pub before: StockDirectory,
+    pub after: StockDirectory,
+    /// Fields that differ, in message order
+    pub changed: Vec<DirectoryField>,
+}
+
+impl ReferenceDataChange {
+    pub fn is_ticker_change(&self) -> bool {
+        self.changed.contains(&DirectoryField::Stock)
+    }
+}
+
+/// Current directory entry of each locate, derived from Stock Directory
+/// ('R') messages.
+///
+/// The first entry for a locate is taken as its reference data for the
+/// day. Later entries that differ from it produce a [`ReferenceDataChange`];
+/// entries re-sent unchanged do not.
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceDataTracker {
+    entries: HashMap<u16, StockDirectory>,
+}
+
+impl ReferenceDataTracker {
+    pub fn new() -> ReferenceDataTracker {
+        ReferenceDataTracker::default()
+    }
+
+    /// Apply a message, returning the change if it updated an existing entry
+    pub fn update(&mut self, msg: &Message) -> Option<ReferenceDataChange> {
+        let Body::StockDirectory(ref dir) = msg.body else {
+            return None;
+        };
+        match self.entries.entry(msg.stock_locate) {
+            Entry::Occupied(mut e) => {
+                let changed = DirectoryField::diff(e.get(), dir);
+                if changed.is_empty() {
+                    return None;
+                }
+                let before = e.insert(dir.clone());
+                Some(ReferenceDataChange {
+                    stock_locate: msg.stock_locate,
+                    timestamp: msg.timestamp,
+                    before,
+                    after: dir.clone(),
+                    changed,
+                })
+            }
+            Entry::Vacant(e) => {
+                e.insert(dir.clone());
+                None
+            }
+        }
+    }
+
+    /// Current entry for a locate code
+    pub fn get(&self, locate: u16) -> Option<&StockDirectory> {
+        self.entries.get(&locate)
+    }
+}
+
+/// Iterator of [`ReferenceDataChange`]s from a stream of messages. Errors
+/// from the message stream are passed through.
+#[derive(Debug)]
+pub struct ReferenceDataStream<I> {
+    messages: I,
+    tracker: ReferenceDataTracker,
+}
+
+impl<I: Iterator<Item = Result<Message>>> ReferenceDataStream<I> {
+    pub fn new(messages: I) -> ReferenceDataStream<I> {
+        ReferenceDataStream {
+            messages,
+            tracker: ReferenceDataTracker::new(),
+        }
+    }
+
+    /// Directory entries as of the last message read
+    pub fn tracker(&self) -> &ReferenceDataTracker {
+        &self.tracker
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>> Iterator for ReferenceDataStream<I> {
+    type Item = Result<ReferenceDataChange>;
+
+    fn next(&mut self) -> Option<Result<ReferenceDataChange>> {
+        loop {
+            match self.messages.next()? {
+                Ok(msg) => {
+                    if let Some(change) = self.tracker.update(&msg) {
+                        return Some(Ok(change));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::tests::directory_msg;
+    use crate::FinancialStatus;
+
+    #[test]
+    fn reports_intraday_changes() {
+        let mut deficient = directory_msg(3, "ABC");
+        if let Body::StockDirectory(ref mut dir) = deficient.body {
+            dir.financial_status = FinancialStatus::Deficient;
+        }
+        let messages = vec![
+            Ok(directory_msg(3, "ABC")),
+            Ok(directory_msg(4, "XYZ")),
+            // re-sent unchanged
+            Ok(directory_msg(3, "ABC")),
+            Ok(deficient),
+            Ok(directory_msg(4, "XYZW")),
+        ];
+        let mut stream = ReferenceDataStream::new(messages.into_iter());
+        let changes: Vec<_> = stream.by_ref().map(|c| c.unwrap()).collect();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].changed, [DirectoryField::FinancialStatus]);
+        assert_eq!(changes[0].before.financial_status, FinancialStatus::Normal);
+        assert!(!changes[0].is_ticker_change());
+        assert!(changes[1].is_ticker_change());
+        assert_eq!(stream.tracker().get(4).unwrap().stock.trim_end(), "XYZW");
+    }
+}
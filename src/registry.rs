@@ -0,0 +1,210 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::{FeedProfile, Result};
+
+type ParseFn = dyn Fn(&[u8]) -> Result<Arc<dyn Any + Send + Sync>> + Send + Sync;
+
+/// Parsers for message types outside the ITCH 5.0 specification, such as
+/// private messages injected by a redistributor or types added in a later
+/// version of the protocol.
+///
+/// A [`MessageStream`](crate::MessageStream) given a registry with
+/// [`MessageStreamBuilder::parsers`](crate::MessageStreamBuilder::parsers)
+/// yields messages of a registered type as [`Body::Extension`], where
+/// otherwise they would be an unknown message type:
+///
+/// ```ignore
+/// struct Heartbeat { session: u32 }
+///
+/// let mut registry = itchy::ParserRegistry::new();
+/// registry.register(b'k', |body| {
+///     let session = body.get(..4).ok_or(itchy::Error::Parse("short heartbeat".into()))?;
+///     Ok(Heartbeat { session: u32::from_be_bytes(session.try_into().unwrap()) })
+/// });
+/// let stream = itchy::MessageStream::builder()
+///     .parsers(registry)
+///     .open("/path/to/file.itch")?;
+/// ```
+///
+/// [`Body::Extension`]: crate::Body::Extension
+#[derive(Clone, Default)]
+pub struct ParserRegistry {
+    parsers: HashMap<u8, Option<Arc<ParseFn>>>,
+}
+
+impl ParserRegistry {
+    pub fn new() -> ParserRegistry {
+        ParserRegistry::default()
+    }
+
+    /// Parse messages of type `tag` with `parser`, which is given the body
+    /// of the message after the common header of type, stock locate,
+    /// tracking number and timestamp. Its value is available from
+    /// [`Extension::value`].
+    ///
+    /// # Panics
+    ///
+    /// If `tag` is a TotalView-ITCH 5.0 type
+    pub fn register<T, F>(&mut self, tag: u8, parser: F)
+    where
+        T: Any + Send + Sync,
+        F: Fn(&[u8]) -> Result<T> + Send + Sync + 'static,
+    {
+        let parser = move |body: &[u8]| -> Result<Arc<dyn Any + Send + Sync>> {
+            Ok(Arc::new(parser(body)?))
+        };
+        self.insert(tag, Some(Arc::new(parser)));
+    }
+
+    /// Pass messages of type `tag` through with only their raw bytes
+    ///
+    /// # Panics
+    ///
+    /// If `tag` is a TotalView-ITCH 5.0 type
+    pub fn register_raw(&mut self, tag: u8) {
+        self.insert(tag, None);
+    }
+
+    pub fn contains(&self, tag: u8) -> bool {
+        self.parsers.contains_key(&tag)
+    }
+
+    fn insert(&mut self, tag: u8, parser: Option<Arc<ParseFn>>) {
+        assert!(
+            !FeedProfile::TotalView.allows(tag),
+            "'{}' is an ITCH 5.0 message type",
+            tag.escape_ascii()
+        );
+        self.parsers.insert(tag, parser);
+    }
+
+    // parse the body of a registered message type, `None` if it is not
+    // registered
+    pub(crate) fn parse(&self, tag: u8, body: &[u8]) -> Option<Result<Extension>> {
+        let parser = self.parsers.get(&tag)?;
+        let value = match parser {
+            Some(parser) => match parser(body) {
+                Ok(value) => Some(value),
+                Err(e) => return Some(Err(e)),
+            },
+            None => None,
+        };
+        Some(Ok(Extension {
+            raw: body.to_vec(),
+            value,
+        }))
+    }
+}
+
+impl fmt::Debug for ParserRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut tags: Vec<_> = self.parsers.keys().collect();
+        tags.sort();
+        f.debug_struct("ParserRegistry")
+            .field("tags", &tags)
+            .finish()
+    }
+}
+
+/// The body of a message of a type registered with a [`ParserRegistry`].
+///
+/// Extensions compare and hash by their raw bytes, and only the raw bytes
+/// are serialized, so a deserialized extension has no value.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
+pub struct Extension {
+    raw: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    value: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extension {
+    /// An extension with only raw bytes, e.g. to inject into a stream
+    pub fn from_raw(raw: Vec<u8>) -> Extension {
+        Extension { raw, value: None }
+    }
+
+    /// The body of the message on the wire, after the common header
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// The value returned by the registered parser, if it is a `T`
+    pub fn value<T: Any>(&self) -> Option<&T> {
+        self.value.as_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Debug for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Extension")
+            .field("raw", &self.raw)
+            .field("parsed", &self.value.is_some())
+            .finish()
+    }
+}
+
+impl PartialEq for Extension {
+    fn eq(&self, other: &Extension) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for Extension {}
+
+impl Hash for Extension {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::{Body, Error, ErrorPolicy, MessageStream};
+
+    #[derive(Debug, PartialEq)]
+    struct Heartbeat(u32);
+
+    #[test]
+    fn parses_registered_types() {
+        let mut registry = ParserRegistry::new();
+        registry.register(b'k', |body| match *body {
+            [a, b, c, d] => Ok(Heartbeat(u32::from_be_bytes([a, b, c, d]))),
+            _ => Err(Error::Parse("bad heartbeat".into())),
+        });
+        registry.register_raw(b'z');
+
+        let mut data = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f");
+        data.extend_from_slice(&[0, 15, b'k', 0, 1, 0, 2, 0, 0, 0, 0, 0, 9, 0, 0, 1, 0]);
+        data.extend_from_slice(&[0, 11, b'k', 0, 1, 0, 2, 0, 0, 0, 0, 0, 9]);
+        data.extend_from_slice(&[0, 12, b'z', 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 7]);
+        data.extend(hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 9953"));
+
+        let items: Vec<_> = MessageStream::builder()
+            .parsers(registry)
+            .error_policy(ErrorPolicy::SkipMessage)
+            .build(&data[..])
+            .collect();
+        assert_eq!(items.len(), 5);
+        let heartbeat = items[1].as_ref().unwrap();
+        assert_eq!((heartbeat.stock_locate, heartbeat.tracking_number), (1, 2));
+        assert_eq!(heartbeat.timestamp, 9);
+        let Body::Extension(ref ext) = heartbeat.body else {
+            panic!("not an extension: {:?}", heartbeat);
+        };
+        assert_eq!(ext.value(), Some(&Heartbeat(256)));
+        assert!(items[2].is_err());
+        let raw = items[3].as_ref().unwrap();
+        assert_eq!(raw.body, Body::Extension(Extension::from_raw(vec![7])));
+
+        let mut encoded = Vec::new();
+        raw.encode(&mut encoded);
+        assert_eq!(encoded, data[14 + 17 + 13..][..14]);
+    }
+}
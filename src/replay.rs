@@ -0,0 +1,314 @@
+use std::io::Read;
+use std::iter::Peekable;
+use std::path::PathBuf;
+
+use crate::clock::{ClockSource, Session, WallClock};
+use crate::{open_auto, Message, MessageSink, MessageStream, Result};
+
+/// Replays several daily files as one continuous stream, see
+/// [`ContinuousReplayer::new`].
+///
+/// Message timestamps are rewritten from nanoseconds since Eastern midnight
+/// to nanoseconds since the Unix epoch (UTC), using the session date of the
+/// file each message came from, so that they increase across days.
+pub struct ContinuousReplayer {
+    // remaining days, latest first
+    days: Vec<(Session, PathBuf)>,
+    current: Option<(Session, MessageStream<Box<dyn Read + Send>>)>,
+}
+
+impl ContinuousReplayer {
+    /// Replay the given files in date order. Each file is opened with
+    /// [`open_auto`] when the previous day is finished.
+    pub fn new<I, P>(paths_by_date: I) -> ContinuousReplayer
+    where
+        I: IntoIterator<Item = (Session, P)>,
+        P: Into<PathBuf>,
+    {
+        let mut days: Vec<_> = paths_by_date
+            .into_iter()
+            .map(|(session, path)| (session, path.into()))
+            .collect();
+        days.sort_by_key(|&(session, _)| std::cmp::Reverse(session));
+        ContinuousReplayer {
+            days,
+            current: None,
+        }
+    }
+
+    /// The session of the file currently being replayed
+    pub fn session(&self) -> Option<Session> {
+        self.current.as_ref().map(|(session, _)| *session)
+    }
+}
+
+impl Iterator for ContinuousReplayer {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        loop {
+            if let Some((session, stream)) = self.current.as_mut() {
+                match stream.next() {
+                    Some(Ok(mut msg)) => {
+                        msg.timestamp = session.to_utc_nanos(msg.timestamp) as u64;
+                        return Some(Ok(msg));
+                    }
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => self.current = None,
+                }
+            }
+            let (session, path) = self.days.pop()?;
+            match open_auto(&path) {
+                Ok(stream) => self.current = Some((session, stream)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Yields messages no faster than their timestamps allow, so that a
+/// capture plays back at the pace it was recorded, or a multiple of it.
+///
+/// The first message is yielded at once. Each later message waits until
+/// the clock has moved on by the time between it and the first, divided by
+/// the [`speed`](PacedReplayer::speed). Time is kept by a [`ClockSource`]:
+/// the system clock by default, or a
+/// [`SimulatedClock`](crate::clock::SimulatedClock) for tests.
+pub struct PacedReplayer<I, C = WallClock> {
+    messages: I,
+    clock: C,
+    speed: f64,
+    // timestamp of the first message and the clock time it was yielded
+    origin: Option<(u64, u64)>,
+}
+
+impl<I: Iterator<Item = Result<Message>>> PacedReplayer<I> {
+    pub fn new(messages: I) -> PacedReplayer<I> {
+        PacedReplayer::with_clock(messages, WallClock)
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>, C: ClockSource> PacedReplayer<I, C> {
+    pub fn with_clock(messages: I, clock: C) -> PacedReplayer<I, C> {
+        PacedReplayer {
+            messages,
+            clock,
+            speed: 1.0,
+            origin: None,
+        }
+    }
+
+    /// Play back `speed` times faster than recorded (1.0 by default)
+    ///
+    /// # Panics
+    ///
+    /// If `speed` is not positive
+    pub fn speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "replay speed must be positive");
+        self.speed = speed;
+        self
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>, C: ClockSource> Iterator for PacedReplayer<I, C> {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        let msg = match self.messages.next()? {
+            Ok(msg) => msg,
+            Err(e) => return Some(Err(e)),
+        };
+        match self.origin {
+            None => self.origin = Some((msg.timestamp, self.clock.now())),
+            Some((first, start)) => {
+                let elapsed = msg.timestamp.saturating_sub(first) as f64 / self.speed;
+                self.clock.sleep_until(start + elapsed as u64);
+            }
+        }
+        Some(Ok(msg))
+    }
+}
+
+/// Drives a stream up to chosen points in time, exposing the state built
+/// from it at each point, e.g. "the state of the world at 10:00:00".
+///
+/// The state is any [`MessageSink`], such as a [`BookManager`](crate::BookManager)
+/// for books and trading states, or several trackers combined with
+/// [`MessageSink::and`]. While the state is borrowed the stream cannot
+/// advance, so every view is consistent as of a single instant.
+pub struct ReplayController<I: Iterator, S> {
+    messages: Peekable<I>,
+    state: S,
+    timestamp: u64,
+    applied: u64,
+    // the state stopped the stream
+    stopped: bool,
+}
+
+impl<I, S> ReplayController<I, S>
+where
+    I: Iterator<Item = Result<Message>>,
+    S: MessageSink,
+{
+    pub fn new<M>(messages: M, state: S) -> ReplayController<I, S>
+    where
+        M: IntoIterator<IntoIter = I>,
+    {
+        ReplayController {
+            messages: messages.into_iter().peekable(),
+            state,
+            timestamp: 0,
+            applied: 0,
+            stopped: false,
+        }
+    }
+
+    /// Apply every message with a timestamp up to and including `timestamp`
+    /// and return the resulting state. Messages with later timestamps are
+    /// left for the next call.
+    ///
+    /// The stream is assumed to be in timestamp order, as ITCH files are.
+    /// If the state stops the stream, nothing more is applied.
+    pub fn advance_to(&mut self, timestamp: u64) -> Result<&S> {
+        while !self.stopped {
+            let Some(next) = self.messages.peek() else {
+                break;
+            };
+            if matches!(next, Ok(msg) if msg.timestamp > timestamp) {
+                break;
+            }
+            let Some(msg) = self.messages.next() else {
+                break;
+            };
+            let msg = msg?;
+            self.applied += 1;
+            self.stopped = self.state.accept(msg).is_break();
+        }
+        self.timestamp = self.timestamp.max(timestamp);
+        Ok(&self.state)
+    }
+
+    /// Apply the rest of the stream
+    pub fn finish(&mut self) -> Result<&S> {
+        self.advance_to(u64::MAX)
+    }
+
+    /// The state as of [`timestamp`](ReplayController::timestamp)
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// The latest point in time the stream has been advanced to
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Number of messages applied so far
+    pub fn messages_applied(&self) -> u64 {
+        self.applied
+    }
+
+    pub fn into_state(self) -> S {
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{SimulatedClock, MARKET_OPEN};
+    use crate::orders::tests::{add, msg};
+    use crate::{Body, Book, BookManager, Reconciler, Side};
+
+    #[test]
+    fn replays_days_in_order() {
+        let mut paths = Vec::new();
+        for day in [16u8, 15] {
+            let mut data = Vec::new();
+            for ts in [MARKET_OPEN, MARKET_OPEN + 1] {
+                data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+                data.extend_from_slice(&ts.to_be_bytes()[2..]);
+                data.push(b'Q');
+            }
+            let path =
+                std::env::temp_dir().join(format!("itchy-replay-{}-{}", std::process::id(), day));
+            std::fs::write(&path, &data).unwrap();
+            paths.push((Session::new(2024, 1, day).unwrap(), path));
+        }
+        let replayer = ContinuousReplayer::new(paths.clone());
+        let timestamps: Vec<_> = replayer.map(|m| m.unwrap().timestamp).collect();
+        for (_, path) in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+
+        // 2024-01-15T14:30:00Z
+        let open = 1_705_329_000_000_000_000;
+        let day = 86_400_000_000_000;
+        assert_eq!(timestamps, [open, open + 1, open + day, open + day + 1]);
+    }
+
+    #[test]
+    fn paces_against_clock() {
+        let messages = vec![
+            Ok(msg(1_000, add(1, Side::Buy, 100, 10_000))),
+            Ok(msg(1_001_000, add(2, Side::Buy, 100, 10_000))),
+            Ok(msg(3_001_000, add(3, Side::Buy, 100, 10_000))),
+        ];
+        let clock = SimulatedClock::new(500);
+        let replay = PacedReplayer::with_clock(messages.into_iter(), clock.clone()).speed(2.0);
+        let times: Vec<_> = replay
+            .map(|msg| {
+                msg.unwrap();
+                clock.now()
+            })
+            .collect();
+        assert_eq!(times, [500, 500_500, 1_500_500]);
+    }
+
+    #[test]
+    fn stops_at_each_instant() {
+        let messages = vec![
+            msg(10, add(1, Side::Buy, 100, 10_000)),
+            msg(20, add(2, Side::Sell, 50, 10_100)),
+            msg(20, Body::DeleteOrder { reference: 1 }),
+            msg(30, add(3, Side::Buy, 70, 9_900)),
+        ];
+        let state = BookManager::new().and(Reconciler::new());
+        let mut replay = ReplayController::new(messages.into_iter().map(Ok), state);
+
+        let (books, stats) = replay.advance_to(19).unwrap().parts();
+        let book = books.book(1).unwrap();
+        assert_eq!(book.best_bid().unwrap().price, 10_000.into());
+        assert!(book.best_ask().is_none());
+        assert_eq!(stats.report().symbols[&1].adds, 1);
+
+        // both messages at the target instant are applied
+        let books = replay.advance_to(20).unwrap().parts().0;
+        assert!(books.book(1).unwrap().best_bid().is_none());
+        assert_eq!(replay.messages_applied(), 3);
+
+        let (books, stats) = replay.finish().unwrap().parts();
+        assert_eq!(books.book(1).unwrap().best_bid().unwrap().shares, 70);
+        assert_eq!(stats.report().symbols[&1].adds, 3);
+    }
+
+    #[test]
+    fn stops_reading_once_the_state_breaks() {
+        let mut read = 0;
+        let messages = (0..100).map(|ts| {
+            read += 1;
+            Ok(msg(ts, Body::DeleteOrder { reference: ts }))
+        });
+        let state = crate::sink_fn(|_| std::ops::ControlFlow::Break(()));
+        let mut replay = ReplayController::new(messages, state);
+        replay.advance_to(50).unwrap();
+        replay.finish().unwrap();
+        assert_eq!(replay.messages_applied(), 1);
+        drop(replay);
+        assert_eq!(read, 1);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{Error, Message, Result};
+
+// timestamps are nanoseconds since midnight, so must be within a day
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+
+/// How [`Retimed`] moves the timestamps of a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeShift {
+    /// Add a number of nanoseconds, negative to move messages earlier
+    By(i64),
+    /// Move every message by the same amount so that the first is at this
+    /// time, e.g. `StartAt(0)` to rebase a stream to midnight
+    StartAt(u64),
+}
+
+/// Rewrites the timestamps of a stream of messages, e.g. to line up
+/// captures of different sessions for a composite test scenario or a
+/// synchronized replay.
+///
+/// Messages keep their order and relative spacing, and only their header
+/// timestamps change: times within message bodies, such as the release
+/// time of an IPO quoting period, are kept. A message moved before
+/// midnight or past the end of the day is an error, and is left out.
+///
+/// Timestamps are times of day, so moving a capture to another date needs
+/// no shift: give its new date to whatever converts feed times, such as a
+/// [`clock::Session`](crate::clock::Session).
+///
+/// ```ignore
+/// let stream = itchy::MessageStream::from_file("/path/to/file.itch")?;
+/// for msg in itchy::Retimed::new(stream, itchy::TimeShift::StartAt(0)) {
+///     println!("{:?}", msg?);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Retimed<I> {
+    iter: I,
+    shift: TimeShift,
+    offset: Option<i64>,
+}
+
+impl<I> Retimed<I>
+where
+    I: Iterator<Item = Result<Message>>,
+{
+    pub fn new(iter: I, shift: TimeShift) -> Retimed<I> {
+        let offset = match shift {
+            TimeShift::By(offset) => Some(offset),
+            TimeShift::StartAt(_) => None,
+        };
+        Retimed {
+            iter,
+            shift,
+            offset,
+        }
+    }
+
+    /// Nanoseconds added to each timestamp, known once the first message
+    /// has been seen when starting at a given time
+    pub fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+
+    fn retime(&mut self, mut msg: Message) -> Result<Message> {
+        let offset = match (self.offset, self.shift) {
+            (Some(offset), _) => offset,
+            (None, TimeShift::StartAt(start)) => {
+                let offset = start as i64 - msg.timestamp as i64;
+                self.offset = Some(offset);
+                offset
+            }
+            (None, TimeShift::By(offset)) => offset,
+        };
+        let timestamp = msg.timestamp as i64 + offset;
+        if !(0..NANOS_PER_DAY as i64).contains(&timestamp) {
+            return Err(Error::Parse(format!(
+                "timestamp {} moved by {}ns is outside the day",
+                msg.timestamp, offset
+            )));
+        }
+        msg.timestamp = timestamp as u64;
+        Ok(msg)
+    }
+}
+
+impl<I> Iterator for Retimed<I>
+where
+    I: Iterator<Item = Result<Message>>,
+{
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        match self.iter.next()? {
+            Ok(msg) => Some(self.retime(msg)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn shifts_and_rebases() {
+        let messages = || {
+            [5_000, 6_000, 9_000]
+                .into_iter()
+                .map(|ts| Ok(msg(ts, add(1, Side::Buy, 100, 10_000))))
+        };
+        let timestamps = |shift| -> Vec<_> {
+            Retimed::new(messages(), shift)
+                .map(|m| m.map(|m| m.timestamp).ok())
+                .collect()
+        };
+        assert_eq!(
+            timestamps(TimeShift::StartAt(0)),
+            [Some(0), Some(1_000), Some(4_000)]
+        );
+        assert_eq!(
+            timestamps(TimeShift::By(1_000)),
+            [Some(6_000), Some(7_000), Some(10_000)]
+        );
+        assert_eq!(
+            timestamps(TimeShift::By(-6_000)),
+            [None, Some(0), Some(3_000)]
+        );
+
+        let mut retimed = Retimed::new(messages(), TimeShift::StartAt(NANOS_PER_DAY - 2_000));
+        assert_eq!(retimed.offset(), None);
+        assert!(retimed.next().unwrap().is_ok());
+        assert_eq!(retimed.offset(), Some(NANOS_PER_DAY as i64 - 7_000));
+        assert!(retimed.next().unwrap().is_ok());
+        assert!(retimed.next().unwrap().is_err());
+    }
+}
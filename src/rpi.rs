@@ -0,0 +1,130 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::{ArrayString8, Body, InterestFlag, Message, Symbol};
+
+/// Latest retail price improvement interest for a symbol
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RpiState {
+    pub interest_flag: InterestFlag,
+    /// Timestamp at which the current flag took effect
+    pub since: u64,
+}
+
+/// Emitted when the RPI interest of a symbol changes
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RpiChange {
+    pub stock: ArrayString8,
+    pub stock_locate: u16,
+    /// `None` if this is the first indicator seen for the symbol
+    pub previous: Option<InterestFlag>,
+    pub current: InterestFlag,
+    pub timestamp: u64,
+}
+
+/// Per-symbol retail price improvement state, derived from
+/// Retail Price Improvement Indicator ('N') messages
+#[derive(Debug, Clone, Default)]
+pub struct RpiTracker {
+    states: HashMap<ArrayString8, RpiState>,
+}
+
+impl RpiTracker {
+    pub fn new() -> RpiTracker {
+        RpiTracker::default()
+    }
+
+    /// Apply a message, returning a change event if the symbol's interest changed.
+    /// Repeated indicators with an unchanged flag do not produce an event.
+    pub fn update(&mut self, msg: &Message) -> Option<RpiChange> {
+        let rpi = match msg.body {
+            Body::RetailPriceImprovementIndicator(ref rpi) => rpi,
+            _ => return None,
+        };
+        let new = RpiState {
+            interest_flag: rpi.interest_flag,
+            since: msg.timestamp,
+        };
+        let previous = match self.states.entry(rpi.stock) {
+            Entry::Occupied(mut e) => {
+                if e.get().interest_flag == rpi.interest_flag {
+                    return None;
+                }
+                Some(e.insert(new).interest_flag)
+            }
+            Entry::Vacant(e) => {
+                e.insert(new);
+                None
+            }
+        };
+        Some(RpiChange {
+            stock: rpi.stock,
+            stock_locate: msg.stock_locate,
+            previous,
+            current: rpi.interest_flag,
+            timestamp: msg.timestamp,
+        })
+    }
+
+    /// Current state for a symbol. Trailing padding is ignored.
+    pub fn get(&self, symbol: &str) -> Option<&RpiState> {
+        self.states.get(&Symbol::new(symbol)?.padded())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ArrayString8, &RpiState)> {
+        self.states.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RetailPriceImprovementIndicator;
+
+    fn rpi(timestamp: u64, stock: &str, interest_flag: InterestFlag) -> Message {
+        Message {
+            tag: b'N',
+            stock_locate: 1,
+            tracking_number: 0,
+            timestamp,
+            body: Body::RetailPriceImprovementIndicator(RetailPriceImprovementIndicator {
+                stock: ArrayString8::from(stock).unwrap(),
+                interest_flag,
+            }),
+        }
+    }
+
+    #[test]
+    fn emits_changes_only() {
+        use InterestFlag::*;
+        let mut tracker = RpiTracker::new();
+        let first = tracker
+            .update(&rpi(1, "ESSA    ", RPIAvailableBuySide))
+            .unwrap();
+        assert_eq!(first.previous, None);
+
+        assert!(tracker
+            .update(&rpi(2, "ESSA    ", RPIAvailableBuySide))
+            .is_none());
+        assert_eq!(tracker.get("ESSA").unwrap().since, 1);
+
+        let change = tracker
+            .update(&rpi(3, "ESSA    ", RPINoneAvailable))
+            .unwrap();
+        assert_eq!(change.previous, Some(RPIAvailableBuySide));
+        assert_eq!(change.current, RPINoneAvailable);
+        assert_eq!(tracker.get("ESSA").unwrap().since, 3);
+        assert_eq!(tracker.get("ESSA    ").unwrap().since, 3);
+        assert_eq!(tracker.len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::{Body, Message, Result};
+
+// messages applying to an earlier order, kept with the order they apply to
+const ORDER_TAGS: &[u8] = b"ECXDU";
+
+/// Thins out a stream by message type, to make small but representative
+/// test files.
+///
+/// Types with a sampling rate set by [`rate`](StratifiedSampler::rate) are
+/// kept at that rate, and every other type is kept in full, so system
+/// events, the stock directory, trading actions and the like are complete.
+/// Order executions, cancels, deletes and replaces cannot have a rate of
+/// their own: they are kept exactly when the order they apply to was, so
+/// books built from the sampled stream stay consistent. An order replaced
+/// by a kept replace is kept in turn.
+///
+/// Orders and trades are chosen by hashing their order reference (or the
+/// match number of trades without one), so the sample is deterministic
+/// for a given seed.
+///
+/// ```ignore
+/// let mut sampler = itchy::StratifiedSampler::new(7)
+///     .rate(b"AF", 0.01)
+///     .rate(b"P", 0.05);
+/// let stream = itchy::MessageStream::from_file("/path/to/file.itch")?;
+/// sampler.rewrite(stream, std::fs::File::create("sample.itch")?)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    seed: u64,
+    // fraction of each type kept
+    rates: [f64; 256],
+    // orders kept, by reference
+    orders: HashSet<u64>,
+}
+
+impl StratifiedSampler {
+    /// A sampler keeping every message until rates are set
+    pub fn new(seed: u64) -> StratifiedSampler {
+        StratifiedSampler {
+            seed,
+            rates: [1.0; 256],
+            orders: HashSet::new(),
+        }
+    }
+
+    /// Keep this fraction of the messages of these types, from 0 (none) to
+    /// 1 (all)
+    ///
+    /// # Panics
+    ///
+    /// If `rate` is outside that range, or `tags` includes an execution,
+    /// cancel, delete or replace, which are sampled with their orders
+    pub fn rate(mut self, tags: &[u8], rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "sampling rate must be between 0 and 1"
+        );
+        for &tag in tags {
+            assert!(
+                !ORDER_TAGS.contains(&tag),
+                "'{}' messages are sampled with their orders",
+                tag as char
+            );
+            self.rates[tag as usize] = rate;
+        }
+        self
+    }
+
+    /// Whether to keep a message. Messages must be passed in stream order.
+    pub fn keep(&mut self, msg: &Message) -> bool {
+        let rate = self.rates[msg.tag as usize];
+        match msg.body {
+            Body::AddOrder(ref add) => {
+                let keep = self.sampled(add.reference, rate);
+                if keep {
+                    self.orders.insert(add.reference);
+                }
+                keep
+            }
+            Body::OrderExecuted { reference, .. }
+            | Body::OrderExecutedWithPrice { reference, .. }
+            | Body::OrderCancelled { reference, .. } => self.orders.contains(&reference),
+            Body::DeleteOrder { reference } => self.orders.remove(&reference),
+            Body::ReplaceOrder(ref replace) => {
+                let keep = self.orders.remove(&replace.old_reference);
+                if keep {
+                    self.orders.insert(replace.new_reference);
+                }
+                keep
+            }
+            Body::NonCrossTrade(ref trade) if trade.reference != 0 => {
+                self.sampled(trade.reference, rate)
+            }
+            Body::NonCrossTrade(ref trade) => self.sampled(trade.match_number, rate),
+            Body::CrossTrade(ref cross) => self.sampled(cross.match_number, rate),
+            Body::BrokenTrade { match_number } => self.sampled(match_number, rate),
+            _ => self.sampled(msg.timestamp ^ (u64::from(msg.stock_locate) << 48), rate),
+        }
+    }
+
+    /// Sample a stream of messages, writing those kept out as
+    /// length-prefixed ITCH 5.0. Returns the number of messages written.
+    pub fn rewrite<I, W>(&mut self, messages: I, mut writer: W) -> Result<u64>
+    where
+        I: IntoIterator<Item = Result<Message>>,
+        W: Write,
+    {
+        let mut buf = Vec::new();
+        let mut count = 0;
+        for msg in messages {
+            let msg = msg?;
+            if self.keep(&msg) {
+                buf.clear();
+                msg.encode(&mut buf);
+                writer.write_all(&buf)?;
+                count += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    // whether a key is in the sample, from a pseudo-random value fixed by
+    // the seed (splitmix64)
+    fn sampled(&self, key: u64, rate: f64) -> bool {
+        if rate >= 1.0 {
+            return true;
+        }
+        let mut z = self.seed ^ key.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        ((z >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{EventCode, ReplaceOrder, Side};
+
+    #[test]
+    fn keeps_order_lifecycles_together() {
+        let mut sampler = StratifiedSampler::new(1).rate(b"A", 0.25);
+        let tagged = |tag, body| Message {
+            tag,
+            ..msg(0, body)
+        };
+        assert!(sampler.keep(&tagged(
+            b'S',
+            Body::SystemEvent {
+                event: EventCode::StartOfMessages
+            }
+        )));
+
+        let mut kept = Vec::new();
+        for reference in 1..=1000 {
+            if sampler.keep(&tagged(b'A', add(reference, Side::Buy, 100, 10_000))) {
+                kept.push(reference);
+            }
+        }
+        assert!((200..300).contains(&kept.len()), "{}", kept.len());
+
+        for reference in 1..=1000 {
+            let replace = tagged(
+                b'U',
+                Body::ReplaceOrder(ReplaceOrder {
+                    old_reference: reference,
+                    new_reference: reference + 1000,
+                    shares: 100,
+                    price: 10_000.into(),
+                }),
+            );
+            assert_eq!(sampler.keep(&replace), kept.contains(&reference));
+            let delete = tagged(
+                b'D',
+                Body::DeleteOrder {
+                    reference: reference + 1000,
+                },
+            );
+            assert_eq!(sampler.keep(&delete), kept.contains(&reference));
+        }
+        assert!(sampler.orders.is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+use std::fs::File;
+use std::io::{self, prelude::*, SeekFrom};
+use std::path::Path;
+
+use crate::parallel::run_parallel;
+use crate::{Error, Message, Result, SliceStream};
+
+// the seek table is a skippable frame at the end of the file
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+const SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+// frame count, descriptor and magic number
+const FOOTER_LEN: u64 = 9;
+const CHECKSUM_FLAG: u8 = 0x80;
+
+// decompressed frames are cut at the first message boundary past this size
+const DEFAULT_FRAME_SIZE: usize = 1 << 20;
+
+/// A frame of a file in the zstd seekable format, from its seek table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SeekableFrame {
+    /// Byte offset of the compressed frame in the file
+    pub compressed_offset: u64,
+    pub compressed_size: u32,
+    /// Byte offset of the frame's contents in the decompressed stream
+    pub offset: u64,
+    pub size: u32,
+}
+
+/// Compresses a stream of length-prefixed messages in the zstd seekable
+/// format: independent zstd frames followed by a table of their sizes,
+/// which any zstd decompressor can read, and which [`SeekableReader`] can
+/// seek into.
+///
+/// Frames are cut at message boundaries, so each holds whole messages and
+/// can be parsed on its own, as [`analyze_parallel_zstd`] does. Frames are
+/// only written as enough data arrives and the seek table is written by
+/// [`finish`](SeekableWriter::finish), without which the output is plain
+/// zstd. With the `zstd` feature.
+///
+/// ```ignore
+/// let mut writer = itchy::SeekableWriter::new(File::create("day.itch.zst")?, 3);
+/// io::copy(&mut File::open("day.itch")?, &mut writer)?;
+/// writer.finish()?;
+/// ```
+#[derive(Debug)]
+pub struct SeekableWriter<W: Write> {
+    writer: W,
+    level: i32,
+    frame_size: usize,
+    buf: Vec<u8>,
+    // offset in `buf` of the next message not yet known to be complete
+    boundary: usize,
+    frames: Vec<(u32, u32)>,
+}
+
+impl<W: Write> SeekableWriter<W> {
+    /// Compress at the given zstd level, 0 for zstd's default
+    pub fn new(writer: W, level: i32) -> SeekableWriter<W> {
+        SeekableWriter {
+            writer,
+            level,
+            frame_size: DEFAULT_FRAME_SIZE,
+            buf: Vec::new(),
+            boundary: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Decompressed size of each frame, before it is rounded up to a message
+    /// boundary (1 MiB by default). Smaller frames make seeking cheaper and
+    /// compress less well.
+    ///
+    /// # Panics
+    ///
+    /// If `frame_size` is zero or over 2 GiB
+    pub fn frame_size(mut self, frame_size: usize) -> Self {
+        assert!(
+            frame_size > 0 && frame_size <= u32::MAX as usize / 2,
+            "frame size must be non-zero and fit a seek table"
+        );
+        self.frame_size = frame_size;
+        self
+    }
+
+    /// The frames written so far, as `(compressed, decompressed)` sizes
+    pub fn frames(&self) -> &[(u32, u32)] {
+        &self.frames
+    }
+
+    /// Compress what remains into a last frame, write the seek table, flush
+    /// the writer and return it. Trailing bytes of an incomplete message
+    /// end up in the last frame.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.buf.is_empty() {
+            self.write_frame(self.buf.len())?;
+        }
+        let mut table = Vec::with_capacity(8 * self.frames.len() + 17);
+        table.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+        let table_len = 8 * self.frames.len() as u64 + FOOTER_LEN;
+        let table_len = u32::try_from(table_len)
+            .map_err(|_| io::Error::other("too many frames for a seek table"))?;
+        table.extend_from_slice(&table_len.to_le_bytes());
+        for &(compressed, decompressed) in &self.frames {
+            table.extend_from_slice(&compressed.to_le_bytes());
+            table.extend_from_slice(&decompressed.to_le_bytes());
+        }
+        table.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        table.push(0);
+        table.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+        self.writer.write_all(&table)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    // compress the first `end` bytes of the buffer into a frame
+    fn write_frame(&mut self, end: usize) -> io::Result<()> {
+        let compressed = zstd::bulk::compress(&self.buf[..end], self.level)?;
+        let size = |n: usize| {
+            u32::try_from(n).map_err(|_| io::Error::other("frame too large for a seek table"))
+        };
+        self.frames.push((size(compressed.len())?, size(end)?));
+        self.writer.write_all(&compressed)?;
+        self.buf.drain(..end);
+        self.boundary -= end.min(self.boundary);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SeekableWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        loop {
+            // find the first message boundary at or past the frame size
+            while self.boundary < self.frame_size && self.boundary + 2 <= self.buf.len() {
+                let len = &self.buf[self.boundary..self.boundary + 2];
+                self.boundary += 2 + u16::from_be_bytes([len[0], len[1]]) as usize;
+            }
+            if self.boundary < self.frame_size || self.boundary > self.buf.len() {
+                return Ok(data.len());
+            }
+            self.write_frame(self.boundary)?;
+        }
+    }
+
+    /// Flushes the underlying writer, without cutting a frame short
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads a file in the zstd seekable format, decompressing only the frames
+/// needed to read from a position.
+///
+/// It implements `Seek` in terms of decompressed offsets, so a
+/// [`MessageStream`](crate::MessageStream) over it can
+/// [`restore`](crate::MessageStream::restore) checkpoints, and the offsets
+/// of a [`TimeIndex`](crate::TimeIndex) built from it can be seeked to.
+/// Files from other seekable zstd writers can be read, but frame checksums
+/// are not verified. With the `zstd` feature.
+#[derive(Debug)]
+pub struct SeekableReader<R> {
+    reader: R,
+    frames: Vec<SeekableFrame>,
+    pos: u64,
+    // index and contents of the last frame decompressed
+    current: Option<usize>,
+    data: Vec<u8>,
+}
+
+impl SeekableReader<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SeekableReader<File>> {
+        SeekableReader::new(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> SeekableReader<R> {
+    /// Read the seek table from the end of the file
+    pub fn new(mut reader: R) -> Result<SeekableReader<R>> {
+        let frames = read_seek_table(&mut reader)?;
+        Ok(SeekableReader {
+            reader,
+            frames,
+            pos: 0,
+            current: None,
+            data: Vec::new(),
+        })
+    }
+
+    pub fn frames(&self) -> &[SeekableFrame] {
+        &self.frames
+    }
+
+    /// Size of the decompressed stream
+    pub fn len(&self) -> u64 {
+        self.frames.last().map_or(0, |f| f.offset + f.size as u64)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> Read for SeekableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let ix = self
+            .frames
+            .partition_point(|f| f.offset + f.size as u64 <= self.pos);
+        let Some(frame) = self.frames.get(ix) else {
+            return Ok(0);
+        };
+        if self.current != Some(ix) {
+            self.current = None;
+            self.data = read_frame(&mut self.reader, frame)?;
+            self.current = Some(ix);
+        }
+        let available = &self.data[(self.pos - frame.offset) as usize..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekableReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of stream")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+fn read_seek_table<R: Read + Seek>(reader: &mut R) -> Result<Vec<SeekableFrame>> {
+    let bad_table = |reason: &str| Error::Parse(format!("invalid zstd seek table: {}", reason));
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    if file_len < FOOTER_LEN + 8 {
+        return Err(bad_table("file too short"));
+    }
+    let mut footer = [0; FOOTER_LEN as usize];
+    reader.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+    reader.read_exact(&mut footer)?;
+    let le32 = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    if le32(&footer[5..]) != SEEKABLE_MAGIC {
+        return Err(bad_table("no seekable magic number"));
+    }
+    let frame_ct = le32(&footer[..4]) as u64;
+    let entry_len = if footer[4] & CHECKSUM_FLAG != 0 {
+        12
+    } else {
+        8
+    };
+    let table_len = frame_ct * entry_len + FOOTER_LEN;
+    let table_start = file_len
+        .checked_sub(table_len + 8)
+        .ok_or_else(|| bad_table("more frames than fit in the file"))?;
+
+    let mut table = vec![0; (table_len + 8) as usize];
+    reader.seek(SeekFrom::Start(table_start))?;
+    reader.read_exact(&mut table)?;
+    if le32(&table) != SKIPPABLE_MAGIC || le32(&table[4..]) as u64 != table_len {
+        return Err(bad_table("no skippable frame header"));
+    }
+    let mut frames = Vec::with_capacity(frame_ct as usize);
+    let (mut compressed_offset, mut offset) = (0, 0);
+    for entry in table[8..]
+        .chunks_exact(entry_len as usize)
+        .take(frame_ct as usize)
+    {
+        let frame = SeekableFrame {
+            compressed_offset,
+            compressed_size: le32(entry),
+            offset,
+            size: le32(&entry[4..]),
+        };
+        compressed_offset += frame.compressed_size as u64;
+        offset += frame.size as u64;
+        frames.push(frame);
+    }
+    if compressed_offset != table_start {
+        return Err(bad_table("frame sizes do not match the file"));
+    }
+    Ok(frames)
+}
+
+// decompress one frame
+fn read_frame<R: Read + Seek>(reader: &mut R, frame: &SeekableFrame) -> io::Result<Vec<u8>> {
+    let mut compressed = vec![0; frame.compressed_size as usize];
+    reader.seek(SeekFrom::Start(frame.compressed_offset))?;
+    reader.read_exact(&mut compressed)?;
+    let data = zstd::bulk::decompress(&compressed, frame.size as usize)?;
+    if data.len() != frame.size as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "zstd frame at {} decompressed to {} bytes, expected {}",
+                frame.compressed_offset,
+                data.len(),
+                frame.size
+            ),
+        ));
+    }
+    Ok(data)
+}
+
+/// Run a map-reduce style analysis over a zstd seekable file on all
+/// available cores, as [`analyze_parallel`](crate::analyze_parallel) does
+/// for uncompressed files.
+///
+/// Each frame is decompressed and parsed on a worker thread and folded
+/// into its own accumulator, and the results are combined with `reduce`
+/// in file order. Frames must hold whole messages, as those written by a
+/// [`SeekableWriter`] do. With the `zstd` feature.
+pub fn analyze_parallel_zstd<P, A, F, G>(path: P, fold: F, reduce: G) -> Result<A>
+where
+    P: AsRef<Path>,
+    A: Default + Send,
+    F: Fn(&mut A, Message) + Sync,
+    G: Fn(A, A) -> A,
+{
+    let path = path.as_ref();
+    let frames = SeekableReader::open(path)?.frames;
+    let indexed: Vec<_> = frames.iter().enumerate().collect();
+    run_parallel(
+        &indexed,
+        |&(ix, frame)| {
+            let data = read_frame(&mut File::open(path)?, frame)?;
+            let mut end = 0;
+            while end + 2 <= data.len() {
+                end += 2 + u16::from_be_bytes([data[end], data[end + 1]]) as usize;
+            }
+            if end != data.len() {
+                return Err(Error::Parse(format!(
+                    "zstd frame {} does not end on a message boundary",
+                    ix
+                )));
+            }
+            let mut acc = A::default();
+            for msg in SliceStream::new(&data) {
+                fold(&mut acc, msg?);
+            }
+            Ok(acc)
+        },
+        reduce,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MessageStream, TimeIndex};
+
+    #[test]
+    fn seeks_and_parses_frames_in_parallel() {
+        let mut data = Vec::new();
+        for ts in 0..100u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&(ts * 1000).to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let mut writer = SeekableWriter::new(Vec::new(), 0).frame_size(100);
+        for chunk in data.chunks(33) {
+            writer.write_all(chunk).unwrap();
+        }
+        // 100 byte frames round up to 8 messages
+        assert_eq!(writer.frames().len(), 12);
+        let compressed = writer.finish().unwrap();
+        assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), data);
+
+        let mut reader = SeekableReader::new(io::Cursor::new(&compressed)).unwrap();
+        assert_eq!(reader.frames().len(), 13);
+        assert_eq!(reader.len(), data.len() as u64);
+        let index = TimeIndex::build(&mut reader, 10_000).unwrap();
+        let entry = *index.seek(55_000).unwrap();
+        reader.seek(SeekFrom::Start(entry.offset)).unwrap();
+        let first = MessageStream::from_reader(reader).next().unwrap().unwrap();
+        assert_eq!(first.timestamp, 50_000);
+
+        let path = std::env::temp_dir().join(format!("itchy-seekable-{}", std::process::id()));
+        std::fs::write(&path, &compressed).unwrap();
+        let timestamps = analyze_parallel_zstd(
+            &path,
+            |ts: &mut Vec<u64>, msg| ts.push(msg.timestamp),
+            |mut a, b| {
+                a.extend(b);
+                a
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+        let expected: Vec<_> = (0..100).map(|ts| ts * 1000).collect();
+        assert_eq!(timestamps.unwrap(), expected);
+    }
+}
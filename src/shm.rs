@@ -0,0 +1,397 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem::size_of;
+use std::ops::ControlFlow;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+
+use crate::{decode_message, Error, Message, MessageSink, Result};
+
+const MAGIC: u64 = u64::from_le_bytes(*b"ITCHSHM1");
+
+// bytes of a slot holding a message: one length byte, then the message
+// without its length prefix. The longest ITCH 5.0 message is 50 bytes.
+const FRAME_WORDS: usize = 7;
+const FRAME_BYTES: usize = FRAME_WORDS * 8;
+
+// the producer's state and the slots are on separate cache lines
+#[repr(C, align(64))]
+struct Header {
+    magic: AtomicU64,
+    capacity: AtomicU64,
+    _pad: [u64; 6],
+    // messages published so far
+    written: AtomicU64,
+    closed: AtomicU64,
+}
+
+// a seqlock: `seq` is the message number plus one once the frame is
+// complete, and zero while it is being written
+#[repr(C, align(64))]
+struct Slot {
+    seq: AtomicU64,
+    frame: [AtomicU64; FRAME_WORDS],
+}
+
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+    // slots in the mapping, fixed when it was made rather than read back
+    // from the header, which another process could change
+    capacity: u64,
+}
+
+// the mapping is only accessed through atomics
+unsafe impl Send for Mapping {}
+
+impl Mapping {
+    fn new(file: &File, capacity: usize, writable: bool) -> io::Result<Mapping> {
+        let len = mapping_len(capacity).ok_or_else(too_large)?;
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping {
+            ptr: ptr as *mut u8,
+            len,
+            capacity: capacity as u64,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.ptr as *const Header) }
+    }
+
+    fn slot(&self, seq: u64) -> &Slot {
+        let index = (seq & (self.capacity - 1)) as usize;
+        unsafe { &*(self.ptr.add(size_of::<Header>()) as *const Slot).add(index) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "ring capacity too large")
+}
+
+// None if a ring of this capacity would not fit in memory
+fn mapping_len(capacity: usize) -> Option<usize> {
+    capacity
+        .checked_mul(size_of::<Slot>())?
+        .checked_add(size_of::<Header>())
+}
+
+/// Publishes messages to a ring buffer in shared memory, read by any
+/// number of [`ShmSubscriber`]s in other processes on the same host.
+///
+/// The ring is a file, normally under `/dev/shm`, of fixed-size slots each
+/// holding one message in its wire format. The publisher never waits for
+/// subscribers: one that falls more than a ring's length behind skips
+/// ahead, counting the messages it lost. Linux only, with the `shm`
+/// feature.
+///
+/// As a [`MessageSink`], messages too long for a slot stop the stream and
+/// the error is kept for [`error`](ShmPublisher::error).
+pub struct ShmPublisher {
+    map: Mapping,
+    written: u64,
+    frame: Vec<u8>,
+    error: Option<Error>,
+}
+
+impl ShmPublisher {
+    /// Create the ring, replacing any existing file at `path`. The capacity
+    /// in messages must be a power of two.
+    ///
+    /// The ring is written to a new file which is then renamed over `path`,
+    /// so subscribers still attached to a ring it replaces keep their
+    /// mapping, and see no more messages.
+    pub fn create<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<ShmPublisher> {
+        if !capacity.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ring capacity must be a power of two",
+            ));
+        }
+        let len = mapping_len(capacity).ok_or_else(too_large)?;
+        let path = path.as_ref();
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.tmp", std::process::id()));
+        let new_path = path.with_file_name(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&new_path)?;
+        file.set_len(len as u64)?;
+        let map = Mapping::new(&file, capacity, true)?;
+        let header = map.header();
+        header.capacity.store(capacity as u64, Ordering::Relaxed);
+        header.magic.store(MAGIC, Ordering::Release);
+        if let Err(e) = std::fs::rename(&new_path, path) {
+            let _ = std::fs::remove_file(&new_path);
+            return Err(e);
+        }
+        Ok(ShmPublisher {
+            map,
+            written: 0,
+            frame: Vec::with_capacity(FRAME_BYTES),
+            error: None,
+        })
+    }
+
+    pub fn publish(&mut self, msg: &Message) -> Result<()> {
+        self.frame.clear();
+        self.frame.push(0);
+        msg.encode_unframed(&mut self.frame);
+        if self.frame.len() > FRAME_BYTES {
+            return Err(Error::Parse(format!(
+                "'{}' message of {} bytes does not fit a ring slot",
+                msg.tag as char,
+                self.frame.len() - 1
+            )));
+        }
+        self.frame[0] = (self.frame.len() - 1) as u8;
+        self.frame.resize(FRAME_BYTES, 0);
+
+        let slot = self.map.slot(self.written);
+        slot.seq.store(0, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (word, bytes) in slot.frame.iter().zip(self.frame.chunks_exact(8)) {
+            let mut value = [0; 8];
+            value.copy_from_slice(bytes);
+            word.store(u64::from_le_bytes(value), Ordering::Relaxed);
+        }
+        self.written += 1;
+        slot.seq.store(self.written, Ordering::Release);
+        self.map
+            .header()
+            .written
+            .store(self.written, Ordering::Release);
+        Ok(())
+    }
+
+    /// Number of messages published
+    pub fn published(&self) -> u64 {
+        self.written
+    }
+
+    /// The error that stopped the stream, as a sink
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
+
+/// Subscribers see the end of the stream once the publisher is dropped
+impl Drop for ShmPublisher {
+    fn drop(&mut self) {
+        self.map.header().closed.store(1, Ordering::Release);
+    }
+}
+
+impl MessageSink for ShmPublisher {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.publish(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// Reads messages from a ring created by a [`ShmPublisher`].
+///
+/// As an iterator it spins waiting for messages and ends once the
+/// publisher has been dropped and every remaining message has been read.
+pub struct ShmSubscriber {
+    map: Mapping,
+    next: u64,
+    lost: u64,
+}
+
+impl ShmSubscriber {
+    /// Attach to a ring, starting with the next message published
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ShmSubscriber> {
+        let file = File::open(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not an itchy shm ring");
+        let file_len = file.metadata()?.len() as usize;
+        if file_len < size_of::<Header>() {
+            return Err(invalid());
+        }
+        let header = Mapping::new(&file, 0, false)?;
+        // the capacity is only valid once the magic is seen
+        if header.header().magic.load(Ordering::Acquire) != MAGIC {
+            return Err(invalid());
+        }
+        let capacity = header.header().capacity.load(Ordering::Relaxed) as usize;
+        let fits = mapping_len(capacity).is_some_and(|len| file_len >= len);
+        if !capacity.is_power_of_two() || !fits {
+            return Err(invalid());
+        }
+        drop(header);
+        let map = Mapping::new(&file, capacity, false)?;
+        let next = map.header().written.load(Ordering::Acquire);
+        Ok(ShmSubscriber { map, next, lost: 0 })
+    }
+
+    /// Start from the oldest message still in the ring instead
+    pub fn rewind(&mut self) {
+        let header = self.map.header();
+        let written = header.written.load(Ordering::Acquire);
+        self.next = written.saturating_sub(self.map.capacity);
+    }
+
+    /// The next message, or `None` if none has been published yet
+    pub fn try_recv(&mut self) -> Option<Result<Message>> {
+        loop {
+            let written = self.map.header().written.load(Ordering::Acquire);
+            if self.next >= written {
+                return None;
+            }
+            let slot = self.map.slot(self.next);
+            let before = slot.seq.load(Ordering::Acquire);
+            let mut frame = [0; FRAME_BYTES];
+            for (word, bytes) in slot.frame.iter().zip(frame.chunks_exact_mut(8)) {
+                bytes.copy_from_slice(&word.load(Ordering::Relaxed).to_le_bytes());
+            }
+            fence(Ordering::Acquire);
+            let after = slot.seq.load(Ordering::Relaxed);
+            if before == self.next + 1 && after == before {
+                self.next += 1;
+                let len = (frame[0] as usize).min(FRAME_BYTES - 1);
+                return Some(decode_message(&frame[1..1 + len]));
+            }
+            // overwritten by a later lap, skip to the oldest message left
+            let oldest = written.saturating_sub(self.map.capacity).max(self.next + 1);
+            self.lost += oldest - self.next;
+            self.next = oldest;
+        }
+    }
+
+    /// Whether the publisher has been dropped
+    pub fn is_closed(&self) -> bool {
+        self.map.header().closed.load(Ordering::Acquire) != 0
+    }
+
+    /// Messages overwritten before they could be read
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+}
+
+impl Iterator for ShmSubscriber {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        loop {
+            // check before reading, so no message published before closing
+            // is missed
+            let closed = self.is_closed();
+            if let Some(item) = self.try_recv() {
+                return Some(item);
+            }
+            if closed {
+                return None;
+            }
+            std::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn round_trips_and_detects_overrun() {
+        let path = std::env::temp_dir().join(format!("itchy-shm-{}", std::process::id()));
+        let mut publisher = ShmPublisher::create(&path, 4).unwrap();
+        let mut subscriber = ShmSubscriber::open(&path).unwrap();
+        assert!(subscriber.try_recv().is_none());
+
+        let messages: Vec<_> = (0..6)
+            .map(|ts| Message {
+                tag: b'A',
+                ..msg(ts, add(ts, Side::Buy, 100, 10_000))
+            })
+            .collect();
+        publisher.publish(&messages[0]).unwrap();
+        let first = subscriber.try_recv().unwrap().unwrap();
+        assert_eq!((first.tag, first.body), (b'A', messages[0].body.clone()));
+        // laps the subscriber, which loses message 1
+        for m in &messages[1..] {
+            publisher.publish(m).unwrap();
+        }
+        drop(publisher);
+        let rest: Vec<_> = subscriber.by_ref().map(|m| m.unwrap().timestamp).collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rest, [2, 3, 4, 5]);
+        assert_eq!(subscriber.lost(), 1);
+    }
+
+    #[test]
+    fn recreating_leaves_attached_subscribers_alone() {
+        let path = std::env::temp_dir().join(format!("itchy-shm-new-{}", std::process::id()));
+        let mut old = ShmPublisher::create(&path, 2).unwrap();
+        let mut subscriber = ShmSubscriber::open(&path).unwrap();
+        let mut new = ShmPublisher::create(&path, 8).unwrap();
+        for ts in 0..4 {
+            let m = Message {
+                tag: b'A',
+                ..msg(ts, add(ts, Side::Buy, 100, 10_000))
+            };
+            old.publish(&m).unwrap();
+            new.publish(&m).unwrap();
+        }
+        drop(old);
+        let seen: Vec<_> = subscriber.by_ref().map(|m| m.unwrap().timestamp).collect();
+        assert_eq!((seen, subscriber.lost()), (vec![2, 3], 2));
+        let mut reopened = ShmSubscriber::open(&path).unwrap();
+        reopened.rewind();
+        assert_eq!(reopened.try_recv().unwrap().unwrap().timestamp, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_oversized_capacity() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let path = std::env::temp_dir().join(format!("itchy-shm-huge-{}", std::process::id()));
+        let publisher = ShmPublisher::create(&path, 2).unwrap();
+        // a capacity whose mapping length wraps around
+        let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(8)).unwrap();
+        file.write_all(&(1u64 << 58).to_le_bytes()).unwrap();
+        let err = ShmSubscriber::open(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        drop(publisher);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{Book, BookManager, Message, PriceLevel, Result};
+
+/// Derived signals of a book after an update, see [`SignalStream`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookSignals {
+    pub stock_locate: u16,
+    /// Timestamp of the message which changed the book
+    pub timestamp: u64,
+    pub best_bid: Option<PriceLevel>,
+    pub best_ask: Option<PriceLevel>,
+    /// See [`Book::spread`]
+    pub spread: Option<f64>,
+    /// See [`Book::microprice`]
+    pub microprice: Option<f64>,
+    /// See [`Book::imbalance`]
+    pub imbalance: Option<f64>,
+}
+
+/// Iterator of [`BookSignals`] from a stream of messages.
+///
+/// Each message is applied to a [`BookManager`], and the signals of the
+/// book it changed are computed from the book as it stands after the
+/// message. Messages which change no book yield nothing. Errors from the
+/// message stream are passed through.
+#[derive(Debug)]
+pub struct SignalStream<I> {
+    messages: I,
+    books: BookManager,
+    levels: usize,
+}
+
+impl<I: Iterator<Item = Result<Message>>> SignalStream<I> {
+    /// Compute the imbalance over the top `levels` levels of each side
+    ///
+    /// # Panics
+    ///
+    /// If `levels` is zero
+    pub fn new(messages: I, levels: usize) -> SignalStream<I> {
+        assert!(levels > 0, "imbalance needs at least one level");
+        SignalStream {
+            messages,
+            books: BookManager::new(),
+            levels,
+        }
+    }
+
+    /// The books as of the last message applied
+    pub fn books(&self) -> &BookManager {
+        &self.books
+    }
+
+    fn signals(&self, msg: &Message) -> Option<BookSignals> {
+        let book = self.books.book(msg.stock_locate)?;
+        Some(BookSignals {
+            stock_locate: msg.stock_locate,
+            timestamp: msg.timestamp,
+            best_bid: book.best_bid(),
+            best_ask: book.best_ask(),
+            spread: book.spread(),
+            microprice: book.microprice(),
+            imbalance: book.imbalance(self.levels),
+        })
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>> Iterator for SignalStream<I> {
+    type Item = Result<BookSignals>;
+
+    fn next(&mut self) -> Option<Result<BookSignals>> {
+        loop {
+            match self.messages.next()? {
+                Ok(msg) => {
+                    if self.books.update(&msg) {
+                        if let Some(signals) = self.signals(&msg) {
+                            return Some(Ok(signals));
+                        }
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn computes_signals() {
+        let messages = vec![
+            msg(1, add(1, Side::Buy, 300, 10_000)),
+            msg(2, add(2, Side::Sell, 100, 10_100)),
+            msg(3, add(3, Side::Buy, 200, 9_900)),
+        ];
+        let signals: Vec<_> = SignalStream::new(messages.into_iter().map(Ok), 1)
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(signals.len(), 3);
+        assert_eq!(signals[0].spread, None);
+        assert_eq!(signals[0].imbalance, Some(1.0));
+        let last = signals[2];
+        assert!((last.spread.unwrap() - 0.01).abs() < 1e-9);
+        // three times the size on the bid pulls the microprice towards the ask
+        assert!((last.microprice.unwrap() - 1.0075).abs() < 1e-9);
+        // only the top level counts
+        assert_eq!(last.imbalance, Some(0.5));
+    }
+}
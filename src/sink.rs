@@ -0,0 +1,160 @@
+use std::ops::ControlFlow;
+
+use crate::{
+    BookManager, HeatmapExporter, LatencyStats, Message, MpidAggregator, OrderFlow, Reconciler,
+    Result, TopMovers, TradeClassifier,
+};
+
+/// A consumer of parsed messages, see [`drive`].
+///
+/// The trackers in this crate are sinks, and closures can be made into
+/// sinks with [`sink_fn`]. Sinks can be combined with
+/// [`and`](MessageSink::and) so that one pass over a stream feeds several
+/// of them.
+pub trait MessageSink {
+    /// Consume a message. Returning `Break` stops the stream early.
+    fn accept(&mut self, msg: Message) -> ControlFlow<()>;
+
+    /// Feed every message to this sink, then to `other`
+    fn and<S: MessageSink>(self, other: S) -> Chain<Self, S>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: other,
+        }
+    }
+}
+
+/// A sink calling a closure, see [`sink_fn`]
+#[derive(Debug, Clone)]
+pub struct FnSink<F>(F);
+
+/// Make a sink from a closure
+pub fn sink_fn<F: FnMut(Message) -> ControlFlow<()>>(f: F) -> FnSink<F> {
+    FnSink(f)
+}
+
+impl<F: FnMut(Message) -> ControlFlow<()>> MessageSink for FnSink<F> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        (self.0)(msg)
+    }
+}
+
+impl<S: MessageSink + ?Sized> MessageSink for &mut S {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        (**self).accept(msg)
+    }
+}
+
+impl<S: MessageSink + ?Sized> MessageSink for Box<S> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        (**self).accept(msg)
+    }
+}
+
+/// Collects every message
+impl MessageSink for Vec<Message> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        self.push(msg);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Two sinks fed the same messages, see [`MessageSink::and`].
+///
+/// The stream stops as soon as either sink breaks.
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Chain<A, B> {
+    /// The two sinks, e.g. to read state built by trackers
+    pub fn parts(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: MessageSink, B: MessageSink> MessageSink for Chain<A, B> {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        self.first.accept(msg.clone())?;
+        self.second.accept(msg)
+    }
+}
+
+macro_rules! tracker_sink {
+    ($($tracker:ty),*) => {
+        $(
+            impl MessageSink for $tracker {
+                fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+                    self.update(&msg);
+                    ControlFlow::Continue(())
+                }
+            }
+        )*
+    };
+}
+
+tracker_sink!(
+    BookManager,
+    HeatmapExporter,
+    LatencyStats,
+    MpidAggregator,
+    OrderFlow,
+    Reconciler,
+    TopMovers,
+    TradeClassifier
+);
+
+/// Feed a stream of messages into a sink until the stream ends, the sink
+/// breaks or an error occurs. Returns the number of messages accepted.
+pub fn drive<I, S>(messages: I, mut sink: S) -> Result<u64>
+where
+    I: IntoIterator<Item = Result<Message>>,
+    S: MessageSink,
+{
+    let mut accepted = 0;
+    for msg in messages {
+        accepted += 1;
+        if sink.accept(msg?).is_break() {
+            break;
+        }
+    }
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn drives_chained_sinks() {
+        let messages: Vec<_> = (1..=5)
+            .map(|r| Ok(msg(r, add(r, Side::Buy, 100, 10_000))))
+            .collect();
+        let mut books = BookManager::new();
+        let mut collected = Vec::new();
+        let mut seen = 0;
+        let stop_after_three = sink_fn(|_| {
+            seen += 1;
+            if seen == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        let sink = (&mut books).and(&mut collected).and(stop_after_three);
+        assert_eq!(drive(messages, sink).unwrap(), 3);
+        assert_eq!(collected.len(), 3);
+        assert_eq!(books.orders().len(), 3);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{decode_message, Error, Message, Result, StreamPosition};
+
+/// Parses messages directly from an in-memory capture, see
+/// [`MessageStream::from_slice`](crate::MessageStream::from_slice).
+///
+/// Unlike a [`MessageStream`](crate::MessageStream) reading from a slice,
+/// nothing is copied through an internal buffer, and positions are byte
+/// offsets into the slice itself. Parsing stops at the first error, which
+/// carries the position of the failing message.
+#[derive(Debug, Clone)]
+pub struct SliceStream<'a> {
+    data: &'a [u8],
+    offset: usize,
+    message_ct: u64,
+    failed: bool,
+}
+
+impl<'a> SliceStream<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> SliceStream<'a> {
+        SliceStream {
+            data,
+            offset: 0,
+            message_ct: 0,
+            failed: false,
+        }
+    }
+
+    /// Position of the next message
+    pub fn position(&self) -> StreamPosition {
+        StreamPosition {
+            message_index: self.message_ct,
+            byte_offset: self.offset as u64,
+        }
+    }
+
+    /// The bytes not yet parsed, e.g. a truncated message after an error
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
+    /// The next message with its frame in the slice, length prefix included
+    pub fn next_frame(&mut self) -> Option<Result<(Message, &'a [u8])>> {
+        let rest = self.remaining();
+        if rest.is_empty() || self.failed {
+            return None;
+        }
+        let parsed = match *rest {
+            [a, b, ref body @ ..] if body.len() >= u16::from_be_bytes([a, b]) as usize => {
+                let len = u16::from_be_bytes([a, b]) as usize;
+                decode_message(&body[..len]).map(|msg| (msg, &rest[..2 + len]))
+            }
+            _ => Err(Error::Parse(format!(
+                "truncated message in the last {} bytes",
+                rest.len()
+            ))),
+        };
+        match parsed {
+            Ok((msg, frame)) => {
+                self.offset += frame.len();
+                self.message_ct += 1;
+                Some(Ok((msg, frame)))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(Error::Stream {
+                    position: SliceStream::position(self),
+                    source: Box::new(e),
+                }))
+            }
+        }
+    }
+}
+
+impl Iterator for SliceStream<'_> {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        Some(self.next_frame()?.map(|(msg, _)| msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::messages::tests::hex_to_bytes;
+    use crate::{MessageStream, StreamPosition};
+
+    #[test]
+    fn parses_in_place() {
+        let mut data = hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f");
+        data.extend(hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 9953"));
+        data.extend_from_slice(&[0, 12, b'S']);
+
+        let mut stream = MessageStream::from_slice(&data);
+        let (_, frame) = stream.next_frame().unwrap().unwrap();
+        assert!(std::ptr::eq(frame, &data[..14]));
+        assert!(stream.next().unwrap().is_ok());
+        let position = StreamPosition {
+            message_index: 2,
+            byte_offset: 28,
+        };
+        assert_eq!(stream.position(), position);
+        let error = stream.next().unwrap().unwrap_err();
+        assert_eq!(error.position(), Some(position));
+        assert!(stream.next().is_none());
+        assert_eq!(stream.remaining(), [0, 12, b'S']);
+    }
+}
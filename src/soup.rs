@@ -0,0 +1,154 @@
+use std::io::{self, prelude::*, BufReader};
+
+use crate::{decode_message, Envelope, Error, Result, SessionId};
+
+/// Iterator over the sequenced messages of a SoupBinTCP session, as sent by
+/// the server.
+///
+/// Sequence numbers start from the Login Accepted packet, or from 1 if the
+/// capture does not include one. Heartbeats, debug and unsequenced packets
+/// are skipped, and iteration ends at an End of Session packet.
+#[derive(Debug)]
+pub struct SoupStream<R> {
+    reader: BufReader<R>,
+    session: SessionId,
+    next_sequence: u64,
+    ended: bool,
+}
+
+impl<R: Read> SoupStream<R> {
+    pub fn new(reader: R) -> SoupStream<R> {
+        SoupStream {
+            reader: BufReader::new(reader),
+            session: SessionId::new(),
+            next_sequence: 1,
+            ended: false,
+        }
+    }
+
+    pub fn session(&self) -> SessionId {
+        self.session
+    }
+
+    /// Sequence number of the next sequenced message
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    // read one packet, returning its type and payload
+    fn read_packet(&mut self) -> Result<Option<(u8, Vec<u8>)>> {
+        let mut len = [0; 2];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let len = u16::from_be_bytes(len) as usize;
+        if len == 0 {
+            return Err(Error::Parse("empty SoupBinTCP packet".into()));
+        }
+        let mut kind = [0];
+        let mut payload = vec![0; len - 1];
+        self.reader
+            .read_exact(&mut kind)
+            .and_then(|()| self.reader.read_exact(&mut payload))
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => Error::Parse("truncated SoupBinTCP packet".into()),
+                _ => e.into(),
+            })?;
+        Ok(Some((kind[0], payload)))
+    }
+
+    fn login_accepted(&mut self, payload: &[u8]) -> Result<()> {
+        let invalid = || Error::Parse("invalid SoupBinTCP login accepted packet".into());
+        if payload.len() != 30 || !payload.is_ascii() {
+            return Err(invalid());
+        }
+        let text = std::str::from_utf8(payload).map_err(|_| invalid())?;
+        let (session, sequence) = text.split_at(10);
+        // the session is left-padded with spaces, store it right-padded as
+        // in MoldUDP64
+        self.session =
+            SessionId::from(&format!("{:<10}", session.trim_start())).map_err(|_| invalid())?;
+        self.next_sequence = sequence.trim().parse().map_err(|_| invalid())?;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for SoupStream<R> {
+    type Item = Result<Envelope>;
+
+    fn next(&mut self) -> Option<Result<Envelope>> {
+        while !self.ended {
+            let (kind, payload) = match self.read_packet() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => return None,
+                Err(e) => {
+                    self.ended = true;
+                    return Some(Err(e));
+                }
+            };
+            match kind {
+                b'S' => {
+                    let sequence = self.next_sequence;
+                    self.next_sequence += 1;
+                    return Some(decode_message(&payload).map(|message| Envelope {
+                        session: self.session,
+                        sequence,
+                        receive_ts: None,
+                        message,
+                    }));
+                }
+                b'A' => {
+                    if let Err(e) = self.login_accepted(&payload) {
+                        return Some(Err(e));
+                    }
+                }
+                b'J' => {
+                    self.ended = true;
+                    let reason = payload.first().map_or('?', |&c| c as char);
+                    return Some(Err(Error::Parse(format!(
+                        "SoupBinTCP login rejected ({})",
+                        reason
+                    ))));
+                }
+                b'Z' => self.ended = true,
+                // heartbeats, debug and unsequenced data
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+
+    fn packet(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = ((payload.len() + 1) as u16).to_be_bytes().to_vec();
+        packet.push(kind);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn reads_session() {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        let mut data = packet(b'A', format!("{:>10}{:>20}", "SESS", 100).as_bytes());
+        data.extend(packet(b'H', b""));
+        data.extend(packet(b'S', &event));
+        data.extend(packet(b'S', &event));
+        data.extend(packet(b'Z', b""));
+        data.extend(packet(b'S', &event));
+
+        let mut stream = SoupStream::new(&data[..]);
+        let first = stream.next().unwrap().unwrap();
+        assert_eq!(first.session.as_str(), "SESS      ");
+        assert_eq!(first.sequence, 100);
+        assert_eq!(stream.next().unwrap().unwrap().sequence, 101);
+        assert!(stream.next().is_none());
+        assert_eq!(stream.next_sequence(), 102);
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{Checkpoint, Error, Result};
+
+const MAGIC: &[u8; 8] = b"ITCHST02";
+// states saved before message counts were widened to 64 bits
+const MAGIC_V1: &[u8; 8] = b"ITCHST01";
+
+/// The state of a seekable [`MessageStream`], which can be saved to disk so
+/// that a job can resume where it stopped after a restart. See
+/// [`MessageStream::state`].
+///
+/// Alongside the position of the next message, this holds the state of
+/// any symbol filter, sampling and event window the stream was built with,
+/// so the resumed stream yields exactly the messages the original would
+/// have. Analysis state built from the messages is not included.
+///
+/// [`MessageStream`]: crate::MessageStream
+/// [`MessageStream::state`]: crate::MessageStream::state
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StreamState {
+    pub(crate) checkpoint: Checkpoint,
+    pub(crate) sampled: u32,
+    pub(crate) window_open: Option<bool>,
+    // sorted
+    pub(crate) locates: Option<Vec<u16>>,
+}
+
+impl StreamState {
+    /// Position of the next message
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint
+    }
+
+    /// Write the state to a file, replacing it atomically so that a crash
+    /// while saving leaves the previous state intact
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&self.checkpoint.offset.to_le_bytes())?;
+        out.write_all(&self.checkpoint.message_ct.to_le_bytes())?;
+        out.write_all(&self.sampled.to_le_bytes())?;
+        out.write_all(&[match self.window_open {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        }])?;
+        match self.locates {
+            None => out.write_all(&[0])?,
+            Some(ref locates) => {
+                out.write_all(&[1])?;
+                out.write_all(&(locates.len() as u32).to_le_bytes())?;
+                for locate in locates {
+                    out.write_all(&locate.to_le_bytes())?;
+                }
+            }
+        }
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a state written by [`save`](Self::save), including by earlier
+    /// versions
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<StreamState> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC && &magic != MAGIC_V1 {
+            return Err(Error::Parse("not a saved stream state".into()));
+        }
+        let offset = u64::from_le_bytes(read_array(&mut input)?);
+        let message_ct = if &magic == MAGIC_V1 {
+            u32::from_le_bytes(read_array(&mut input)?).into()
+        } else {
+            u64::from_le_bytes(read_array(&mut input)?)
+        };
+        let sampled = u32::from_le_bytes(read_array(&mut input)?);
+        let window_open = match read_array::<1>(&mut input)? {
+            [0] => None,
+            [1] => Some(false),
+            [2] => Some(true),
+            _ => return Err(Error::Parse("invalid event window state".into())),
+        };
+        let locates = match read_array::<1>(&mut input)? {
+            [0] => None,
+            [1] => {
+                let len = u32::from_le_bytes(read_array(&mut input)?);
+                let locates = (0..len)
+                    .map(|_| Ok(u16::from_le_bytes(read_array(&mut input)?)))
+                    .collect::<Result<_>>()?;
+                Some(locates)
+            }
+            _ => return Err(Error::Parse("invalid symbol filter state".into())),
+        };
+        Ok(StreamState {
+            checkpoint: Checkpoint { offset, message_ct },
+            sampled,
+            window_open,
+            locates,
+        })
+    }
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N]> {
+    let mut buf = [0; N];
+    input.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MessageStream;
+
+    #[test]
+    fn resumes_after_restart() {
+        let mut data = Vec::new();
+        for ts in 0..100u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let dir = std::env::temp_dir();
+        let capture = dir.join(format!("itchy-state-{}.itch", std::process::id()));
+        let saved = dir.join(format!("itchy-state-{}", std::process::id()));
+        std::fs::write(&capture, &data).unwrap();
+
+        let mut stream = MessageStream::from_file(&capture).unwrap();
+        stream.by_ref().take(40).for_each(drop);
+        stream.state().unwrap().save(&saved).unwrap();
+        drop(stream);
+
+        let state = super::StreamState::load(&saved).unwrap();
+        let mut stream = MessageStream::from_file(&capture).unwrap();
+        stream.resume(&state).unwrap();
+        let timestamps: Vec<_> = stream.map(|m| m.unwrap().timestamp).collect();
+        std::fs::remove_file(&capture).unwrap();
+        std::fs::remove_file(&saved).unwrap();
+        assert_eq!(timestamps, (40..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn loads_32_bit_message_counts() {
+        let path = std::env::temp_dir().join(format!("itchy-state-v1-{}", std::process::id()));
+        let mut saved = super::MAGIC_V1.to_vec();
+        saved.extend_from_slice(&560u64.to_le_bytes());
+        saved.extend_from_slice(&40u32.to_le_bytes());
+        saved.extend_from_slice(&0u32.to_le_bytes());
+        saved.extend_from_slice(&[0, 0]);
+        std::fs::write(&path, &saved).unwrap();
+        let state = super::StreamState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(state.checkpoint().offset(), 560);
+        assert_eq!(state.checkpoint().message_ct, 40);
+    }
+}
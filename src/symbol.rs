@@ -0,0 +1,254 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::{ArrayString4, ArrayString8, Error, Message};
+
+// Suffixes in NASDAQ, CQS and CMS symbology, after a root of capital
+// letters. `X` stands for a class letter. Units and class U look the same
+// in CQS and CMS, so the entry for units comes first and wins.
+const SUFFIXES: &[[&str; 3]] = &[
+    ["-", "p", " PR"],
+    ["-X", "pX", " PRX"],
+    ["-*", "p.CL", " PRCL"],
+    ["-X*", "pX.CL", " PRXCL"],
+    ["-#", "pw", " PRWI"],
+    ["-X#", "pXw", " PRXWI"],
+    ["+", ".WS", " WS"],
+    ["+X", ".WS.X", " WSX"],
+    ["+#", ".WSw", " WSWI"],
+    ["=", ".U", " U"],
+    ["^", "r", " RT"],
+    ["^#", "rw", " RTWI"],
+    ["#", "w", " WI"],
+    ["*", ".CL", " CL"],
+    ["%", ".CV", " CV"],
+    ["%*", ".CV.CL", " CVCL"],
+    [".X#", ".Xw", " XWI"],
+    [".X", ".X", " X"],
+];
+
+const NASDAQ: usize = 0;
+const CQS: usize = 1;
+const CMS: usize = 2;
+
+/// A stock symbol without the space padding it has on the wire.
+///
+/// Comparison, hashing and display ignore trailing spaces, so `"AAPL    "`
+/// as parsed and `"AAPL"` from external data are the same symbol. Maps
+/// keyed by `Symbol` can be queried with a `&str`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Symbol(ArrayString8);
+
+impl Symbol {
+    /// Returns `None` if the symbol is longer than 8 characters
+    pub fn new(symbol: &str) -> Option<Symbol> {
+        ArrayString8::from(symbol.trim_end()).ok().map(Symbol)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The symbol padded to 8 characters, as on the wire
+    pub fn padded(&self) -> ArrayString8 {
+        let mut padded = self.0;
+        while !padded.is_full() {
+            padded.push(' ');
+        }
+        padded
+    }
+
+    /// The symbol in CQS convention, e.g. `ABCpA` for the NASDAQ `ABC-A`,
+    /// for joining with consolidated tape data from the NYSE side. Returns
+    /// `None` for suffixes with no CQS equivalent.
+    ///
+    /// The conversion covers class shares, preferreds, warrants, units,
+    /// rights, when issued and called securities, and their common
+    /// combinations, as in NASDAQ's symbology table. It is best effort:
+    /// exchanges do not always follow the conventions.
+    pub fn to_cqs(&self) -> Option<String> {
+        convert(self.as_str(), NASDAQ, CQS)
+    }
+
+    /// The symbol in CMS convention, e.g. `ABC PRA` for the NASDAQ `ABC-A`,
+    /// see [`to_cqs`](Symbol::to_cqs)
+    pub fn to_cms(&self) -> Option<String> {
+        convert(self.as_str(), NASDAQ, CMS)
+    }
+
+    /// The NASDAQ symbol of a CQS symbol, see [`to_cqs`](Symbol::to_cqs)
+    pub fn from_cqs(symbol: &str) -> Option<Symbol> {
+        Symbol::new(&convert(symbol.trim_end(), CQS, NASDAQ)?)
+    }
+
+    /// The NASDAQ symbol of a CMS symbol, see [`to_cqs`](Symbol::to_cqs)
+    pub fn from_cms(symbol: &str) -> Option<Symbol> {
+        Symbol::new(&convert(symbol.trim_end(), CMS, NASDAQ)?)
+    }
+}
+
+// change the suffix of a symbol from one convention to another
+fn convert(symbol: &str, from: usize, to: usize) -> Option<String> {
+    let root_len = symbol
+        .find(|c: char| !c.is_ascii_uppercase())
+        .unwrap_or(symbol.len());
+    let (root, suffix) = symbol.split_at(root_len);
+    if root.is_empty() {
+        return None;
+    }
+    if suffix.is_empty() {
+        return Some(root.to_string());
+    }
+    SUFFIXES.iter().find_map(|row| {
+        let class = match_suffix(row[from], suffix)?;
+        let mut converted = root.to_string();
+        for c in row[to].chars() {
+            converted.push(if c == 'X' { class? } else { c });
+        }
+        Some(converted)
+    })
+}
+
+// match a suffix against a pattern, with the class letter if it has one
+fn match_suffix(pattern: &str, suffix: &str) -> Option<Option<char>> {
+    if pattern.len() != suffix.len() {
+        return None;
+    }
+    let mut class = None;
+    for (p, c) in pattern.chars().zip(suffix.chars()) {
+        if p == 'X' && c.is_ascii_uppercase() {
+            class = Some(c);
+        } else if p != c {
+            return None;
+        }
+    }
+    Some(class)
+}
+
+impl From<ArrayString8> for Symbol {
+    fn from(stock: ArrayString8) -> Symbol {
+        let mut trimmed = stock;
+        trimmed.truncate(stock.trim_end().len());
+        Symbol(trimmed)
+    }
+}
+
+impl From<&ArrayString8> for Symbol {
+    fn from(stock: &ArrayString8) -> Symbol {
+        Symbol::from(*stock)
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Symbol, Error> {
+        Symbol::new(s).ok_or_else(|| Error::Parse(format!("symbol '{}' is too long", s)))
+    }
+}
+
+// hash as the trimmed str, to agree with `Borrow<str>`
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other.trim_end()
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An MPID padded to 4 characters, as on the wire, for map lookups.
+/// Returns `None` if it is longer than 4 characters.
+pub(crate) fn padded_mpid(mpid: &str) -> Option<ArrayString4> {
+    let mut padded = ArrayString4::from(mpid.trim_end()).ok()?;
+    while !padded.is_full() {
+        padded.push(' ');
+    }
+    Some(padded)
+}
+
+impl Message {
+    /// The stock symbol of the message without its padding, for message
+    /// types which have one
+    pub fn stock_trimmed(&self) -> Option<&str> {
+        self.body.stock().map(|s| s.trim_end())
+    }
+
+    /// The stock symbol of the message, for message types which have one
+    pub fn symbol(&self) -> Option<Symbol> {
+        self.body.stock().map(Symbol::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+    use std::collections::HashMap;
+
+    #[test]
+    fn ignores_padding() {
+        let msg = msg(1, add(1, Side::Buy, 100, 10_000));
+        assert_eq!(msg.stock_trimmed(), Some("ZXZZT"));
+        let symbol = msg.symbol().unwrap();
+        assert_eq!(symbol, "ZXZZT");
+        assert_eq!(symbol, "ZXZZT   ");
+        assert_eq!(symbol.to_string(), "ZXZZT");
+        assert_eq!(symbol.padded().as_str(), "ZXZZT   ");
+        assert_eq!("ZXZZT".parse::<Symbol>().unwrap(), symbol);
+        assert!("TOOLONGSYM".parse::<Symbol>().is_err());
+
+        let volumes = HashMap::from([(symbol, 100)]);
+        assert_eq!(volumes.get("ZXZZT"), Some(&100));
+    }
+
+    #[test]
+    fn maps_cqs_and_cms_symbology() {
+        let cases = [
+            ("BRK.A", "BRK.A", "BRK A"),
+            ("ABC-", "ABCp", "ABC PR"),
+            ("ABC-B", "ABCpB", "ABC PRB"),
+            ("ABC-B*", "ABCpB.CL", "ABC PRBCL"),
+            ("ABC+", "ABC.WS", "ABC WS"),
+            ("ABC+A", "ABC.WS.A", "ABC WSA"),
+            ("ABC=", "ABC.U", "ABC U"),
+            ("ABC^#", "ABCrw", "ABC RTWI"),
+            ("ABC.A#", "ABC.Aw", "ABC AWI"),
+            ("AAPL", "AAPL", "AAPL"),
+        ];
+        for (nasdaq, cqs, cms) in cases {
+            let symbol = Symbol::new(nasdaq).unwrap();
+            assert_eq!(symbol.to_cqs().as_deref(), Some(cqs));
+            assert_eq!(symbol.to_cms().as_deref(), Some(cms));
+            assert_eq!(Symbol::from_cqs(cqs), Some(symbol));
+            assert_eq!(Symbol::from_cms(cms), Some(symbol));
+        }
+        assert_eq!(Symbol::new("ABC.AB").unwrap().to_cqs(), None);
+        assert_eq!(Symbol::from_cqs("ABCDEFG.WS.A"), None);
+    }
+}
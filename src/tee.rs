@@ -0,0 +1,141 @@
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+use crate::{Error, Message, Result};
+
+// messages per batch sent to each consumer, and batches in flight
+const BATCH_SIZE: usize = 256;
+const QUEUE_DEPTH: usize = 16;
+
+/// One consumer of a stream split by [`MessageStream::tee`].
+///
+/// Yields every message of the stream, in order. Each consumer has its own
+/// bounded queue, so the parser waits for the slowest consumer rather than
+/// buffering without limit; dropping a consumer detaches it.
+///
+/// [`MessageStream::tee`]: crate::MessageStream::tee
+#[derive(Debug)]
+pub struct TeeReceiver {
+    batches: Receiver<Vec<Result<Message>>>,
+    batch: std::vec::IntoIter<Result<Message>>,
+}
+
+impl Iterator for TeeReceiver {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        loop {
+            if let Some(item) = self.batch.next() {
+                return Some(item);
+            }
+            self.batch = self.batches.recv().ok()?.into_iter();
+        }
+    }
+}
+
+/// Parse `messages` on a new thread, fanning every item out to `n` receivers
+pub(crate) fn spawn<I>(messages: I, n: usize) -> Vec<TeeReceiver>
+where
+    I: Iterator<Item = Result<Message>> + Send + 'static,
+{
+    assert!(n > 0, "tee needs at least one consumer");
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..n)
+        .map(|_| {
+            let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+            let receiver = TeeReceiver {
+                batches: rx,
+                batch: Vec::new().into_iter(),
+            };
+            (Some(tx), receiver)
+        })
+        .unzip();
+    // like `thread::spawn`, panics if the OS cannot create a thread
+    #[allow(clippy::expect_used)]
+    thread::Builder::new()
+        .name("itchy-tee".into())
+        .spawn(move || run(messages, senders))
+        .expect("failed to spawn tee thread");
+    receivers
+}
+
+fn run<I>(messages: I, mut senders: Vec<Option<SyncSender<Vec<Result<Message>>>>>)
+where
+    I: Iterator<Item = Result<Message>>,
+{
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for item in messages {
+        batch.push(item);
+        if batch.len() == BATCH_SIZE && !send(&mut senders, &mut batch) {
+            // every consumer has gone
+            return;
+        }
+    }
+    send(&mut senders, &mut batch);
+}
+
+// send a copy of the batch to each remaining consumer, false if none remain
+fn send(
+    senders: &mut [Option<SyncSender<Vec<Result<Message>>>>],
+    batch: &mut Vec<Result<Message>>,
+) -> bool {
+    let batch = std::mem::replace(batch, Vec::with_capacity(BATCH_SIZE));
+    for slot in senders.iter_mut() {
+        if let Some(sender) = slot {
+            let copy = batch.iter().map(duplicate).collect();
+            if sender.send(copy).is_err() {
+                *slot = None;
+            }
+        }
+    }
+    senders.iter().any(Option::is_some)
+}
+
+fn duplicate(item: &Result<Message>) -> Result<Message> {
+    match item {
+        Ok(msg) => Ok(msg.clone()),
+        Err(e) => Err(duplicate_error(e)),
+    }
+}
+
+// errors are not `Clone`, as I/O errors are not
+fn duplicate_error(error: &Error) -> Error {
+    match error {
+        Error::Parse(s) => Error::Parse(s.clone()),
+        Error::Io(e) => Error::Io(std::io::Error::new(e.kind(), e.to_string())),
+        Error::Nom(e) => Error::Nom(e.clone()),
+        Error::Stream { position, source } => Error::Stream {
+            position: *position,
+            source: Box::new(duplicate_error(source)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MessageStream;
+
+    #[test]
+    fn fans_out_messages() {
+        let mut data = Vec::new();
+        for ts in 0..1000u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        // a truncated message at the end
+        data.extend_from_slice(&[0, 12, b'S']);
+        let mut receivers = MessageStream::from_reader(std::io::Cursor::new(data)).tee(3);
+        // a dropped consumer does not hold up the others
+        receivers.pop();
+        let handles: Vec<_> = receivers
+            .into_iter()
+            .map(|rx| std::thread::spawn(move || rx.collect::<Vec<_>>()))
+            .collect();
+        for handle in handles {
+            let items = handle.join().unwrap();
+            assert_eq!(items.len(), 1001);
+            assert_eq!(items[999].as_ref().unwrap().timestamp, 999);
+            assert!(items[1000].is_err());
+        }
+    }
+}
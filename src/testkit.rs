@@ -0,0 +1,147 @@
+//! Fixtures and helpers for integration tests.
+//!
+//! The `testkit` feature bundles a few small synthetic captures, each with
+//! the messages it should parse to as golden JSON, so that code built on
+//! this crate can be tested without downloading a multi-gigabyte capture.
+//!
+//! ```ignore
+//! use itchy::testkit::{self, SESSION};
+//!
+//! #[test]
+//! fn classifies_trades() {
+//!     let trades: Vec<itchy::Trade> = itchy::TradeTape::new(SESSION.stream())
+//!         .collect::<Result<_, _>>()
+//!         .unwrap();
+//!     testkit::assert_golden("tests/golden/trades.json", &trades);
+//! }
+//! ```
+
+// fixtures and assertions for tests, which panic on failure
+#![allow(clippy::expect_used, clippy::panic)]
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{drive, Message, MessageSink, MessageStream, Result};
+
+/// Environment variable which, when set, makes [`assert_golden`] write the
+/// actual output to the golden file instead of comparing against it
+pub const BLESS_VAR: &str = "ITCHY_BLESS";
+
+/// A bundled synthetic capture
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    pub name: &'static str,
+    /// The capture, as length-prefixed ITCH 5.0 messages
+    pub data: &'static [u8],
+    /// The messages in the capture, as JSON
+    pub golden: &'static str,
+}
+
+/// A trading day for two symbols: directory, Reg SHO and trading state
+/// messages, opening and closing crosses, and order adds, executions,
+/// cancels, replaces, deletes and a non-cross trade
+pub const SESSION: Fixture = Fixture {
+    name: "session",
+    data: include_bytes!("../fixtures/session.itch"),
+    golden: include_str!("../fixtures/session.json"),
+};
+
+/// A single symbol paused by a LULD band, then resumed with a halt cross
+pub const HALT: Fixture = Fixture {
+    name: "halt",
+    data: include_bytes!("../fixtures/halt.itch"),
+    golden: include_str!("../fixtures/halt.json"),
+};
+
+/// All bundled fixtures
+pub const FIXTURES: &[Fixture] = &[SESSION, HALT];
+
+impl Fixture {
+    /// Stream the messages in the capture
+    pub fn stream(&self) -> MessageStream<&'static [u8]> {
+        MessageStream::from_reader(self.data)
+    }
+
+    /// Parse the whole capture
+    pub fn messages(&self) -> Result<Vec<Message>> {
+        self.stream().collect()
+    }
+
+    /// The messages the capture is expected to parse to
+    pub fn golden_messages(&self) -> Vec<Message> {
+        serde_json::from_str(self.golden).expect("invalid golden messages")
+    }
+
+    /// Feed the capture into a sink, see [`drive`]
+    pub fn run<S: MessageSink>(&self, sink: S) -> Result<u64> {
+        drive(self.stream(), sink)
+    }
+}
+
+/// Assert that `actual` serializes to the same JSON as the golden file at
+/// `path`.
+///
+/// If the [`BLESS_VAR`] environment variable is set the golden file is
+/// (re)written instead, so new outputs can be recorded with e.g.
+/// `ITCHY_BLESS=1 cargo test`.
+pub fn assert_golden<T, P>(path: P, actual: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if std::env::var_os(BLESS_VAR).is_some() {
+        let mut json = serde_json::to_string_pretty(actual).expect("failed to serialize output");
+        json.push('\n');
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("failed to create golden directory");
+        }
+        fs::write(path, json).expect("failed to write golden file");
+        return;
+    }
+    let golden = fs::read_to_string(path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read golden file {}: {} (set {} to create it)",
+            path.display(),
+            e,
+            BLESS_VAR
+        )
+    });
+    let expected: T = serde_json::from_str(&golden)
+        .unwrap_or_else(|e| panic!("invalid golden file {}: {}", path.display(), e));
+    assert_eq!(
+        actual,
+        &expected,
+        "output differs from golden file {} (set {} to update it)",
+        path.display(),
+        BLESS_VAR
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_match_golden() {
+        for fixture in FIXTURES {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("fixtures")
+                .join(format!("{}.json", fixture.name));
+            let messages = fixture.messages().unwrap();
+            assert_golden(path, &messages);
+            assert_eq!(messages, fixture.golden_messages());
+            assert_eq!(
+                fixture
+                    .run(crate::sink_fn(|_| std::ops::ControlFlow::Continue(())))
+                    .unwrap(),
+                messages.len() as u64
+            );
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use crate::{
+    ArrayString8, Body, CrossType, Message, OrderTracker, OrderUpdate, Price4, RegShoAction,
+    Result, Side, TradingState,
+};
+
+/// How a trade came about
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TradeKind {
+    /// Continuous trading against the book, or a non-displayed order
+    RegularWay,
+    OpeningCross,
+    ClosingCross,
+    /// The IPO or halt resumption auction
+    HaltCross,
+    IntradayCross,
+    /// The extended trading close
+    ExtendedClose,
+}
+
+impl From<CrossType> for TradeKind {
+    fn from(cross_type: CrossType) -> TradeKind {
+        match cross_type {
+            CrossType::Opening => TradeKind::OpeningCross,
+            CrossType::Closing => TradeKind::ClosingCross,
+            CrossType::IpoOrHalted => TradeKind::HaltCross,
+            CrossType::Intraday => TradeKind::IntradayCross,
+            CrossType::ExtendedTradingClose => TradeKind::ExtendedClose,
+        }
+    }
+}
+
+/// Size of a trade relative to the symbol's round lot
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LotKind {
+    /// Fewer shares than a round lot
+    Odd,
+    /// A whole number of round lots
+    Round,
+    /// More than a round lot, but not a whole number of them
+    Mixed,
+}
+
+impl LotKind {
+    pub fn classify(shares: u64, round_lot: u32) -> LotKind {
+        let round_lot = round_lot.max(1) as u64;
+        if shares < round_lot {
+            LotKind::Odd
+        } else if shares.is_multiple_of(round_lot) {
+            LotKind::Round
+        } else {
+            LotKind::Mixed
+        }
+    }
+}
+
+/// Round lot of symbols without a stock directory message
+pub(crate) const DEFAULT_ROUND_LOT: u32 = 100;
+
+/// A printable trade, with the market conditions it happened in
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Trade {
+    pub stock_locate: u16,
+    pub timestamp: u64,
+    pub stock: ArrayString8,
+    pub shares: u64,
+    pub price: Price4,
+    pub match_number: u64,
+    /// Side of the resting order, not known for crosses
+    pub side: Option<Side>,
+    pub kind: TradeKind,
+    /// Size of the trade against the symbol's round lot
+    pub lot: LotKind,
+    /// True if the Reg SHO short sale price test was in effect
+    pub short_sale_restricted: bool,
+    /// Trading state of the symbol at the time of the trade
+    pub trading_state: TradingState,
+}
+
+/// Builds a classified trade tape from executions, non-cross trades and
+/// crosses, tracking the Reg SHO and trading state of every symbol.
+///
+/// Non-printable executions do not appear on the tape. Symbols are
+/// assumed to be trading and unrestricted until told otherwise, and to
+/// trade in round lots of 100 shares until their stock directory message.
+#[derive(Debug, Clone, Default)]
+pub struct TradeClassifier {
+    orders: OrderTracker,
+    reg_sho: HashMap<u16, RegShoAction>,
+    trading: HashMap<u16, TradingState>,
+    round_lots: HashMap<u16, u32>,
+}
+
+impl TradeClassifier {
+    pub fn new() -> TradeClassifier {
+        TradeClassifier::default()
+    }
+
+    /// Apply a message, returning the trade it reports if any
+    pub fn update(&mut self, msg: &Message) -> Option<Trade> {
+        let locate = msg.stock_locate;
+        let (stock, shares, price, match_number, side, kind) = match msg.body {
+            Body::RegShoRestriction { action, .. } => {
+                self.reg_sho.insert(locate, action);
+                return None;
+            }
+            Body::TradingAction { trading_state, .. } => {
+                self.trading.insert(locate, trading_state);
+                return None;
+            }
+            Body::StockDirectory(ref directory) => {
+                self.round_lots.insert(locate, directory.round_lot_size);
+                return None;
+            }
+            Body::NonCrossTrade(ref trade) => (
+                trade.stock,
+                trade.shares as u64,
+                trade.price,
+                trade.match_number,
+                Some(trade.side),
+                TradeKind::RegularWay,
+            ),
+            Body::CrossTrade(ref cross) => (
+                cross.stock,
+                cross.shares,
+                cross.cross_price,
+                cross.match_number,
+                None,
+                cross.cross_type.into(),
+            ),
+            _ => match self.orders.apply(msg)? {
+                Ok(OrderUpdate::Executed {
+                    order,
+                    shares,
+                    price,
+                    match_number,
+                    printable: true,
+                    ..
+                }) => (
+                    order.stock,
+                    shares as u64,
+                    price,
+                    match_number,
+                    Some(order.side),
+                    TradeKind::RegularWay,
+                ),
+                _ => return None,
+            },
+        };
+        Some(Trade {
+            stock_locate: locate,
+            timestamp: msg.timestamp,
+            stock,
+            shares,
+            price,
+            match_number,
+            side,
+            kind,
+            lot: LotKind::classify(shares, self.round_lot(locate)),
+            short_sale_restricted: self.is_short_sale_restricted(locate),
+            trading_state: self.trading_state(locate),
+        })
+    }
+
+    pub fn is_short_sale_restricted(&self, locate: u16) -> bool {
+        self.reg_sho
+            .get(&locate)
+            .is_some_and(|&action| action != RegShoAction::None)
+    }
+
+    pub fn trading_state(&self, locate: u16) -> TradingState {
+        self.trading
+            .get(&locate)
+            .copied()
+            .unwrap_or(TradingState::Trading)
+    }
+
+    /// Round lot size of the symbol, from its stock directory message
+    pub fn round_lot(&self, locate: u16) -> u32 {
+        self.round_lots
+            .get(&locate)
+            .copied()
+            .unwrap_or(DEFAULT_ROUND_LOT)
+    }
+}
+
+/// Iterator of classified [`Trade`]s from a stream of messages, see
+/// [`TradeClassifier`]. Errors from the message stream are passed through.
+#[derive(Debug)]
+pub struct TradeTape<I> {
+    messages: I,
+    classifier: TradeClassifier,
+}
+
+impl<I: Iterator<Item = Result<Message>>> TradeTape<I> {
+    pub fn new(messages: I) -> TradeTape<I> {
+        TradeTape {
+            messages,
+            classifier: TradeClassifier::new(),
+        }
+    }
+
+    pub fn classifier(&self) -> &TradeClassifier {
+        &self.classifier
+    }
+}
+
+impl<I: Iterator<Item = Result<Message>>> Iterator for TradeTape<I> {
+    type Item = Result<Trade>;
+
+    fn next(&mut self) -> Option<Result<Trade>> {
+        loop {
+            match self.messages.next()? {
+                Ok(msg) => {
+                    if let Some(trade) = self.classifier.update(&msg) {
+                        return Some(Ok(trade));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::tests::{add, msg};
+    use crate::{ArrayString4, CrossTrade};
+
+    #[test]
+    fn classifies_trades() {
+        let stock = ArrayString8::from("ZXZZT   ").unwrap();
+        let exec = |reference, printable| Body::OrderExecutedWithPrice {
+            reference,
+            executed: 10,
+            match_number: reference,
+            printable,
+            price: 10_000.into(),
+        };
+        let messages = vec![
+            msg(1, add(1, Side::Buy, 100, 10_000)),
+            msg(2, exec(1, true)),
+            msg(3, exec(1, false)),
+            msg(
+                4,
+                Body::RegShoRestriction {
+                    stock,
+                    action: RegShoAction::Intraday,
+                },
+            ),
+            msg(
+                5,
+                Body::TradingAction {
+                    stock,
+                    trading_state: TradingState::Halted,
+                    reason: ArrayString4::from("LUDP").unwrap(),
+                },
+            ),
+            msg(
+                6,
+                Body::CrossTrade(CrossTrade {
+                    shares: 500,
+                    stock,
+                    cross_price: 10_100.into(),
+                    match_number: 9,
+                    cross_type: CrossType::IpoOrHalted,
+                }),
+            ),
+        ];
+        let mut tape = TradeTape::new(messages.into_iter().map(Ok));
+        let trades: Vec<_> = tape.by_ref().map(|t| t.unwrap()).collect();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].kind, TradeKind::RegularWay);
+        assert_eq!(trades[0].side, Some(Side::Buy));
+        assert_eq!(trades[0].lot, LotKind::Odd);
+        assert_eq!(trades[1].lot, LotKind::Round);
+        assert!(!trades[0].short_sale_restricted);
+        assert_eq!(trades[1].kind, TradeKind::HaltCross);
+        assert!(trades[1].short_sale_restricted);
+        assert_eq!(trades[1].trading_state, TradingState::Halted);
+        assert!(tape.classifier().is_short_sale_restricted(1));
+    }
+
+    #[test]
+    fn classifies_lots() {
+        assert_eq!(LotKind::classify(99, 100), LotKind::Odd);
+        assert_eq!(LotKind::classify(300, 100), LotKind::Round);
+        assert_eq!(LotKind::classify(150, 100), LotKind::Mixed);
+        assert_eq!(LotKind::classify(15, 10), LotKind::Mixed);
+    }
+}
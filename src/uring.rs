@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use io_uring::{opcode, types, IoUring};
+
+/// A file reader using io_uring, with several reads kept in flight ahead of
+/// the consumer.
+///
+/// Reads go into a ring of buffers registered with the kernel, so large
+/// uncompressed files can be read with far fewer system calls than by
+/// reading a few kilobytes at a time. Linux only, with the `uring` feature.
+pub struct UringReader {
+    // declared first so it is dropped before the buffers it reads into
+    ring: IoUring,
+    file: File,
+    file_len: u64,
+    // offset of the next read to submit
+    next_offset: u64,
+    buffers: Vec<Box<[u8]>>,
+    slots: Vec<Slot>,
+    // buffers in file order
+    queue: VecDeque<usize>,
+    // position in the buffer at the front of the queue
+    pos: usize,
+    in_flight: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    // file offset and length of the data the buffer holds
+    offset: u64,
+    len: usize,
+    // bytes read so far
+    filled: usize,
+    pending: bool,
+}
+
+impl UringReader {
+    /// Open a file with a read-ahead of 8 buffers of 1 MiB
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<UringReader> {
+        UringReader::with_depth(path, 8, 1 << 20)
+    }
+
+    /// Open a file, keeping up to `depth` reads of `buffer_size` bytes in
+    /// flight. Both must be non-zero.
+    pub fn with_depth<P: AsRef<Path>>(
+        path: P,
+        depth: usize,
+        buffer_size: usize,
+    ) -> io::Result<UringReader> {
+        if depth == 0 || buffer_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "read-ahead must be non-empty",
+            ));
+        }
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let ring = IoUring::new(depth.next_power_of_two() as u32)?;
+        let buffers: Vec<Box<[u8]>> = (0..depth)
+            .map(|_| vec![0; buffer_size].into_boxed_slice())
+            .collect();
+        let iovecs: Vec<_> = buffers
+            .iter()
+            .map(|buf| libc::iovec {
+                iov_base: buf.as_ptr() as *mut _,
+                iov_len: buf.len(),
+            })
+            .collect();
+        // SAFETY: the buffers are heap allocations which are neither moved
+        // nor freed until the ring is dropped
+        unsafe { ring.submitter().register_buffers(&iovecs)? };
+        let mut reader = UringReader {
+            ring,
+            file,
+            file_len,
+            next_offset: 0,
+            buffers,
+            slots: vec![Slot::default(); depth],
+            queue: VecDeque::with_capacity(depth),
+            pos: 0,
+            in_flight: 0,
+        };
+        for index in 0..depth {
+            reader.start(index)?;
+        }
+        Ok(reader)
+    }
+
+    // read the next chunk of the file into a buffer, if any remains
+    fn start(&mut self, index: usize) -> io::Result<()> {
+        if self.next_offset >= self.file_len {
+            return Ok(());
+        }
+        let len = (self.file_len - self.next_offset).min(self.buffers[index].len() as u64);
+        self.slots[index] = Slot {
+            offset: self.next_offset,
+            len: len as usize,
+            filled: 0,
+            pending: true,
+        };
+        self.next_offset += len;
+        self.queue.push_back(index);
+        self.submit(index)
+    }
+
+    // submit a read for the unfilled part of a buffer
+    fn submit(&mut self, index: usize) -> io::Result<()> {
+        let slot = self.slots[index];
+        let buf = &mut self.buffers[index][slot.filled..slot.len];
+        let entry = opcode::ReadFixed::new(
+            types::Fd(self.file.as_raw_fd()),
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+            index as u16,
+        )
+        .offset(slot.offset + slot.filled as u64)
+        .build()
+        .user_data(index as u64);
+        // SAFETY: the buffer is registered and outlives the read, and there
+        // is at most one read per buffer so the queue cannot be full
+        unsafe {
+            self.ring
+                .submission()
+                .push(&entry)
+                .map_err(|_| io::Error::other("submission queue full"))?;
+        }
+        self.in_flight += 1;
+        self.ring.submit()?;
+        Ok(())
+    }
+
+    // wait until the buffer at the front of the queue is filled
+    fn wait_front(&mut self, index: usize) -> io::Result<()> {
+        while self.slots[index].pending {
+            self.ring.submit_and_wait(1)?;
+            let completed: Vec<_> = self
+                .ring
+                .completion()
+                .map(|cqe| (cqe.user_data() as usize, cqe.result()))
+                .collect();
+            for (done, result) in completed {
+                self.in_flight -= 1;
+                if result < 0 {
+                    return Err(io::Error::from_raw_os_error(-result));
+                }
+                let slot = &mut self.slots[done];
+                slot.filled += result as usize;
+                if result == 0 {
+                    // the file was truncated while reading
+                    slot.len = slot.filled;
+                }
+                if slot.filled == slot.len {
+                    slot.pending = false;
+                } else {
+                    self.submit(done)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for UringReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let Some(&index) = self.queue.front() else {
+                return Ok(0);
+            };
+            self.wait_front(index)?;
+            let slot = self.slots[index];
+            if self.pos < slot.len {
+                let len = out.len().min(slot.len - self.pos);
+                out[..len].copy_from_slice(&self.buffers[index][self.pos..self.pos + len]);
+                self.pos += len;
+                return Ok(len);
+            }
+            // recycle the used buffer for the next chunk
+            self.queue.pop_front();
+            self.pos = 0;
+            self.start(index)?;
+        }
+    }
+}
+
+impl Drop for UringReader {
+    fn drop(&mut self) {
+        // the kernel may still be writing into the buffers
+        while self.in_flight > 0 {
+            if self.ring.submit_and_wait(1).is_err() {
+                break;
+            }
+            self.in_flight -= self.ring.completion().count();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageStream;
+
+    #[test]
+    fn reads_file_ahead() {
+        let mut data = Vec::new();
+        for ts in 0..1000u64 {
+            data.extend_from_slice(&[0, 12, b'S', 0, 0, 0, 0]);
+            data.extend_from_slice(&ts.to_be_bytes()[2..]);
+            data.push(b'O');
+        }
+        let path = std::env::temp_dir().join(format!("itchy-uring-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let reader = UringReader::with_depth(&path, 3, 500);
+        std::fs::remove_file(&path).unwrap();
+        let reader = match reader {
+            Ok(reader) => reader,
+            // io_uring may be disabled, e.g. in containers
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+            Err(e) => panic!("{}", e),
+        };
+        let stream = MessageStream::from_reader(reader);
+        let timestamps: Vec<_> = stream.map(|msg| msg.unwrap().timestamp).collect();
+        assert_eq!(timestamps, (0..1000).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{ArrayString8, Body, Message, SymbolDirectory};
+
+/// A stock locate inconsistency found by [`LocateChecker`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LocateWarning {
+    /// The message refers to a locate code with no stock directory entry
+    UndefinedLocate {
+        locate: u16,
+        tag: u8,
+        timestamp: u64,
+    },
+    /// The symbol in the message body differs from the directory entry for its locate
+    SymbolMismatch {
+        locate: u16,
+        tag: u8,
+        timestamp: u64,
+        expected: ArrayString8,
+        found: ArrayString8,
+    },
+}
+
+impl fmt::Display for LocateWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LocateWarning::UndefinedLocate {
+                locate,
+                tag,
+                timestamp,
+            } => write!(
+                f,
+                "'{}' message at {} refers to undefined locate {}",
+                *tag as char, timestamp, locate
+            ),
+            LocateWarning::SymbolMismatch {
+                locate,
+                tag,
+                timestamp,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' message at {} has symbol {:?} but locate {} is {:?}",
+                *tag as char, timestamp, found, locate, expected
+            ),
+        }
+    }
+}
+
+/// Opt-in validation that each message's `stock_locate` is consistent with
+/// the stock directory.
+///
+/// Locates are learned from stock directory ('R') messages as they are
+/// checked, or can be seeded from an existing [`SymbolDirectory`].
+/// Messages with locate 0 (market-wide messages) are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct LocateChecker {
+    symbols: HashMap<u16, ArrayString8>,
+}
+
+impl LocateChecker {
+    pub fn new() -> LocateChecker {
+        LocateChecker::default()
+    }
+
+    pub fn from_directory(directory: &SymbolDirectory) -> LocateChecker {
+        LocateChecker {
+            symbols: directory
+                .iter()
+                .map(|(locate, dir)| (locate, dir.stock))
+                .collect(),
+        }
+    }
+
+    /// Check a message, returning a warning if it is inconsistent
+    pub fn check(&mut self, msg: &Message) -> Option<LocateWarning> {
+        if let Body::StockDirectory(ref dir) = msg.body {
+            self.symbols.insert(msg.stock_locate, dir.stock);
+            return None;
+        }
+        if msg.stock_locate == 0 {
+            return None;
+        }
+        let Some(expected) = self.symbols.get(&msg.stock_locate) else {
+            return Some(LocateWarning::UndefinedLocate {
+                locate: msg.stock_locate,
+                tag: msg.tag,
+                timestamp: msg.timestamp,
+            });
+        };
+        match msg.body.stock() {
+            Some(found) if found != expected => Some(LocateWarning::SymbolMismatch {
+                locate: msg.stock_locate,
+                tag: msg.tag,
+                timestamp: msg.timestamp,
+                expected: *expected,
+                found: *found,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn msg(tag: u8, locate: u16, body: Body) -> Message {
+        Message {
+            tag,
+            stock_locate: locate,
+            tracking_number: 0,
+            timestamp: 0,
+            body,
+        }
+    }
+
+    fn stock(s: &str) -> ArrayString8 {
+        ArrayString8::from(s).unwrap()
+    }
+
+    #[test]
+    fn detects_inconsistencies() {
+        let mut dir = SymbolDirectory::builder();
+        dir.insert(
+            5,
+            StockDirectory {
+                stock: stock("AAPL    "),
+                market_category: MarketCategory::NasdaqGlobalSelect,
+                financial_status: FinancialStatus::Normal,
+                round_lot_size: 100,
+                round_lots_only: false,
+                issue_classification: IssueClassification::CommonStock,
+                issue_subtype: IssueSubType::CommonShares,
+                authenticity: true,
+                short_sale_threshold: None,
+                ipo_flag: None,
+                luld_ref_price_tier: LuldRefPriceTier::Tier1,
+                etp_flag: None,
+                etp_leverage_factor: 0,
+                inverse_indicator: false,
+            },
+        );
+        let mut checker = LocateChecker::from_directory(&dir.build());
+
+        let halt = |s| Body::TradingAction {
+            stock: stock(s),
+            trading_state: TradingState::Halted,
+            reason: ArrayString4::from("T1  ").unwrap(),
+        };
+        assert_eq!(checker.check(&msg(b'H', 5, halt("AAPL    "))), None);
+        assert!(matches!(
+            checker.check(&msg(b'H', 5, halt("MSFT    "))),
+            Some(LocateWarning::SymbolMismatch { locate: 5, .. })
+        ));
+        assert!(matches!(
+            checker.check(&msg(b'D', 9, Body::DeleteOrder { reference: 1 })),
+            Some(LocateWarning::UndefinedLocate { locate: 9, .. })
+        ));
+        let event = Body::SystemEvent {
+            event: EventCode::StartOfMessages,
+        };
+        assert_eq!(checker.check(&msg(b'S', 0, event)), None);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::{Error, MessageStream, Result};
+
+/// Number of messages inspected by [`detect_version`]
+const SNIFF_MESSAGES: usize = 32;
+
+/// Revision of the ITCH specification a stream was written with
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpecVersion {
+    /// ITCH 4.1: no locate or tracking numbers, timestamps split into
+    /// seconds ('T') messages and nanosecond offsets
+    Itch41,
+    /// ITCH 5.0, as used by Nasdaq, BX and PSX (which share a message layout)
+    Itch50,
+    /// The messages do not match either specification
+    Unknown,
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SpecVersion::Itch41 => "ITCH 4.1",
+            SpecVersion::Itch50 => "ITCH 5.0",
+            SpecVersion::Unknown => "unknown ITCH version",
+        };
+        f.write_str(name)
+    }
+}
+
+// Message lengths (excluding the length prefix) by tag
+pub(crate) fn length_v50(tag: u8) -> Option<usize> {
+    let len = match tag {
+        b'S' | b'W' => 12,
+        b'R' => 39,
+        b'H' => 25,
+        b'Y' | b'N' => 20,
+        b'L' => 26,
+        b'V' | b'J' | b'U' => 35,
+        b'K' => 28,
+        b'h' => 21,
+        b'A' | b'C' => 36,
+        b'F' | b'Q' => 40,
+        b'E' => 31,
+        b'X' => 23,
+        b'D' | b'B' => 19,
+        b'P' => 44,
+        b'I' => 50,
+        b'O' => 48,
+        _ => return None,
+    };
+    Some(len)
+}
+
+fn length_v41(tag: u8) -> Option<usize> {
+    let len = match tag {
+        b'T' => 5,
+        b'S' => 6,
+        b'R' | b'L' => 20,
+        b'H' => 19,
+        b'Y' | b'N' => 14,
+        b'A' | b'C' => 30,
+        b'F' | b'Q' => 34,
+        b'E' => 25,
+        b'X' => 17,
+        b'D' | b'B' => 13,
+        b'U' => 29,
+        b'P' => 38,
+        b'I' => 44,
+        _ => return None,
+    };
+    Some(len)
+}
+
+/// Guess the specification version of a stream from the lengths and tags
+/// of its first few messages.
+///
+/// Only the framing is inspected, and the reader is left part-way through
+/// the stream.
+pub fn detect_version<R: Read>(reader: R) -> Result<SpecVersion> {
+    let mut reader = BufReader::new(reader);
+    let mut frame = [0; u16::MAX as usize];
+    let (mut v41, mut v50) = (0, 0);
+    for _ in 0..SNIFF_MESSAGES {
+        let mut len = [0; 2];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let len = u16::from_be_bytes(len) as usize;
+        match reader.read_exact(&mut frame[..len]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let Some(&tag) = frame[..len].first() else {
+            continue;
+        };
+        v41 += (length_v41(tag) == Some(len)) as usize;
+        v50 += (length_v50(tag) == Some(len)) as usize;
+    }
+    Ok(if v50 > v41 {
+        SpecVersion::Itch50
+    } else if v41 > v50 {
+        SpecVersion::Itch41
+    } else {
+        SpecVersion::Unknown
+    })
+}
+
+/// Open a file, decompressing it if it is gzipped, after checking that it
+/// contains ITCH 5.0 messages.
+///
+/// ITCH 4.1 is not supported by the parser, so such files produce an error
+/// rather than a stream of misparsed messages.
+pub fn open_auto<P: AsRef<Path>>(path: P) -> Result<MessageStream<Box<dyn Read + Send>>> {
+    let path = path.as_ref();
+    let open = || -> Result<Box<dyn Read + Send>> {
+        let mut file = BufReader::new(File::open(path)?);
+        let gzipped = file.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        Ok(if gzipped {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        })
+    };
+    match detect_version(open()?)? {
+        // an empty file is trivially valid
+        SpecVersion::Itch50 => {}
+        SpecVersion::Unknown if File::open(path)?.metadata()?.len() == 0 => {}
+        version => {
+            return Err(Error::Parse(format!(
+                "{}: expected ITCH 5.0, found {}",
+                path.display(),
+                version
+            )))
+        }
+    }
+    Ok(MessageStream::from_reader(open()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+
+    #[test]
+    fn detects_versions() {
+        let v50 =
+            hex_to_bytes(b"000c 5300 0000 0028 6aab 3b3a 994f 000c 5300 0000 0028 6aab 3b3a 9953");
+        assert_eq!(detect_version(&v50[..]).unwrap(), SpecVersion::Itch50);
+        // seconds message, then a system event
+        let v41 = hex_to_bytes(b"0005 5400 0081 1c 0006 5300 0000 004f");
+        assert_eq!(detect_version(&v41[..]).unwrap(), SpecVersion::Itch41);
+        assert_eq!(detect_version(&b""[..]).unwrap(), SpecVersion::Unknown);
+
+        let path = std::env::temp_dir().join(format!("itchy-version-{}", std::process::id()));
+        std::fs::write(&path, &v41).unwrap();
+        assert!(open_auto(&path).is_err());
+        std::fs::write(&path, &v50).unwrap();
+        assert_eq!(open_auto(&path).unwrap().count(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{MoldPacket, Packet, PacketSource, Result};
+
+const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(3);
+const DEFAULT_DOWN_AFTER: Duration = Duration::from_secs(10);
+
+/// A change in the health of a feed, reported by a [`Watchdog`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WatchdogEvent {
+    /// No MoldUDP64 data or heartbeats for this long, though other packets
+    /// may still arrive
+    FeedStale { silent: Duration },
+    /// No packets at all for this long
+    FeedDown { silent: Duration },
+    /// Packets are arriving again, after this long without them
+    Recovered { silent: Duration },
+}
+
+type EventHook = Box<dyn FnMut(WatchdogEvent) + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Health {
+    Up,
+    Stale,
+    Down,
+}
+
+struct Monitor {
+    stale_after: Duration,
+    down_after: Duration,
+    // last packet of any kind, and last valid MoldUDP64 packet
+    last_packet: Instant,
+    last_alive: Instant,
+    health: Health,
+    on_event: Option<EventHook>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<dyn crate::MetricsRegistry>>,
+}
+
+impl Monitor {
+    fn emit(&mut self, event: WatchdogEvent) {
+        #[cfg(feature = "tracing")]
+        match event {
+            WatchdogEvent::Recovered { silent } => {
+                tracing::info!(silent_ms = silent.as_millis() as u64, "feed recovered")
+            }
+            _ => tracing::warn!(?event, "feed unhealthy"),
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_feed_event(&event);
+        }
+        if let Some(ref mut on_event) = self.on_event {
+            on_event(event);
+        }
+    }
+
+    fn check(&mut self, now: Instant) {
+        let silent = now.duration_since(self.last_packet);
+        let quiet = now.duration_since(self.last_alive);
+        if silent >= self.down_after && self.health != Health::Down {
+            self.health = Health::Down;
+            self.emit(WatchdogEvent::FeedDown { silent });
+        } else if quiet >= self.stale_after && self.health == Health::Up {
+            self.health = Health::Stale;
+            self.emit(WatchdogEvent::FeedStale { silent: quiet });
+        }
+    }
+
+    fn receive(&mut self, packet: &Packet, now: Instant) {
+        let silent = now.duration_since(self.last_packet);
+        self.last_packet = now;
+        let alive = MoldPacket::parse(&packet.data).is_ok();
+        if alive {
+            self.last_alive = now;
+        }
+        let recovered = match self.health {
+            Health::Up => false,
+            Health::Stale => alive,
+            Health::Down => true,
+        };
+        if recovered {
+            self.health = if alive { Health::Up } else { Health::Stale };
+            self.emit(WatchdogEvent::Recovered { silent });
+        }
+    }
+}
+
+/// Watches a live [`PacketSource`], reporting when the feed goes quiet.
+///
+/// A feed is stale when no valid MoldUDP64 packet, with data or a
+/// heartbeat, has arrived within `stale_after`, and down when no packet
+/// at all has arrived within `down_after`. Each change is reported once
+/// to the [`on_event`](Watchdog::on_event) hook, as a tracing event when
+/// the `tracing` feature is enabled, and to a `MetricsRegistry` with the
+/// `metrics` feature.
+/// Once packets arrive again, [`WatchdogEvent::Recovered`] is reported.
+///
+/// Sources usually block while the feed is quiet, so silence is checked
+/// from a separate thread, started when the first packet is requested and
+/// stopped when the watchdog is dropped. The watchdog is itself a source,
+/// passing packets through unchanged:
+///
+/// ```ignore
+/// let socket = UdpSocket::bind("0.0.0.0:26400")?;
+/// let watchdog = itchy::Watchdog::new(socket)
+///     .stale_after(Duration::from_secs(2))
+///     .on_event(|event| eprintln!("{:?}", event));
+/// for envelope in itchy::MoldReceiver::new(watchdog) {
+///     println!("{:?}", envelope?.message);
+/// }
+/// ```
+pub struct Watchdog<S> {
+    source: S,
+    monitor: Arc<Mutex<Monitor>>,
+    started: bool,
+}
+
+impl<S: PacketSource> Watchdog<S> {
+    pub fn new(source: S) -> Watchdog<S> {
+        let now = Instant::now();
+        let monitor = Monitor {
+            stale_after: DEFAULT_STALE_AFTER,
+            down_after: DEFAULT_DOWN_AFTER,
+            last_packet: now,
+            last_alive: now,
+            health: Health::Up,
+            on_event: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        };
+        Watchdog {
+            source,
+            monitor: Arc::new(Mutex::new(monitor)),
+            started: false,
+        }
+    }
+
+    /// Report the feed stale after this long without data or heartbeats
+    /// (3 seconds by default)
+    pub fn stale_after(self, threshold: Duration) -> Self {
+        self.with_monitor(|monitor| monitor.stale_after = threshold)
+    }
+
+    /// Report the feed down after this long without any packets (10 seconds
+    /// by default)
+    pub fn down_after(self, threshold: Duration) -> Self {
+        self.with_monitor(|monitor| monitor.down_after = threshold)
+    }
+
+    /// Call `f` with each change in the health of the feed, from the
+    /// watchdog's thread or the reading thread
+    pub fn on_event<F>(self, f: F) -> Self
+    where
+        F: FnMut(WatchdogEvent) + Send + 'static,
+    {
+        self.with_monitor(|monitor| monitor.on_event = Some(Box::new(f)))
+    }
+
+    /// Report changes in the health of the feed to the given registry
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: Arc<dyn crate::MetricsRegistry>) {
+        if let Ok(mut monitor) = self.monitor.lock() {
+            monitor.metrics = Some(metrics);
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    fn with_monitor(self, f: impl FnOnce(&mut Monitor)) -> Self {
+        if let Ok(mut monitor) = self.monitor.lock() {
+            f(&mut monitor);
+        }
+        self
+    }
+
+    fn start(&mut self) {
+        self.started = true;
+        let Ok(mut monitor) = self.monitor.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        monitor.last_packet = now;
+        monitor.last_alive = now;
+        let interval = monitor.stale_after.min(monitor.down_after) / 4;
+        drop(monitor);
+        let weak = Arc::downgrade(&self.monitor);
+        #[allow(clippy::expect_used)]
+        // like `thread::spawn`, panics if the OS cannot create a thread
+        thread::Builder::new()
+            .name("itchy-watchdog".into())
+            .spawn(move || watch(weak, interval))
+            .expect("failed to spawn watchdog thread");
+    }
+}
+
+fn watch(monitor: Weak<Mutex<Monitor>>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let Some(monitor) = monitor.upgrade() else {
+            return;
+        };
+        let Ok(mut monitor) = monitor.lock() else {
+            return;
+        };
+        monitor.check(Instant::now());
+    }
+}
+
+impl<S: PacketSource> PacketSource for Watchdog<S> {
+    fn next_packet(&mut self) -> Option<Result<Packet>> {
+        if !self.started {
+            self.start();
+        }
+        let packet = self.source.next_packet()?;
+        if let (Ok(ref packet), Ok(mut monitor)) = (&packet, self.monitor.lock()) {
+            monitor.receive(packet, Instant::now());
+        }
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::tests::hex_to_bytes;
+    use crate::mold::tests::mold_packet;
+    use std::sync::mpsc;
+
+    // a feed which goes silent for a while after its first packet
+    struct Pausing(Vec<Packet>, Duration);
+
+    impl PacketSource for Pausing {
+        fn next_packet(&mut self) -> Option<Result<Packet>> {
+            if self.0.len() == 1 {
+                thread::sleep(self.1);
+            }
+            self.0.pop().map(Ok)
+        }
+    }
+
+    #[test]
+    fn reports_stale_down_and_recovery() {
+        let event = hex_to_bytes(b"5300 0000 0028 6aab 3b3a 994f");
+        let packet = |data| Packet {
+            data,
+            receive_ts: None,
+        };
+        let packets = vec![
+            packet(mold_packet("SESSION", 2, &[])),
+            packet(mold_packet("SESSION", 1, &[&event[..]])),
+        ];
+        let (tx, rx) = mpsc::channel();
+        let mut watchdog = Watchdog::new(Pausing(packets, Duration::from_millis(400)))
+            .stale_after(Duration::from_millis(40))
+            .down_after(Duration::from_millis(120))
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            });
+        while let Some(packet) = watchdog.next_packet() {
+            assert!(packet.is_ok());
+        }
+        drop(watchdog);
+
+        let events: Vec<_> = rx.iter().collect();
+        assert!(matches!(
+            events[..],
+            [
+                WatchdogEvent::FeedStale { .. },
+                WatchdogEvent::FeedDown { .. },
+                WatchdogEvent::Recovered { .. }
+            ]
+        ));
+        if let WatchdogEvent::Recovered { silent } = events[2] {
+            assert!(silent >= Duration::from_millis(400));
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::ControlFlow;
+
+use crate::{ArrayString8, Body, Error, Message, MessageSink, Result};
+
+// topic of messages not about a single instrument, e.g. system events
+const MARKET_WIDE: &[u8; 8] = b"        ";
+
+fn topic(symbol: &str) -> Result<ArrayString8> {
+    let mut topic = ArrayString8::from(symbol.trim_end())
+        .map_err(|_| Error::Parse(format!("symbol {:?} is longer than 8 characters", symbol)))?;
+    while !topic.is_full() {
+        topic.push(' ');
+    }
+    Ok(topic)
+}
+
+fn zmq_error(e: zmq::Error) -> Error {
+    Error::Io(io::Error::from(e))
+}
+
+/// Publishes messages on a ZeroMQ PUB socket, as two-frame messages of a
+/// topic and the message encoded as JSON.
+///
+/// The topic is the stock symbol, space-padded to 8 characters as on the
+/// wire, so that subscribing to one symbol does not also match longer
+/// symbols starting with it. Messages not about a single instrument are
+/// published under 8 spaces. The symbols of locate codes are learnt from
+/// the stock directory messages published, so those must be published
+/// first, as they are in an ITCH stream. With the `zmq` feature.
+///
+/// As a [`MessageSink`], send errors stop the stream and the error is kept
+/// for [`error`](ZmqPublisher::error).
+pub struct ZmqPublisher {
+    socket: zmq::Socket,
+    symbols: HashMap<u16, ArrayString8>,
+    payload: Vec<u8>,
+    error: Option<Error>,
+}
+
+impl ZmqPublisher {
+    /// Create a PUB socket bound to `endpoint`, e.g. `"tcp://*:5556"`
+    pub fn bind(context: &zmq::Context, endpoint: &str) -> Result<ZmqPublisher> {
+        let socket = context.socket(zmq::PUB).map_err(zmq_error)?;
+        socket.bind(endpoint).map_err(zmq_error)?;
+        Ok(ZmqPublisher::from_socket(socket))
+    }
+
+    /// Publish on a socket set up by the caller
+    pub fn from_socket(socket: zmq::Socket) -> ZmqPublisher {
+        ZmqPublisher {
+            socket,
+            symbols: HashMap::new(),
+            payload: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn publish(&mut self, msg: &Message) -> Result<()> {
+        if let Body::StockDirectory(ref dir) = msg.body {
+            self.symbols.insert(msg.stock_locate, dir.stock);
+        }
+        let topic = match self.symbols.get(&msg.stock_locate) {
+            Some(stock) if msg.stock_locate != 0 => stock.as_bytes(),
+            _ => MARKET_WIDE,
+        };
+        self.payload.clear();
+        serde_json::to_writer(&mut self.payload, msg).map_err(io::Error::from)?;
+        self.socket
+            .send_multipart([topic, &self.payload[..]], 0)
+            .map_err(zmq_error)
+    }
+
+    /// The error that stopped the stream, as a sink
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    pub fn get_ref(&self) -> &zmq::Socket {
+        &self.socket
+    }
+}
+
+impl MessageSink for ZmqPublisher {
+    fn accept(&mut self, msg: Message) -> ControlFlow<()> {
+        match self.publish(&msg) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// Receives the messages sent by a [`ZmqPublisher`] on a SUB socket.
+///
+/// Nothing is received until a subscription is made. As an iterator it
+/// blocks waiting for messages and never ends.
+pub struct ZmqSubscriber {
+    socket: zmq::Socket,
+}
+
+impl ZmqSubscriber {
+    /// Create a SUB socket connected to `endpoint`, e.g.
+    /// `"tcp://localhost:5556"`
+    pub fn connect(context: &zmq::Context, endpoint: &str) -> Result<ZmqSubscriber> {
+        let socket = context.socket(zmq::SUB).map_err(zmq_error)?;
+        socket.connect(endpoint).map_err(zmq_error)?;
+        Ok(ZmqSubscriber::from_socket(socket))
+    }
+
+    /// Receive on a socket set up by the caller
+    pub fn from_socket(socket: zmq::Socket) -> ZmqSubscriber {
+        ZmqSubscriber { socket }
+    }
+
+    /// Receive the messages about a symbol, or with `""` the messages not
+    /// about a single instrument
+    pub fn subscribe(&self, symbol: &str) -> Result<()> {
+        let topic = topic(symbol)?;
+        self.socket
+            .set_subscribe(topic.as_bytes())
+            .map_err(zmq_error)
+    }
+
+    pub fn unsubscribe(&self, symbol: &str) -> Result<()> {
+        let topic = topic(symbol)?;
+        self.socket
+            .set_unsubscribe(topic.as_bytes())
+            .map_err(zmq_error)
+    }
+
+    /// Receive every message
+    pub fn subscribe_all(&self) -> Result<()> {
+        self.socket.set_subscribe(b"").map_err(zmq_error)
+    }
+
+    /// Wait for the next message
+    pub fn recv(&mut self) -> Result<Message> {
+        let frames = self.socket.recv_multipart(0).map_err(zmq_error)?;
+        let [_, payload] = &frames[..] else {
+            return Err(Error::Parse(format!(
+                "expected a topic and a payload, got {} frames",
+                frames.len()
+            )));
+        };
+        Ok(serde_json::from_slice(payload).map_err(io::Error::from)?)
+    }
+
+    pub fn get_ref(&self) -> &zmq::Socket {
+        &self.socket
+    }
+}
+
+impl Iterator for ZmqSubscriber {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        Some(self.recv())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::tests::directory_msg;
+    use crate::orders::tests::{add, msg};
+    use crate::Side;
+
+    #[test]
+    fn filters_by_symbol() {
+        let context = zmq::Context::new();
+        let endpoint = format!("inproc://itchy-{}", std::process::id());
+        let mut publisher = ZmqPublisher::bind(&context, &endpoint).unwrap();
+        let mut subscriber = ZmqSubscriber::connect(&context, &endpoint).unwrap();
+        subscriber.subscribe("AB").unwrap();
+        subscriber.subscribe("").unwrap();
+
+        let messages = [
+            directory_msg(1, "AB"),
+            directory_msg(2, "ABC"),
+            Message {
+                stock_locate: 2,
+                ..msg(5, add(1, Side::Buy, 100, 10_000))
+            },
+            Message {
+                stock_locate: 1,
+                ..msg(6, add(2, Side::Buy, 100, 10_000))
+            },
+            Message {
+                stock_locate: 0,
+                ..msg(7, Body::Breach(crate::LevelBreached::L1))
+            },
+        ];
+        // subscriptions take effect asynchronously, so publish until one
+        // arrives
+        publisher.publish(&messages[0]).unwrap();
+        subscriber.socket.set_rcvtimeo(10).unwrap();
+        while subscriber.recv().is_err() {
+            publisher.publish(&messages[0]).unwrap();
+        }
+        subscriber.socket.set_rcvtimeo(-1).unwrap();
+        for m in &messages[1..] {
+            publisher.publish(m).unwrap();
+        }
+        // skipping any copies of the first message still in flight
+        let received: Vec<_> = subscriber
+            .by_ref()
+            .map(|m| m.unwrap())
+            .filter(|m| m != &messages[0])
+            .take(2)
+            .collect();
+        assert_eq!(received, messages[3..]);
+    }
+}